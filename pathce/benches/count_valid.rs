@@ -0,0 +1,52 @@
+//! Compare counting the valid values of an item serially and in parallel, to find the length from
+//! which the parallel count pays off. Run with `cargo bench --bench count_valid`.
+#![feature(test)]
+
+extern crate test;
+
+use pathce::common::{DefaultVertexId, INVALID_VERTEX_ID};
+use pathce::count_valid_values;
+use test::{black_box, Bencher};
+
+fn build_values(len: usize) -> Vec<DefaultVertexId> {
+    (0..len)
+        .map(|i| if i % 3 == 0 { INVALID_VERTEX_ID } else { i })
+        .collect()
+}
+
+fn bench_serial(b: &mut Bencher, len: usize) {
+    let values = build_values(len);
+    b.iter(|| count_valid_values(black_box(&values), usize::MAX));
+}
+
+fn bench_parallel(b: &mut Bencher, len: usize) {
+    let values = build_values(len);
+    b.iter(|| count_valid_values(black_box(&values), 0));
+}
+
+macro_rules! bench_len {
+    ($($serial:ident, $parallel:ident, $len:expr;)*) => {
+        $(
+            #[bench]
+            fn $serial(b: &mut Bencher) {
+                bench_serial(b, $len);
+            }
+
+            #[bench]
+            fn $parallel(b: &mut Bencher) {
+                bench_parallel(b, $len);
+            }
+        )*
+    };
+}
+
+bench_len! {
+    serial_256, parallel_256, 1 << 8;
+    serial_1k, parallel_1k, 1 << 10;
+    serial_4k, parallel_4k, 1 << 12;
+    serial_16k, parallel_16k, 1 << 14;
+    serial_32k, parallel_32k, 1 << 15;
+    serial_64k, parallel_64k, 1 << 16;
+    serial_256k, parallel_256k, 1 << 18;
+    serial_1m, parallel_1m, 1 << 20;
+}
@@ -0,0 +1,118 @@
+//! An end-to-end example on a tiny LDBC-like graph: build a schema and a graph in memory, build the
+//! catalog, and estimate the cardinality of a pattern.
+//!
+//! Run with `cargo run --example ldbc_estimate`.
+
+use std::sync::Arc;
+
+use pathce::catalog_builder::CatalogBuilder;
+use pathce::common::{EdgeCardinality, LabelId};
+use pathce::estimate::CardinalityEstimator;
+use pathce::graph::{LabeledGraph, LabeledGraphBuilder};
+use pathce::pattern::RawPattern;
+use pathce::schema::{Schema, SchemaUnchecked};
+use rayon::ThreadPoolBuilder;
+
+const PERSON: LabelId = 0;
+const COMMENT: LabelId = 1;
+const KNOWS: LabelId = 0;
+const HAS_CREATOR: LabelId = 1;
+const REPLY_OF: LabelId = 2;
+
+const MAX_PATH_LENGTH: usize = 3;
+const MAX_STAR_LENGTH: usize = 1;
+const MAX_STAR_DEGREE: usize = 3;
+
+fn build_schema() -> Schema {
+    SchemaUnchecked::default()
+        .add_vertex_label("Person".into(), PERSON)
+        .add_vertex_label("Comment".into(), COMMENT)
+        .add_edge_label("Person_knows_Person".into(), KNOWS)
+        .add_edge_label("Comment_hasCreator_Person".into(), HAS_CREATOR)
+        .add_edge_label("Comment_replyOf_Comment".into(), REPLY_OF)
+        .add_vertex((PERSON, false))
+        .add_vertex((COMMENT, false))
+        .add_edge((PERSON, PERSON, KNOWS, EdgeCardinality::ManyToMany))
+        .add_edge((COMMENT, PERSON, HAS_CREATOR, EdgeCardinality::ManyToOne))
+        .add_edge((COMMENT, COMMENT, REPLY_OF, EdgeCardinality::ManyToOne))
+        .try_into()
+        .unwrap()
+}
+
+fn build_graph(schema: &Schema) -> LabeledGraph {
+    let mut builder = LabeledGraphBuilder::new(2);
+    for v in schema.vertices() {
+        builder = builder.add_vertex_label(v.label);
+    }
+    for e in schema.edges() {
+        builder = builder.add_edge_label(e.label, e.from, e.to);
+    }
+    let persons = 0..8;
+    let comments = 100..124;
+    for person in persons.clone() {
+        builder = builder.add_vertex(person, PERSON);
+    }
+    for comment in comments.clone() {
+        builder = builder.add_vertex(comment, COMMENT);
+    }
+    for src in persons.clone() {
+        for dst in persons
+            .clone()
+            .filter(|dst| (src + dst) % 3 == 0 && src != *dst)
+        {
+            builder = builder.add_edge(src, dst, KNOWS);
+        }
+    }
+    for (i, comment) in comments.clone().enumerate() {
+        builder = builder.add_edge(comment, i % persons.len(), HAS_CREATOR);
+        if i > 0 && i % 2 == 0 {
+            builder = builder.add_edge(comment, comment - 1, REPLY_OF);
+        }
+    }
+    builder.build().unwrap()
+}
+
+fn main() {
+    let schema = Arc::new(build_schema());
+    let graph = Arc::new(build_graph(&schema));
+    let pool = Arc::new(ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+
+    let catalog = CatalogBuilder::new(schema, graph, pool)
+        .max_path_length(MAX_PATH_LENGTH)
+        .max_star_length(MAX_STAR_LENGTH)
+        .max_star_degree(MAX_STAR_DEGREE)
+        .buckets(4)
+        .build()
+        .unwrap();
+
+    // (c1: Comment)-[:replyOf]->(c2: Comment)-[:hasCreator]->(p1: Person)-[:knows]->(p2: Person)
+    let pattern = RawPattern::new()
+        .push_back_vertex((0, COMMENT))
+        .push_back_vertex((1, COMMENT))
+        .push_back_vertex((2, PERSON))
+        .push_back_vertex((3, PERSON))
+        .push_back_edge((0, 0, 1, REPLY_OF))
+        .push_back_edge((1, 1, 2, HAS_CREATOR))
+        .push_back_edge((2, 2, 3, KNOWS))
+        .to_general()
+        .unwrap();
+
+    let estimator = CardinalityEstimator::new(
+        &catalog,
+        MAX_PATH_LENGTH,
+        MAX_STAR_LENGTH,
+        MAX_STAR_DEGREE,
+        10,
+        false,
+        false,
+        false,
+    );
+    let card = estimator.estimate(&pattern).unwrap();
+    println!("estimated cardinality: {card}");
+    assert!(card.is_finite() && card > 0.0);
+
+    let (lower, upper) = estimator.estimate_bounds(&pattern).unwrap();
+    println!("estimated bounds: [{lower}, {upper}]");
+    assert_eq!(upper, card);
+    assert!(lower <= upper);
+}
@@ -29,15 +29,45 @@ pub struct AnalyzeArgs {
     /// Specify the maximum degree of star (for star statistics)
     #[arg(long, default_value = "5")]
     max_star_degree: usize,
+    /// Specify the maximum number of stars per center label and degree (for star statistics)
+    #[arg(long)]
+    max_star_combinations: Option<usize>,
     /// Specify whether to use greedy binning
     #[arg(long)]
     greedy: bool,
+    /// Specify the fraction of the buckets initially of equal size (for greedy binning)
+    #[arg(long)]
+    initial_bucket_ratio: Option<f64>,
+    /// Specify the minimum number of neighbors counted in parallel (for greedy binning)
+    #[arg(long)]
+    par_count_threshold: Option<usize>,
+    /// Specify whether to store the joint statistics of length-2 paths (for maximum path length 1)
+    #[arg(long)]
+    joint_statistics: bool,
     /// Specify whether to skip path statistics
     #[arg(long)]
     skip_path: bool,
+    /// Specify whether to skip star statistics except those of single vertices
+    #[arg(long)]
+    skip_star: bool,
     /// Specify whether to save bucket maps (for debugging)
     #[arg(long)]
     save_bucket_map: bool,
+    /// Specify whether to materialize empty statistics as tables (for debugging)
+    #[arg(long)]
+    keep_empty_stats: bool,
+    /// Specify whether to key the catalog by 128-bit hashes of pattern encodings
+    #[arg(long)]
+    hash_encodings: bool,
+    /// Specify whether to validate the exported catalog by importing it back
+    #[arg(long)]
+    validate: bool,
+    /// Specify the file to dump the catalog metadata as JSON (for debugging)
+    #[arg(long, value_name = "METADATA_JSON")]
+    dump_metadata: Option<PathBuf>,
+    /// Specify whether to only print what would be built, without scanning the graph
+    #[arg(long)]
+    dry_run: bool,
     /// Specify the number of worker threads.
     #[arg(short, long, value_name = "THREADS", default_value = "8")]
     threads: usize,
@@ -104,22 +134,49 @@ pub fn analyze(args: AnalyzeArgs) {
     );
     println!("estimate memory footprint: {memory_footprint} bytes");
 
-    let builder = CatalogBuilder::new(schema, graph, pool)
+    let mut builder = CatalogBuilder::new(schema, graph, pool)
         .max_path_length(args.max_path_length)
         .max_star_length(args.max_star_length)
         .max_star_degree(args.max_star_degree)
         .buckets(args.buckets)
         .enable_greedy_bucket(args.greedy)
         .save_bucket_map(args.save_bucket_map)
-        .skip_path(args.skip_path);
+        .keep_empty_stats(args.keep_empty_stats)
+        .hash_encodings(args.hash_encodings)
+        .joint_statistics(args.joint_statistics)
+        .skip_path(args.skip_path)
+        .skip_star(args.skip_star);
+    if let Some(limit) = args.max_star_combinations {
+        builder = builder.max_star_combinations(limit);
+    }
+    if let Some(ratio) = args.initial_bucket_ratio {
+        builder = builder.initial_bucket_ratio(ratio);
+    }
+    if let Some(threshold) = args.par_count_threshold {
+        builder = builder.par_count_threshold(threshold);
+    }
+
+    if args.dry_run {
+        let plan = builder.plan();
+        println!("{}", serde_json::to_string_pretty(&plan).unwrap());
+        return;
+    }
 
     let start = Instant::now();
     let catalog = builder.build().unwrap();
     println!("total building time: {} s", start.elapsed().as_secs_f64());
 
+    if let Some(path) = args.dump_metadata {
+        catalog.dump_metadata_json(path).unwrap();
+    }
+
     if let Some(output) = args.output {
         let start = Instant::now();
-        catalog.export(output).unwrap();
+        if args.validate {
+            catalog.export_validated(output).unwrap();
+        } else {
+            catalog.export(output).unwrap();
+        }
         println!("export time: {} s", start.elapsed().as_secs_f64());
     }
 }
@@ -3,11 +3,11 @@ use std::path::PathBuf;
 use std::time::Instant;
 
 use clap::Args;
-use pathce::catalog::DuckCatalog;
+use log::info;
+use pathce::catalog::{DuckCatalog, DuckConfig};
 use pathce::common::TagId;
 use pathce::estimate::CardinalityEstimator;
 use pathce::pattern::RawPattern;
-use log::info;
 
 #[derive(Debug, Args)]
 pub struct EstimateArgs {
@@ -17,6 +17,12 @@ pub struct EstimateArgs {
     /// Specify the pattern path.
     #[arg(short, long, value_name = "PATTERN_FILE")]
     patterns: Vec<PathBuf>,
+    /// Specify the `max_expression_depth` of DuckDB.
+    #[arg(long)]
+    max_expression_depth: Option<usize>,
+    /// Specify whether to query the catalog database in place instead of loading it into memory.
+    #[arg(long)]
+    attached: bool,
     /// Specify the maximum path length.
     #[arg(long, default_value = "3")]
     max_path_length: usize,
@@ -38,6 +44,9 @@ pub struct EstimateArgs {
     /// Specify whether to estimate cyclic patterns using spanning trees only
     #[arg(long)]
     disable_cyclic: bool,
+    /// Specify whether to split paths and stars missing from the catalog into smaller pieces.
+    #[arg(long)]
+    enable_fallback: bool,
     /// Specify a predefined elimination order.
     #[arg(long)]
     order: Option<String>,
@@ -52,7 +61,15 @@ fn parse_order(order: String) -> Vec<TagId> {
 }
 
 pub fn estimate(args: EstimateArgs) {
-    let catalog = DuckCatalog::import(args.catalog).unwrap();
+    let mut config = DuckConfig::default();
+    if let Some(depth) = args.max_expression_depth {
+        config = config.max_expression_depth(depth);
+    }
+    let catalog = if args.attached {
+        DuckCatalog::import_attached_with_config(args.catalog, &config).unwrap()
+    } else {
+        DuckCatalog::import_with_config(args.catalog, &config).unwrap()
+    };
     let estimator = CardinalityEstimator::new(
         &catalog,
         args.max_path_length,
@@ -62,7 +79,8 @@ pub fn estimate(args: EstimateArgs) {
         args.disable_star,
         args.disable_prune,
         args.disable_cyclic,
-    );
+    )
+    .enable_fallback(args.enable_fallback);
     if let Some(order) = args.order {
         assert_eq!(
             args.patterns.len(),
@@ -3,7 +3,7 @@ use std::path::PathBuf;
 use std::time::Instant;
 
 use clap::Args;
-use pathce::catalog::DuckCatalog;
+use pathce::catalog::{DuckCatalog, DuckConfig};
 use pathce::estimate::{CardinalityEstimatorManual, CatalogPattern};
 
 #[derive(Debug, Args)]
@@ -14,10 +14,17 @@ pub struct EstimateManualArgs {
     /// Specify the pattern path.
     #[arg(short, long, value_name = "PATTERN_FILE")]
     patterns: Vec<PathBuf>,
+    /// Specify the `max_expression_depth` of DuckDB.
+    #[arg(long)]
+    max_expression_depth: Option<usize>,
 }
 
 pub fn estimate_manual(args: EstimateManualArgs) {
-    let catalog = DuckCatalog::import(args.catalog).unwrap();
+    let mut config = DuckConfig::default();
+    if let Some(depth) = args.max_expression_depth {
+        config = config.max_expression_depth(depth);
+    }
+    let catalog = DuckCatalog::import_with_config(args.catalog, &config).unwrap();
     let estimator = CardinalityEstimatorManual::new(&catalog);
     for pattern in args.patterns {
         let pattern: CatalogPattern =
@@ -2,7 +2,7 @@ use std::path::PathBuf;
 use std::time::Instant;
 
 use clap::Args;
-use pathce::graph::LabeledGraph;
+use pathce::graph::{CsvFormat, CsvOptions, Delimiter, LabeledGraph};
 use pathce::schema::Schema;
 
 #[derive(Debug, Args)]
@@ -19,6 +19,18 @@ pub struct SerializeArgs {
     /// Specify the CSV delimiter.
     #[arg(long, value_name = "DELIMITER", default_value = ",")]
     delimiter: char,
+    /// Specify whether the fields are separated by runs of whitespace (overrides `--delimiter`).
+    #[arg(long)]
+    whitespace: bool,
+    /// Specify the CSV quote character.
+    #[arg(long, value_name = "QUOTE", default_value = "\"")]
+    quote: char,
+    /// Specify whether to keep quotes as part of the fields.
+    #[arg(long)]
+    disable_quoting: bool,
+    /// Specify whether to add the vertices referenced by edges but missing from the vertex files.
+    #[arg(long)]
+    auto_add_missing_vertices: bool,
     /// Specify the number of graph building threads.
     #[arg(short, long, value_name = "THREADS", default_value = "8")]
     threads: usize,
@@ -27,9 +39,25 @@ pub struct SerializeArgs {
 pub fn serialize(args: SerializeArgs) {
     println!("{:#?}", args);
     let schema = Schema::import_json(args.schema).unwrap();
+    let delimiter = if args.whitespace {
+        Delimiter::Whitespace
+    } else {
+        Delimiter::Byte(args.delimiter as u8)
+    };
+    let format = CsvFormat::default()
+        .delimiter(delimiter)
+        .quote(args.quote as u8)
+        .quoting(!args.disable_quoting);
+    let options = CsvOptions::new(format).auto_add_missing_vertices(args.auto_add_missing_vertices);
     let start = Instant::now();
     let graph =
-        LabeledGraph::from_csv(args.input, &schema, args.delimiter as u8, args.threads).unwrap();
+        LabeledGraph::from_csv_with_progress(args.input, &schema, options, args.threads, |p| {
+            println!(
+                "read {} rows of {} ({} rows in total)",
+                p.rows, p.label_name, p.total_rows
+            )
+        })
+        .unwrap();
     let time = start.elapsed().as_secs_f64();
     println!("graph building time: {time} s");
 
@@ -2,7 +2,7 @@ use ahash::{HashMap, HashMapExt};
 use itertools::Itertools;
 
 use crate::common::{BucketId, DefaultVertexId, LocalBucketMap, VertexId};
-use crate::factorization::ColumnRef;
+use crate::factorization::{ColumnRef, DEFAULT_PAR_COUNT_THRESHOLD};
 
 type BucketValuesMap = HashMap<BucketId, Vec<(DefaultVertexId, usize)>>;
 
@@ -11,24 +11,56 @@ pub struct GreedyBinner {
     budget: usize,
     current_num_buckets: usize,
     bucket_map: LocalBucketMap,
+    par_count_threshold: usize,
 }
 
+/// The default fraction of the budget spent on the initial equal-size buckets.
+pub const DEFAULT_INITIAL_RATIO: f64 = 0.5;
+
 impl GreedyBinner {
     pub fn new<I>(budget: usize, vertices: I) -> Self
     where
         I: IntoIterator<Item = DefaultVertexId> + Clone,
     {
-        let initial_budget = budget.div_ceil(2);
-        let budget = budget - initial_budget;
+        Self::with_initial_ratio(budget, vertices, DEFAULT_INITIAL_RATIO)
+    }
+
+    /// Start from equal-size buckets taking the `ratio` (in `(0, 1]`) of the budget, rounded up,
+    /// and leave the rest to the incremental splits of [`Self::update`].
+    pub fn with_initial_ratio<I>(budget: usize, vertices: I, ratio: f64) -> Self
+    where
+        I: IntoIterator<Item = DefaultVertexId> + Clone,
+    {
+        assert!(ratio > 0. && ratio <= 1., "invalid initial ratio: {ratio}");
+        let initial_budget = ((budget as f64 * ratio).ceil() as usize).min(budget);
         let bucket_map = build_initial_bucket_map(initial_budget, vertices);
-        let current_num_buckets = initial_budget;
+        Self::from_existing(bucket_map, budget - initial_budget)
+    }
+
+    /// Resume binning from `bucket_map` with `additional_budget` more buckets to split off.
+    ///
+    /// The result refines `bucket_map`: existing buckets are only split, never merged, and at most
+    /// `additional_budget` buckets are added. Resuming from a finished map is in general *not* the
+    /// same as a fresh binner with the total budget, which would have split its initial buckets
+    /// differently. Only resuming from the initial map of [`Self::new`], the equal-size buckets
+    /// with half of the budget, behaves exactly like a fresh binner given the same updates.
+    pub fn from_existing(bucket_map: LocalBucketMap, additional_budget: usize) -> Self {
+        let current_num_buckets = bucket_map.values().max().map_or(0, |max| max + 1);
         Self {
-            budget,
+            budget: additional_budget,
             current_num_buckets,
             bucket_map,
+            par_count_threshold: DEFAULT_PAR_COUNT_THRESHOLD,
         }
     }
 
+    /// Count the neighbors of the items at least `threshold` long in parallel, see
+    /// [`crate::count_valid_values`].
+    pub fn par_count_threshold(mut self, threshold: usize) -> Self {
+        self.par_count_threshold = threshold;
+        self
+    }
+
     pub fn should_finish(&self) -> bool {
         self.budget == 0
     }
@@ -39,7 +71,12 @@ impl GreedyBinner {
         if self.budget == 0 {
             return;
         }
-        let count_map = compute_count_map(self.bucket_map.len(), vertex_column, neighbor_column);
+        let count_map = compute_count_map(
+            self.bucket_map.len(),
+            vertex_column,
+            neighbor_column,
+            self.par_count_threshold,
+        );
         // Ignore PK column
         if count_map.values().filter(|c| **c == 1).count() as f64
             > PK_THRESHOLD * count_map.len() as f64
@@ -75,7 +112,11 @@ fn split_buckets(
     bucket_values_map: &BucketValuesMap,
     bucket_split_num_map: &HashMap<BucketId, usize>,
 ) -> usize {
-    for (bucket_id, bucket_split_num) in bucket_split_num_map {
+    // Split in the order of bucket ids so that the new bucket ids are deterministic.
+    for (bucket_id, bucket_split_num) in bucket_split_num_map
+        .iter()
+        .sorted_unstable_by_key(|(bucket_id, _)| **bucket_id)
+    {
         let bucket_values = bucket_values_map.get(bucket_id).unwrap();
         let bucket_values_counts = bucket_values
             .iter()
@@ -127,7 +168,7 @@ fn compute_bucket_split_num(
         .sorted_unstable_by(|b1, b2| {
             let v1 = bucket_count_mean_variance.get(b1).unwrap().2;
             let v2 = bucket_count_mean_variance.get(b2).unwrap().2;
-            v2.total_cmp(&v1)
+            v2.total_cmp(&v1).then(b1.cmp(b2))
         })
         .collect_vec();
 
@@ -163,10 +204,13 @@ fn compute_count_map(
     vertex_count: usize,
     vertex_column: &ColumnRef,
     neighbor_column: &ColumnRef,
+    par_count_threshold: usize,
 ) -> HashMap<DefaultVertexId, usize> {
     let mut count_map = HashMap::with_capacity(vertex_count);
-    for (vertices, neighbors) in vertex_column.items().zip(neighbor_column.items()) {
-        let multiplicity = neighbors.iter().filter(|v| v.is_valid()).count();
+    for (index, vertices) in vertex_column.items().enumerate() {
+        let multiplicity = neighbor_column
+            .count_valid_adaptive(index, par_count_threshold)
+            .unwrap();
         if multiplicity == 0 {
             continue;
         }
@@ -216,6 +260,9 @@ where
     I: IntoIterator<Item = DefaultVertexId> + Clone,
 {
     let vertex_count = vertices.clone().into_iter().count();
+    if vertex_count == 0 {
+        return LocalBucketMap::default();
+    }
     let big_bucket_count = vertex_count % budget;
     let big_bucket_size = vertex_count.div_ceil(budget);
     let small_bucket_size = vertex_count / budget;
@@ -254,6 +301,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::factorization::{ColumnGroup, SingleColumnGroup};
 
     #[test]
     fn test_build_initial_bucket_map() {
@@ -371,6 +419,103 @@ mod tests {
         assert_eq!(bucket_map.values().unique().count(), 5);
     }
 
+    fn build_columns(counts: impl Fn(DefaultVertexId) -> usize) -> (ColumnRef, ColumnRef) {
+        let mut vertex_group = SingleColumnGroup::single();
+        let mut neighbor_group = SingleColumnGroup::multiple();
+        for v in 0..40 {
+            vertex_group.extend_one(v);
+            neighbor_group.extend(0..counts(v));
+        }
+        let vertex_column = ColumnGroup::from(vertex_group).get_column(0).unwrap();
+        let neighbor_column = ColumnGroup::from(neighbor_group).get_column(0).unwrap();
+        (vertex_column, neighbor_column)
+    }
+
+    #[test]
+    fn test_initial_ratio() {
+        // A few vertices with many neighbors among many with few.
+        let updates = [
+            build_columns(|v| if v % 10 == 0 { 100 + v } else { v % 3 + 1 }),
+            build_columns(|v| if v < 4 { 50 * (v + 1) } else { v % 5 + 1 }),
+        ];
+        let bin = |mut binner: GreedyBinner| {
+            for (vertex_column, neighbor_column) in &updates {
+                binner.update(vertex_column, neighbor_column);
+            }
+            binner.finish()
+        };
+
+        assert_eq!(
+            bin(GreedyBinner::with_initial_ratio(16, 0..40, 0.5)),
+            bin(GreedyBinner::new(16, 0..40))
+        );
+        let half = bin(GreedyBinner::with_initial_ratio(16, 0..40, 0.5));
+        let low = bin(GreedyBinner::with_initial_ratio(16, 0..40, 0.125));
+        let full = bin(GreedyBinner::with_initial_ratio(16, 0..40, 1.));
+        assert_ne!(low, half);
+        assert_eq!(full, build_initial_bucket_map(16, 0..40));
+        for bucket_map in [&half, &low, &full] {
+            assert_eq!(bucket_map.len(), 40);
+            assert!(bucket_map.values().unique().count() <= 16);
+        }
+    }
+
+    #[test]
+    fn test_from_existing() {
+        let updates = [
+            build_columns(|v| v * v % 17 + 1),
+            build_columns(|v| (v % 7 + 1) * (v % 3 + 1)),
+        ];
+        let bin = |mut binner: GreedyBinner| {
+            for (vertex_column, neighbor_column) in &updates {
+                binner.update(vertex_column, neighbor_column);
+            }
+            binner.finish()
+        };
+
+        let fresh = bin(GreedyBinner::new(16, 0..40));
+        let initial = build_initial_bucket_map(8, 0..40);
+        let resumed = bin(GreedyBinner::from_existing(initial, 8));
+        assert_eq!(resumed, fresh);
+        assert!(fresh.values().unique().count() > 8);
+    }
+
+    #[test]
+    fn test_from_finished() {
+        let updates = [
+            build_columns(|v| v * v % 17 + 1),
+            build_columns(|v| (v % 7 + 1) * (v % 3 + 1)),
+        ];
+        let bin = |mut binner: GreedyBinner| {
+            for (vertex_column, neighbor_column) in &updates {
+                binner.update(vertex_column, neighbor_column);
+            }
+            binner.finish()
+        };
+
+        let coarse = bin(GreedyBinner::new(8, 0..40));
+        let num_coarse_buckets = coarse.values().unique().count();
+        let resumed = bin(GreedyBinner::from_existing(coarse.clone(), 8));
+        let num_resumed_buckets = resumed.values().unique().count();
+        assert!(num_resumed_buckets > num_coarse_buckets);
+        assert!(num_resumed_buckets <= num_coarse_buckets + 8);
+        assert_eq!(bin(GreedyBinner::from_existing(coarse.clone(), 8)), resumed);
+        // Every resumed bucket lies within a single coarse bucket.
+        let parents = resumed
+            .iter()
+            .map(|(v, bucket_id)| (*bucket_id, coarse[v]))
+            .into_group_map();
+        assert!(parents
+            .values()
+            .all(|coarse_ids| coarse_ids.iter().all_equal()));
+        // The vertices kept in their coarse buckets keep the bucket ids.
+        let max_coarse_id = *coarse.values().max().unwrap();
+        assert!(resumed
+            .iter()
+            .filter(|(_, bucket_id)| **bucket_id <= max_coarse_id)
+            .all(|(v, bucket_id)| coarse[v] == *bucket_id));
+    }
+
     // #[test]
     // fn test_greedy_binning() {
     //     let mut binner = GreedyBinner::new(8, 1..=13);
@@ -1,3 +1,5 @@
 mod greedy;
+mod stats;
 
-pub use greedy::GreedyBinner;
+pub(crate) use greedy::GreedyBinner;
+pub use stats::{BucketMapStats, BucketSizeStats};
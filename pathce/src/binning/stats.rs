@@ -0,0 +1,95 @@
+use itertools::Itertools;
+
+use crate::common::{GlobalBucketMap, LabelId};
+
+/// Statistics of the populations of the buckets of a vertex label.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BucketSizeStats {
+    pub num_buckets: usize,
+    pub min: usize,
+    pub median: usize,
+    pub p95: usize,
+    pub max: usize,
+    /// The Gini coefficient of the bucket sizes, where 0 means all buckets have the same size.
+    pub gini: f64,
+}
+
+impl BucketSizeStats {
+    fn from_sizes(mut sizes: Vec<usize>) -> Self {
+        if sizes.is_empty() {
+            return Self::default();
+        }
+        sizes.sort_unstable();
+        let n = sizes.len();
+        // Nearest-rank percentile
+        let percentile = |p: f64| sizes[((p * n as f64).ceil() as usize).clamp(1, n) - 1];
+        let total = sizes.iter().sum::<usize>() as f64;
+        let gini = sizes
+            .iter()
+            .enumerate()
+            .map(|(i, size)| ((2 * i + 1) as f64 - n as f64) * *size as f64)
+            .sum::<f64>()
+            / (n as f64 * total);
+        Self {
+            num_buckets: n,
+            min: sizes[0],
+            median: percentile(0.5),
+            p95: percentile(0.95),
+            max: sizes[n - 1],
+            gini,
+        }
+    }
+}
+
+pub trait BucketMapStats {
+    /// Compute the statistics of the bucket sizes of `label`, or `None` if `label` is not binned.
+    fn bucket_size_stats(&self, label: LabelId) -> Option<BucketSizeStats>;
+}
+
+impl BucketMapStats for GlobalBucketMap {
+    fn bucket_size_stats(&self, label: LabelId) -> Option<BucketSizeStats> {
+        let bucket_map = self.get(&label)?;
+        let sizes = bucket_map
+            .values()
+            .copied()
+            .counts()
+            .into_values()
+            .collect::<Vec<_>>();
+        Some(BucketSizeStats::from_sizes(sizes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ahash::HashMapExt;
+
+    use super::*;
+    use crate::common::LocalBucketMap;
+
+    #[test]
+    fn test_bucket_size_stats() {
+        let mut global_bucket_map = GlobalBucketMap::new();
+        // Buckets 0..4 have sizes 1, 2, 3 and 10, respectively.
+        let local_bucket_map: LocalBucketMap = [1, 2, 3, 10]
+            .into_iter()
+            .enumerate()
+            .flat_map(|(bucket_id, size)| (0..size).map(move |i| (bucket_id * 100 + i, bucket_id)))
+            .collect();
+        global_bucket_map.insert(0, local_bucket_map);
+        global_bucket_map.insert(1, (0..8).map(|v| (v, v % 4)).collect());
+
+        let stats = global_bucket_map.bucket_size_stats(0).unwrap();
+        assert_eq!(stats.num_buckets, 4);
+        assert_eq!(stats.min, 1);
+        assert_eq!(stats.median, 2);
+        assert_eq!(stats.p95, 10);
+        assert_eq!(stats.max, 10);
+        assert!((stats.gini - 0.4375).abs() < 1e-9);
+
+        let stats = global_bucket_map.bucket_size_stats(1).unwrap();
+        assert_eq!((stats.min, stats.median, stats.max), (2, 2, 2));
+        assert_eq!(stats.gini, 0.0);
+
+        assert!(global_bucket_map.bucket_size_stats(2).is_none());
+    }
+}
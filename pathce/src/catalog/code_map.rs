@@ -0,0 +1,183 @@
+use std::collections::hash_map::Entry;
+
+use ahash::HashMap;
+use murmur3::murmur3_x64_128;
+use serde::{Deserialize, Serialize};
+
+use crate::common::LabelId;
+
+/// A map from pattern encodings to label ids.
+///
+/// By default, the full encodings are used as keys. When `hashed` is set, the map is keyed by the
+/// 128-bit murmur3 hash of the encoding instead, and the full encoding is kept only for keys whose
+/// hash collides with an existing one. Since the full encoding is not stored, the caller must
+/// provide a way to recover the encoding of a label (e.g., by encoding the pattern stored in the
+/// catalog), which is used to verify lookups.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(super) struct CodeMap {
+    hashed: bool,
+    hashes: HashMap<u128, LabelId>,
+    codes: HashMap<Vec<u8>, LabelId>,
+}
+
+fn hash_code(code: &[u8]) -> u128 {
+    murmur3_x64_128(&mut &code[..], 0).unwrap()
+}
+
+impl CodeMap {
+    pub(super) fn is_empty(&self) -> bool {
+        self.hashes.is_empty() && self.codes.is_empty()
+    }
+
+    pub(super) fn is_hashed(&self) -> bool {
+        self.hashed
+    }
+
+    pub(super) fn set_hashed(&mut self, hashed: bool) {
+        assert!(self.is_empty(), "cannot change the keys of a non-empty map");
+        self.hashed = hashed;
+    }
+
+    /// Get the label id of `code`. `code_of` returns the encoding of an existing label id.
+    pub(super) fn get<F>(&self, code: &[u8], code_of: F) -> Option<LabelId>
+    where
+        F: Fn(LabelId) -> Vec<u8>,
+    {
+        if !self.hashed {
+            return self.codes.get(code).copied();
+        }
+        self.get_with_hash(hash_code(code), code, code_of)
+    }
+
+    /// Insert `code` with the given label id. If `code` already exists, the existing label id is
+    /// returned as an error. `code_of` returns the encoding of an existing label id.
+    pub(super) fn insert<F>(
+        &mut self,
+        code: Vec<u8>,
+        label_id: LabelId,
+        code_of: F,
+    ) -> Result<(), LabelId>
+    where
+        F: Fn(LabelId) -> Vec<u8>,
+    {
+        if !self.hashed {
+            return match self.codes.entry(code) {
+                Entry::Occupied(entry) => Err(*entry.get()),
+                Entry::Vacant(entry) => {
+                    entry.insert(label_id);
+                    Ok(())
+                }
+            };
+        }
+        self.insert_with_hash(hash_code(&code), code, label_id, code_of)
+    }
+
+    /// Change the label id of an existing `code`, and return whether it exists. `code_of` returns
+    /// the encoding of an existing label id.
+    pub(super) fn set<F>(&mut self, code: &[u8], label_id: LabelId, code_of: F) -> bool
+    where
+        F: Fn(LabelId) -> Vec<u8>,
+    {
+        if self.hashed {
+            if let Some(existing) = self.hashes.get_mut(&hash_code(code)) {
+                if code_of(*existing) == code {
+                    *existing = label_id;
+                    return true;
+                }
+            }
+        }
+        match self.codes.get_mut(code) {
+            Some(existing) => {
+                *existing = label_id;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn get_with_hash<F>(&self, hash: u128, code: &[u8], code_of: F) -> Option<LabelId>
+    where
+        F: Fn(LabelId) -> Vec<u8>,
+    {
+        let label_id = *self.hashes.get(&hash)?;
+        if code_of(label_id) == code {
+            Some(label_id)
+        } else {
+            self.codes.get(code).copied()
+        }
+    }
+
+    fn insert_with_hash<F>(
+        &mut self,
+        hash: u128,
+        code: Vec<u8>,
+        label_id: LabelId,
+        code_of: F,
+    ) -> Result<(), LabelId>
+    where
+        F: Fn(LabelId) -> Vec<u8>,
+    {
+        if let Some(existing) = self.get_with_hash(hash, &code, &code_of) {
+            return Err(existing);
+        }
+        match self.hashes.entry(hash) {
+            Entry::Occupied(_) => {
+                // Hash collision
+                self.codes.insert(code, label_id);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(label_id);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_map() {
+        let codes = [vec![1, 2, 3], vec![4, 5], vec![6]];
+        let code_of = |label_id: LabelId| codes[label_id as usize].clone();
+        for hashed in [false, true] {
+            let mut map = CodeMap::default();
+            map.set_hashed(hashed);
+            assert_eq!(map.insert(codes[0].clone(), 0, code_of), Ok(()));
+            assert_eq!(map.insert(codes[1].clone(), 1, code_of), Ok(()));
+            assert_eq!(map.insert(codes[0].clone(), 2, code_of), Err(0));
+            assert_eq!(map.get(&codes[0], code_of), Some(0));
+            assert_eq!(map.get(&codes[1], code_of), Some(1));
+            assert_eq!(map.get(&codes[2], code_of), None);
+            assert!(map.set(&codes[1], 1 + LabelId::MAX / 2, |_| codes[1].clone()));
+            assert_eq!(
+                map.get(&codes[1], |_| codes[1].clone()),
+                Some(1 + LabelId::MAX / 2)
+            );
+            assert!(!map.set(&codes[2], 2, code_of));
+        }
+    }
+
+    #[test]
+    fn test_code_map_collision() {
+        let codes = [vec![1, 2, 3], vec![4, 5], vec![6]];
+        let code_of = |label_id: LabelId| codes[label_id as usize].clone();
+        let mut map = CodeMap::default();
+        map.set_hashed(true);
+        // Force all codes to have the same hash
+        for (label_id, code) in codes.iter().enumerate().take(2) {
+            let result = map.insert_with_hash(0, code.clone(), label_id as LabelId, code_of);
+            assert_eq!(result, Ok(()));
+        }
+        assert_eq!(map.hashes.len(), 1);
+        assert_eq!(map.codes.len(), 1);
+        assert_eq!(
+            map.insert_with_hash(0, codes[1].clone(), 2, code_of),
+            Err(1)
+        );
+        assert_eq!(map.get_with_hash(0, &codes[0], code_of), Some(0));
+        assert_eq!(map.get_with_hash(0, &codes[1], code_of), Some(1));
+        assert_eq!(map.get_with_hash(0, &codes[2], code_of), None);
+    }
+}
@@ -1,27 +1,48 @@
 use std::cell::Cell;
-use std::collections::hash_map::Entry;
+use std::collections::BTreeMap;
 use std::fmt::Display;
 use std::fs::{create_dir_all, exists, remove_file, File};
-use std::io::{BufReader, BufWriter};
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::Path;
+use std::sync::Arc;
 
 use ahash::HashMap;
-use duckdb::Connection;
+use duckdb::arrow::array::{ArrayRef, UInt16Array, UInt64Array};
+use duckdb::arrow::record_batch::RecordBatch;
+use duckdb::{Connection, OptionalExt};
 use itertools::Itertools;
 use log::trace;
+use murmur3::murmur3_x64_128;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
-use super::Catalog;
-use crate::common::{LabelId, LocalBucketMap, TagId};
-use crate::error::{GCardError, GCardResult};
-use crate::pattern::{GeneralPattern, GraphPattern, PathPattern};
-use crate::statistics::{PathStatistics, StarStatistics};
+use super::code_map::CodeMap;
+use super::{lookup_quantile, Catalog};
+use crate::catalog_builder::CatalogBuilder;
+use crate::common::{BucketId, DefaultVertexId, EdgeDirection, LabelId, LocalBucketMap, TagId};
+use crate::error::{CatalogError, GCardResult};
+use crate::pattern::{EncodedPattern, GeneralPattern, GraphPattern, PathPattern};
+use crate::statistics::{JointStatistics, PathStatistics, RequiredStatistics, StarStatistics};
 
 const METADATA: &str = "metadata.bincode";
+/// The header of the metadata file, followed by the version of the layout of [`Metadata`]. Files
+/// without it have the unversioned layout of [`LegacyMetadata`], which starts with the number of
+/// paths, and the header read as such a number is far larger than any catalog.
+const METADATA_MAGIC: [u8; 8] = *b"PATHCEMD";
+/// The version of the layout of [`Metadata`], which must be bumped whenever its fields change.
+/// Version 1 keys the encodings by [`CodeMap`], and adds the star center ranks and the degree
+/// quantiles. Version 2 adds the joint paths.
+const METADATA_VERSION: u32 = 2;
 const DATA: &str = "data.db";
 const DATA_WAL: &str = "data.db.wal";
 const PATH_STATS: &str = "path_stats.bincode";
 const STAR_STATS: &str = "star_stats.bincode";
+/// The maximum number of rows of a data chunk appended to DuckDB.
+const CHUNK_SIZE: usize = 2048;
+/// The number of statistics staged in memory at a time by [`DuckCatalog::add_paths`] and
+/// [`DuckCatalog::add_stars`].
+const STAGING_BATCH_SIZE: usize = 256;
 
 #[derive(Debug)]
 pub struct DuckCatalog {
@@ -30,10 +51,66 @@ pub struct DuckCatalog {
     next_table_id: Cell<usize>,
     path_statistics: Vec<PathStatistics>,
     star_statistics: Vec<StarStatistics>,
+    path_statistics_index: HashMap<LabelId, usize>,
+    star_statistics_index: HashMap<LabelId, usize>,
+    keep_empty_stats: bool,
+    /// Whether the statistics are queried from the attached database file of an imported catalog.
+    attached: bool,
+    /// The hash of the files an imported catalog was imported from, computed on import and
+    /// cleared when the statistics are changed, see [`DuckCatalog::content_hash`].
+    content_hash: Option<u128>,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 struct Metadata {
+    paths: Vec<PathPattern>,
+    stars: Vec<GeneralPattern>,
+    star_center_ranks: Vec<TagId>,
+    path_label_map: CodeMap,
+    star_label_map: CodeMap,
+    edge_count_map: HashMap<LabelId, usize>,
+    /// Evenly spaced quantiles of the degrees along each edge label, from the minimum to the
+    /// maximum. Empty if migrated from [`LegacyMetadata`], which has no quantiles.
+    degree_quantile_map: HashMap<(LabelId, EdgeDirection), Vec<u64>>,
+    /// The length-2 paths with joint statistics, whose label ids are their indices.
+    joint_paths: Vec<PathPattern>,
+    joint_label_map: CodeMap,
+}
+
+/// The layout of [`Metadata`] of version 1, which has no joint paths.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MetadataV1 {
+    paths: Vec<PathPattern>,
+    stars: Vec<GeneralPattern>,
+    star_center_ranks: Vec<TagId>,
+    path_label_map: CodeMap,
+    star_label_map: CodeMap,
+    edge_count_map: HashMap<LabelId, usize>,
+    degree_quantile_map: HashMap<(LabelId, EdgeDirection), Vec<u64>>,
+}
+
+impl From<MetadataV1> for Metadata {
+    fn from(v1: MetadataV1) -> Self {
+        let mut joint_label_map = CodeMap::default();
+        joint_label_map.set_hashed(v1.path_label_map.is_hashed());
+        Metadata {
+            paths: v1.paths,
+            stars: v1.stars,
+            star_center_ranks: v1.star_center_ranks,
+            path_label_map: v1.path_label_map,
+            star_label_map: v1.star_label_map,
+            edge_count_map: v1.edge_count_map,
+            degree_quantile_map: v1.degree_quantile_map,
+            joint_paths: vec![],
+            joint_label_map,
+        }
+    }
+}
+
+/// The unversioned layout of [`Metadata`], which is keyed by the full encodings and has the center
+/// rank of each star in its key.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LegacyMetadata {
     paths: Vec<PathPattern>,
     stars: Vec<GeneralPattern>,
     path_label_map: HashMap<Vec<u8>, LabelId>,
@@ -41,6 +118,139 @@ struct Metadata {
     edge_count_map: HashMap<LabelId, usize>,
 }
 
+impl From<LegacyMetadata> for Metadata {
+    fn from(legacy: LegacyMetadata) -> Self {
+        let mut metadata = Metadata {
+            star_center_ranks: vec![0; legacy.stars.len()],
+            paths: legacy.paths,
+            stars: legacy.stars,
+            edge_count_map: legacy.edge_count_map,
+            ..Default::default()
+        };
+        // The maps are not hashed, so the encodings of existing labels are never needed.
+        for (code, label_id) in legacy.path_label_map {
+            metadata
+                .path_label_map
+                .insert(code, label_id, |_| unreachable!())
+                .unwrap();
+        }
+        for ((center_rank, code), label_id) in legacy.star_label_map {
+            metadata.star_center_ranks[label_index(label_id)] = center_rank;
+            metadata
+                .star_label_map
+                .insert(star_code(center_rank, &code), label_id, |_| unreachable!())
+                .unwrap();
+        }
+        metadata
+    }
+}
+
+/// Read the metadata file, migrating the unversioned layout and older versions.
+fn read_metadata(path: &Path) -> GCardResult<Metadata> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut magic = [0; METADATA_MAGIC.len()];
+    reader.read_exact(&mut magic)?;
+    if magic != METADATA_MAGIC {
+        let legacy: LegacyMetadata = bincode::deserialize_from(magic.chain(reader))?;
+        return Ok(legacy.into());
+    }
+    let version: u32 = bincode::deserialize_from(&mut reader)?;
+    match version {
+        METADATA_VERSION => Ok(bincode::deserialize_from(reader)?),
+        1 => {
+            let v1: MetadataV1 = bincode::deserialize_from(reader)?;
+            Ok(v1.into())
+        }
+        _ => Err(CatalogError::UnsupportedMetadataVersion(version).into()),
+    }
+}
+
+fn write_metadata(path: &Path, metadata: &Metadata) -> GCardResult<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(&METADATA_MAGIC)?;
+    bincode::serialize_into(&mut writer, &METADATA_VERSION)?;
+    bincode::serialize_into(writer, metadata)?;
+    Ok(())
+}
+
+/// Session settings of the DuckDB connection of a catalog.
+#[derive(Debug, Clone)]
+pub struct DuckConfig {
+    max_expression_depth: usize,
+    settings: Vec<(String, String)>,
+}
+
+impl Default for DuckConfig {
+    fn default() -> Self {
+        Self {
+            max_expression_depth: 9999999,
+            settings: vec![],
+        }
+    }
+}
+
+impl DuckConfig {
+    /// Set `max_expression_depth`. The default is very high to avoid errors when exporting the
+    /// database, and when estimating large (especially cyclic) patterns.
+    pub fn max_expression_depth(mut self, depth: usize) -> Self {
+        self.max_expression_depth = depth;
+        self
+    }
+
+    /// Add a setting applied as `set {name} = {value}`. The value is inserted into the statement
+    /// verbatim, so string values must be quoted.
+    pub fn setting(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.settings.push((name.into(), value.into()));
+        self
+    }
+
+    fn open_in_memory(&self) -> GCardResult<Connection> {
+        let conn = Connection::open_in_memory()?;
+        let sql = format!("set max_expression_depth = {}", self.max_expression_depth);
+        execute_sql(&conn, &sql)?;
+        for (name, value) in &self.settings {
+            let sql = format!("set {name} = {value}");
+            execute_sql(&conn, &sql)?;
+        }
+        Ok(conn)
+    }
+}
+
+/// Return the index of the path or star with the given label id in the metadata.
+fn label_index(label_id: LabelId) -> usize {
+    if label_id > LabelId::MAX / 2 {
+        (label_id - LabelId::MAX / 2 - 1) as usize
+    } else {
+        label_id as usize
+    }
+}
+
+/// Return the key of a star in the code map, i.e., its encoding prefixed by its center rank.
+fn star_code(center_rank: TagId, code: &[u8]) -> Vec<u8> {
+    let mut star_code = Vec::with_capacity(code.len() + 1);
+    star_code.push(center_rank);
+    star_code.extend_from_slice(code);
+    star_code
+}
+
+impl Metadata {
+    fn path_code(&self, label_id: LabelId) -> Vec<u8> {
+        self.paths[label_index(label_id)].encode().into_bytes()
+    }
+
+    fn star_code(&self, label_id: LabelId) -> Vec<u8> {
+        let index = label_index(label_id);
+        star_code(
+            self.star_center_ranks[index],
+            self.stars[index].encode().as_bytes(),
+        )
+    }
+
+    fn joint_code(&self, label_id: LabelId) -> Vec<u8> {
+        self.joint_paths[label_id as usize].encode().into_bytes()
+    }
+}
+
 impl Display for DuckCatalog {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let metadata = &self.metadata;
@@ -51,18 +261,126 @@ impl Display for DuckCatalog {
     }
 }
 
+/// Stage the non-zero cells of path statistics as the rows of its table, or return `None` if the
+/// statistics are empty.
+fn stage_path_rows(stats: &PathStatistics) -> GCardResult<Option<RecordBatch>> {
+    let mut s = vec![];
+    let mut t = vec![];
+    let mut mode_s = vec![];
+    let mut mode_t = vec![];
+    let mut count = vec![];
+    for (i, ((max_degree_s, max_degree_t), counts)) in stats
+        .start_max_degree
+        .iter()
+        .zip_eq(&stats.end_max_degree)
+        .zip_eq(&stats.count)
+        .enumerate()
+    {
+        for (j, ((max_degree_s, max_degree_t), c)) in max_degree_s
+            .iter()
+            .zip_eq(max_degree_t.iter())
+            .zip_eq(counts.iter())
+            .enumerate()
+            .filter(|(_, (_, c))| **c != 0)
+        {
+            s.push(i as u16);
+            t.push(j as u16);
+            mode_s.push(*max_degree_s);
+            mode_t.push(*max_degree_t);
+            count.push(*c);
+        }
+    }
+    if count.is_empty() {
+        return Ok(None);
+    }
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt16Array::from(s)),
+        Arc::new(UInt16Array::from(t)),
+        Arc::new(UInt64Array::from(mode_s)),
+        Arc::new(UInt64Array::from(mode_t)),
+        Arc::new(UInt64Array::from(count)),
+    ];
+    record_batch(["s", "t", "_mode_s", "_mode_t", "_count"], columns).map(Some)
+}
+
+/// Stage the non-zero cells of joint statistics as the rows of its table. Unlike other statistics,
+/// empty joint statistics are kept as empty tables.
+fn stage_joint_rows(stats: &JointStatistics) -> GCardResult<Option<RecordBatch>> {
+    let mut s = vec![];
+    let mut t = vec![];
+    let mut count = vec![];
+    for (i, counts) in stats.count.iter().enumerate() {
+        for (j, c) in counts.iter().enumerate().filter(|(_, c)| **c != 0) {
+            s.push(i as u16);
+            t.push(j as u16);
+            count.push(*c);
+        }
+    }
+    if count.is_empty() {
+        return Ok(None);
+    }
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt16Array::from(s)),
+        Arc::new(UInt16Array::from(t)),
+        Arc::new(UInt64Array::from(count)),
+    ];
+    record_batch(["s", "t", "_count"], columns).map(Some)
+}
+
+/// Stage star statistics as the rows of its table, or return `None` if the statistics are empty.
+fn stage_star_rows(stats: &StarStatistics) -> GCardResult<Option<RecordBatch>> {
+    if stats.count.iter().all(|count| *count == 0) {
+        return Ok(None);
+    }
+    let ids = (0..stats.count.len()).map(|i| i as u16).collect_vec();
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt16Array::from(ids)),
+        Arc::new(UInt64Array::from(stats.max_degree.clone())),
+        Arc::new(UInt64Array::from(stats.count.clone())),
+    ];
+    record_batch(["id", "_mode", "_count"], columns).map(Some)
+}
+
+/// Remove the statistics of `label_id` from those kept in memory, if any, updating the index of
+/// the statistics moved into its place.
+fn remove_stats<T>(stats: &mut Vec<T>, index: &mut HashMap<LabelId, usize>, label_id: LabelId) {
+    let Some(i) = index.remove(&label_id) else {
+        return;
+    };
+    stats.swap_remove(i);
+    if let Some(moved) = index.values_mut().find(|j| **j == stats.len()) {
+        *moved = i;
+    }
+}
+
+fn record_batch<const N: usize>(
+    names: [&str; N],
+    columns: Vec<ArrayRef>,
+) -> GCardResult<RecordBatch> {
+    let batch = RecordBatch::try_from_iter(names.into_iter().zip_eq(columns))
+        .map_err(CatalogError::Staging)?;
+    Ok(batch)
+}
+
 fn execute_sql(conn: &Connection, sql: &str) -> GCardResult<()> {
     trace!("{sql}");
     conn.execute_batch(sql)?;
     Ok(())
 }
 
+fn hash_files(first: &Path, second: &Path) -> GCardResult<u128> {
+    let first = BufReader::new(File::open(first)?);
+    let second = BufReader::new(File::open(second)?);
+    Ok(murmur3_x64_128(&mut first.chain(second), 0)?)
+}
+
 impl DuckCatalog {
     pub fn init() -> GCardResult<Self> {
-        let conn = Connection::open_in_memory()?;
-        // To avoid the error when exporting the database
-        let sql = "set max_expression_depth = 9999999";
-        execute_sql(&conn, sql)?;
+        Self::init_with_config(&DuckConfig::default())
+    }
+
+    pub fn init_with_config(config: &DuckConfig) -> GCardResult<Self> {
+        let conn = config.open_in_memory()?;
         let next_table_id = Cell::new(0);
         let metadata = Default::default();
         let ret = Self {
@@ -71,27 +389,55 @@ impl DuckCatalog {
             next_table_id,
             path_statistics: Vec::new(),
             star_statistics: Vec::new(),
+            path_statistics_index: HashMap::default(),
+            star_statistics_index: HashMap::default(),
+            keep_empty_stats: false,
+            attached: false,
+            content_hash: None,
         };
         // Add empty star
         let table_name = format!("star_{}", LabelId::MAX / 2);
-        ret.add_star_stats(&table_name, vec![], vec![])?;
+        ret.add_star_stats(&table_name, None)?;
         // Add empty path
         let table_name = format!("path_{}", LabelId::MAX / 2);
-        ret.add_path_stats(&table_name, vec![], vec![], vec![])?;
+        ret.add_path_stats(&table_name, None)?;
         Ok(ret)
     }
 
     pub fn import<P: AsRef<Path>>(dir: P) -> GCardResult<Self> {
+        Self::import_with_config(dir, &DuckConfig::default())
+    }
+
+    pub fn import_with_config<P: AsRef<Path>>(dir: P, config: &DuckConfig) -> GCardResult<Self> {
+        Self::import_inner(dir, config, false)
+    }
+
+    /// Import the catalog like [`Self::import`], but query the database file in place instead of
+    /// copying it into memory. This lowers the memory footprint at the cost of slower estimation,
+    /// and the estimates are the same. The imported catalog cannot be exported.
+    pub fn import_attached<P: AsRef<Path>>(dir: P) -> GCardResult<Self> {
+        Self::import_attached_with_config(dir, &DuckConfig::default())
+    }
+
+    pub fn import_attached_with_config<P: AsRef<Path>>(
+        dir: P,
+        config: &DuckConfig,
+    ) -> GCardResult<Self> {
+        Self::import_inner(dir, config, true)
+    }
+
+    fn import_inner<P: AsRef<Path>>(
+        dir: P,
+        config: &DuckConfig,
+        attached: bool,
+    ) -> GCardResult<Self> {
         let data_path = dir.as_ref().join(DATA);
         let metadata_path = dir.as_ref().join(METADATA);
 
-        let file = File::open(metadata_path)?;
-        let reader = BufReader::new(file);
-        let metadata = bincode::deserialize_from(reader)?;
+        let metadata = read_metadata(&metadata_path)?;
+        let content_hash = hash_files(&metadata_path, &data_path)?;
 
-        let conn = Connection::open_in_memory()?;
-        let sql = "set max_expression_depth = 9999999";
-        execute_sql(&conn, sql)?;
+        let conn = config.open_in_memory()?;
 
         let sql = format!(
             "attach '{}' as input (READ_ONLY)",
@@ -99,11 +445,17 @@ impl DuckCatalog {
         );
         execute_sql(&conn, &sql)?;
 
-        let sql = "copy from database input to memory";
-        execute_sql(&conn, sql)?;
+        if attached {
+            // Resolve the statistics tables in the attached database. Temporary views created
+            // during estimation are kept in the temp catalog, so the database stays read-only.
+            execute_sql(&conn, "use input")?;
+        } else {
+            let sql = "copy from database input to memory";
+            execute_sql(&conn, sql)?;
 
-        let sql = "detach input";
-        execute_sql(&conn, sql)?;
+            let sql = "detach input";
+            execute_sql(&conn, sql)?;
+        }
 
         Ok(Self {
             metadata,
@@ -111,10 +463,18 @@ impl DuckCatalog {
             next_table_id: Cell::new(0),
             path_statistics: Vec::new(),
             star_statistics: Vec::new(),
+            path_statistics_index: HashMap::default(),
+            star_statistics_index: HashMap::default(),
+            keep_empty_stats: false,
+            attached,
+            content_hash: Some(content_hash),
         })
     }
 
     pub fn export<P: AsRef<Path>>(&self, dir: P) -> GCardResult<()> {
+        if self.attached {
+            return Err(CatalogError::ExportAttached.into());
+        }
         let db_path = dir.as_ref().join(DATA);
         let wal_path = dir.as_ref().join(DATA_WAL);
         let metadata_path = dir.as_ref().join(METADATA);
@@ -138,9 +498,7 @@ impl DuckCatalog {
         let sql = "detach output";
         execute_sql(&self.conn, sql)?;
 
-        let file = File::create(metadata_path)?;
-        let writer = BufWriter::new(file);
-        bincode::serialize_into(writer, &self.metadata)?;
+        write_metadata(&metadata_path, &self.metadata)?;
 
         let file = File::create(path_stats_path)?;
         let writer = BufWriter::new(file);
@@ -153,131 +511,464 @@ impl DuckCatalog {
         Ok(())
     }
 
-    fn add_star_stats(
-        &self,
-        table_name: &str,
-        count: Vec<u64>,
-        max_degree: Vec<u64>,
-    ) -> GCardResult<()> {
+    /// Dump the metadata as pretty JSON for inspection. The encodings of patterns are hex-encoded
+    /// and the patterns are rendered as strings.
+    pub fn dump_metadata_json<P: AsRef<Path>>(&self, path: P) -> GCardResult<()> {
+        let metadata = &self.metadata;
+        let paths = metadata
+            .paths
+            .iter()
+            .map(|path| {
+                let code = path.encode();
+                json!({
+                    "label_id": self.get_path_label_id(&code),
+                    "code": code.to_string(),
+                    "pattern": path.to_string(),
+                })
+            })
+            .collect_vec();
+        let stars = metadata
+            .stars
+            .iter()
+            .zip_eq(&metadata.star_center_ranks)
+            .map(|(star, center_rank)| {
+                let code = star.encode();
+                json!({
+                    "label_id": self.get_star_label_id(*center_rank, &code),
+                    "center_rank": center_rank,
+                    "code": code.to_string(),
+                    "pattern": star.to_string(),
+                })
+            })
+            .collect_vec();
+        let joints = metadata
+            .joint_paths
+            .iter()
+            .map(|path| {
+                let code = path.encode();
+                json!({
+                    "label_id": self.get_joint_label_id(&code),
+                    "code": code.to_string(),
+                    "pattern": path.to_string(),
+                })
+            })
+            .collect_vec();
+        let edge_counts: BTreeMap<_, _> = metadata.edge_count_map.iter().collect();
+        let value = json!({
+            "hash_encodings": metadata.path_label_map.is_hashed(),
+            "paths": paths,
+            "stars": stars,
+            "joints": joints,
+            "edge_counts": edge_counts,
+        });
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, &value)?;
+        Ok(())
+    }
+
+    /// Materialize empty statistics as zero-row tables instead of skipping them.
+    ///
+    /// The label ids of empty statistics are still shifted past `LabelId::MAX / 2`, so estimation
+    /// is unaffected. This is only useful for inspecting the coverage of the catalog.
+    pub fn set_keep_empty_stats(&mut self, keep: bool) {
+        self.keep_empty_stats = keep;
+    }
+
+    /// Key the encodings of paths and stars by their 128-bit hashes to save memory.
+    ///
+    /// Lookups are verified against the encodings of the stored patterns, and the full encoding is
+    /// kept only on hash collisions. This must be set before adding any path or star.
+    pub fn set_hash_encodings(&mut self, enable: bool) -> GCardResult<()> {
+        let metadata = &mut self.metadata;
+        if !metadata.path_label_map.is_empty()
+            || !metadata.star_label_map.is_empty()
+            || !metadata.joint_label_map.is_empty()
+        {
+            return Err(CatalogError::NonEmpty.into());
+        }
+        metadata.path_label_map.set_hashed(enable);
+        metadata.star_label_map.set_hashed(enable);
+        metadata.joint_label_map.set_hashed(enable);
+        Ok(())
+    }
+
+    /// Create the table of star statistics and append the staged rows, if any.
+    fn add_star_stats(&self, table_name: &str, rows: Option<RecordBatch>) -> GCardResult<()> {
         let sql = format!("create table {table_name} (id uint16, _mode uint64, _count uint64)");
         execute_sql(&self.conn, &sql)?;
+        self.append_rows(table_name, rows)
+    }
+
+    /// Append the staged rows to the table in chunks that fit in a DuckDB data chunk.
+    fn append_rows(&self, table_name: &str, rows: Option<RecordBatch>) -> GCardResult<()> {
+        let Some(rows) = rows else {
+            return Ok(());
+        };
         let mut appender = self.conn.appender(table_name)?;
-        appender.append_rows(
-            max_degree
-                .into_iter()
-                .zip_eq(count)
-                .enumerate()
-                .map(|(i, (max_degree, count))| [i as u64, max_degree, count]),
-        )?;
+        for offset in (0..rows.num_rows()).step_by(CHUNK_SIZE) {
+            let len = CHUNK_SIZE.min(rows.num_rows() - offset);
+            appender.append_record_batch(rows.slice(offset, len))?;
+        }
         Ok(())
     }
 
     pub fn add_star(&mut self, star: StarStatistics) -> GCardResult<LabelId> {
-        let stats_cloned = star.clone();
-        let StarStatistics {
-            star,
-            center_rank,
-            count,
-            max_degree,
-        } = star;
-        let empty_stats = count.iter().all(|count| *count == 0);
+        let rows = stage_star_rows(&star)?;
+        self.add_star_staged(star, rows)
+    }
+
+    /// Add the statistics of stars, staging the rows of their tables in parallel.
+    ///
+    /// The connection is not thread-safe, so the tables are still written one at a time, but only
+    /// with bulk appends.
+    pub fn add_stars(&mut self, stars: Vec<StarStatistics>) -> GCardResult<Vec<LabelId>> {
+        let mut label_ids = Vec::with_capacity(stars.len());
+        for stars in &stars.into_iter().chunks(STAGING_BATCH_SIZE) {
+            let stars = stars.collect_vec();
+            let rows: Vec<_> = stars
+                .par_iter()
+                .map(stage_star_rows)
+                .collect::<GCardResult<_>>()?;
+            for (star, rows) in stars.into_iter().zip_eq(rows) {
+                label_ids.push(self.add_star_staged(star, rows)?);
+            }
+        }
+        Ok(label_ids)
+    }
+
+    fn add_star_staged(
+        &mut self,
+        stats: StarStatistics,
+        rows: Option<RecordBatch>,
+    ) -> GCardResult<LabelId> {
+        self.invalidate_content_hash();
+        let empty_stats = rows.is_none();
         let mut label_id = self.metadata.stars.len() as LabelId;
         if empty_stats {
             label_id += LabelId::MAX / 2 + 1;
         }
-        match self
-            .metadata
-            .star_label_map
-            .entry((center_rank, star.encode()))
-        {
-            Entry::Occupied(entry) => {
-                return Err(GCardError::Catalog(format!(
-                    "star already exists in the catalog, label_id: {}",
-                    entry.get()
-                )))
-            }
-            Entry::Vacant(entry) => {
-                entry.insert(label_id);
-            }
+        let Metadata {
+            stars,
+            star_center_ranks,
+            star_label_map,
+            ..
+        } = &mut self.metadata;
+        let code = star_code(stats.center_rank, stats.star.encode().as_bytes());
+        if let Err(existing) = star_label_map.insert(code, label_id, |label_id| {
+            let index = label_index(label_id);
+            star_code(star_center_ranks[index], stars[index].encode().as_bytes())
+        }) {
+            return Err(CatalogError::DuplicateStar(existing).into());
         }
-        self.metadata.stars.push(star);
+        stars.push(stats.star.clone());
+        star_center_ranks.push(stats.center_rank);
         if !empty_stats {
             let table_name = format!("star_{label_id}");
-            self.add_star_stats(&table_name, count, max_degree)?;
-            self.star_statistics.push(stats_cloned);
+            self.add_star_stats(&table_name, rows)?;
+            self.star_statistics_index
+                .insert(label_id, self.star_statistics.len());
+            self.star_statistics.push(stats);
+        } else if self.keep_empty_stats {
+            let table_name = format!("star_{label_id}");
+            self.add_star_stats(&table_name, None)?;
         }
         Ok(label_id)
     }
 
-    fn add_path_stats(
-        &self,
-        table_name: &str,
-        count: Vec<Box<[u64]>>,
-        start_max_degree: Vec<Box<[u64]>>,
-        end_max_degree: Vec<Box<[u64]>>,
-    ) -> GCardResult<()> {
+    /// Create the table of path statistics and append the staged rows, if any.
+    fn add_path_stats(&self, table_name: &str, rows: Option<RecordBatch>) -> GCardResult<()> {
         let sql = format!("create table {table_name} (s uint16, t uint16, _mode_s uint64, _mode_t uint64, _count uint64)");
         execute_sql(&self.conn, &sql)?;
+        self.append_rows(table_name, rows)
+    }
 
-        let mut appender = self.conn.appender(table_name)?;
-        for (i, ((max_degree_s, max_degree_t), count)) in start_max_degree
-            .into_iter()
-            .zip_eq(end_max_degree)
-            .zip_eq(count)
-            .enumerate()
-        {
-            for (j, ((max_degree_s, max_degree_t), count)) in max_degree_s
-                .iter()
-                .zip_eq(max_degree_t.iter())
-                .zip_eq(count.iter())
-                .enumerate()
-                .filter(|(_, ((_, _), count))| **count != 0)
-            {
-                appender.append_row([i as u64, j as u64, *max_degree_s, *max_degree_t, *count])?;
+    pub fn add_path(&mut self, stats: PathStatistics) -> GCardResult<LabelId> {
+        let rows = stage_path_rows(&stats)?;
+        self.add_path_staged(stats, rows)
+    }
+
+    /// Add the statistics of paths, staging the rows of their tables in parallel.
+    ///
+    /// The connection is not thread-safe, so the tables are still written one at a time, but only
+    /// with bulk appends.
+    pub fn add_paths(&mut self, paths: Vec<PathStatistics>) -> GCardResult<Vec<LabelId>> {
+        let mut label_ids = Vec::with_capacity(paths.len());
+        for paths in &paths.into_iter().chunks(STAGING_BATCH_SIZE) {
+            let paths = paths.collect_vec();
+            let rows: Vec<_> = paths
+                .par_iter()
+                .map(stage_path_rows)
+                .collect::<GCardResult<_>>()?;
+            for (stats, rows) in paths.into_iter().zip_eq(rows) {
+                label_ids.push(self.add_path_staged(stats, rows)?);
             }
         }
-        Ok(())
+        Ok(label_ids)
     }
 
-    pub fn add_path(&mut self, stats: PathStatistics) -> GCardResult<LabelId> {
-        let stats_cloned = stats.clone();
-        let PathStatistics {
-            path,
-            count,
-            start_max_degree,
-            end_max_degree,
-        } = stats;
+    fn add_path_staged(
+        &mut self,
+        stats: PathStatistics,
+        rows: Option<RecordBatch>,
+    ) -> GCardResult<LabelId> {
+        self.invalidate_content_hash();
         // Optimize empty statistics
-        let empty_stats = count
-            .iter()
-            .all(|count| count.iter().all(|count| *count == 0));
+        let empty_stats = rows.is_none();
         let mut label_id = self.metadata.paths.len() as LabelId;
         if empty_stats {
             label_id += LabelId::MAX / 2 + 1;
         }
-        match self.metadata.path_label_map.entry(path.encode()) {
-            Entry::Occupied(entry) => {
-                return Err(GCardError::Catalog(format!(
-                    "path {} already exists in the catalog, label_id: {}",
-                    path,
-                    entry.get()
-                )))
-            }
-            Entry::Vacant(entry) => {
-                entry.insert(label_id);
-            }
+        let Metadata {
+            paths,
+            path_label_map,
+            ..
+        } = &mut self.metadata;
+        let path = &stats.path;
+        if let Err(existing) =
+            path_label_map.insert(path.encode().into_bytes(), label_id, |label_id| {
+                paths[label_index(label_id)].encode().into_bytes()
+            })
+        {
+            let err = CatalogError::DuplicatePath {
+                path: Box::new(path.clone()),
+                existing,
+            };
+            return Err(err.into());
         }
-        self.metadata.paths.push(path);
+        paths.push(path.clone());
         if !empty_stats {
             let table_name = format!("path_{label_id}");
-            self.add_path_stats(&table_name, count, start_max_degree, end_max_degree)?;
-            self.path_statistics.push(stats_cloned);
+            self.add_path_stats(&table_name, rows)?;
+            self.path_statistics_index
+                .insert(label_id, self.path_statistics.len());
+            self.path_statistics.push(stats);
+        } else if self.keep_empty_stats {
+            let table_name = format!("path_{label_id}");
+            self.add_path_stats(&table_name, None)?;
         }
 
         Ok(label_id)
     }
 
+    /// Return the label id of a path or star stored at `index` of the metadata.
+    fn shifted_label_id(index: usize, empty_stats: bool) -> LabelId {
+        let mut label_id = index as LabelId;
+        if empty_stats {
+            label_id += LabelId::MAX / 2 + 1;
+        }
+        label_id
+    }
+
+    /// Replace the statistics of a path already in the catalog, e.g., after the edges of the
+    /// graph change. The label id of the path changes if its statistics become empty or
+    /// non-empty, so the new one is returned.
+    pub fn replace_path(&mut self, stats: PathStatistics) -> GCardResult<LabelId> {
+        if self.attached {
+            return Err(CatalogError::ReplaceAttached.into());
+        }
+        self.invalidate_content_hash();
+        let old_label_id = self
+            .get_path_label_id(&stats.path.encode())
+            .ok_or_else(|| CatalogError::UnknownPath(Box::new(stats.path.clone())))?;
+        let rows = stage_path_rows(&stats)?;
+        let empty_stats = rows.is_none();
+        let label_id = Self::shifted_label_id(label_index(old_label_id), empty_stats);
+        execute_sql(
+            &self.conn,
+            &format!("drop table if exists path_{old_label_id}"),
+        )?;
+        remove_stats(
+            &mut self.path_statistics,
+            &mut self.path_statistics_index,
+            old_label_id,
+        );
+        let Metadata {
+            paths,
+            path_label_map,
+            ..
+        } = &mut self.metadata;
+        assert!(
+            path_label_map.set(stats.path.encode().as_bytes(), label_id, |label_id| {
+                paths[label_index(label_id)].encode().into_bytes()
+            })
+        );
+        if !empty_stats || self.keep_empty_stats {
+            let table_name = format!("path_{label_id}");
+            self.add_path_stats(&table_name, rows)?;
+        }
+        if !empty_stats {
+            self.path_statistics_index
+                .insert(label_id, self.path_statistics.len());
+            self.path_statistics.push(stats);
+        }
+        Ok(label_id)
+    }
+
+    /// Replace the statistics of a star already in the catalog like [`Self::replace_path`].
+    pub fn replace_star(&mut self, stats: StarStatistics) -> GCardResult<LabelId> {
+        if self.attached {
+            return Err(CatalogError::ReplaceAttached.into());
+        }
+        self.invalidate_content_hash();
+        let code = star_code(stats.center_rank, stats.star.encode().as_bytes());
+        let old_label_id = self
+            .get_star_label_id(stats.center_rank, &stats.star.encode())
+            .ok_or_else(|| CatalogError::UnknownStar {
+                star: Box::new(stats.star.clone()),
+                center_rank: stats.center_rank,
+            })?;
+        let rows = stage_star_rows(&stats)?;
+        let empty_stats = rows.is_none();
+        let label_id = Self::shifted_label_id(label_index(old_label_id), empty_stats);
+        execute_sql(
+            &self.conn,
+            &format!("drop table if exists star_{old_label_id}"),
+        )?;
+        remove_stats(
+            &mut self.star_statistics,
+            &mut self.star_statistics_index,
+            old_label_id,
+        );
+        let Metadata {
+            stars,
+            star_center_ranks,
+            star_label_map,
+            ..
+        } = &mut self.metadata;
+        assert!(star_label_map.set(&code, label_id, |label_id| {
+            let index = label_index(label_id);
+            star_code(star_center_ranks[index], stars[index].encode().as_bytes())
+        }));
+        if !empty_stats || self.keep_empty_stats {
+            let table_name = format!("star_{label_id}");
+            self.add_star_stats(&table_name, rows)?;
+        }
+        if !empty_stats {
+            self.star_statistics_index
+                .insert(label_id, self.star_statistics.len());
+            self.star_statistics.push(stats);
+        }
+        Ok(label_id)
+    }
+
+    /// Create the table of joint statistics and append the staged rows, if any.
+    fn add_joint_stats(&self, table_name: &str, rows: Option<RecordBatch>) -> GCardResult<()> {
+        let sql = format!("create table {table_name} (s uint16, t uint16, _count uint64)");
+        execute_sql(&self.conn, &sql)?;
+        self.append_rows(table_name, rows)
+    }
+
+    /// Add the joint statistics of a length-2 path, which are stored in the table
+    /// `joint_{label_id}`.
+    pub fn add_joint(&mut self, stats: JointStatistics) -> GCardResult<LabelId> {
+        self.invalidate_content_hash();
+        let rows = stage_joint_rows(&stats)?;
+        let label_id = self.metadata.joint_paths.len() as LabelId;
+        let Metadata {
+            joint_paths,
+            joint_label_map,
+            ..
+        } = &mut self.metadata;
+        let path = &stats.path;
+        if let Err(existing) =
+            joint_label_map.insert(path.encode().into_bytes(), label_id, |label_id| {
+                joint_paths[label_id as usize].encode().into_bytes()
+            })
+        {
+            let err = CatalogError::DuplicatePath {
+                path: Box::new(path.clone()),
+                existing,
+            };
+            return Err(err.into());
+        }
+        joint_paths.push(stats.path);
+        self.add_joint_stats(&format!("joint_{label_id}"), rows)?;
+        Ok(label_id)
+    }
+
+    /// Add the joint statistics of length-2 paths like [`Self::add_joint`].
+    pub fn add_joints(&mut self, joints: Vec<JointStatistics>) -> GCardResult<Vec<LabelId>> {
+        joints
+            .into_iter()
+            .map(|stats| self.add_joint(stats))
+            .collect()
+    }
+
+    /// Replace the joint statistics of a path already in the catalog like [`Self::replace_path`].
+    /// The label id of joint statistics never changes.
+    pub fn replace_joint(&mut self, stats: JointStatistics) -> GCardResult<LabelId> {
+        if self.attached {
+            return Err(CatalogError::ReplaceAttached.into());
+        }
+        self.invalidate_content_hash();
+        let label_id = self
+            .get_joint_label_id(&stats.path.encode())
+            .ok_or_else(|| CatalogError::UnknownPath(Box::new(stats.path.clone())))?;
+        let rows = stage_joint_rows(&stats)?;
+        let table_name = format!("joint_{label_id}");
+        execute_sql(&self.conn, &format!("drop table if exists {table_name}"))?;
+        self.add_joint_stats(&table_name, rows)?;
+        Ok(label_id)
+    }
+
+    /// Recompute the statistics of the paths and stars containing `edge_label_id`, along with its
+    /// edge count and degree quantiles, e.g., after edges of the label are inserted. They are
+    /// computed from the graph and the schema of `builder`, which must be configured like the one
+    /// which built the catalog, so that the result is the same as rebuilding it. Greedy binning
+    /// depends on all the edges, so it is not supported.
+    pub fn recompute_edge_label(
+        &mut self,
+        edge_label_id: LabelId,
+        builder: &CatalogBuilder,
+    ) -> GCardResult<()> {
+        builder.recompute_edge_label(self, edge_label_id)
+    }
+
+    /// Return the paths (including the joint paths) and stars in the catalog containing an edge
+    /// of `edge_label_id`, keyed like the statistics computed by
+    /// [`crate::statistics::StatisticsAnalyzer`].
+    pub fn statistics_with_edge_label(&self, edge_label_id: LabelId) -> RequiredStatistics {
+        let metadata = &self.metadata;
+        let paths = metadata
+            .paths
+            .iter()
+            .chain(&metadata.joint_paths)
+            .filter(|path| path.edges().iter().any(|e| e.label_id() == edge_label_id))
+            .map(|path| (path.encode(), path.clone()))
+            .collect();
+        let stars = metadata
+            .stars
+            .iter()
+            .zip_eq(&metadata.star_center_ranks)
+            .filter(|(star, _)| star.edges().iter().any(|e| e.label_id() == edge_label_id))
+            .map(|(star, center_rank)| (*center_rank, star.encode()))
+            .collect();
+        RequiredStatistics { paths, stars }
+    }
+
+    /// Replace the edge count and the degree quantiles of `edge_label_id`, e.g., after the edges of
+    /// the graph change.
+    pub fn replace_edge_label_summary(
+        &mut self,
+        edge_label_id: LabelId,
+        count: usize,
+        quantiles: [Vec<u64>; 2],
+    ) {
+        self.invalidate_content_hash();
+        let metadata = &mut self.metadata;
+        metadata.edge_count_map.insert(edge_label_id, count);
+        for (direction, quantiles) in [EdgeDirection::Out, EdgeDirection::In]
+            .into_iter()
+            .zip(quantiles)
+        {
+            metadata
+                .degree_quantile_map
+                .insert((edge_label_id, direction), quantiles);
+        }
+    }
+
     pub fn add_edge_count(&mut self, edge_label_id: LabelId, count: usize) {
+        self.invalidate_content_hash();
         assert!(self
             .metadata
             .edge_count_map
@@ -285,6 +976,20 @@ impl DuckCatalog {
             .is_none());
     }
 
+    pub fn add_degree_quantiles(
+        &mut self,
+        edge_label_id: LabelId,
+        direction: EdgeDirection,
+        quantiles: Vec<u64>,
+    ) {
+        self.invalidate_content_hash();
+        assert!(self
+            .metadata
+            .degree_quantile_map
+            .insert((edge_label_id, direction), quantiles)
+            .is_none());
+    }
+
     pub fn add_bucket_map(
         &self,
         label_id: LabelId,
@@ -298,6 +1003,66 @@ impl DuckCatalog {
         Ok(())
     }
 
+    /// Return the bucket of `vertex_id` of `label_id`, or `None` if the vertex is not binned or
+    /// the catalog is built without saving the bucket maps.
+    pub fn vertex_bucket(
+        &self,
+        label_id: LabelId,
+        vertex_id: DefaultVertexId,
+    ) -> GCardResult<Option<BucketId>> {
+        let table_name = format!("bucket_{label_id}");
+        let sql = format!("select count(*) from duckdb_tables() where table_name = '{table_name}'");
+        let count: usize = self.conn.query_row(&sql, [], |row| row.get(0))?;
+        if count == 0 {
+            return Ok(None);
+        }
+        let sql = format!("select bucket_id from {table_name} where id = ?");
+        let bucket_id = self
+            .conn
+            .query_row(&sql, [vertex_id as u64], |row| row.get(0))
+            .optional()?;
+        Ok(bucket_id)
+    }
+
+    /// Return the non-empty path statistics added to the catalog, which are not kept by imported
+    /// catalogs.
+    pub fn path_statistics(&self) -> &[PathStatistics] {
+        &self.path_statistics
+    }
+
+    /// Return the non-empty star statistics added to the catalog, which are not kept by imported
+    /// catalogs.
+    pub fn star_statistics(&self) -> &[StarStatistics] {
+        &self.star_statistics
+    }
+
+    /// Return the paths in the catalog, in the order they were added.
+    pub fn paths(&self) -> &[PathPattern] {
+        &self.metadata.paths
+    }
+
+    /// Return the stars in the catalog, in the order they were added.
+    pub fn stars(&self) -> &[GeneralPattern] {
+        &self.metadata.stars
+    }
+
+    /// Return the paths with joint statistics in the catalog, in the order they were added.
+    pub fn joint_paths(&self) -> &[PathPattern] {
+        &self.metadata.joint_paths
+    }
+
+    /// Return the 128-bit murmur3 hash of the exported files if the catalog is imported, which is
+    /// equal for imports of the same files. The files are hashed on import, and the hash is
+    /// `None` once the statistics are changed, e.g., by [`Self::replace_path`].
+    pub fn content_hash(&self) -> Option<u128> {
+        self.content_hash
+    }
+
+    /// Clear the content hash, which no longer matches the statistics being changed.
+    fn invalidate_content_hash(&mut self) {
+        self.content_hash = None;
+    }
+
     pub fn conn(&self) -> &Connection {
         &self.conn
     }
@@ -308,26 +1073,442 @@ impl DuckCatalog {
 }
 
 impl Catalog for DuckCatalog {
-    fn get_path_label_id(&self, code: &[u8]) -> Option<LabelId> {
-        self.metadata.path_label_map.get(code).copied()
+    fn get_path_label_id(&self, code: &EncodedPattern) -> Option<LabelId> {
+        self.metadata
+            .path_label_map
+            .get(code.as_bytes(), |label_id| {
+                self.metadata.path_code(label_id)
+            })
     }
 
     fn get_path(&self, label_id: LabelId) -> Option<&PathPattern> {
-        self.metadata.paths.get(label_id as usize)
+        self.metadata.paths.get(label_index(label_id))
     }
 
-    fn get_star_label_id(&self, rank: TagId, code: &[u8]) -> Option<LabelId> {
+    fn get_star_label_id(&self, rank: TagId, code: &EncodedPattern) -> Option<LabelId> {
         self.metadata
             .star_label_map
-            .get(&(rank, code.to_owned()))
-            .copied()
+            .get(&star_code(rank, code.as_bytes()), |label_id| {
+                self.metadata.star_code(label_id)
+            })
     }
 
     fn get_star(&self, label_id: LabelId) -> Option<&GeneralPattern> {
-        self.metadata.stars.get(label_id as usize)
+        self.metadata.stars.get(label_index(label_id))
+    }
+
+    fn get_star_center_rank(&self, label_id: LabelId) -> Option<TagId> {
+        self.metadata
+            .star_center_ranks
+            .get(label_index(label_id))
+            .copied()
     }
 
     fn get_edge_count(&self, label_id: LabelId) -> Option<usize> {
         self.metadata.edge_count_map.get(&label_id).copied()
     }
+
+    fn get_joint_label_id(&self, code: &EncodedPattern) -> Option<LabelId> {
+        self.metadata
+            .joint_label_map
+            .get(code.as_bytes(), |label_id| {
+                self.metadata.joint_code(label_id)
+            })
+    }
+
+    fn get_joint(&self, label_id: LabelId) -> Option<&PathPattern> {
+        self.metadata.joint_paths.get(label_id as usize)
+    }
+
+    fn get_path_stats(&self, label_id: LabelId) -> Option<&PathStatistics> {
+        let index = self.path_statistics_index.get(&label_id)?;
+        self.path_statistics.get(*index)
+    }
+
+    fn get_star_stats(&self, label_id: LabelId) -> Option<&StarStatistics> {
+        let index = self.star_statistics_index.get(&label_id)?;
+        self.star_statistics.get(*index)
+    }
+
+    fn degree_quantile(
+        &self,
+        edge_label_id: LabelId,
+        direction: EdgeDirection,
+        q: f64,
+    ) -> Option<u64> {
+        let quantiles = self
+            .metadata
+            .degree_quantile_map
+            .get(&(edge_label_id, direction))?;
+        lookup_quantile(quantiles, q)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::error::GCardError;
+    use crate::pattern::RawPattern;
+    use crate::test_utils::TempDir;
+
+    fn table_exists(catalog: &DuckCatalog, table_name: &str) -> bool {
+        let sql = format!("select count(*) from duckdb_tables() where table_name = '{table_name}'");
+        let count: usize = catalog
+            .conn()
+            .query_row(&sql, [], |row| row.get(0))
+            .unwrap();
+        count > 0
+    }
+
+    fn build_empty_path_stats(edge_label_id: LabelId) -> PathStatistics {
+        let path = RawPattern::new()
+            .push_back_vertex((0, 0))
+            .push_back_vertex((1, 1))
+            .push_back_edge((0, 0, 1, edge_label_id))
+            .to_path()
+            .unwrap();
+        PathStatistics {
+            path,
+            count: vec![vec![0; 2].into_boxed_slice(); 2],
+            start_max_degree: vec![vec![0; 2].into_boxed_slice(); 2],
+            end_max_degree: vec![vec![0; 2].into_boxed_slice(); 2],
+        }
+    }
+
+    fn build_catalog(scale: u64) -> DuckCatalog {
+        let mut catalog = DuckCatalog::init().unwrap();
+        let mut stats = build_empty_path_stats(0);
+        stats.count = vec![
+            vec![scale, 2].into_boxed_slice(),
+            vec![0, 3].into_boxed_slice(),
+        ];
+        stats.start_max_degree = vec![vec![1, 2].into_boxed_slice(); 2];
+        stats.end_max_degree = vec![vec![1, 1].into_boxed_slice(); 2];
+        catalog.add_path(stats).unwrap();
+        catalog.add_path(build_empty_path_stats(1)).unwrap();
+        let star = RawPattern::new()
+            .push_back_vertex((0, 0))
+            .to_general()
+            .unwrap();
+        catalog
+            .add_star(StarStatistics {
+                star,
+                center_rank: 0,
+                count: vec![3, 4],
+                max_degree: vec![1, 1],
+            })
+            .unwrap();
+        catalog
+    }
+
+    #[test]
+    fn test_export_validated() {
+        let catalog = build_catalog(1);
+        let dir = TempDir::new("export");
+        catalog.export_validated(&dir).unwrap();
+
+        assert!(catalog.validate_estimates(&build_catalog(1)).is_ok());
+        assert!(catalog.validate_estimates(&build_catalog(2)).is_err());
+        assert!(catalog
+            .validate_estimates(&DuckCatalog::init().unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_import_attached() {
+        let catalog = build_catalog(1);
+        let dir = TempDir::new("attached");
+        catalog.export(&dir).unwrap();
+        let attached = DuckCatalog::import_attached(&dir).unwrap();
+        catalog.validate_estimates(&attached).unwrap();
+        assert!(attached.export(dir.join("copy")).is_err());
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let catalog = build_catalog(1);
+        assert_eq!(catalog.content_hash(), None);
+        let dir = TempDir::new("content_hash");
+        catalog.export(&dir).unwrap();
+        let mut imported = DuckCatalog::import(&dir).unwrap();
+        assert!(imported.content_hash().is_some());
+        assert_eq!(
+            DuckCatalog::import_attached(&dir).unwrap().content_hash(),
+            imported.content_hash()
+        );
+
+        // Changing the files after import does not change the hash, unlike changing the catalog.
+        let other = DuckCatalog::import(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(other.content_hash(), imported.content_hash());
+        let mut stats = build_empty_path_stats(0);
+        stats.count = vec![vec![5, 2].into_boxed_slice(), vec![0, 3].into_boxed_slice()];
+        imported.replace_path(stats).unwrap();
+        assert_eq!(imported.content_hash(), None);
+    }
+
+    #[test]
+    fn test_import_legacy_metadata() {
+        // The metadata of `build_catalog(1)` exported before the metadata was versioned. The
+        // layout of the database is unchanged.
+        let catalog = build_catalog(1);
+        let dir = TempDir::new("legacy");
+        catalog.export(&dir).unwrap();
+        let legacy_path =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/legacy_metadata.bincode");
+        std::fs::copy(legacy_path, dir.join(METADATA)).unwrap();
+        let legacy = DuckCatalog::import(&dir).unwrap();
+        assert_eq!(legacy.metadata.paths, catalog.metadata.paths);
+        assert_eq!(legacy.metadata.stars, catalog.metadata.stars);
+        assert_eq!(
+            legacy.metadata.star_center_ranks,
+            catalog.metadata.star_center_ranks
+        );
+        assert!(legacy.metadata.degree_quantile_map.is_empty());
+        assert_eq!(legacy.degree_quantile(0, EdgeDirection::Out, 0.5), None);
+        catalog.validate_estimates(&legacy).unwrap();
+
+        // Exported again, the metadata is versioned.
+        legacy.export(&dir).unwrap();
+        let mut header = [0; METADATA_MAGIC.len()];
+        File::open(dir.join(METADATA))
+            .unwrap()
+            .read_exact(&mut header)
+            .unwrap();
+        assert_eq!(header, METADATA_MAGIC);
+        let reimported = DuckCatalog::import(&dir).unwrap();
+        catalog.validate_estimates(&reimported).unwrap();
+
+        // Unknown versions are rejected.
+        let mut writer = File::create(dir.join(METADATA)).unwrap();
+        writer.write_all(&METADATA_MAGIC).unwrap();
+        bincode::serialize_into(&mut writer, &(METADATA_VERSION + 1)).unwrap();
+        drop(writer);
+        assert!(matches!(
+            DuckCatalog::import(&dir),
+            Err(GCardError::Catalog(CatalogError::UnsupportedMetadataVersion(version)))
+                if version == METADATA_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn test_joints() {
+        let joint_count = |catalog: &DuckCatalog, label_id: LabelId| -> u64 {
+            let sql = format!("select coalesce(sum(_count), 0)::ubigint from joint_{label_id}");
+            catalog
+                .conn()
+                .query_row(&sql, [], |row| row.get(0))
+                .unwrap()
+        };
+        let path = RawPattern::new()
+            .push_back_vertex((0, 0))
+            .push_back_vertex((1, 1))
+            .push_back_vertex((2, 0))
+            .push_back_edge((0, 0, 1, 0))
+            .push_back_edge((1, 2, 1, 0))
+            .to_path()
+            .unwrap();
+        let mut stats = JointStatistics {
+            path: path.clone(),
+            count: vec![vec![1, 2].into_boxed_slice(), vec![0, 3].into_boxed_slice()],
+        };
+        let mut catalog = build_catalog(1);
+        let label_id = catalog.add_joint(stats.clone()).unwrap();
+        assert!(catalog.add_joint(stats.clone()).is_err());
+        assert_eq!(catalog.get_joint_label_id(&path.encode()), Some(label_id));
+        assert_eq!(catalog.get_joint(label_id), Some(&path));
+        assert_eq!(catalog.get_path_label_id(&path.encode()), None);
+        assert_eq!(joint_count(&catalog, label_id), 6);
+        assert_eq!(catalog.statistics_with_edge_label(0).paths.len(), 2);
+
+        stats.count[1][1] = 4;
+        assert_eq!(catalog.replace_joint(stats).unwrap(), label_id);
+        assert_eq!(joint_count(&catalog, label_id), 7);
+
+        let dir = TempDir::new("joints");
+        catalog.export(&dir).unwrap();
+        let imported = DuckCatalog::import(&dir).unwrap();
+        assert_eq!(imported.joint_paths(), [path]);
+        assert_eq!(
+            imported.get_joint_label_id(&imported.joint_paths()[0].encode()),
+            Some(label_id)
+        );
+        assert_eq!(joint_count(&imported, label_id), 7);
+
+        // The metadata of version 1 has no joint paths.
+        let catalog = build_catalog(1);
+        catalog.export(&dir).unwrap();
+        let metadata = &catalog.metadata;
+        let v1 = MetadataV1 {
+            paths: metadata.paths.clone(),
+            stars: metadata.stars.clone(),
+            star_center_ranks: metadata.star_center_ranks.clone(),
+            edge_count_map: metadata.edge_count_map.clone(),
+            ..Default::default()
+        };
+        let mut writer = File::create(dir.join(METADATA)).unwrap();
+        writer.write_all(&METADATA_MAGIC).unwrap();
+        bincode::serialize_into(&mut writer, &1u32).unwrap();
+        bincode::serialize_into(&mut writer, &v1).unwrap();
+        drop(writer);
+        let migrated = DuckCatalog::import(&dir).unwrap();
+        assert_eq!(migrated.metadata.paths, metadata.paths);
+        assert!(migrated.joint_paths().is_empty());
+    }
+
+    #[test]
+    fn test_dump_metadata_json() {
+        let catalog = build_catalog(1);
+        let dir = TempDir::new("metadata");
+        let path = dir.join("metadata.json");
+        catalog.dump_metadata_json(&path).unwrap();
+        let value: serde_json::Value = serde_json::from_reader(File::open(&path).unwrap()).unwrap();
+
+        let paths = value["paths"].as_array().unwrap();
+        assert_eq!(paths.len(), 2);
+        let path = &catalog.metadata.paths[0];
+        assert_eq!(paths[0]["label_id"], 0);
+        assert_eq!(paths[0]["code"], path.encode().to_string());
+        assert_eq!(paths[0]["pattern"], path.to_string());
+        assert!(paths[1]["label_id"].as_u64().unwrap() > (LabelId::MAX / 2) as u64);
+        let stars = value["stars"].as_array().unwrap();
+        assert_eq!(stars.len(), 1);
+        assert_eq!(stars[0]["center_rank"], 0);
+        assert_eq!(stars[0]["pattern"], "(0:0)");
+        assert_eq!(value["hash_encodings"], false);
+    }
+
+    #[test]
+    fn test_duck_config() {
+        let config = DuckConfig::default()
+            .max_expression_depth(100)
+            .setting("threads", "1");
+        let catalog = DuckCatalog::init_with_config(&config).unwrap();
+        let setting = |name: &str| -> String {
+            let sql = format!("select current_setting('{name}')::varchar");
+            catalog
+                .conn()
+                .query_row(&sql, [], |row| row.get(0))
+                .unwrap()
+        };
+        assert_eq!(setting("max_expression_depth"), "100");
+        assert_eq!(setting("threads"), "1");
+
+        let config = DuckConfig::default().setting("no_such_setting", "1");
+        assert!(DuckCatalog::init_with_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_keep_empty_stats() {
+        let mut catalog = DuckCatalog::init().unwrap();
+        let label_id = catalog.add_path(build_empty_path_stats(0)).unwrap();
+        assert!(label_id > LabelId::MAX / 2);
+        assert!(!table_exists(&catalog, &format!("path_{label_id}")));
+
+        catalog.set_keep_empty_stats(true);
+        let label_id = catalog.add_path(build_empty_path_stats(1)).unwrap();
+        assert!(label_id > LabelId::MAX / 2);
+        let table_name = format!("path_{label_id}");
+        assert!(table_exists(&catalog, &table_name));
+        let sql = format!("select count(*) from {table_name}");
+        let count: usize = catalog
+            .conn()
+            .query_row(&sql, [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_hash_encodings() {
+        let mut catalog = DuckCatalog::init().unwrap();
+        catalog.set_hash_encodings(true).unwrap();
+        let stats = build_empty_path_stats(0);
+        let code = stats.path.encode();
+        let label_id = catalog.add_path(stats).unwrap();
+        assert_eq!(catalog.get_path_label_id(&code), Some(label_id));
+        assert!(catalog.add_path(build_empty_path_stats(0)).is_err());
+        assert_eq!(
+            catalog.get_path_label_id(&build_empty_path_stats(1).path.encode()),
+            None
+        );
+        assert!(catalog.set_hash_encodings(false).is_err());
+    }
+
+    #[test]
+    fn test_get_stats_for() {
+        let catalog = build_catalog(1);
+        let path = &catalog.path_statistics()[0];
+        assert_eq!(catalog.get_path_stats_for(&path.path), Some(path));
+        // Empty statistics are not kept.
+        let empty = build_empty_path_stats(1);
+        assert!(catalog.get_path_label_id(&empty.path.encode()).is_some());
+        assert!(catalog.get_path_stats_for(&empty.path).is_none());
+
+        let star = &catalog.star_statistics()[0];
+        assert_eq!(catalog.get_star_stats_for(&star.star, 0), Some(star));
+        assert!(catalog.get_star_stats_for(&star.star, 1).is_none());
+    }
+
+    #[test]
+    fn test_add_paths() {
+        const BUCKETS: usize = 60;
+        let build_path_stats = |edge_label_id| {
+            let row = |i: usize| -> Box<[u64]> {
+                (0..BUCKETS).map(|j| (i * BUCKETS + j) as u64 + 1).collect()
+            };
+            PathStatistics {
+                count: (0..BUCKETS).map(row).collect(),
+                start_max_degree: (0..BUCKETS).map(row).collect(),
+                end_max_degree: vec![vec![1; BUCKETS].into_boxed_slice(); BUCKETS],
+                ..build_empty_path_stats(edge_label_id)
+            }
+        };
+        let mut catalog = DuckCatalog::init().unwrap();
+        let label_ids = catalog
+            .add_paths(vec![
+                build_path_stats(0),
+                build_empty_path_stats(1),
+                build_path_stats(2),
+            ])
+            .unwrap();
+        assert_eq!(label_ids, vec![0, LabelId::MAX / 2 + 2, 2]);
+        assert!(!table_exists(&catalog, &format!("path_{}", label_ids[1])));
+
+        // The rows span multiple data chunks.
+        let num_rows = BUCKETS * BUCKETS;
+        assert!(num_rows > CHUNK_SIZE);
+        let expected_sum = (num_rows * (num_rows + 1) / 2) as u64;
+        for label_id in [0, 2] {
+            let sql = format!(
+                "select count(*), sum(_count)::uint64, sum(_mode_s)::uint64, sum(_mode_t)::uint64, \
+                 sum(s * {BUCKETS} + t)::uint64 from path_{label_id}"
+            );
+            let row: (usize, u64, u64, u64, u64) = catalog
+                .conn()
+                .query_row(&sql, [], |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                    ))
+                })
+                .unwrap();
+            let expected_index_sum = expected_sum - num_rows as u64;
+            assert_eq!(
+                row,
+                (
+                    num_rows,
+                    expected_sum,
+                    expected_sum,
+                    num_rows as u64,
+                    expected_index_sum
+                )
+            );
+        }
+        assert_eq!(catalog.path_statistics().len(), 2);
+    }
 }
@@ -2,17 +2,22 @@ use std::collections::hash_map::Entry;
 
 use ahash::HashMap;
 
-use super::Catalog;
-use crate::common::{LabelId, TagId};
-use crate::pattern::{GeneralPattern, GraphPattern, PathPattern};
+use super::{lookup_quantile, Catalog};
+use crate::common::{EdgeDirection, LabelId, TagId};
+use crate::pattern::{EncodedPattern, GeneralPattern, GraphPattern, PathPattern};
+use crate::statistics::{PathStatistics, StarStatistics};
 
 #[derive(Debug, Default)]
 pub struct MockCatalog {
     paths: Vec<PathPattern>,
     stars: Vec<GeneralPattern>,
-    path_label_map: HashMap<Vec<u8>, LabelId>,
-    star_label_map: HashMap<(TagId, Vec<u8>), LabelId>,
+    star_center_ranks: Vec<TagId>,
+    path_label_map: HashMap<EncodedPattern, LabelId>,
+    star_label_map: HashMap<(TagId, EncodedPattern), LabelId>,
     edge_count_map: HashMap<LabelId, usize>,
+    degree_quantile_map: HashMap<(LabelId, EdgeDirection), Vec<u64>>,
+    path_stats: HashMap<LabelId, PathStatistics>,
+    star_stats: HashMap<LabelId, StarStatistics>,
 }
 
 impl MockCatalog {
@@ -20,6 +25,16 @@ impl MockCatalog {
         self.edge_count_map.insert(edge_label_id, count);
     }
 
+    pub fn add_degree_quantiles(
+        &mut self,
+        edge_label_id: LabelId,
+        direction: EdgeDirection,
+        quantiles: Vec<u64>,
+    ) {
+        self.degree_quantile_map
+            .insert((edge_label_id, direction), quantiles);
+    }
+
     pub fn add_path(&mut self, path: PathPattern) -> LabelId {
         match self.path_label_map.entry(path.encode()) {
             Entry::Occupied(entry) => *entry.get(),
@@ -39,14 +54,33 @@ impl MockCatalog {
                 let label_id = self.stars.len() as LabelId;
                 entry.insert(label_id);
                 self.stars.push(star);
+                self.star_center_ranks.push(rank);
                 label_id
             }
         }
     }
 }
 
+impl MockCatalog {
+    /// Add the path of `stats` along with its statistics, which can be joined by
+    /// [`crate::estimate::memory`].
+    pub fn add_path_stats(&mut self, stats: PathStatistics) -> LabelId {
+        let label_id = self.add_path(stats.path.clone());
+        self.path_stats.insert(label_id, stats);
+        label_id
+    }
+
+    /// Add the star of `stats` along with its statistics, which can be joined by
+    /// [`crate::estimate::memory`].
+    pub fn add_star_stats(&mut self, stats: StarStatistics) -> LabelId {
+        let label_id = self.add_star(stats.star.clone(), stats.center_rank);
+        self.star_stats.insert(label_id, stats);
+        label_id
+    }
+}
+
 impl Catalog for MockCatalog {
-    fn get_path_label_id(&self, code: &[u8]) -> Option<LabelId> {
+    fn get_path_label_id(&self, code: &EncodedPattern) -> Option<LabelId> {
         self.path_label_map.get(code).copied()
     }
 
@@ -54,15 +88,37 @@ impl Catalog for MockCatalog {
         self.paths.get(label_id as usize)
     }
 
-    fn get_star_label_id(&self, rank: TagId, code: &[u8]) -> Option<LabelId> {
-        self.star_label_map.get(&(rank, code.to_owned())).copied()
+    fn get_star_label_id(&self, rank: TagId, code: &EncodedPattern) -> Option<LabelId> {
+        self.star_label_map.get(&(rank, code.clone())).copied()
     }
 
     fn get_star(&self, label_id: LabelId) -> Option<&GeneralPattern> {
         self.stars.get(label_id as usize)
     }
 
+    fn get_star_center_rank(&self, label_id: LabelId) -> Option<TagId> {
+        self.star_center_ranks.get(label_id as usize).copied()
+    }
+
     fn get_edge_count(&self, label_id: LabelId) -> Option<usize> {
         self.edge_count_map.get(&label_id).copied()
     }
+
+    fn get_path_stats(&self, label_id: LabelId) -> Option<&PathStatistics> {
+        self.path_stats.get(&label_id)
+    }
+
+    fn get_star_stats(&self, label_id: LabelId) -> Option<&StarStatistics> {
+        self.star_stats.get(&label_id)
+    }
+
+    fn degree_quantile(
+        &self,
+        edge_label_id: LabelId,
+        direction: EdgeDirection,
+        q: f64,
+    ) -> Option<u64> {
+        let quantiles = self.degree_quantile_map.get(&(edge_label_id, direction))?;
+        lookup_quantile(quantiles, q)
+    }
 }
@@ -1,17 +1,22 @@
+mod code_map;
 mod duck;
 mod mock;
 
-pub use duck::DuckCatalog;
+pub use duck::{DuckCatalog, DuckConfig};
 pub use mock::MockCatalog;
 
-use crate::common::{LabelId, TagId};
-use crate::pattern::{encode_edge, encode_vertex, GeneralPattern, PathPattern};
+use crate::common::{EdgeDirection, LabelId, TagId};
+use crate::pattern::{
+    encode_edge, encode_vertex, EncodedPattern, GeneralPattern, GraphPattern, PathPattern,
+};
+use crate::statistics::{PathStatistics, StarStatistics};
 
 pub trait Catalog {
-    fn get_path_label_id(&self, code: &[u8]) -> Option<LabelId>;
+    fn get_path_label_id(&self, code: &EncodedPattern) -> Option<LabelId>;
     fn get_path(&self, label_id: LabelId) -> Option<&PathPattern>;
-    fn get_star_label_id(&self, rank: TagId, code: &[u8]) -> Option<LabelId>;
+    fn get_star_label_id(&self, rank: TagId, code: &EncodedPattern) -> Option<LabelId>;
     fn get_star(&self, label_id: LabelId) -> Option<&GeneralPattern>;
+    fn get_star_center_rank(&self, label_id: LabelId) -> Option<TagId>;
     fn get_edge_count(&self, label_id: LabelId) -> Option<usize>;
 
     fn get_edge_label_id(
@@ -28,4 +33,65 @@ pub trait Catalog {
         let code = encode_vertex(vertex);
         self.get_star_label_id(0, &code)
     }
+
+    /// Return the label id of the joint statistics of the length-2 path with encoding `code`, or
+    /// `None` if the catalog has none.
+    fn get_joint_label_id(&self, _code: &EncodedPattern) -> Option<LabelId> {
+        None
+    }
+
+    /// Return the length-2 path of the joint statistics `label_id`.
+    fn get_joint(&self, _label_id: LabelId) -> Option<&PathPattern> {
+        None
+    }
+
+    /// Return the statistics of the path `label_id`, or `None` if they are not held in memory.
+    fn get_path_stats(&self, _label_id: LabelId) -> Option<&PathStatistics> {
+        None
+    }
+
+    /// Return the statistics of the star `label_id`, or `None` if they are not held in memory.
+    fn get_star_stats(&self, _label_id: LabelId) -> Option<&StarStatistics> {
+        None
+    }
+
+    /// Return the statistics of the path matching the encoding of `path`, or `None` if the path is
+    /// not in the catalog or its statistics are not held in memory.
+    fn get_path_stats_for(&self, path: &PathPattern) -> Option<&PathStatistics> {
+        let label_id = self.get_path_label_id(&path.encode())?;
+        self.get_path_stats(label_id)
+    }
+
+    /// Return the statistics of the star matching the encoding of `star` centered at `center_rank`,
+    /// or `None` if the star is not in the catalog or its statistics are not held in memory.
+    fn get_star_stats_for(
+        &self,
+        star: &GeneralPattern,
+        center_rank: TagId,
+    ) -> Option<&StarStatistics> {
+        let label_id = self.get_star_label_id(center_rank, &star.encode())?;
+        self.get_star_stats(label_id)
+    }
+
+    /// Return the `q`-quantile (`0 <= q <= 1`) of the degrees along `edge_label_id` in the given
+    /// direction, rounded up to the nearest stored quantile, or `None` if it is not recorded.
+    fn degree_quantile(
+        &self,
+        _edge_label_id: LabelId,
+        _direction: EdgeDirection,
+        _q: f64,
+    ) -> Option<u64> {
+        None
+    }
+}
+
+/// Look up the `q`-quantile in `quantiles`, which are evenly spaced from the minimum to the
+/// maximum.
+fn lookup_quantile(quantiles: &[u64], q: f64) -> Option<u64> {
+    if !(0.0..=1.0).contains(&q) || quantiles.is_empty() {
+        return None;
+    }
+    // Tolerate rounding errors such as `0.3 * 10.0 > 3.0`
+    let index = (q * (quantiles.len() - 1) as f64 - 1e-9).ceil().max(0.0) as usize;
+    quantiles.get(index).copied()
 }
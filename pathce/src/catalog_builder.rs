@@ -2,21 +2,42 @@ use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::time::Instant;
 
-use ahash::HashMap;
-use log::{debug, info};
+use ahash::{HashMap, HashSet, HashSetExt};
+use itertools::Itertools;
+use log::{debug, info, warn};
 use murmur3::murmur3_32;
+use num::integer::binomial;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use rayon::ThreadPool;
+use serde::Serialize;
+use tracing::{info_span, instrument, Span};
 
 use crate::binning::GreedyBinner;
-use crate::catalog::DuckCatalog;
-use crate::common::GlobalBucketMap;
-use crate::error::GCardResult;
-use crate::graph::LabeledGraph;
-use crate::pattern::PathPattern;
+use crate::catalog::{Catalog, DuckCatalog, DuckConfig, MockCatalog};
+use crate::common::{EdgeDirection, GlobalBucketMap, LabelId};
+use crate::error::{CatalogError, GCardResult, SchemaError};
+use crate::estimate::decompose::heuristic::HeuristicDecomposer;
+use crate::factorization::DEFAULT_PAR_COUNT_THRESHOLD;
+use crate::graph::{LabeledGraph, LabeledVertex};
+use crate::pattern::{EncodedPattern, GeneralPattern, GraphPattern, PathPattern, RawPattern};
 use crate::sample::PathSampler;
 use crate::schema::Schema;
-use crate::statistics::StatisticsAnalyzer;
+use crate::statistics::{RequiredStatistics, StatisticsAnalyzer};
+
+/// What [`CatalogBuilder::build`] would produce, as enumerated from the schema alone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct BuildPlan {
+    /// The number of path statistics.
+    pub num_paths: usize,
+    /// The number of nodes of the path trees traversed to compute the path statistics.
+    pub num_path_tree_nodes: usize,
+    /// The number of star statistics of single vertices and of the endpoints of paths.
+    pub num_stars: usize,
+    /// An upper bound of the number of additional star statistics merged from several paths.
+    pub num_star_combinations: u64,
+    /// An upper bound of the total number of rows of the statistics tables.
+    pub estimated_rows: u64,
+}
 
 #[derive(Debug, Clone)]
 pub struct CatalogBuilder {
@@ -28,8 +49,24 @@ pub struct CatalogBuilder {
     max_star_degree: usize,
     buckets: usize,
     enable_greedy_bucket: bool,
+    initial_bucket_ratio: Option<f64>,
+    par_count_threshold: usize,
     save_bucket_map: bool,
     skip_path: bool,
+    skip_star: bool,
+    keep_empty_stats: bool,
+    hash_encodings: bool,
+    strict_coverage: bool,
+    duck_config: DuckConfig,
+    workload: Option<Vec<GeneralPattern>>,
+    workload_limit: usize,
+    workload_disable_prune: bool,
+    workload_disable_cyclic: bool,
+    initial_bucket_map: Option<GlobalBucketMap>,
+    path_weights: Option<HashMap<EncodedPattern, f64>>,
+    max_star_combinations: Option<usize>,
+    path_sample: Option<(HashMap<LabelId, f64>, u64)>,
+    joint_statistics: bool,
 }
 
 impl CatalogBuilder {
@@ -43,8 +80,24 @@ impl CatalogBuilder {
             max_star_degree: 4,
             buckets: 200,
             enable_greedy_bucket: true,
+            initial_bucket_ratio: None,
+            par_count_threshold: DEFAULT_PAR_COUNT_THRESHOLD,
             save_bucket_map: false,
             skip_path: false,
+            skip_star: false,
+            keep_empty_stats: false,
+            hash_encodings: false,
+            strict_coverage: false,
+            duck_config: DuckConfig::default(),
+            workload: None,
+            workload_limit: 10,
+            workload_disable_prune: false,
+            workload_disable_cyclic: false,
+            initial_bucket_map: None,
+            path_weights: None,
+            max_star_combinations: None,
+            path_sample: None,
+            joint_statistics: false,
         }
     }
 
@@ -53,6 +106,73 @@ impl CatalogBuilder {
         self
     }
 
+    /// Only compute the statistics of single-vertex stars, for workloads of paths only. The catalog
+    /// must be used by an estimator with stars disabled.
+    pub fn skip_star(mut self, skip: bool) -> Self {
+        self.skip_star = skip;
+        self
+    }
+
+    pub fn keep_empty_stats(mut self, keep: bool) -> Self {
+        self.keep_empty_stats = keep;
+        self
+    }
+
+    pub fn hash_encodings(mut self, enable: bool) -> Self {
+        self.hash_encodings = enable;
+        self
+    }
+
+    /// Fail the build instead of warning when a non-empty edge label has no statistics of its
+    /// single-edge path, which would make the estimates of patterns containing it zero.
+    pub fn strict_coverage(mut self, strict: bool) -> Self {
+        self.strict_coverage = strict;
+        self
+    }
+
+    pub fn duck_config(mut self, config: DuckConfig) -> Self {
+        self.duck_config = config;
+        self
+    }
+
+    /// Only compute the statistics required to estimate the patterns of the workload.
+    ///
+    /// The patterns are decomposed with the maximum lengths and degree of the builder, with stars
+    /// disabled if they are skipped, and with the settings of [`Self::workload_limit`],
+    /// [`Self::workload_disable_prune`] and [`Self::workload_disable_cyclic`]. They estimate
+    /// identically to a full build as long as the estimator uses the same settings.
+    pub fn for_workload(mut self, patterns: &[GeneralPattern]) -> Self {
+        self.workload = Some(patterns.to_vec());
+        self
+    }
+
+    /// Decompose cyclic patterns of the workload with at most `limit` spanning trees, 10 by
+    /// default.
+    pub fn workload_limit(mut self, limit: usize) -> Self {
+        self.workload_limit = limit;
+        self
+    }
+
+    /// Decompose the workload without pruning cyclic patterns.
+    pub fn workload_disable_prune(mut self, disable: bool) -> Self {
+        self.workload_disable_prune = disable;
+        self
+    }
+
+    /// Decompose cyclic patterns of the workload using spanning trees only.
+    pub fn workload_disable_cyclic(mut self, disable: bool) -> Self {
+        self.workload_disable_cyclic = disable;
+        self
+    }
+
+    /// Also store the joint statistics of the length-2 paths when the maximum path length is 1, see
+    /// [`StatisticsAnalyzer::joint_statistics`]. They are not computed for a workload, whose
+    /// decompositions only require paths within the maximum length.
+    pub fn joint_statistics(mut self, enable: bool) -> Self {
+        self.joint_statistics = enable;
+        self
+    }
+
     pub fn max_path_length(mut self, len: usize) -> Self {
         self.max_path_length = len;
         self
@@ -68,6 +188,23 @@ impl CatalogBuilder {
         self
     }
 
+    /// Compute at most `limit` stars per center label and degree, see
+    /// [`StatisticsAnalyzer::max_star_combinations`]. The truncated stars are missing from the
+    /// catalog, so estimating patterns containing them requires fallback.
+    pub fn max_star_combinations(mut self, limit: usize) -> Self {
+        self.max_star_combinations = Some(limit);
+        self
+    }
+
+    /// Compute the path statistics from a uniform sample of the given fraction of the vertices of
+    /// each label in `fractions`, chosen with `seed`, for labels too large for per-vertex counts.
+    /// The counts are scaled up by the inverse fractions, so they are noisier for rare paths, and
+    /// the maximum degrees may be underestimated.
+    pub fn sample_path_vertices(mut self, fractions: HashMap<LabelId, f64>, seed: u64) -> Self {
+        self.path_sample = Some((fractions, seed));
+        self
+    }
+
     pub fn buckets(mut self, buckets: usize) -> Self {
         self.buckets = buckets;
         self
@@ -78,48 +215,171 @@ impl CatalogBuilder {
         self
     }
 
+    /// Spend the `ratio` (in `(0, 1]`, 0.5 by default) of the greedy binning budget of each label
+    /// on equal-size buckets, and the rest on splitting the buckets with the most varying degrees.
+    pub fn initial_bucket_ratio(mut self, ratio: f64) -> Self {
+        self.initial_bucket_ratio = Some(ratio);
+        self
+    }
+
+    /// Count the neighbors of a vertex in parallel during greedy binning only if it has at least
+    /// `threshold` of them, see [`crate::count_valid_values`].
+    pub fn par_count_threshold(mut self, threshold: usize) -> Self {
+        self.par_count_threshold = threshold;
+        self
+    }
+
     pub fn save_bucket_map(mut self, enable: bool) -> Self {
         self.save_bucket_map = enable;
         self
     }
 
-    pub fn build(self) -> GCardResult<DuckCatalog> {
-        let start = Instant::now();
-        let edges = self.schema.generate_paths(1);
-        info!("path generation: {} s", start.elapsed().as_secs_f64());
+    /// Resume greedy binning from `bucket_map`, e.g., the result of [`Self::bucket_map`] with
+    /// fewer buckets, so that only the additional buckets are split off. The result refines
+    /// `bucket_map` rather than matching a fresh build with `buckets`, see
+    /// [`GreedyBinner::from_existing`]. Labels missing from `bucket_map` are binned from scratch.
+    /// Hash binning ignores it.
+    pub fn initial_bucket_map(mut self, bucket_map: GlobalBucketMap) -> Self {
+        self.initial_bucket_map = Some(bucket_map);
+        self
+    }
 
-        let start = Instant::now();
-        let global_bucket_map = if self.enable_greedy_bucket {
+    /// Weight paths by their frequencies in a query log, keyed by path encodings.
+    ///
+    /// The greedy binning budget of a vertex label is scaled by the total weight of the paths
+    /// touching it relative to the heaviest label, so the heaviest labels keep all `buckets` and
+    /// the others get fewer, down to a single bucket. Hash binning ignores the weights.
+    pub fn path_weights(mut self, weights: HashMap<EncodedPattern, f64>) -> Self {
+        self.path_weights = Some(weights);
+        self
+    }
+
+    /// Compute the bucket map that [`Self::build`] would use.
+    pub fn bucket_map(&self) -> GCardResult<GlobalBucketMap> {
+        if self.enable_greedy_bucket {
+            let edges = self.schema.generate_paths(1);
             self.greedy_binning(&edges)
         } else {
-            self.hash_binning()
-        };
-        let global_bucket_map = Arc::new(global_bucket_map);
-        info!("binning: {} s", start.elapsed().as_secs_f64());
+            Ok(self.hash_binning())
+        }
+    }
 
-        let analyzer = StatisticsAnalyzer::new(
-            self.graph.clone(),
-            self.schema.clone(),
-            global_bucket_map.clone(),
-            self.buckets,
-            self.max_path_length,
-            self.max_star_length,
-            self.max_star_degree,
-        );
+    /// Count the statistics that [`Self::build`] would compute without scanning the graph, to
+    /// catch a misconfiguration before an expensive build.
+    pub fn plan(&self) -> BuildPlan {
+        let mut plan = BuildPlan::default();
+        if let Some(workload) = &self.workload {
+            let required = self.required_statistics(workload);
+            if !self.skip_path {
+                plan.num_paths = required.paths.len();
+                plan.num_path_tree_nodes = required.path_prefix_codes().len();
+            }
+            plan.num_stars = required.stars.len();
+        } else {
+            let mut paths = HashSet::new();
+            let mut stars = HashSet::new();
+            for v in self.schema.vertices() {
+                let path = RawPattern::new()
+                    .push_back_vertex((0, v.label))
+                    .to_path()
+                    .unwrap();
+                stars.insert((path.get_vertex_rank(0).unwrap(), path.encode()));
+                // The paths starting from `v`, keyed like the star states of the analyzer
+                let mut state = HashSet::new();
+                let mut num_star_paths = 0;
+                let tree = self
+                    .schema
+                    .generate_path_tree_from_path_end(&path, self.max_path_length);
+                let mut queue = tree.root().children();
+                while let Some(node) = queue.pop() {
+                    let path = node.path();
+                    let code = path.encode();
+                    let start_rank = path.get_vertex_rank(path.start().tag_id()).unwrap();
+                    if state.insert((start_rank, code.clone())) {
+                        num_star_paths += (path.len() <= self.max_star_length) as u64;
+                        stars.insert((start_rank, code.clone()));
+                        if path.is_symmetric() {
+                            let end_rank = path.get_vertex_rank(path.end().tag_id()).unwrap();
+                            stars.insert((end_rank, code.clone()));
+                        }
+                    }
+                    if !self.skip_path {
+                        plan.num_path_tree_nodes += 1;
+                        paths.insert(code);
+                    }
+                    queue.extend(node.children());
+                }
+                for degree in 2..=self.max_star_degree as u64 {
+                    let count = binomial(num_star_paths as u128, degree as u128);
+                    plan.num_star_combinations = plan
+                        .num_star_combinations
+                        .saturating_add(count.try_into().unwrap_or(u64::MAX));
+                }
+            }
+            plan.num_paths = paths.len();
+            plan.num_stars = stars.len();
+            if self.skip_star {
+                plan.num_stars = self.schema.vertices().len();
+                plan.num_star_combinations = 0;
+            }
+        }
+        let buckets = self.buckets as u64;
+        let path_rows = (plan.num_paths as u64).saturating_mul(buckets * buckets);
+        let star_rows = (plan.num_stars as u64)
+            .saturating_add(plan.num_star_combinations)
+            .saturating_mul(buckets);
+        plan.estimated_rows = path_rows.saturating_add(star_rows);
+        plan
+    }
 
-        let path_stat_map = if !self.skip_path {
+    #[instrument(skip_all)]
+    pub fn build(self) -> GCardResult<DuckCatalog> {
+        let edges = info_span!("path_generation").in_scope(|| {
             let start = Instant::now();
-            let path_stat_map: BTreeMap<_, _> = self
+            let edges = self.schema.generate_paths(1);
+            info!("path generation: {} s", start.elapsed().as_secs_f64());
+            edges
+        });
+
+        let global_bucket_map = info_span!("binning").in_scope(|| -> GCardResult<_> {
+            let start = Instant::now();
+            let global_bucket_map = if self.enable_greedy_bucket {
+                self.greedy_binning(&edges)?
+            } else {
+                self.hash_binning()
+            };
+            info!("binning: {} s", start.elapsed().as_secs_f64());
+            Ok(Arc::new(global_bucket_map))
+        })?;
+
+        let mut analyzer = self.analyzer(global_bucket_map.clone());
+        if let Some(workload) = &self.workload {
+            let required = info_span!("workload_decomposition")
+                .in_scope(|| self.required_statistics(workload));
+            info!(
+                "workload requires {} paths and {} stars",
+                required.paths.len(),
+                required.stars.len()
+            );
+            analyzer = analyzer.restrict_to(required);
+        }
+
+        // Rayon scopes run on the pool threads, so the current span is entered explicitly there.
+        let span = Span::current();
+        let (path_stat_map, joint_stat_map) = if !self.skip_path {
+            let start = Instant::now();
+            let (path_stat_map, joint_stat_map) = self
                 .pool
-                .scope(|_| analyzer.compute_path_statistics())
-                .into_iter()
-                .collect();
+                .scope(|_| span.in_scope(|| analyzer.compute_path_statistics()));
+            let path_stat_map: BTreeMap<_, _> = path_stat_map.into_iter().collect();
+            let joint_stat_map: BTreeMap<_, _> = joint_stat_map.into_iter().collect();
             info!(
-                "path statistics: {} s, count: {}",
+                "path statistics: {} s, count: {}, joint count: {}",
                 start.elapsed().as_secs_f64(),
-                path_stat_map.len()
+                path_stat_map.len(),
+                joint_stat_map.len()
             );
-            path_stat_map
+            (path_stat_map, joint_stat_map)
         } else {
             Default::default()
         };
@@ -127,7 +387,15 @@ impl CatalogBuilder {
         let start = Instant::now();
         let star_stat_map: BTreeMap<_, _> = self
             .pool
-            .scope(|_| analyzer.compute_star_statistics())
+            .scope(|_| {
+                span.in_scope(|| {
+                    if self.skip_star {
+                        analyzer.compute_vertex_star_statistics()
+                    } else {
+                        analyzer.compute_star_statistics()
+                    }
+                })
+            })
             .into_iter()
             .collect();
         info!(
@@ -136,28 +404,200 @@ impl CatalogBuilder {
             star_stat_map.len()
         );
 
-        let start = Instant::now();
-        let mut catalog = DuckCatalog::init()?;
-        for stats in path_stat_map.into_values() {
-            catalog.add_path(stats)?;
+        info_span!("build_catalog").in_scope(|| {
+            let start = Instant::now();
+            let mut catalog = DuckCatalog::init_with_config(&self.duck_config)?;
+            catalog.set_keep_empty_stats(self.keep_empty_stats);
+            catalog.set_hash_encodings(self.hash_encodings)?;
+            self.pool.install(|| {
+                catalog.add_paths(path_stat_map.into_values().collect())?;
+                catalog.add_joints(joint_stat_map.into_values().collect())?;
+                catalog.add_stars(star_stat_map.into_values().collect())
+            })?;
+
+            // Update edge counts and degree quantiles
+            for e in self.schema.edges() {
+                let count = self.graph.get_num_edges(e.label).unwrap();
+                catalog.add_edge_count(e.label, count);
+                for (vertex_label, direction) in
+                    [(e.from, EdgeDirection::Out), (e.to, EdgeDirection::In)]
+                {
+                    let quantiles = self.degree_quantiles(vertex_label, e.label, direction);
+                    catalog.add_degree_quantiles(e.label, direction, quantiles);
+                }
+            }
+
+            if self.save_bucket_map {
+                for (label_id, bucket_map) in global_bucket_map.as_ref() {
+                    catalog.add_bucket_map(*label_id, bucket_map)?;
+                }
+            }
+            if !self.skip_path {
+                self.check_edge_coverage(&catalog, &edges)?;
+            }
+            info!("build catalog: {} s", start.elapsed().as_secs_f64());
+            Ok(catalog)
+        })
+    }
+
+    /// Check that the single-edge path of every edge label with edges has non-empty statistics in
+    /// `catalog`. Paths missing from a catalog restricted to a workload are not required.
+    fn check_edge_coverage(&self, catalog: &DuckCatalog, edges: &[PathPattern]) -> GCardResult<()> {
+        let mut missing = vec![];
+        for path in edges {
+            let label_id = path.edges()[0].label_id();
+            if self.graph.get_num_edges(label_id).unwrap_or_default() == 0 {
+                continue;
+            }
+            let covered = match catalog.get_path_label_id(&path.encode()) {
+                Some(path_label_id) => path_label_id <= LabelId::MAX / 2,
+                None => self.workload.is_some(),
+            };
+            if !covered {
+                let name = self.schema.get_edge_label_name(label_id).unwrap();
+                warn!("no statistics of the single-edge path of edge label {name}");
+                missing.push(name.clone());
+            }
         }
-        for stats in star_stat_map.into_values() {
-            catalog.add_star(stats)?;
+        if self.strict_coverage && !missing.is_empty() {
+            return Err(CatalogError::MissingEdgeStatistics(missing).into());
         }
+        Ok(())
+    }
 
-        // Update edge counts
-        for e in self.schema.edges() {
-            let count = self.graph.get_num_edges(e.label).unwrap();
-            catalog.add_edge_count(e.label, count);
+    fn analyzer(&self, bucket_map: Arc<GlobalBucketMap>) -> StatisticsAnalyzer {
+        let analyzer = StatisticsAnalyzer::new(
+            self.graph.clone(),
+            self.schema.clone(),
+            bucket_map,
+            self.buckets,
+            self.max_path_length,
+            self.max_star_length,
+            self.max_star_degree,
+        );
+        let analyzer = analyzer.joint_statistics(self.joint_statistics);
+        let analyzer = match self.max_star_combinations {
+            Some(limit) => analyzer.max_star_combinations(limit),
+            None => analyzer,
+        };
+        match &self.path_sample {
+            Some((fractions, seed)) => analyzer.sample_path_vertices(fractions.clone(), *seed),
+            None => analyzer,
+        }
+    }
+
+    /// See [`DuckCatalog::recompute_edge_label`].
+    pub(crate) fn recompute_edge_label(
+        &self,
+        catalog: &mut DuckCatalog,
+        edge_label_id: LabelId,
+    ) -> GCardResult<()> {
+        if self.enable_greedy_bucket {
+            return Err(CatalogError::RecomputeGreedyBinning.into());
+        }
+        let edge = self
+            .schema
+            .get_edge(edge_label_id)
+            .ok_or(SchemaError::UnknownEdgeLabel(edge_label_id))?;
+        let required = catalog.statistics_with_edge_label(edge_label_id);
+        info!(
+            "recompute {} paths and {} stars of edge label {edge_label_id}",
+            required.paths.len(),
+            required.stars.len()
+        );
+        let analyzer = self
+            .analyzer(Arc::new(self.hash_binning()))
+            .restrict_to(required);
+        let (path_stats, joint_stats, star_stats) = self.pool.install(|| {
+            let (path_stats, joint_stats) = analyzer.compute_path_statistics();
+            let star_stats = if self.skip_star {
+                analyzer.compute_vertex_star_statistics()
+            } else {
+                analyzer.compute_star_statistics()
+            };
+            (path_stats, joint_stats, star_stats)
+        });
+        for stats in path_stats.into_values() {
+            catalog.replace_path(stats)?;
+        }
+        for stats in joint_stats.into_values() {
+            catalog.replace_joint(stats)?;
+        }
+        for stats in star_stats.into_values() {
+            catalog.replace_star(stats)?;
+        }
+        let count = self.graph.get_num_edges(edge_label_id).unwrap();
+        let quantiles = [
+            self.degree_quantiles(edge.from, edge_label_id, EdgeDirection::Out),
+            self.degree_quantiles(edge.to, edge_label_id, EdgeDirection::In),
+        ];
+        catalog.replace_edge_label_summary(edge_label_id, count, quantiles);
+        Ok(())
+    }
+
+    /// Compute [`DEGREE_QUANTILES`] evenly spaced quantiles of the degrees of all vertices of
+    /// `vertex_label` along `edge_label`, including the vertices without such edges.
+    fn degree_quantiles(
+        &self,
+        vertex_label: LabelId,
+        edge_label: LabelId,
+        direction: EdgeDirection,
+    ) -> Vec<u64> {
+        let mut degrees = self
+            .graph
+            .vertices(vertex_label)
+            .unwrap()
+            .iter()
+            .map(|id| {
+                let vertex = LabeledVertex::new(*id, vertex_label);
+                self.graph
+                    .neighbors(vertex, edge_label, direction)
+                    .unwrap()
+                    .len() as u64
+            })
+            .collect::<Vec<_>>();
+        if degrees.is_empty() {
+            return vec![];
         }
+        degrees.sort_unstable();
+        let n = degrees.len();
+        // Nearest-rank percentile
+        (0..=DEGREE_QUANTILES)
+            .map(|i| {
+                let rank = (i as f64 / DEGREE_QUANTILES as f64 * n as f64).ceil() as usize;
+                degrees[rank.clamp(1, n) - 1]
+            })
+            .collect()
+    }
 
-        if self.save_bucket_map {
-            for (label_id, bucket_map) in global_bucket_map.as_ref() {
-                catalog.add_bucket_map(*label_id, bucket_map)?;
+    /// Collect the paths and stars used by the decompositions of the workload.
+    fn required_statistics(&self, workload: &[GeneralPattern]) -> RequiredStatistics {
+        let mut catalog = MockCatalog::default();
+        for len in 1..=self.max_path_length {
+            for path in self.schema.generate_paths(len) {
+                catalog.add_path(path);
             }
         }
-        info!("build catalog: {} s", start.elapsed().as_secs_f64());
-        Ok(catalog)
+        for e in self.schema.edges() {
+            catalog.add_edge_count(e.label, self.graph.get_num_edges(e.label).unwrap());
+        }
+        let mut required = RequiredStatistics::default();
+        for pattern in workload {
+            let decomposer = HeuristicDecomposer::new(
+                &catalog,
+                self.max_path_length,
+                self.max_star_length,
+                self.max_star_degree,
+                self.workload_limit,
+                self.skip_star,
+                self.workload_disable_prune,
+                self.workload_disable_cyclic,
+            );
+            let RequiredStatistics { paths, stars } = decomposer.required_statistics(pattern);
+            required.paths.extend(paths);
+            required.stars.extend(stars);
+        }
+        required
     }
 
     fn hash_binning(&self) -> GlobalBucketMap {
@@ -180,23 +620,85 @@ impl CatalogBuilder {
             .collect()
     }
 
-    fn greedy_binning(&self, base_paths: &[PathPattern]) -> GlobalBucketMap {
+    /// Compute the greedy binning budget of each vertex label from the path weights.
+    fn greedy_budgets(&self) -> HashMap<LabelId, usize> {
+        let mut budgets: HashMap<_, _> = self
+            .schema
+            .vertices()
+            .iter()
+            .map(|v| (v.label, self.buckets))
+            .collect();
+        let Some(path_weights) = &self.path_weights else {
+            return budgets;
+        };
+        let mut label_weights: HashMap<LabelId, f64> = HashMap::default();
+        let mut num_weighted_paths = 0;
+        for len in 1..=self.max_path_length {
+            for path in self.schema.generate_paths(len) {
+                let Some(weight) = path_weights.get(&path.encode()) else {
+                    continue;
+                };
+                num_weighted_paths += 1;
+                for label_id in path.vertices().iter().map(|v| v.label_id()).unique() {
+                    *label_weights.entry(label_id).or_default() += weight;
+                }
+            }
+        }
+        if num_weighted_paths < path_weights.len() {
+            warn!(
+                "{} weighted paths are not paths of the schema up to length {}",
+                path_weights.len() - num_weighted_paths,
+                self.max_path_length
+            );
+        }
+        let max_weight = label_weights.values().copied().fold(0., f64::max);
+        if max_weight <= 0. {
+            return budgets;
+        }
+        for (label_id, budget) in budgets.iter_mut() {
+            let weight = label_weights.get(label_id).copied().unwrap_or_default();
+            let scaled = (self.buckets as f64 * weight / max_weight).ceil() as usize;
+            *budget = scaled.clamp(1, self.buckets);
+        }
+        budgets
+    }
+
+    fn greedy_binning(&self, base_paths: &[PathPattern]) -> GCardResult<GlobalBucketMap> {
+        let budgets = self.greedy_budgets();
         let mut binners: HashMap<_, _> = self
             .schema
             .vertices()
             .iter()
             .map(|v| {
-                let vertices = self.graph.vertices(v.label).unwrap();
-                let binner = GreedyBinner::new(self.buckets, vertices.iter().copied());
-                (v.label, binner)
+                let initial = self
+                    .initial_bucket_map
+                    .as_ref()
+                    .and_then(|bucket_map| bucket_map.get(&v.label));
+                let budget = budgets.get(&v.label).copied().unwrap_or(self.buckets);
+                let binner = if let Some(bucket_map) = initial {
+                    let num_buckets = bucket_map.values().max().map_or(0, |max| max + 1);
+                    let additional_budget = budget.saturating_sub(num_buckets);
+                    GreedyBinner::from_existing(bucket_map.clone(), additional_budget)
+                } else {
+                    let vertices = self.graph.vertices(v.label).unwrap();
+                    let vertices = vertices.iter().copied();
+                    match self.initial_bucket_ratio {
+                        Some(ratio) => GreedyBinner::with_initial_ratio(budget, vertices, ratio),
+                        None => GreedyBinner::new(budget, vertices),
+                    }
+                };
+                (
+                    v.label,
+                    binner.par_count_threshold(self.par_count_threshold),
+                )
             })
             .collect();
 
         let num_paths = base_paths.len();
-        let sampler = PathSampler::new(self.graph.clone());
+        let sampler = PathSampler::new(self.graph.clone()).with_schema(self.schema.clone());
 
-        self.pool.scope(|_| {
-            base_paths.iter().enumerate().for_each(|(i, path)| {
+        self.pool.scope(|_| -> GCardResult<()> {
+            for (i, path) in base_paths.iter().enumerate() {
                 let path_start = path.start();
                 let path_end = path.end();
 
@@ -206,11 +708,11 @@ impl CatalogBuilder {
 
                 if start_should_finish && end_should_finish {
                     debug!("[{:0>4}/{:0>4}] path: {}, skipped", i + 1, num_paths, path,);
-                    return;
+                    continue;
                 }
 
                 let start = Instant::now();
-                let table = sampler.sample(path);
+                let table = sampler.sample(path)?;
                 debug!(
                     "[{:0>4}/{:0>4}] path: {}, sample time: {} s",
                     i + 1,
@@ -229,12 +731,457 @@ impl CatalogBuilder {
                     .get_mut(&path_end.label_id())
                     .unwrap()
                     .update(&end_col, &start_col);
-            });
-        });
+            }
+            Ok(())
+        })?;
 
-        binners
+        let global_bucket_map = binners
             .into_iter()
             .map(|(label_id, binner)| (label_id, binner.finish()))
-            .collect()
+            .collect();
+        Ok(global_bucket_map)
+    }
+}
+
+/// The number of intervals between the degree quantiles stored in the catalog, i.e., deciles.
+pub const DEGREE_QUANTILES: usize = 10;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{EdgeCardinality, LabelId};
+    use crate::estimate::CardinalityEstimator;
+    use crate::graph::LabeledGraphBuilder;
+    use crate::pattern::RawPattern;
+    use crate::schema::SchemaUnchecked;
+    use crate::test_utils::{
+        build_estimator, build_ldbc_graph, build_ldbc_schema, ldbc_catalog_builder, TempDir,
+    };
+
+    const PERSON: LabelId = 0;
+    const COMMENT: LabelId = 1;
+    const FORUM: LabelId = 2;
+    const KNOWS: LabelId = 0;
+    const HAS_CREATOR: LabelId = 1;
+    const HAS_MEMBER: LabelId = 2;
+
+    /// Build a schema and a graph in which no `Forum` vertex exists.
+    fn build_schema_and_graph_with_empty_label() -> (Schema, LabeledGraph) {
+        let schema: Schema = SchemaUnchecked::default()
+            .add_vertex_label("Person".into(), PERSON)
+            .add_vertex_label("Comment".into(), COMMENT)
+            .add_vertex_label("Forum".into(), FORUM)
+            .add_edge_label("Person_knows_Person".into(), KNOWS)
+            .add_edge_label("Comment_hasCreator_Person".into(), HAS_CREATOR)
+            .add_edge_label("Forum_hasMember_Person".into(), HAS_MEMBER)
+            .add_vertex((PERSON, false))
+            .add_vertex((COMMENT, false))
+            .add_vertex((FORUM, false))
+            .add_edge((PERSON, PERSON, KNOWS, EdgeCardinality::ManyToMany))
+            .add_edge((COMMENT, PERSON, HAS_CREATOR, EdgeCardinality::ManyToOne))
+            .add_edge((FORUM, PERSON, HAS_MEMBER, EdgeCardinality::ManyToMany))
+            .try_into()
+            .unwrap();
+        let mut builder = LabeledGraphBuilder::new(2);
+        for v in schema.vertices() {
+            builder = builder.add_vertex_label(v.label);
+        }
+        for e in schema.edges() {
+            builder = builder.add_edge_label(e.label, e.from, e.to);
+        }
+        for person in 0..8 {
+            builder = builder.add_vertex(person, PERSON);
+            for dst in (person + 1..8).filter(|dst| (person + dst) % 3 == 0) {
+                builder = builder.add_edge(person, dst, KNOWS);
+            }
+        }
+        for comment in 100..116 {
+            builder =
+                builder
+                    .add_vertex(comment, COMMENT)
+                    .add_edge(comment, comment % 8, HAS_CREATOR);
+        }
+        (schema, builder.build().unwrap())
+    }
+
+    #[test]
+    fn test_build_with_empty_label() {
+        let (schema, graph) = build_schema_and_graph_with_empty_label();
+        assert!(graph.vertices(FORUM).unwrap().is_empty());
+        let schema = Arc::new(schema);
+        let graph = Arc::new(graph);
+        for (greedy, keep_empty_stats) in [(false, false), (true, false), (true, true)] {
+            let catalog = ldbc_catalog_builder(schema.clone(), graph.clone())
+                .enable_greedy_bucket(greedy)
+                .keep_empty_stats(keep_empty_stats)
+                .build()
+                .unwrap();
+            let estimator = build_estimator(&catalog);
+
+            // (c: Comment)-[:hasCreator]->(p1: Person)-[:knows]->(p2: Person)
+            let pattern = RawPattern::new()
+                .push_back_vertex((0, COMMENT))
+                .push_back_vertex((1, PERSON))
+                .push_back_vertex((2, PERSON))
+                .push_back_edge((0, 0, 1, HAS_CREATOR))
+                .push_back_edge((1, 1, 2, KNOWS))
+                .to_general()
+                .unwrap();
+            let expected: usize = (100..116)
+                .map(|comment| {
+                    let creator = LabeledVertex::new(comment % 8, PERSON);
+                    graph.outgoing_degree(creator, KNOWS).unwrap()
+                })
+                .sum();
+            assert_eq!(estimator.estimate(&pattern).unwrap(), expected as f64);
+
+            // (f: Forum)-[:hasMember]->(p1: Person)-[:knows]->(p2: Person)
+            let pattern = RawPattern::new()
+                .push_back_vertex((0, FORUM))
+                .push_back_vertex((1, PERSON))
+                .push_back_vertex((2, PERSON))
+                .push_back_edge((0, 0, 1, HAS_MEMBER))
+                .push_back_edge((1, 1, 2, KNOWS))
+                .to_general()
+                .unwrap();
+            assert_eq!(estimator.estimate(&pattern).unwrap(), 0.0);
+        }
+    }
+
+    #[test]
+    fn test_check_edge_coverage() {
+        let (schema, graph) = build_schema_and_graph_with_empty_label();
+        let schema = Arc::new(schema);
+        let builder = ldbc_catalog_builder(schema.clone(), Arc::new(graph))
+            .max_path_length(1)
+            .max_star_degree(1)
+            .strict_coverage(true);
+        let catalog = builder.clone().build().unwrap();
+
+        // A graph in which only the hasMember edges exist, which are empty in the catalog.
+        let mut graph_builder = LabeledGraphBuilder::new(2);
+        for v in schema.vertices() {
+            graph_builder = graph_builder.add_vertex_label(v.label);
+        }
+        for e in schema.edges() {
+            graph_builder = graph_builder.add_edge_label(e.label, e.from, e.to);
+        }
+        let graph = graph_builder
+            .add_vertex(0, PERSON)
+            .add_vertex(200, FORUM)
+            .add_edge(200, 0, HAS_MEMBER)
+            .build()
+            .unwrap();
+        let builder = ldbc_catalog_builder(schema.clone(), Arc::new(graph));
+        let edges = schema.generate_paths(1);
+        assert!(builder.check_edge_coverage(&catalog, &edges).is_ok());
+        let err = builder
+            .strict_coverage(true)
+            .check_edge_coverage(&catalog, &edges)
+            .unwrap_err();
+        assert!(err.to_string().contains("Forum_hasMember_Person"));
+    }
+
+    #[test]
+    fn test_recompute_edge_label() {
+        let (schema, _) = build_schema_and_graph_with_empty_label();
+        let schema = Arc::new(schema);
+        // Build the graph with the knows edges kept by `knows`.
+        let build_graph = |knows: fn(usize, usize) -> bool| {
+            let mut builder = LabeledGraphBuilder::new(2);
+            for v in schema.vertices() {
+                builder = builder.add_vertex_label(v.label);
+            }
+            for e in schema.edges() {
+                builder = builder.add_edge_label(e.label, e.from, e.to);
+            }
+            for person in 0..8 {
+                builder = builder.add_vertex(person, PERSON);
+                for dst in (person + 1..8).filter(|dst| knows(person, *dst)) {
+                    builder = builder.add_edge(person, dst, KNOWS);
+                }
+            }
+            for comment in 100..116 {
+                builder = builder.add_vertex(comment, COMMENT).add_edge(
+                    comment,
+                    comment % 8,
+                    HAS_CREATOR,
+                );
+            }
+            Arc::new(builder.build().unwrap())
+        };
+        let builder =
+            |graph| ldbc_catalog_builder(schema.clone(), graph).enable_greedy_bucket(false);
+        let graph = build_graph(|src, dst| (src + dst) % 3 == 0);
+        // The knows statistics are empty at first, and become non-empty.
+        for old_graph in [
+            build_graph(|_, _| false),
+            build_graph(|src, dst| src * dst == 0),
+        ] {
+            let mut catalog = builder(old_graph).build().unwrap();
+            catalog
+                .recompute_edge_label(KNOWS, &builder(graph.clone()))
+                .unwrap();
+            let expected = builder(graph.clone()).build().unwrap();
+            assert_eq!(
+                catalog.path_statistics().len(),
+                expected.path_statistics().len()
+            );
+            for stats in expected.path_statistics() {
+                assert_eq!(catalog.get_path_stats_for(&stats.path), Some(stats));
+            }
+            assert_eq!(
+                catalog.star_statistics().len(),
+                expected.star_statistics().len()
+            );
+            for stats in expected.star_statistics() {
+                let actual = catalog.get_star_stats_for(&stats.star, stats.center_rank);
+                assert_eq!(actual, Some(stats));
+            }
+            assert_eq!(
+                catalog.get_edge_count(KNOWS),
+                expected.get_edge_count(KNOWS)
+            );
+            assert_eq!(
+                catalog.degree_quantile(KNOWS, EdgeDirection::In, 0.5),
+                expected.degree_quantile(KNOWS, EdgeDirection::In, 0.5)
+            );
+
+            // (c: Comment)-[:hasCreator]->(p1: Person)-[:knows]->(p2: Person)-[:knows]->(p3:
+            // Person)
+            let pattern = RawPattern::with_vertices_edges(
+                [(0, COMMENT), (1, PERSON), (2, PERSON), (3, PERSON)],
+                [(0, 0, 1, HAS_CREATOR), (1, 1, 2, KNOWS), (2, 2, 3, KNOWS)],
+            )
+            .to_general()
+            .unwrap();
+            let estimate = |catalog| build_estimator(catalog).estimate(&pattern).unwrap();
+            assert!(estimate(&expected) > 0.0);
+            assert_eq!(estimate(&catalog), estimate(&expected));
+        }
+
+        let mut catalog = builder(graph.clone()).build().unwrap();
+        assert!(catalog
+            .recompute_edge_label(KNOWS, &builder(graph).enable_greedy_bucket(true))
+            .is_err());
+    }
+
+    #[test]
+    fn test_for_workload() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let comment = schema.get_vertex_label_id("Comment").unwrap();
+        let city = schema.get_vertex_label_id("City").unwrap();
+        let knows = schema.get_edge_label_id("Person_knows_Person").unwrap();
+        let has_creator = schema
+            .get_edge_label_id("Comment_hasCreator_Person")
+            .unwrap();
+        let is_located_in = schema.get_edge_label_id("Person_isLocatedIn_City").unwrap();
+        let workload = [
+            // (c: Comment)-[:hasCreator]->(p1: Person)-[:knows]->(p2: Person)
+            RawPattern::new()
+                .push_back_vertex((0, comment))
+                .push_back_vertex((1, person))
+                .push_back_vertex((2, person))
+                .push_back_edge((0, 0, 1, has_creator))
+                .push_back_edge((1, 1, 2, knows)),
+            // (p1: Person)-[:knows]->(p2: Person)-[:isLocatedIn]->(c: City)
+            RawPattern::new()
+                .push_back_vertex((0, person))
+                .push_back_vertex((1, person))
+                .push_back_vertex((2, city))
+                .push_back_edge((0, 0, 1, knows))
+                .push_back_edge((1, 1, 2, is_located_in)),
+            // A triangle of persons.
+            RawPattern::new()
+                .push_back_vertex((0, person))
+                .push_back_vertex((1, person))
+                .push_back_vertex((2, person))
+                .push_back_edge((0, 0, 1, knows))
+                .push_back_edge((1, 1, 2, knows))
+                .push_back_edge((2, 0, 2, knows)),
+        ]
+        .map(|pattern| pattern.to_general().unwrap());
+
+        let builder = ldbc_catalog_builder(schema, graph).enable_greedy_bucket(false);
+        let full = builder.clone().build().unwrap();
+        let restricted = builder.clone().for_workload(&workload).build().unwrap();
+        assert!(restricted.to_string().lines().count() < full.to_string().lines().count());
+
+        let full_estimator = build_estimator(&full);
+        let estimator = build_estimator(&restricted);
+        for pattern in &workload {
+            assert_eq!(
+                estimator.estimate(pattern).unwrap(),
+                full_estimator.estimate(pattern).unwrap()
+            );
+        }
+
+        // Only the spanning trees of the triangle are decomposed.
+        let builder = builder
+            .for_workload(&workload)
+            .workload_limit(1)
+            .workload_disable_cyclic(true);
+        assert!(builder.plan().num_stars < restricted.star_statistics().len());
+        let restricted = builder.build().unwrap();
+        let full_estimator = CardinalityEstimator::new(&full, 2, 1, 2, 1, false, false, true);
+        let estimator = CardinalityEstimator::new(&restricted, 2, 1, 2, 1, false, false, true);
+        for pattern in &workload {
+            assert_eq!(
+                estimator.estimate(pattern).unwrap(),
+                full_estimator.estimate(pattern).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_degree_quantile() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let catalog = ldbc_catalog_builder(schema.clone(), graph.clone())
+            .max_path_length(1)
+            .max_star_degree(1)
+            .build()
+            .unwrap();
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let knows = schema.get_edge_label_id("Person_knows_Person").unwrap();
+        let mut degrees = graph
+            .vertices(person)
+            .unwrap()
+            .iter()
+            .map(|id| {
+                let vertex = LabeledVertex::new(*id, person);
+                graph.outgoing_degree(vertex, knows).unwrap() as u64
+            })
+            .collect::<Vec<_>>();
+        degrees.sort_unstable();
+        let n = degrees.len();
+        let quantile = |q| catalog.degree_quantile(knows, EdgeDirection::Out, q);
+        assert_eq!(quantile(0.0), Some(degrees[0]));
+        assert_eq!(quantile(0.5), Some(degrees[n.div_ceil(2) - 1]));
+        assert_eq!(quantile(1.0), Some(degrees[n - 1]));
+        assert!(quantile(0.3) <= quantile(0.35));
+        assert_eq!(quantile(1.5), None);
+        assert!(catalog
+            .degree_quantile(LabelId::MAX, EdgeDirection::In, 0.5)
+            .is_none());
+    }
+
+    #[test]
+    fn test_plan() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let builder = ldbc_catalog_builder(schema, graph);
+        let plan = builder.clone().max_star_degree(1).plan();
+        assert_eq!(plan.num_star_combinations, 0);
+        assert_eq!(
+            plan.estimated_rows,
+            (plan.num_paths * 16 + plan.num_stars * 4) as u64
+        );
+        let catalog = builder.clone().max_star_degree(1).build().unwrap();
+        assert_eq!(catalog.to_string().lines().count(), plan.num_paths);
+        let dir = TempDir::new("plan");
+        let path = dir.join("metadata.json");
+        catalog.dump_metadata_json(&path).unwrap();
+        let value: serde_json::Value =
+            serde_json::from_reader(std::fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(value["stars"].as_array().unwrap().len(), plan.num_stars);
+
+        let plan_with_combinations = builder.clone().max_star_degree(2).plan();
+        assert_eq!(plan_with_combinations.num_paths, plan.num_paths);
+        assert!(plan_with_combinations.num_star_combinations > 0);
+        let plan_without_path = builder.skip_path(true).max_star_degree(1).plan();
+        assert_eq!(plan_without_path.num_paths, 0);
+        assert_eq!(plan_without_path.num_stars, plan.num_stars);
+    }
+
+    #[test]
+    fn test_skip_star() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let builder = ldbc_catalog_builder(schema.clone(), graph).enable_greedy_bucket(false);
+        let plan = builder.clone().skip_star(true).plan();
+        assert_eq!(plan.num_stars, schema.vertices().len());
+        assert_eq!(plan.num_star_combinations, 0);
+        let full = builder.clone().build().unwrap();
+        let catalog = builder.skip_star(true).build().unwrap();
+        assert_eq!(catalog.star_statistics().len(), plan.num_stars);
+
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let knows = schema.get_edge_label_id("Person_knows_Person").unwrap();
+        let path = RawPattern::with_vertices_edges(
+            [(0, person), (1, person), (2, person), (3, person)],
+            [(0, 0, 1, knows), (1, 1, 2, knows), (2, 2, 3, knows)],
+        )
+        .to_general()
+        .unwrap();
+        let full_estimator = CardinalityEstimator::new(&full, 2, 1, 2, 10, true, false, false);
+        let estimator = CardinalityEstimator::new(&catalog, 2, 1, 2, 10, true, false, false);
+        let estimate = estimator.estimate(&path).unwrap();
+        assert!(estimate > 0.);
+        assert_eq!(estimate, full_estimator.estimate(&path).unwrap());
+    }
+
+    #[test]
+    fn test_vertex_bucket() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let builder = ldbc_catalog_builder(schema.clone(), graph)
+            .max_path_length(1)
+            .max_star_degree(1)
+            .enable_greedy_bucket(false);
+        let bucket_map = builder.bucket_map().unwrap();
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let (vertex_id, bucket_id) = bucket_map[&person].iter().next().unwrap();
+
+        let catalog = builder.clone().save_bucket_map(true).build().unwrap();
+        assert_eq!(
+            catalog.vertex_bucket(person, *vertex_id).unwrap(),
+            Some(*bucket_id)
+        );
+        assert_eq!(catalog.vertex_bucket(person, usize::MAX).unwrap(), None);
+        assert_eq!(catalog.vertex_bucket(LabelId::MAX, 0).unwrap(), None);
+
+        let catalog = builder.build().unwrap();
+        assert_eq!(catalog.vertex_bucket(person, *vertex_id).unwrap(), None);
+    }
+
+    #[test]
+    fn test_path_weights() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let code_of = |edge_label| {
+            let edge_label = schema.get_edge_label_id(edge_label).unwrap();
+            schema
+                .generate_paths(1)
+                .into_iter()
+                .find(|path| path.edges()[0].label_id() == edge_label)
+                .unwrap()
+                .encode()
+        };
+        let path_weights = [
+            (code_of("Person_knows_Person"), 2.),
+            (code_of("Comment_hasCreator_Person"), 1.),
+        ]
+        .into_iter()
+        .collect();
+        let builder = ldbc_catalog_builder(schema.clone(), graph)
+            .max_path_length(1)
+            .path_weights(path_weights);
+
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let comment = schema.get_vertex_label_id("Comment").unwrap();
+        let tag = schema.get_vertex_label_id("Tag").unwrap();
+        let budgets = builder.greedy_budgets();
+        assert_eq!(budgets[&person], 4);
+        assert_eq!(budgets[&comment], 2);
+        assert_eq!(budgets[&tag], 1);
+
+        let bucket_map = builder.bucket_map().unwrap();
+        assert!(bucket_map[&comment]
+            .values()
+            .all(|bucket_id| *bucket_id < 2));
+        assert!(bucket_map[&tag].values().all(|bucket_id| *bucket_id == 0));
     }
 }
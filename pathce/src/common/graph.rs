@@ -1,9 +1,14 @@
 use serde::{Deserialize, Serialize};
 
+/// The direction of an edge relative to a vertex.
+///
+/// The discriminants are part of the serialized format and are stable: `Out` is 0 and `In` is 1.
+/// Serde encodes the variant index, so the variants must not be reordered either.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[repr(u8)]
 pub enum EdgeDirection {
-    Out,
-    In,
+    Out = 0,
+    In = 1,
 }
 
 impl EdgeDirection {
@@ -15,14 +20,125 @@ impl EdgeDirection {
     }
 }
 
+/// The cardinality of an edge label.
+///
+/// The discriminants are part of the serialized format and are stable: `ManyToMany` is 0,
+/// `ManyToOne` is 1, `OneToMany` is 2 and `OneToOne` is 3. Serde encodes the variant index, so the
+/// variants must not be reordered either.
 #[derive(
     Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
 )]
+#[repr(u8)]
 pub enum EdgeCardinality {
     #[default]
-    ManyToMany,
+    ManyToMany = 0,
 
-    ManyToOne,
-    OneToMany,
-    OneToOne,
+    ManyToOne = 1,
+    OneToMany = 2,
+    OneToOne = 3,
+}
+
+impl EdgeCardinality {
+    /// Classify an edge label by the maximum outgoing degree of its sources and the maximum
+    /// incoming degree of its destinations.
+    pub fn from_degrees(max_out: usize, max_in: usize) -> Self {
+        match (max_out <= 1, max_in <= 1) {
+            (true, true) => EdgeCardinality::OneToOne,
+            (true, false) => EdgeCardinality::ManyToOne,
+            (false, true) => EdgeCardinality::OneToMany,
+            (false, false) => EdgeCardinality::ManyToMany,
+        }
+    }
+
+    /// Whether following an edge of this cardinality in `direction` reaches at most one vertex,
+    /// e.g., `ManyToOne` is functional along [`EdgeDirection::Out`].
+    pub fn is_functional(self, direction: EdgeDirection) -> bool {
+        match direction {
+            EdgeDirection::Out => {
+                matches!(self, EdgeCardinality::ManyToOne | EdgeCardinality::OneToOne)
+            }
+            EdgeDirection::In => {
+                matches!(self, EdgeCardinality::OneToMany | EdgeCardinality::OneToOne)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cardinality_from_degrees() {
+        use EdgeCardinality::*;
+        let cases = [
+            (0, 0, OneToOne),
+            (1, 0, OneToOne),
+            (0, 1, OneToOne),
+            (1, 1, OneToOne),
+            (1, 2, ManyToOne),
+            (0, 5, ManyToOne),
+            (2, 1, OneToMany),
+            (5, 0, OneToMany),
+            (2, 2, ManyToMany),
+            (5, 7, ManyToMany),
+        ];
+        for (max_out, max_in, expected) in cases {
+            assert_eq!(
+                EdgeCardinality::from_degrees(max_out, max_in),
+                expected,
+                "max_out: {max_out}, max_in: {max_in}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_cardinality_is_functional() {
+        use EdgeCardinality::*;
+        use EdgeDirection::*;
+        let cases = [
+            (ManyToMany, Out, false),
+            (ManyToMany, In, false),
+            (ManyToOne, Out, true),
+            (ManyToOne, In, false),
+            (OneToMany, Out, false),
+            (OneToMany, In, true),
+            (OneToOne, Out, true),
+            (OneToOne, In, true),
+        ];
+        for (cardinality, direction, expected) in cases {
+            assert_eq!(
+                cardinality.is_functional(direction),
+                expected,
+                "{cardinality:?}, {direction:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_wire_values() {
+        fn assert_wire_value<T>(value: T, expected: u8)
+        where
+            T: Serialize + for<'de> Deserialize<'de> + PartialEq + std::fmt::Debug,
+        {
+            // bincode encodes the variant index as a little-endian u32.
+            let bytes = bincode::serialize(&value).unwrap();
+            assert_eq!(bytes, [expected, 0, 0, 0], "{value:?}");
+            let decoded: T = bincode::deserialize(&bytes).unwrap();
+            assert_eq!(decoded, value);
+        }
+        for (direction, expected) in [(EdgeDirection::Out, 0), (EdgeDirection::In, 1)] {
+            assert_eq!(direction as u8, expected);
+            assert_wire_value(direction, expected);
+        }
+        for (cardinality, expected) in [
+            (EdgeCardinality::ManyToMany, 0),
+            (EdgeCardinality::ManyToOne, 1),
+            (EdgeCardinality::OneToMany, 2),
+            (EdgeCardinality::OneToOne, 3),
+        ] {
+            assert_eq!(cardinality as u8, expected);
+            assert_wire_value(cardinality, expected);
+        }
+    }
 }
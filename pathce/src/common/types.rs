@@ -4,6 +4,7 @@ use bimap::BiHashMap;
 pub type LabelId = u32;
 pub type DefaultVertexId = usize;
 pub type InternalId = u32;
+/// Maps the vertex ids of a label to internal ids, which are contiguous in `0..len`.
 pub type InternalVertexMap = BiHashMap<DefaultVertexId, InternalId>;
 pub type TagId = u8;
 pub type BucketId = usize;
@@ -12,10 +13,14 @@ pub type LocalBucketMap = HashMap<DefaultVertexId, BucketId>;
 
 pub const INVALID_TAG_ID: TagId = u8::MAX;
 
-const INVALID_VERTEX_ID: DefaultVertexId = usize::MAX;
+/// The sentinel marking a missing vertex, e.g., a missing neighbor in a sampled path. It is
+/// rejected as the id of a real vertex when building a graph.
+pub const INVALID_VERTEX_ID: DefaultVertexId = usize::MAX;
 
 pub trait VertexId: Default + Clone + Copy + Send {
+    /// Return the sentinel marking a missing vertex.
     fn invalid() -> Self;
+    /// Return whether `self` is not the sentinel returned by [`VertexId::invalid`].
     fn is_valid(&self) -> bool;
 }
 
@@ -28,3 +33,17 @@ impl VertexId for DefaultVertexId {
         *self != INVALID_VERTEX_ID
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_vertex_id() {
+        assert_eq!(DefaultVertexId::invalid(), INVALID_VERTEX_ID);
+        assert!(!DefaultVertexId::invalid().is_valid());
+        for id in [0, 1, DefaultVertexId::default(), INVALID_VERTEX_ID - 1] {
+            assert!(id.is_valid(), "{id}");
+        }
+    }
+}
@@ -0,0 +1,355 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use rayon::ThreadPool;
+
+use crate::common::{DefaultVertexId, EdgeDirection, LabelId};
+use crate::graph::{LabeledGraph, LabeledVertex};
+use crate::pattern::{GraphPattern, PatternVertex};
+
+/// A vertex of the pattern in the matching order, with the edges to the vertices before it.
+#[derive(Debug, Clone)]
+struct MatchStep {
+    vertex: PatternVertex,
+    /// The edge whose neighbors are the candidates: `(position, edge label, direction)`.
+    expand: Option<(usize, LabelId, EdgeDirection)>,
+    /// The other edges to check: `(src position, dst position, edge label)`.
+    checks: Vec<(usize, usize, LabelId)>,
+}
+
+/// Order the vertices so that every vertex but the first is adjacent to a vertex before it,
+/// preferring the vertices with the most edges to the matched ones.
+fn matching_order<P: GraphPattern>(pattern: &P) -> Vec<MatchStep> {
+    let mut order: Vec<PatternVertex> = vec![];
+    let mut steps = vec![];
+    while order.len() < pattern.vertices().len() {
+        let position = |tag_id| order.iter().position(|v| v.tag_id() == tag_id);
+        let vertex = pattern
+            .vertices()
+            .iter()
+            .filter(|v| position(v.tag_id()).is_none())
+            .max_by_key(|v| {
+                let matched = pattern
+                    .neighbor_tag_ids(v.tag_id())
+                    .unwrap()
+                    .filter(|n| position(*n).is_some())
+                    .count();
+                let degree = pattern.get_vertex_degree(v.tag_id()).unwrap();
+                (matched, degree, std::cmp::Reverse(v.tag_id()))
+            })
+            .copied()
+            .unwrap();
+        let current = order.len();
+        let mut expand = None;
+        let mut checks = vec![];
+        let mut visited_edges = vec![];
+        for adj in pattern.adjacencies(vertex.tag_id()).unwrap() {
+            // A self loop may appear in both adjacency lists, so it is visited only once.
+            if visited_edges.contains(&adj.edge_tag_id()) {
+                continue;
+            }
+            visited_edges.push(adj.edge_tag_id());
+            let neighbor = adj.neighbor_tag_id();
+            let edge = pattern.get_edge(adj.edge_tag_id()).unwrap();
+            let neighbor_position = if neighbor == vertex.tag_id() {
+                current
+            } else if let Some(position) = position(neighbor) {
+                position
+            } else {
+                continue;
+            };
+            let direction = adj.direction();
+            if expand.is_none() && neighbor_position != current {
+                let reverse = match direction {
+                    EdgeDirection::Out => EdgeDirection::In,
+                    EdgeDirection::In => EdgeDirection::Out,
+                };
+                expand = Some((neighbor_position, edge.label_id(), reverse));
+                continue;
+            }
+            let (src, dst) = match direction {
+                EdgeDirection::Out => (current, neighbor_position),
+                EdgeDirection::In => (neighbor_position, current),
+            };
+            checks.push((src, dst, edge.label_id()));
+        }
+        order.push(vertex);
+        steps.push(MatchStep {
+            vertex,
+            expand,
+            checks,
+        });
+    }
+    steps
+}
+
+struct Matcher<'a> {
+    graph: &'a LabeledGraph,
+    steps: Vec<MatchStep>,
+    deadline: Option<Instant>,
+    timed_out: AtomicBool,
+}
+
+impl<'a> Matcher<'a> {
+    fn count(&self, assignment: &mut Vec<DefaultVertexId>) -> u128 {
+        let position = assignment.len();
+        if position == self.steps.len() {
+            return 1;
+        }
+        if self.timed_out.load(Ordering::Relaxed) {
+            return 0;
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            self.timed_out.store(true, Ordering::Relaxed);
+            return 0;
+        }
+        let step = &self.steps[position];
+        let label_id = step.vertex.label_id();
+        let Some(vertex_map) = self.graph.get_internal_vertex_map(label_id) else {
+            return 0;
+        };
+        let (src_position, edge_label_id, direction) = step.expand.unwrap();
+        let src_label_id = self.steps[src_position].vertex.label_id();
+        let src = LabeledVertex::new(assignment[src_position], src_label_id);
+        let Some(candidates) = self.graph.neighbors(src, edge_label_id, direction) else {
+            return 0;
+        };
+        let mut count = 0;
+        for candidate in candidates.iter() {
+            if !vertex_map.contains_left(&candidate) {
+                continue;
+            }
+            assignment.push(candidate);
+            let multiplicity = self.check(assignment, &step.checks);
+            if multiplicity > 0 {
+                count += multiplicity * self.count(assignment);
+            }
+            assignment.pop();
+        }
+        count
+    }
+
+    /// Return the number of ways to map the checked edges, i.e., the product of the numbers of
+    /// parallel edges between their matched endpoints.
+    fn check(&self, assignment: &[DefaultVertexId], checks: &[(usize, usize, LabelId)]) -> u128 {
+        let mut product = 1;
+        for (src, dst, edge_label_id) in checks {
+            let src_label_id = self.steps[*src].vertex.label_id();
+            let src = LabeledVertex::new(assignment[*src], src_label_id);
+            product *= self
+                .graph
+                .edge_multiplicity(src, assignment[*dst], *edge_label_id)
+                as u128;
+            if product == 0 {
+                break;
+            }
+        }
+        product
+    }
+}
+
+/// Count the homomorphisms of the connected `pattern` in `graph` by backtracking, in parallel over
+/// the candidates of the first vertex. See [`LabeledGraph::count_pattern_exact`].
+pub(crate) fn count_pattern_exact<P: GraphPattern>(
+    graph: &LabeledGraph,
+    pattern: &P,
+    timeout: Option<Duration>,
+) -> Option<u128> {
+    let matcher = Matcher {
+        graph,
+        steps: matching_order(pattern),
+        deadline: timeout.map(|timeout| Instant::now() + timeout),
+        timed_out: AtomicBool::new(false),
+    };
+    let Some(first) = matcher.steps.first() else {
+        return Some(1);
+    };
+    let Some(vertices) = graph.vertices(first.vertex.label_id()) else {
+        return Some(0);
+    };
+    let count = vertices
+        .par_iter()
+        .map(|v| {
+            let mut assignment = vec![*v];
+            match matcher.check(&assignment, &matcher.steps[0].checks) {
+                0 => 0,
+                multiplicity => multiplicity * matcher.count(&mut assignment),
+            }
+        })
+        .sum();
+    (!matcher.timed_out.load(Ordering::Relaxed)).then_some(count)
+}
+
+/// Counts patterns exactly with [`LabeledGraph::count_pattern_exact`] in a dedicated thread pool.
+pub struct ExactCounter {
+    graph: Arc<LabeledGraph>,
+    pool: Arc<ThreadPool>,
+}
+
+impl ExactCounter {
+    pub fn new(graph: Arc<LabeledGraph>, pool: Arc<ThreadPool>) -> Self {
+        Self { graph, pool }
+    }
+
+    /// Count the homomorphisms of the connected `pattern` like
+    /// [`LabeledGraph::count_pattern_exact`], in the pool of the counter.
+    ///
+    /// Return `None` if the count is not finished within `timeout`.
+    pub fn count<P: GraphPattern + Sync>(
+        &self,
+        pattern: &P,
+        timeout: Option<Duration>,
+    ) -> Option<u128> {
+        self.pool
+            .install(|| self.graph.count_pattern_exact(pattern, timeout))
+    }
+
+    /// Count each of `patterns` like [`Self::count`], in parallel. The timeout of each count starts
+    /// when the count does.
+    pub fn count_many<P: GraphPattern + Sync>(
+        &self,
+        patterns: &[&P],
+        timeout: Option<Duration>,
+    ) -> Vec<Option<u128>> {
+        self.pool.scope(|_| {
+            patterns
+                .par_iter()
+                .map(|pattern| self.count(*pattern, timeout))
+                .collect()
+        })
+    }
+
+    pub(crate) fn num_threads(&self) -> usize {
+        self.pool.current_num_threads()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+    use crate::counter::{PathCounter, StarCounter};
+    use crate::graph::LabeledGraphBuilder;
+    use crate::pattern::{GeneralPattern, RawPattern};
+    use crate::test_utils::{build_ldbc_graph, build_test_pool};
+
+    #[test]
+    fn test_count_exact() {
+        let graph = Arc::new(build_ldbc_graph());
+        let pool = build_test_pool();
+        let counter = ExactCounter::new(graph.clone(), pool.clone());
+
+        let empty = RawPattern::new().to_general().unwrap();
+        assert_eq!(counter.count(&empty, None), Some(1));
+
+        let vertex = RawPattern::new()
+            .push_back_vertex((0, 6))
+            .to_path()
+            .unwrap();
+        assert_eq!(counter.count(&vertex, None), Some(50));
+
+        let path = RawPattern::with_vertices_edges(
+            [(0, 6), (1, 6), (2, 6), (3, 6)],
+            [(0, 0, 1, 14), (1, 2, 1, 14), (2, 3, 2, 14)],
+        )
+        .to_path()
+        .unwrap();
+        let expected = PathCounter::new(graph.clone(), pool.clone()).count(&path);
+        assert_eq!(expected, 1185);
+        assert_eq!(counter.count(&path, None), Some(expected));
+
+        let star = RawPattern::with_vertices_edges(
+            [(0, 6), (1, 6), (2, 6)],
+            [(0, 0, 1, 14), (1, 0, 2, 14)],
+        )
+        .to_general()
+        .unwrap();
+        let expected = StarCounter::new(graph.clone(), pool).count(&star);
+        assert_eq!(counter.count(&star, None), Some(expected));
+
+        // (a)-[:knows]->(b)-[:knows]->(c), (a)-[:knows]->(c)
+        let triangle = RawPattern::with_vertices_edges(
+            [(0, 6), (1, 6), (2, 6)],
+            [(0, 0, 1, 14), (1, 1, 2, 14), (2, 0, 2, 14)],
+        )
+        .to_general()
+        .unwrap();
+        let knows = |id| {
+            graph
+                .neighbors(LabeledVertex::new(id, 6), 14, EdgeDirection::Out)
+                .unwrap()
+        };
+        let expected: usize = graph
+            .vertices(6)
+            .unwrap()
+            .iter()
+            .map(|a| {
+                let a_knows = knows(*a);
+                a_knows
+                    .iter()
+                    .map(|b| knows(b).iter().filter(|c| a_knows.contains(*c)).count())
+                    .sum::<usize>()
+            })
+            .sum();
+        assert!(expected > 0);
+        assert_eq!(counter.count(&triangle, None), Some(expected as u128));
+
+        assert_eq!(counter.count(&triangle, Some(Duration::ZERO)), None);
+        let patterns = [GeneralPattern::from(path), star, triangle];
+        let counts = patterns
+            .iter()
+            .map(|p| counter.count(p, None))
+            .collect_vec();
+        let patterns = patterns.iter().collect_vec();
+        assert_eq!(counter.count_many(&patterns, None), counts);
+    }
+
+    #[test]
+    fn test_count_exact_multigraph() {
+        // 1 -> 2 twice, 1 -> 3 three times, 2 -> 3 and 3 -> 1.
+        let graph = LabeledGraphBuilder::new(1)
+            .add_vertex_label(0)
+            .add_edge_label(0, 0, 0)
+            .add_vertex(1, 0)
+            .add_vertex(2, 0)
+            .add_vertex(3, 0)
+            .add_edge(1, 2, 0)
+            .add_edge(1, 2, 0)
+            .add_edge(1, 3, 0)
+            .add_edge(1, 3, 0)
+            .add_edge(1, 3, 0)
+            .add_edge(2, 3, 0)
+            .add_edge(3, 1, 0)
+            .build()
+            .unwrap();
+        assert_eq!(graph.edge_multiplicity(LabeledVertex::new(1, 0), 3, 0), 3);
+        assert_eq!(graph.edge_multiplicity(LabeledVertex::new(3, 0), 2, 0), 0);
+
+        let edge = RawPattern::with_vertices_edges([(0, 0), (1, 0)], [(0, 0, 1, 0)])
+            .to_general()
+            .unwrap();
+        assert_eq!(graph.count_pattern_exact(&edge, None), Some(7));
+
+        // The in-degree times the out-degree of the middle vertex, summed over 1, 2 and 3.
+        let path =
+            RawPattern::with_vertices_edges([(0, 0), (1, 0), (2, 0)], [(0, 0, 1, 0), (1, 1, 2, 0)])
+                .to_general()
+                .unwrap();
+        assert_eq!(graph.count_pattern_exact(&path, None), Some(5 + 2 + 4));
+
+        // Only (1, 2, 3) matches, along 2 * 1 * 3 combinations of parallel edges.
+        let triangle = RawPattern::with_vertices_edges(
+            [(0, 0), (1, 0), (2, 0)],
+            [(0, 0, 1, 0), (1, 1, 2, 0), (2, 0, 2, 0)],
+        )
+        .to_general()
+        .unwrap();
+        assert_eq!(graph.count_pattern_exact(&triangle, None), Some(6));
+    }
+}
@@ -1,5 +1,9 @@
+mod exact;
+
 use std::sync::Arc;
 
+pub(crate) use exact::count_pattern_exact;
+pub use exact::ExactCounter;
 use itertools::Itertools;
 use rayon::iter::{
     IndexedParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator,
@@ -81,7 +85,7 @@ impl PathCounter {
                     .par_iter()
                     .map(|neighbor_id| {
                         let neighbor_internal_id =
-                            *neighbor_vertex_map.get_by_left(neighbor_id).unwrap();
+                            *neighbor_vertex_map.get_by_left(&neighbor_id).unwrap();
                         count_vec
                             .as_ref()
                             .get(neighbor_internal_id as usize)
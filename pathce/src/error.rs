@@ -1,21 +1,28 @@
+use std::num::ParseIntError;
+
+use duckdb::arrow::error::ArrowError;
 use thiserror::Error;
 
+use crate::common::{DefaultVertexId, InternalId, LabelId, TagId};
+use crate::pattern::{GeneralPattern, PathPattern};
+use crate::schema::{SchemaEdge, SchemaVertex};
+
 pub type GCardResult<T> = Result<T, GCardError>;
 
 #[derive(Debug, Error)]
 pub enum GCardError {
     #[error("CatalogError: {0}")]
-    Catalog(String),
+    Catalog(#[from] CatalogError),
     #[error("SchemaError: {0}")]
-    Schema(String),
+    Schema(#[from] SchemaError),
     #[error("SampleError: {0}")]
-    Sample(String),
+    Sample(#[from] SampleError),
     #[error("PatternError: {0}")]
-    Pattern(String),
+    Pattern(#[from] PatternError),
     #[error("EstimateError: {0}")]
-    Estimate(String),
+    Estimate(#[from] EstimateError),
     #[error("GraphError: {0}")]
-    Graph(String),
+    Graph(#[from] GraphError),
     #[error(transparent)]
     DuckDb(#[from] duckdb::Error),
     #[error(transparent)]
@@ -29,3 +36,246 @@ pub enum GCardError {
     #[error(transparent)]
     ThreadPool(#[from] rayon::ThreadPoolBuildError),
 }
+
+#[derive(Debug, Error)]
+pub enum CatalogError {
+    #[error("cannot stage statistics: {0}")]
+    Staging(#[from] ArrowError),
+    #[error("cannot export a catalog imported with its database attached")]
+    ExportAttached,
+    #[error("path {0} is missing after import")]
+    PathMissingAfterImport(Box<PathPattern>),
+    #[error("star {0} is missing after import")]
+    StarMissingAfterImport(LabelId),
+    #[error("estimate of {name} changed after import: {expected} -> {actual}")]
+    EstimateChanged {
+        name: String,
+        expected: f64,
+        actual: f64,
+    },
+    #[error("cannot change the encoding keys of a non-empty catalog")]
+    NonEmpty,
+    #[error("path {path} already exists in the catalog, label_id: {existing}")]
+    DuplicatePath {
+        path: Box<PathPattern>,
+        existing: LabelId,
+    },
+    #[error("star already exists in the catalog, label_id: {0}")]
+    DuplicateStar(LabelId),
+    #[error("cannot find path {0} in the catalog")]
+    PathNotFound(LabelId),
+    #[error("cannot find star {0} in the catalog")]
+    StarNotFound(LabelId),
+    #[error("edge label {0} is not in the catalog")]
+    EdgeLabelNotFound(LabelId),
+    #[error("vertex label {0} is not in the catalog")]
+    VertexLabelNotFound(LabelId),
+    #[error("invalid estimate: {0}")]
+    InvalidEstimate(f64),
+    #[error("cannot replace statistics of a catalog imported with its database attached")]
+    ReplaceAttached,
+    #[error("path {0} is not in the catalog")]
+    UnknownPath(Box<PathPattern>),
+    #[error("star {star} with center rank {center_rank} is not in the catalog")]
+    UnknownStar {
+        star: Box<GeneralPattern>,
+        center_rank: TagId,
+    },
+    #[error("cannot recompute statistics with greedy binning, which depends on all the edges")]
+    RecomputeGreedyBinning,
+    #[error("unsupported catalog metadata version {0}")]
+    UnsupportedMetadataVersion(u32),
+    #[error("no statistics of the single-edge paths of non-empty edge labels: {0:?}")]
+    MissingEdgeStatistics(Vec<String>),
+}
+
+#[derive(Debug, Error)]
+pub enum SchemaError {
+    #[error("vertex label id: {0} does not exist")]
+    UnknownVertexLabel(LabelId),
+    #[error("edge label id: {0} does not exist")]
+    UnknownEdgeLabel(LabelId),
+    #[error("duplicate vertex: {0:?}")]
+    DuplicateVertex(SchemaVertex),
+    #[error("duplicate edge: {0:?}")]
+    DuplicateEdge(SchemaEdge),
+    #[error("vertex with label id: {0} does not exist")]
+    MissingVertex(LabelId),
+    #[error("schema not connected, cc: {0:?}")]
+    Disconnected(Vec<Vec<LabelId>>),
+    #[error("label id: {0} is not abstract, so it cannot be a supertype")]
+    ConcreteSupertype(LabelId),
+    #[error("label id: {0} is a subtype of itself")]
+    CyclicSubtype(LabelId),
+}
+
+#[derive(Debug, Error)]
+pub enum SampleError {
+    #[error("vertex label {0} is not in the graph")]
+    VertexLabelNotFound(LabelId),
+    #[error("edge label {0} is not in the graph")]
+    EdgeLabelNotFound(LabelId),
+    #[error("path {new} does not extend path {base} by one edge")]
+    NotExtension {
+        base: Box<PathPattern>,
+        new: Box<PathPattern>,
+    },
+    #[error("table with {num_tags} tags is not a sample of path {path}")]
+    TableMismatch {
+        path: Box<PathPattern>,
+        num_tags: usize,
+    },
+}
+
+#[derive(Debug, Error)]
+pub enum PatternError {
+    #[error("duplicate vertex tag id")]
+    DuplicateVertexTag,
+    #[error("duplicate edge tag id")]
+    DuplicateEdgeTag,
+    #[error("vertex with tag id {0} not exist")]
+    MissingVertex(TagId),
+    #[error("pattern not connected")]
+    NotConnected,
+    #[error("empty path is not allowed")]
+    EmptyPath,
+    #[error("invalid path")]
+    InvalidPath,
+    #[error("cannot take {k} edges of a path of length {len}")]
+    PathOutOfBounds { k: usize, len: usize },
+    #[error("edge {edge} references vertex {vertex} which is not in the pattern")]
+    DanglingEdge { edge: TagId, vertex: TagId },
+    #[error("cannot reconstruct general edge {0}")]
+    GeneralEdge(TagId),
+    #[error("vertex {0} has conflicting labels")]
+    ConflictingLabels(TagId),
+    #[error("invalid pattern encoding of {0} bytes")]
+    InvalidEncoding(usize),
+}
+
+#[derive(Debug, Error)]
+pub enum EstimateError {
+    #[error("edge {0} of the partition is not in the pattern")]
+    UnknownPartitionEdge(TagId),
+    #[error("edge {0} is in more than one group of the partition")]
+    OverlappingPartition(TagId),
+    #[error("edge {0} is not covered by the partition")]
+    UncoveredEdge(TagId),
+    #[error("edges {0:?} form neither a path nor a star")]
+    InvalidGroup(Vec<TagId>),
+    #[error("cannot find edges {0:?} in the catalog")]
+    GroupNotFound(Vec<TagId>),
+    #[error("vertex {vertex} inside edges {group:?} is shared with other groups")]
+    SharedGroupVertex { vertex: TagId, group: Vec<TagId> },
+    #[error("selectivity override of edge {0} which is not in the pattern")]
+    UnknownOverrideEdge(TagId),
+    #[error("invalid selectivity override of edge {edge}: {selectivity}")]
+    InvalidOverride { edge: TagId, selectivity: f64 },
+    #[error("distinct target {0} which is not in the pattern")]
+    UnknownDistinctTarget(TagId),
+    #[error("edge {0} is not in the catalog")]
+    MissingEdge(TagId),
+}
+
+#[derive(Debug, Error)]
+pub enum GraphError {
+    #[error("edges are not sorted: {0:?} is followed by {1:?}")]
+    UnsortedEdges((InternalId, DefaultVertexId), (InternalId, DefaultVertexId)),
+    #[error("index {index} does not fit in {width}")]
+    IndexOverflow { index: usize, width: &'static str },
+    #[error("unsupported graph format version {0}")]
+    UnsupportedFormatVersion(u32),
+    #[error("vertex id {vertex_id} exceeds the number of vertices {num_vertices}")]
+    VertexIdOutOfRange {
+        vertex_id: InternalId,
+        num_vertices: usize,
+    },
+    #[error("invalid vertex id: {0} is reserved as the sentinel")]
+    InvalidVertexId(DefaultVertexId),
+    #[error("duplicate vertex id: {0} found in the csv")]
+    DuplicateVertex(DefaultVertexId),
+    #[error("cannot find vertex {0} in the vertex map")]
+    VertexNotFound(DefaultVertexId),
+    #[error("cannot find src and dst label of edge label {0}")]
+    EdgeLabelEndpointsNotFound(LabelId),
+    #[error("cannot find vertex map of vertex label {0}")]
+    VertexMapNotFound(LabelId),
+    #[error("cannot find vertex label {0} in the graph")]
+    VertexLabelNotFound(LabelId),
+    #[error("cannot find edge label {0} in the graph")]
+    EdgeLabelNotFound(LabelId),
+    #[error("edge label {0} is not in the schema")]
+    EdgeLabelNotInSchema(LabelId),
+    #[error("vertex {vertex_id} appears under both vertex labels {} and {}", .labels.0, .labels.1)]
+    AmbiguousVertexLabel {
+        vertex_id: DefaultVertexId,
+        labels: (LabelId, LabelId),
+    },
+    #[error("edge label {edge_label} has an endpoint {vertex_id} not labeled {vertex_label}")]
+    EndpointLabelMismatch {
+        edge_label: LabelId,
+        vertex_id: DefaultVertexId,
+        vertex_label: LabelId,
+    },
+    #[error("expect {name} in line {line}")]
+    MissingField { name: String, line: usize },
+    #[error("invalid {name} {field:?} in line {line}: {source}")]
+    InvalidField {
+        name: String,
+        field: String,
+        line: usize,
+        source: ParseIntError,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::EdgeCardinality;
+    use crate::graph::LabeledGraphBuilder;
+    use crate::pattern::RawPattern;
+    use crate::schema::{Schema, SchemaUnchecked};
+
+    #[test]
+    fn test_structured_errors() {
+        let err = LabeledGraphBuilder::new(1)
+            .add_vertex_label(0)
+            .add_vertex(1, 0)
+            .add_vertex(1, 0)
+            .build()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            GCardError::Graph(GraphError::DuplicateVertex(1))
+        ));
+        assert_eq!(
+            err.to_string(),
+            "GraphError: duplicate vertex id: 1 found in the csv"
+        );
+
+        let err = RawPattern::with_vertices_edges([(0, 0), (1, 0)], [(0, 0, 0, 0)])
+            .to_general()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            GCardError::Pattern(PatternError::NotConnected)
+        ));
+        assert_eq!(err.to_string(), "PatternError: pattern not connected");
+
+        let err = Schema::try_from(
+            SchemaUnchecked::default()
+                .add_vertex_label("a".into(), 0)
+                .add_vertex_label("b".into(), 1)
+                .add_edge_label("e".into(), 0)
+                .add_vertex((0, false))
+                .add_vertex((1, false))
+                .add_edge((0, 0, 0, EdgeCardinality::ManyToMany)),
+        )
+        .unwrap_err();
+        let GCardError::Schema(SchemaError::Disconnected(mut cc)) = err else {
+            panic!("unexpected error: {err}");
+        };
+        cc.sort();
+        assert_eq!(cc, vec![vec![0], vec![1]]);
+    }
+}
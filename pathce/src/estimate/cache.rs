@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::GCardResult;
+use crate::pattern::EncodedPattern;
+
+/// The estimates of [`super::CardinalityEstimator::estimate_cached`] keyed by the pattern
+/// encodings. The cache is tied to the content hash of the catalog and the settings of the
+/// estimator that filled it, and is cleared when used by an estimator with another key, so a
+/// cache saved to disk stays valid across imports of the same catalog.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct EstimateCache {
+    key: Option<u128>,
+    estimates: HashMap<EncodedPattern, f64>,
+}
+
+impl EstimateCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a cache saved by [`Self::save`], or return an empty cache if `path` does not exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> GCardResult<Self> {
+        if !path.as_ref().exists() {
+            return Ok(Self::new());
+        }
+        let reader = BufReader::new(File::open(path)?);
+        Ok(bincode::deserialize_from(reader)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> GCardResult<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(writer, self)?;
+        Ok(())
+    }
+
+    pub fn get(&self, code: &EncodedPattern) -> Option<f64> {
+        self.estimates.get(code).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.estimates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.estimates.is_empty()
+    }
+
+    /// Clear the cache if it was filled under another key.
+    pub(super) fn validate(&mut self, key: u128) {
+        if self.key != Some(key) {
+            self.key = Some(key);
+            self.estimates.clear();
+        }
+    }
+
+    pub(super) fn insert(&mut self, code: EncodedPattern, card: f64) {
+        self.estimates.insert(code, card);
+    }
+}
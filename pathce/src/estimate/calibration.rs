@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use itertools::Itertools;
+use serde::Serialize;
+
+use super::CardinalityEstimator;
+use crate::counter::ExactCounter;
+use crate::error::GCardResult;
+use crate::pattern::{EncodedPattern, GraphPattern};
+
+/// An estimate of a pattern paired with its exact count, for fitting corrections of estimates.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CalibrationRecord {
+    /// The encoding of the pattern.
+    pub code: EncodedPattern,
+    pub estimate: f64,
+    /// The exact count, or `None` if counting timed out.
+    pub truth: Option<u128>,
+}
+
+/// Estimate every pattern of `workload` and count it exactly with `counter` (giving up after
+/// `timeout`), passing the records to `sink` in order. The patterns are counted in parallel in
+/// chunks of the number of threads of `counter`, so that the records of large workloads are not
+/// held in memory.
+pub fn calibrate<'w, P, F>(
+    estimator: &CardinalityEstimator,
+    counter: &ExactCounter,
+    workload: impl IntoIterator<Item = &'w P>,
+    timeout: Option<Duration>,
+    mut sink: F,
+) -> GCardResult<()>
+where
+    P: GraphPattern + Sync + 'w,
+    F: FnMut(CalibrationRecord) -> GCardResult<()>,
+{
+    for chunk in &workload.into_iter().chunks(counter.num_threads()) {
+        let patterns = chunk.collect_vec();
+        let estimates: Vec<_> = patterns
+            .iter()
+            .map(|pattern| estimator.estimate(*pattern))
+            .try_collect()?;
+        let truths = counter.count_many(&patterns, timeout);
+        for ((pattern, estimate), truth) in patterns.into_iter().zip_eq(estimates).zip_eq(truths) {
+            sink(CalibrationRecord {
+                code: pattern.encode(),
+                estimate,
+                truth,
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Collect the records of [`calibrate`].
+pub fn calibration_records<'w, P>(
+    estimator: &CardinalityEstimator,
+    counter: &ExactCounter,
+    workload: impl IntoIterator<Item = &'w P>,
+    timeout: Option<Duration>,
+) -> GCardResult<Vec<CalibrationRecord>>
+where
+    P: GraphPattern + Sync + 'w,
+{
+    let mut records = vec![];
+    calibrate(estimator, counter, workload, timeout, |record| {
+        records.push(record);
+        Ok(())
+    })?;
+    Ok(records)
+}
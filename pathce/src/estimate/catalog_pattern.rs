@@ -1,10 +1,18 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
 
 use ahash::{HashMap, HashSet, HashSetExt};
 use serde::{Deserialize, Serialize};
 
+use crate::catalog::Catalog;
 use crate::common::{LabelId, TagId};
-use crate::pattern::PatternVertex;
+use crate::error::{CatalogError, EstimateError, GCardResult, PatternError};
+use crate::pattern::{GeneralPattern, GraphPattern, PatternEdge, PatternVertex, RawPattern};
+
+/// The label id of a path standing for a single edge missing from the catalog, which the fallback
+/// of the decomposer cannot split further. Joining it fails instead of estimating 0 like empty
+/// statistics.
+pub const MISSING_LABEL_ID: LabelId = LabelId::MAX;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct CatalogVertex {
@@ -38,6 +46,7 @@ pub struct CatalogEdge {
     tag_id: TagId,
     label_id: LabelId,
     kind: CatalogEdgeKind,
+    covered_edges: Vec<TagId>,
 }
 
 impl CatalogEdge {
@@ -46,6 +55,7 @@ impl CatalogEdge {
             tag_id,
             label_id,
             kind: CatalogEdgeKind::Star { center },
+            covered_edges: vec![],
         }
     }
 
@@ -54,6 +64,7 @@ impl CatalogEdge {
             tag_id,
             label_id,
             kind: CatalogEdgeKind::Path { src, dst },
+            covered_edges: vec![],
         }
     }
 
@@ -62,6 +73,7 @@ impl CatalogEdge {
             tag_id,
             label_id,
             kind: CatalogEdgeKind::General(vertices),
+            covered_edges: vec![],
         }
     }
 
@@ -76,6 +88,98 @@ impl CatalogEdge {
     pub fn kind(&self) -> &CatalogEdgeKind {
         &self.kind
     }
+
+    /// Record the tag ids of the edges in the original pattern subsumed by this edge.
+    pub fn with_covered_edges(mut self, edges: impl IntoIterator<Item = TagId>) -> Self {
+        self.covered_edges = edges.into_iter().collect();
+        self
+    }
+
+    /// Return the tag ids of the edges in the original pattern subsumed by this edge.
+    pub fn covered_edges(&self) -> &[TagId] {
+        &self.covered_edges
+    }
+
+    /// Fail if this edge stands for an edge missing from the catalog (see [`MISSING_LABEL_ID`]).
+    pub fn check_missing(&self) -> GCardResult<()> {
+        if self.label_id == MISSING_LABEL_ID {
+            let tag_id = self.covered_edges.first().copied().unwrap_or(self.tag_id);
+            return Err(EstimateError::MissingEdge(tag_id).into());
+        }
+        Ok(())
+    }
+}
+
+/// The joint statistics of the length-2 path formed by the two edges incident to a vertex, each
+/// covering a single edge of the original pattern. Eliminating the vertex takes the count from
+/// them instead of joining the two edges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JointEdge {
+    pub label_id: LabelId,
+    /// The tag ids of the two edges.
+    pub edges: [TagId; 2],
+    /// The tag id of the vertex of the original pattern at the start of the joint path.
+    pub start: TagId,
+    /// The tag id of the vertex of the original pattern at the end of the joint path.
+    pub end: TagId,
+}
+
+/// The structure of the join graph of a [`CatalogPattern`], e.g., to rank decompositions. A denser
+/// join graph tends to give a tighter bound, but is costlier to join.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct IncidenceStats {
+    pub num_vertices: usize,
+    pub num_edges: usize,
+    /// The number of vertices by the number of edges incident to them.
+    pub incidence_histogram: BTreeMap<usize, usize>,
+}
+
+impl IncidenceStats {
+    /// Return the largest number of edges incident to a vertex.
+    pub fn max_incidence(&self) -> usize {
+        self.incidence_histogram
+            .last_key_value()
+            .map(|(incidence, _)| *incidence)
+            .unwrap_or_default()
+    }
+
+    /// Return the mean number of edges incident to a vertex.
+    pub fn mean_incidence(&self) -> f64 {
+        if self.num_vertices == 0 {
+            return 0.0;
+        }
+        let total: usize = self
+            .incidence_histogram
+            .iter()
+            .map(|(incidence, count)| incidence * count)
+            .sum();
+        total as f64 / self.num_vertices as f64
+    }
+}
+
+/// Render the edge as, e.g., `star(label=4) @ v2`, `path(label=0) v2->v3`, or
+/// `general(label=5) {v1, v2}`.
+impl Display for CatalogEdge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            CatalogEdgeKind::Star { center } => {
+                write!(f, "star(label={}) @ v{center}", self.label_id)
+            }
+            CatalogEdgeKind::Path { src, dst } => {
+                write!(f, "path(label={}) v{src}->v{dst}", self.label_id)
+            }
+            CatalogEdgeKind::General(vertices) => {
+                write!(f, "general(label={}) {{", self.label_id)?;
+                for (i, v) in vertices.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "v{v}")?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
@@ -85,6 +189,8 @@ pub struct CatalogPattern {
     tag_vertex_map: HashMap<TagId, usize>,
     tag_edge_map: HashMap<TagId, usize>,
     adj_list: HashMap<TagId, BTreeSet<TagId>>,
+    /// The joint edges by their middle vertices, whose incident edges are exactly the two edges.
+    joints: BTreeMap<TagId, JointEdge>,
 }
 
 impl CatalogPattern {
@@ -115,6 +221,14 @@ impl CatalogPattern {
     }
 
     pub fn add_edge(&mut self, edge: CatalogEdge) {
+        let vertices = match edge.kind() {
+            CatalogEdgeKind::Star { center } => &[*center][..],
+            CatalogEdgeKind::Path { src, dst } => &[*src, *dst][..],
+            CatalogEdgeKind::General(vertices) => vertices,
+        };
+        for vertex in vertices {
+            self.joints.remove(vertex);
+        }
         let index = self.edges.len();
         assert!(self.tag_edge_map.insert(edge.tag_id, index).is_none());
         match edge.kind() {
@@ -142,6 +256,8 @@ impl CatalogPattern {
             return false;
         }
         let edges = self.adj_list.remove(&tag_id).unwrap();
+        self.joints
+            .retain(|v, joint| *v != tag_id && !joint.edges.iter().any(|e| edges.contains(e)));
         for edge_tag_id in edges {
             let index = self.tag_edge_map.remove(&edge_tag_id).unwrap();
             let edge = self.edges.get(index).unwrap();
@@ -175,9 +291,31 @@ impl CatalogPattern {
         for vertex in vertices {
             self.adj_list.get_mut(vertex).unwrap().remove(&tag_id);
         }
+        self.joints
+            .retain(|_, joint| !joint.edges.contains(&tag_id));
         true
     }
 
+    /// Attach the joint edge to the vertex `tag_id`, whose incident edges must be exactly the two
+    /// edges of `joint`. It is dropped once any of them is removed or another edge is added to the
+    /// vertex.
+    pub fn add_joint(&mut self, tag_id: TagId, joint: JointEdge) {
+        let incident = self.adj_list.get(&tag_id).unwrap();
+        assert_eq!(incident.len(), 2);
+        assert!(joint.edges.iter().all(|e| incident.contains(e)));
+        self.joints.insert(tag_id, joint);
+    }
+
+    /// Return the joint edge attached to the vertex `tag_id`, if any.
+    pub fn get_joint(&self, tag_id: TagId) -> Option<&JointEdge> {
+        self.joints.get(&tag_id)
+    }
+
+    /// Return the vertices with joint edges attached, in ascending order.
+    pub fn joint_vertices(&self) -> impl Iterator<Item = TagId> + '_ {
+        self.joints.keys().copied()
+    }
+
     pub fn vertices(&self) -> impl Iterator<Item = &CatalogVertex> {
         self.vertices
             .iter()
@@ -199,6 +337,113 @@ impl CatalogPattern {
         )
     }
 
+    /// Check that every endpoint referenced by an edge is a vertex of the pattern.
+    pub fn validate(&self) -> GCardResult<()> {
+        for edge in self.edges() {
+            let vertices = match edge.kind() {
+                CatalogEdgeKind::Star { center } => &[*center][..],
+                CatalogEdgeKind::Path { src, dst } => &[*src, *dst][..],
+                CatalogEdgeKind::General(vertices) => vertices,
+            };
+            if let Some(tag_id) = vertices
+                .iter()
+                .find(|tag_id| !self.tag_vertex_map.contains_key(tag_id))
+            {
+                let err = PatternError::DanglingEdge {
+                    edge: edge.tag_id(),
+                    vertex: *tag_id,
+                };
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Expand every edge into the path or star it stands for in the catalog, stitched at the
+    /// shared vertices. The result is isomorphic to the pattern that was decomposed.
+    pub fn reconstruct<C: Catalog>(&self, catalog: &C) -> GCardResult<GeneralPattern> {
+        self.validate()?;
+        let mut next_vertex_tag_id = self
+            .vertices()
+            .map(|v| v.tag_id() + 1)
+            .max()
+            .unwrap_or_default();
+        let mut vertices = BTreeMap::new();
+        let mut edges = vec![];
+        for edge in self.edges() {
+            let (pattern_vertices, pattern_edges, endpoints) = match edge.kind() {
+                CatalogEdgeKind::Star { center } => {
+                    let star = catalog.get_star(edge.label_id());
+                    let rank = catalog.get_star_center_rank(edge.label_id());
+                    let (star, rank) = star
+                        .zip(rank)
+                        .ok_or(CatalogError::StarNotFound(edge.label_id()))?;
+                    let star_center = star.get_vertex_from_rank(rank).unwrap();
+                    (star.vertices(), star.edges(), vec![(star_center, *center)])
+                }
+                CatalogEdgeKind::Path { src, dst } => {
+                    let path = catalog
+                        .get_path(edge.label_id())
+                        .ok_or(CatalogError::PathNotFound(edge.label_id()))?;
+                    let endpoints = vec![(path.start(), *src), (path.end(), *dst)];
+                    (path.vertices(), path.edges(), endpoints)
+                }
+                CatalogEdgeKind::General(_) => {
+                    return Err(PatternError::GeneralEdge(edge.tag_id()).into());
+                }
+            };
+            let mut tag_map = HashMap::default();
+            for (v, tag_id) in endpoints {
+                if *vertices.entry(tag_id).or_insert(v.label_id()) != v.label_id() {
+                    return Err(PatternError::ConflictingLabels(tag_id).into());
+                }
+                tag_map.insert(v.tag_id(), tag_id);
+            }
+            for v in pattern_vertices {
+                tag_map.entry(v.tag_id()).or_insert_with(|| {
+                    let tag_id = next_vertex_tag_id;
+                    next_vertex_tag_id += 1;
+                    vertices.insert(tag_id, v.label_id());
+                    tag_id
+                });
+            }
+            for e in pattern_edges {
+                edges.push(PatternEdge::new(
+                    edges.len() as TagId,
+                    tag_map[&e.src()],
+                    tag_map[&e.dst()],
+                    e.label_id(),
+                ));
+            }
+        }
+        // Vertices not covered by any edge
+        for v in self.vertices() {
+            vertices.entry(v.tag_id()).or_insert(v.label_id());
+        }
+        let vertices = vertices
+            .into_iter()
+            .map(|(tag_id, label_id)| PatternVertex::new(tag_id, label_id));
+        RawPattern::with_vertices_edges(vertices, edges).to_general()
+    }
+
+    /// Return the numbers of vertices and edges, and the distribution of the number of edges
+    /// incident to each vertex.
+    pub fn incidence_stats(&self) -> IncidenceStats {
+        let mut incidence_histogram = BTreeMap::new();
+        for v in self.vertices() {
+            let incidence = self
+                .adj_list
+                .get(&v.tag_id())
+                .map_or(0, |edges| edges.len());
+            *incidence_histogram.entry(incidence).or_default() += 1;
+        }
+        IncidenceStats {
+            num_vertices: self.get_vertices_num(),
+            num_edges: self.get_edges_num(),
+            incidence_histogram,
+        }
+    }
+
     pub fn get_vertex(&self, tag_id: TagId) -> Option<&CatalogVertex> {
         let index = self.tag_vertex_map.get(&tag_id)?;
         self.vertices.get(*index)
@@ -224,6 +469,20 @@ impl From<CatalogVertex> for CatalogPattern {
     }
 }
 
+/// Render the pattern as the list of its edges, e.g., `[path(label=0) v2->v3, star(label=4) @ v2]`.
+impl Display for CatalogPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("[")?;
+        for (i, edge) in self.edges().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{edge}")?;
+        }
+        f.write_str("]")
+    }
+}
+
 impl<'de> Deserialize<'de> for CatalogPattern {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -279,3 +538,123 @@ fn test_ser() {
     ];
     println!("{}", serde_json::to_string_pretty(&p).unwrap());
 }
+
+#[test]
+fn test_validate() {
+    let mut pattern = CatalogPattern::new();
+    pattern.add_vertex(CatalogVertex::new(0, 0));
+    pattern.add_vertex(CatalogVertex::new(1, 0));
+    pattern.add_edge(CatalogEdge::path(0, 0, 0, 1));
+    pattern.add_edge(CatalogEdge::star(1, 1, 0));
+    assert!(pattern.validate().is_ok());
+
+    pattern.add_edge(CatalogEdge::star(2, 1, 2));
+    assert!(pattern.validate().is_err());
+    pattern.remove_edge(2);
+    assert!(pattern.validate().is_ok());
+
+    pattern.add_edge(CatalogEdge::path(3, 0, 1, 3));
+    assert!(pattern.validate().is_err());
+}
+
+#[test]
+fn test_incidence_stats() {
+    let pattern = CatalogPattern::new();
+    assert_eq!(pattern.incidence_stats(), IncidenceStats::default());
+    assert_eq!(pattern.incidence_stats().mean_incidence(), 0.0);
+
+    let mut pattern = CatalogPattern::new();
+    for tag_id in 0..4 {
+        pattern.add_vertex(CatalogVertex::new(tag_id, 0));
+    }
+    pattern.add_edge(CatalogEdge::path(0, 0, 0, 1));
+    pattern.add_edge(CatalogEdge::path(1, 0, 1, 2));
+    pattern.add_edge(CatalogEdge::star(2, 1, 1));
+    pattern.add_edge(CatalogEdge::general(3, 0, vec![1, 2, 3]));
+    let stats = pattern.incidence_stats();
+    assert_eq!(stats.num_vertices, 4);
+    assert_eq!(stats.num_edges, 4);
+    assert_eq!(
+        stats.incidence_histogram,
+        BTreeMap::from([(1, 2), (2, 1), (4, 1)])
+    );
+    assert_eq!(stats.max_incidence(), 4);
+    assert_eq!(stats.mean_incidence(), 2.0);
+
+    pattern.remove_vertex(0);
+    let stats = pattern.incidence_stats();
+    assert_eq!(stats.num_vertices, 3);
+    assert_eq!(stats.num_edges, 3);
+    assert_eq!(
+        stats.incidence_histogram,
+        BTreeMap::from([(1, 1), (2, 1), (3, 1)])
+    );
+}
+
+#[test]
+fn test_reconstruct() {
+    use crate::catalog::MockCatalog;
+
+    // (p1: Person)-[:knows]->(p2: Person)-[:knows]->(p3: Person)<-[:hasCreator]-(c1: Comment),
+    // (p3)<-[:hasCreator]-(c2: Comment)
+    let expected = RawPattern::new()
+        .push_back_vertex((0, 6))
+        .push_back_vertex((1, 6))
+        .push_back_vertex((2, 6))
+        .push_back_vertex((3, 1))
+        .push_back_vertex((4, 1))
+        .push_back_edge((0, 0, 1, 14))
+        .push_back_edge((1, 1, 2, 14))
+        .push_back_edge((2, 3, 2, 1))
+        .push_back_edge((3, 4, 2, 1))
+        .to_general()
+        .unwrap();
+
+    let mut catalog = MockCatalog::default();
+    let path = RawPattern::new()
+        .push_back_vertex((0, 6))
+        .push_back_vertex((1, 6))
+        .push_back_vertex((2, 6))
+        .push_back_edge((0, 0, 1, 14))
+        .push_back_edge((1, 1, 2, 14))
+        .to_path()
+        .unwrap();
+    let path_label_id = catalog.add_path(path);
+    let star = RawPattern::new()
+        .push_back_vertex((0, 1))
+        .push_back_vertex((1, 6))
+        .push_back_vertex((2, 1))
+        .push_back_edge((0, 0, 1, 1))
+        .push_back_edge((1, 2, 1, 1))
+        .to_general()
+        .unwrap();
+    let center_rank = star.get_vertex_rank(1).unwrap();
+    let star_label_id = catalog.add_star(star, center_rank);
+
+    let mut pattern = CatalogPattern::new();
+    pattern.add_vertex(CatalogVertex::new(0, 6));
+    pattern.add_vertex(CatalogVertex::new(2, 6));
+    pattern.add_edge(CatalogEdge::path(0, path_label_id, 0, 2));
+    pattern.add_edge(CatalogEdge::star(1, star_label_id, 2));
+    let reconstructed = pattern.reconstruct(&catalog).unwrap();
+    assert_eq!(reconstructed.encode(), expected.encode());
+
+    pattern.add_edge(CatalogEdge::general(2, 0, vec![0, 2]));
+    assert!(pattern.reconstruct(&catalog).is_err());
+}
+
+#[test]
+fn test_display() {
+    let mut pattern = CatalogPattern::new();
+    for tag_id in 0..4 {
+        pattern.add_vertex(CatalogVertex::new(tag_id, 0));
+    }
+    pattern.add_edge(CatalogEdge::path(0, 0, 2, 3));
+    pattern.add_edge(CatalogEdge::star(1, 4, 2));
+    pattern.add_edge(CatalogEdge::general(2, 5, vec![0, 1]));
+    assert_eq!(
+        pattern.to_string(),
+        "[path(label=0) v2->v3, star(label=4) @ v2, general(label=5) {v0, v1}]"
+    );
+    assert_eq!(CatalogPattern::new().to_string(), "[]");
+}
@@ -1,17 +1,24 @@
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+use std::time::Instant;
 
-use ahash::{HashSet, HashSetExt};
+use ahash::{HashMap, HashSet, HashSetExt};
 use itertools::Itertools;
 use log::{debug, trace};
-use petgraph::algo::is_cyclic_undirected;
-use petgraph::prelude::UnGraphMap;
+use petgraph::unionfind::UnionFind;
 
 use super::PatternDecomposer;
 use crate::catalog::Catalog;
-use crate::common::TagId;
-use crate::estimate::catalog_pattern::{CatalogEdge, CatalogEdgeKind, CatalogPattern};
-use crate::pattern::{GeneralPattern, GraphPattern, PatternEdge, PatternVertex, RawPattern};
+use crate::common::{LabelId, TagId};
+use crate::error::{EstimateError, GCardResult};
+use crate::estimate::catalog_pattern::{
+    CatalogEdge, CatalogEdgeKind, CatalogPattern, JointEdge, MISSING_LABEL_ID,
+};
+use crate::pattern::{
+    EncodedPattern, GeneralPattern, GraphPattern, PatternEdge, PatternVertex, RawPattern,
+};
+use crate::statistics::RequiredStatistics;
 
 pub struct HeuristicDecomposer<'a, C> {
     catalog: &'a C,
@@ -22,6 +29,11 @@ pub struct HeuristicDecomposer<'a, C> {
     disable_star: bool,
     disable_prune: bool,
     disable_cyclic: bool,
+    enable_fallback: bool,
+    deadline: Option<Instant>,
+    /// The stars planned by [`Self::required_statistics`] instead of looking them up, numbered by
+    /// their label ids.
+    planned_stars: Option<Mutex<HashMap<(TagId, EncodedPattern), LabelId>>>,
 }
 
 impl<'a, C> HeuristicDecomposer<'a, C> {
@@ -49,8 +61,34 @@ impl<'a, C> HeuristicDecomposer<'a, C> {
             disable_star,
             disable_prune,
             disable_cyclic,
+            enable_fallback: false,
+            deadline: None,
+            planned_stars: None,
         }
     }
+
+    /// When enabled, a path or star missing from the catalog is split into smaller pieces (joined
+    /// on the shared vertices) until every piece is found, instead of panicking. This assumes
+    /// independence between the pieces and thus trades accuracy for robustness to catalog gaps. A
+    /// single edge missing from the catalog is kept as a path labeled [`MISSING_LABEL_ID`].
+    pub fn enable_fallback(mut self, enable: bool) -> Self {
+        self.enable_fallback = enable;
+        self
+    }
+
+    /// Stop decomposing cyclic patterns into more candidates after `deadline`, once at least one
+    /// candidate is decomposed.
+    pub fn deadline(mut self, deadline: Option<Instant>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    fn should_stop(&self, decomposed: &[CatalogPattern]) -> bool {
+        !decomposed.is_empty()
+            && self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
 }
 
 impl<'a, C: Catalog> HeuristicDecomposer<'a, C> {
@@ -64,6 +102,117 @@ impl<'a, C: Catalog> HeuristicDecomposer<'a, C> {
         self.decompose_candidate_paths(pattern, candidate_paths)
     }
 
+    /// Translate each group of edges of `partition` into a catalog edge, where every edge of
+    /// `pattern` must be in exactly one group. A group must form a path, a cycle, or a star whose
+    /// arms end at leaves of `pattern`, and only share the ends of the path or the center of the
+    /// star with other groups. Groups are not split if they are missing from the catalog.
+    pub fn decompose_with_partition<P: GraphPattern>(
+        &self,
+        pattern: &P,
+        partition: &[Vec<TagId>],
+    ) -> GCardResult<CatalogPattern> {
+        let mut covered = HashSet::new();
+        for &edge in partition.iter().flatten() {
+            if pattern.get_edge(edge).is_none() {
+                return Err(EstimateError::UnknownPartitionEdge(edge).into());
+            }
+            if !covered.insert(edge) {
+                return Err(EstimateError::OverlappingPartition(edge).into());
+            }
+        }
+        if let Some(edge) = pattern
+            .edges()
+            .iter()
+            .find(|e| !covered.contains(&e.tag_id()))
+        {
+            return Err(EstimateError::UncoveredEdge(edge.tag_id()).into());
+        }
+        let edges: Vec<_> = partition
+            .iter()
+            .map(|group| self.translate_group(pattern, group))
+            .try_collect()?;
+        debug!("decompose edges: {:?}", edges);
+        let mut catalog_pattern = build_catalog_pattern(pattern, edges);
+        self.attach_joints(pattern, &mut catalog_pattern);
+        Ok(catalog_pattern)
+    }
+
+    fn translate_group<P: GraphPattern>(
+        &self,
+        pattern: &P,
+        group: &[TagId],
+    ) -> GCardResult<CatalogEdge> {
+        let invalid = || EstimateError::InvalidGroup(group.to_vec());
+        let mut adj_list: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for &tag_id in group {
+            let edge = pattern.get_edge(tag_id).unwrap();
+            if edge.src() == edge.dst() {
+                return Err(invalid().into());
+            }
+            adj_list
+                .entry(edge.src())
+                .or_default()
+                .push((tag_id, edge.dst()));
+            adj_list
+                .entry(edge.dst())
+                .or_default()
+                .push((tag_id, edge.src()));
+        }
+        let (centers, others): (Vec<_>, Vec<_>) = adj_list
+            .iter()
+            .partition(|(_, adjacencies)| adjacencies.len() > 2);
+        let edge = match centers[..] {
+            [] => {
+                // A path starts from a leaf, and a cycle from any of its vertices.
+                let start = others
+                    .iter()
+                    .find(|(_, adjacencies)| adjacencies.len() == 1)
+                    .or(others.first())
+                    .map(|(v, _)| **v)
+                    .ok_or_else(invalid)?;
+                let path = walk_group(&adj_list, start, adj_list[&start][0]);
+                if path.len() != group.len() {
+                    return Err(invalid().into());
+                }
+                let segment = path.to_segment();
+                self.try_translate_segment(pattern, segment)
+            }
+            [(center, adjacencies)] => {
+                let arms = adjacencies
+                    .iter()
+                    .map(|adj| walk_group(&adj_list, *center, *adj))
+                    .collect_vec();
+                if arms.iter().map(PathRef::len).sum::<usize>() != group.len()
+                    || arms.iter().any(|arm| {
+                        arm.end() == *center || pattern.get_vertex_degree(arm.end()).unwrap() != 1
+                    })
+                {
+                    return Err(invalid().into());
+                }
+                let segments = arms.iter().map(PathRef::to_segment).collect_vec();
+                self.try_translate_star(pattern, &segments, *center)
+            }
+            _ => return Err(invalid().into()),
+        };
+        let edge = edge.ok_or_else(|| EstimateError::GroupNotFound(group.to_vec()))?;
+        // The vertices not kept by the catalog edge cannot be joined with other groups.
+        let kept = match edge.kind() {
+            CatalogEdgeKind::Star { center } => vec![*center],
+            CatalogEdgeKind::Path { src, dst } => vec![*src, *dst],
+            CatalogEdgeKind::General(_) => unreachable!(),
+        };
+        if let Some((v, _)) = adj_list.iter().find(|(v, adjacencies)| {
+            !kept.contains(v) && pattern.get_vertex_degree(**v).unwrap() != adjacencies.len()
+        }) {
+            return Err(EstimateError::SharedGroupVertex {
+                vertex: *v,
+                group: group.to_vec(),
+            }
+            .into());
+        }
+        Ok(edge)
+    }
+
     fn decompose_candidate_paths<P: GraphPattern>(
         &self,
         pattern: &P,
@@ -86,12 +235,12 @@ impl<'a, C: Catalog> HeuristicDecomposer<'a, C> {
                 segments.sort_unstable();
                 let (dedup, mut duplicates) = segments.partition_dedup();
                 let segments = dedup.iter().map(|s| s.segment).collect_vec();
-                edges.push(self.translate_star(pattern, &segments, pivot));
+                edges.extend(self.translate_star_with_fallback(pattern, &segments, pivot));
                 while !duplicates.is_empty() {
                     duplicates.sort_unstable();
                     let (dedup, new_duplicates) = duplicates.partition_dedup();
                     let segments = dedup.iter().map(|s| s.segment).collect_vec();
-                    edges.push(self.translate_star(pattern, &segments, pivot));
+                    edges.extend(self.translate_star_with_fallback(pattern, &segments, pivot));
                     duplicates = new_duplicates
                 }
             }
@@ -101,35 +250,126 @@ impl<'a, C: Catalog> HeuristicDecomposer<'a, C> {
             }
         }
         debug!("decompose edges: {:?}", edges);
+        let mut catalog_pattern = build_catalog_pattern(pattern, edges);
+        self.attach_joints(pattern, &mut catalog_pattern);
+        catalog_pattern
+    }
 
-        let mut catalog_pattern = CatalogPattern::new();
-        let mut added_vertices = HashSet::new();
-        for edge in edges {
-            match *edge.kind() {
-                CatalogEdgeKind::Star { center } => {
-                    if !added_vertices.contains(&center) {
-                        added_vertices.insert(center);
-                        let vertex = pattern.get_vertex(center).unwrap();
-                        catalog_pattern.add_vertex(vertex.into());
-                    }
+    /// Attach a [`JointEdge`] to every vertex whose two incident edges each cover a single edge
+    /// towards a distinct vertex of `pattern`, if the catalog has joint statistics of the
+    /// length-2 path formed by these edges.
+    fn attach_joints<P: GraphPattern>(&self, pattern: &P, catalog_pattern: &mut CatalogPattern) {
+        let joints = catalog_pattern
+            .vertices()
+            .filter_map(|v| {
+                let joint = self.try_translate_joint(pattern, catalog_pattern, v.tag_id())?;
+                Some((v.tag_id(), joint))
+            })
+            .collect_vec();
+        for (tag_id, joint) in joints {
+            trace!("attach joint {joint:?} to v{tag_id}");
+            catalog_pattern.add_joint(tag_id, joint);
+        }
+    }
+
+    fn try_translate_joint<P: GraphPattern>(
+        &self,
+        pattern: &P,
+        catalog_pattern: &CatalogPattern,
+        vertex: TagId,
+    ) -> Option<JointEdge> {
+        let incident = catalog_pattern.incident_edges(vertex)?.collect_vec();
+        let [first, second] = incident[..] else {
+            return None;
+        };
+        let mut edges = vec![];
+        let mut ends = vec![];
+        for e in [first, second] {
+            let applicable = match e.kind() {
+                CatalogEdgeKind::Star { center } => *center == vertex,
+                CatalogEdgeKind::Path { src, dst } => src != dst,
+                CatalogEdgeKind::General(_) => false,
+            };
+            let [covered] = e.covered_edges() else {
+                return None;
+            };
+            if !applicable || e.label_id() == MISSING_LABEL_ID {
+                return None;
+            }
+            let edge = pattern.get_edge(*covered)?;
+            let end = if edge.src() == vertex {
+                edge.dst()
+            } else if edge.dst() == vertex {
+                edge.src()
+            } else {
+                return None;
+            };
+            edges.push(edge);
+            ends.push(end);
+        }
+        if ends[0] == ends[1] || ends.contains(&vertex) {
+            return None;
+        }
+        let vertices = [ends[0], vertex, ends[1]].map(|v| pattern.get_vertex(v).unwrap());
+        let path = RawPattern::with_vertices_edges(vertices, edges)
+            .to_path()
+            .ok()?;
+        let label_id = self.catalog.get_joint_label_id(&path.encode())?;
+        let joint_path = self.catalog.get_joint(label_id)?;
+        let start_rank = path.get_vertex_rank(path.start().tag_id()).unwrap();
+        let end_rank = path.get_vertex_rank(path.end().tag_id()).unwrap();
+        let joint_start_rank = joint_path
+            .get_vertex_rank(joint_path.start().tag_id())
+            .unwrap();
+        let joint_end_rank = joint_path
+            .get_vertex_rank(joint_path.end().tag_id())
+            .unwrap();
+        let (start, end) = if (start_rank, end_rank) == (joint_start_rank, joint_end_rank) {
+            (path.start().tag_id(), path.end().tag_id())
+        } else if (start_rank, end_rank) == (joint_end_rank, joint_start_rank) {
+            (path.end().tag_id(), path.start().tag_id())
+        } else {
+            return None;
+        };
+        Some(JointEdge {
+            label_id,
+            edges: [first.tag_id(), second.tag_id()],
+            start,
+            end,
+        })
+    }
+
+    /// Return the paths and stars of the catalog edges which the decompositions of `pattern` are
+    /// made of, i.e., the statistics required to estimate it with the same settings.
+    ///
+    /// Only the paths are looked up in the catalog. A star is planned if all of its arms are paths
+    /// of the catalog, since a catalog built with the same maximum lengths and degree combines its
+    /// stars from all its paths.
+    pub fn required_statistics<P: GraphPattern>(mut self, pattern: &P) -> RequiredStatistics {
+        self.planned_stars = Some(Mutex::default());
+        let decomposed = self.decompose_inner(pattern);
+        let planned_stars: HashMap<_, _> = self
+            .planned_stars
+            .unwrap()
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .map(|(star, label_id)| (label_id, star))
+            .collect();
+        let mut required = RequiredStatistics::default();
+        for e in decomposed.iter().flat_map(CatalogPattern::edges) {
+            match e.kind() {
+                CatalogEdgeKind::Path { .. } => {
+                    let path = self.catalog.get_path(e.label_id()).unwrap();
+                    required.paths.insert(path.encode(), path.clone());
                 }
-                CatalogEdgeKind::Path { src, dst } => {
-                    if !added_vertices.contains(&src) {
-                        added_vertices.insert(src);
-                        let src = pattern.get_vertex(src).unwrap();
-                        catalog_pattern.add_vertex(src.into());
-                    }
-                    if !added_vertices.contains(&dst) {
-                        added_vertices.insert(dst);
-                        let dst = pattern.get_vertex(dst).unwrap();
-                        catalog_pattern.add_vertex(dst.into());
-                    }
+                CatalogEdgeKind::Star { .. } => {
+                    required.stars.insert(planned_stars[&e.label_id()].clone());
                 }
-                _ => unreachable!(),
+                CatalogEdgeKind::General(_) => unreachable!(),
             }
-            catalog_pattern.add_edge(edge);
         }
-        catalog_pattern
+        required
     }
 
     fn decompose_acyclic<P: GraphPattern>(&self, pattern: &P) -> CatalogPattern {
@@ -139,18 +379,32 @@ impl<'a, C: Catalog> HeuristicDecomposer<'a, C> {
 
     fn decompose_cyclic<P: GraphPattern>(&self, pattern: &P) -> Vec<CatalogPattern> {
         // First decompose the pattern using spanning trees
-        let mut catalog_patterns = generate_spanning_trees(pattern, self.limit)
-            .into_iter()
-            .map(|p| self.decompose_acyclic(&p))
-            .collect_vec();
+        let mut catalog_patterns = Vec::new();
+        for tree in generate_spanning_trees(pattern, self.limit) {
+            if self.should_stop(&catalog_patterns) {
+                debug!(
+                    "deadline exceeded after {} decompositions",
+                    catalog_patterns.len()
+                );
+                return catalog_patterns;
+            }
+            catalog_patterns.push(self.decompose_acyclic(&tree));
+        }
 
-        if !self.disable_cyclic {
+        if !self.disable_cyclic && !self.should_stop(&catalog_patterns) {
             if pattern.is_cycle() {
-                catalog_patterns.extend(pattern.vertices().iter().map(|v| {
+                for v in pattern.vertices() {
+                    if self.should_stop(&catalog_patterns) {
+                        debug!(
+                            "deadline exceeded after {} decompositions",
+                            catalog_patterns.len()
+                        );
+                        break;
+                    }
                     let candidate_paths =
                         find_candidate_paths_with_pivots(pattern, &[v.tag_id()].into());
-                    self.decompose_candidate_paths(pattern, candidate_paths)
-                }));
+                    catalog_patterns.push(self.decompose_candidate_paths(pattern, candidate_paths));
+                }
             } else if self.disable_prune {
                 catalog_patterns.push(self.decompose_acyclic(pattern))
             } else {
@@ -230,6 +484,15 @@ impl<'a, C: Catalog> HeuristicDecomposer<'a, C> {
     }
 
     fn translate_path<P: GraphPattern>(&self, pattern: &P, segment: PathSegment) -> CatalogEdge {
+        self.try_translate_path(pattern, segment)
+            .unwrap_or_else(|| panic!("cannot find path segment {segment:?} in the catalog"))
+    }
+
+    fn try_translate_path<P: GraphPattern>(
+        &self,
+        pattern: &P,
+        segment: PathSegment,
+    ) -> Option<CatalogEdge> {
         assert!(segment.len() > 0);
         let real_start_tag_id = segment.start();
         let real_end_tag_id = segment.end();
@@ -293,7 +556,7 @@ impl<'a, C: Catalog> HeuristicDecomposer<'a, C> {
         let edge_tag_id = segment.edges[0];
         let start_rank = path.get_vertex_rank(path.start().tag_id()).unwrap();
         let end_rank = path.get_vertex_rank(path.end().tag_id()).unwrap();
-        let label_id = self.catalog.get_path_label_id(&path.encode()).unwrap();
+        let label_id = self.catalog.get_path_label_id(&path.encode())?;
         let catalog_path = self.catalog.get_path(label_id).unwrap();
         let catalog_start_rank = catalog_path
             .get_vertex_rank(catalog_path.start().tag_id())
@@ -301,13 +564,14 @@ impl<'a, C: Catalog> HeuristicDecomposer<'a, C> {
         let catalog_end_rank = catalog_path
             .get_vertex_rank(catalog_path.end().tag_id())
             .unwrap();
-        if (start_rank, end_rank) == (catalog_start_rank, catalog_end_rank) {
+        let edge = if (start_rank, end_rank) == (catalog_start_rank, catalog_end_rank) {
             CatalogEdge::path(edge_tag_id, label_id, real_start_tag_id, real_end_tag_id)
         } else if (start_rank, end_rank) == (catalog_end_rank, catalog_start_rank) {
             CatalogEdge::path(edge_tag_id, label_id, real_end_tag_id, real_start_tag_id)
         } else {
             unreachable!()
-        }
+        };
+        Some(edge.with_covered_edges(segment.edges.iter().copied()))
     }
 
     fn translate_star<P: GraphPattern>(
@@ -316,6 +580,37 @@ impl<'a, C: Catalog> HeuristicDecomposer<'a, C> {
         segments: &[PathSegment],
         center: TagId,
     ) -> CatalogEdge {
+        self.try_translate_star(pattern, segments, center)
+            .unwrap_or_else(|| panic!("cannot find star {segments:?} in the catalog"))
+    }
+
+    /// Translate a star, or translate its segments separately if the star is missing from the
+    /// catalog and fallback is enabled.
+    fn translate_star_with_fallback<P: GraphPattern>(
+        &self,
+        pattern: &P,
+        segments: &[PathSegment],
+        center: TagId,
+    ) -> Vec<CatalogEdge> {
+        if !self.enable_fallback {
+            return vec![self.translate_star(pattern, segments, center)];
+        }
+        if let Some(edge) = self.try_translate_star(pattern, segments, center) {
+            return vec![edge];
+        }
+        debug!("fallback: star {segments:?} is not in the catalog");
+        segments
+            .iter()
+            .flat_map(|segment| self.translate_segment(pattern, *segment))
+            .collect()
+    }
+
+    fn try_translate_star<P: GraphPattern>(
+        &self,
+        pattern: &P,
+        segments: &[PathSegment],
+        center: TagId,
+    ) -> Option<CatalogEdge> {
         assert!(!segments.is_empty());
         // Segments have the same start vertex (to form a star)
         let start = segments[0].start();
@@ -342,14 +637,29 @@ impl<'a, C: Catalog> HeuristicDecomposer<'a, C> {
             center,
             center_rank,
         );
-        let label_id = self
-            .catalog
-            .get_star_label_id(center_rank, &star.encode())
-            .unwrap();
+        let label_id = if let Some(planned_stars) = &self.planned_stars {
+            // The stars of a catalog are combined from its paths, see `required_statistics`.
+            if !segments
+                .iter()
+                .filter(|segment| segment.len() > 0)
+                .all(|segment| self.try_translate_path(pattern, *segment).is_some())
+            {
+                return None;
+            }
+            let mut planned_stars = planned_stars.lock().unwrap();
+            let next_label_id = planned_stars.len() as LabelId;
+            *planned_stars
+                .entry((center_rank, star.encode()))
+                .or_insert(next_label_id)
+        } else {
+            self.catalog
+                .get_star_label_id(center_rank, &star.encode())?
+        };
         // If the star is a single vertex, use the vertex tag_id as the star's tag_id. Otherwise,
         // use the first edge's tag_id.
         let tag_id = edges.map(|e| e.tag_id()).next().unwrap_or(start);
-        CatalogEdge::star(tag_id, label_id, center)
+        let covered_edges = segments.iter().flat_map(|segment| segment.edges).copied();
+        Some(CatalogEdge::star(tag_id, label_id, center).with_covered_edges(covered_edges))
     }
 
     fn decompose_path<P: GraphPattern>(&self, pattern: &P, path: &PathRef) -> Vec<CatalogEdge> {
@@ -367,23 +677,123 @@ impl<'a, C: Catalog> HeuristicDecomposer<'a, C> {
         assert!(!segments.is_empty());
         segments
             .into_iter()
-            .map(|segment| {
-                let start = segment.start();
-                let end = segment.end();
-                let start_degree = pattern.get_vertex_degree(start).unwrap();
-                let end_degree = pattern.get_vertex_degree(end).unwrap();
-                if self.disable_star || (start_degree > 1 && end_degree > 1) {
-                    self.translate_path(pattern, segment)
-                } else if start_degree == 1 {
-                    self.translate_star(pattern, &[segment], end)
-                } else if end_degree == 1 {
-                    self.translate_star(pattern, &[segment], start)
-                } else {
-                    unreachable!()
-                }
-            })
+            .flat_map(|segment| self.translate_segment(pattern, segment))
             .collect()
     }
+
+    /// Return the center of the star a segment is translated into, which is the other end if one
+    /// of its ends is a leaf of `pattern`, or `None` if it is translated into a path.
+    fn segment_center<P: GraphPattern>(&self, pattern: &P, segment: PathSegment) -> Option<TagId> {
+        let start = segment.start();
+        let end = segment.end();
+        let start_degree = pattern.get_vertex_degree(start).unwrap();
+        let end_degree = pattern.get_vertex_degree(end).unwrap();
+        if self.disable_star || (start_degree > 1 && end_degree > 1) {
+            None
+        } else if start_degree == 1 {
+            Some(end)
+        } else if end_degree == 1 {
+            Some(start)
+        } else {
+            unreachable!()
+        }
+    }
+
+    fn try_translate_segment<P: GraphPattern>(
+        &self,
+        pattern: &P,
+        segment: PathSegment,
+    ) -> Option<CatalogEdge> {
+        self.segment_center(pattern, segment)
+            .and_then(|center| self.try_translate_star(pattern, &[segment], center))
+            .or_else(|| self.try_translate_path(pattern, segment))
+    }
+
+    fn translate_segment<P: GraphPattern>(
+        &self,
+        pattern: &P,
+        segment: PathSegment,
+    ) -> Vec<CatalogEdge> {
+        let center = self.segment_center(pattern, segment);
+        if !self.enable_fallback {
+            let edge = match center {
+                Some(center) => self.translate_star(pattern, &[segment], center),
+                None => self.translate_path(pattern, segment),
+            };
+            return vec![edge];
+        }
+        if let Some(edge) = self.try_translate_segment(pattern, segment) {
+            return vec![edge];
+        }
+        if segment.len() == 1 {
+            // A single edge cannot be split further, so it is marked to fail the join.
+            debug!("fallback: edge {segment:?} is not in the catalog");
+            let edge = CatalogEdge::path(
+                segment.edges[0],
+                MISSING_LABEL_ID,
+                segment.start(),
+                segment.end(),
+            );
+            return vec![edge.with_covered_edges(segment.edges.iter().copied())];
+        }
+        debug!("fallback: split path segment {segment:?}");
+        let (first, second) = segment.split_at(segment.len() / 2);
+        let mut edges = self.translate_segment(pattern, first);
+        edges.extend(self.translate_segment(pattern, second));
+        edges
+    }
+}
+
+fn build_catalog_pattern<P: GraphPattern>(pattern: &P, edges: Vec<CatalogEdge>) -> CatalogPattern {
+    let mut catalog_pattern = CatalogPattern::new();
+    let mut added_vertices = HashSet::new();
+    for edge in edges {
+        match *edge.kind() {
+            CatalogEdgeKind::Star { center } => {
+                if !added_vertices.contains(&center) {
+                    added_vertices.insert(center);
+                    let vertex = pattern.get_vertex(center).unwrap();
+                    catalog_pattern.add_vertex(vertex.into());
+                }
+            }
+            CatalogEdgeKind::Path { src, dst } => {
+                if !added_vertices.contains(&src) {
+                    added_vertices.insert(src);
+                    let src = pattern.get_vertex(src).unwrap();
+                    catalog_pattern.add_vertex(src.into());
+                }
+                if !added_vertices.contains(&dst) {
+                    added_vertices.insert(dst);
+                    let dst = pattern.get_vertex(dst).unwrap();
+                    catalog_pattern.add_vertex(dst.into());
+                }
+            }
+            _ => unreachable!(),
+        }
+        catalog_pattern.add_edge(edge);
+    }
+    catalog_pattern
+}
+
+/// Walk the edges of a group from `start` through `first` (an edge and the neighbor it leads to),
+/// until reaching a vertex not of degree 2 in the group or returning to `start`.
+fn walk_group(
+    adj_list: &BTreeMap<TagId, Vec<(TagId, TagId)>>,
+    start: TagId,
+    first: (TagId, TagId),
+) -> PathRef {
+    let mut path = PathRef::new(start);
+    let (mut edge, mut vertex) = first;
+    path.push(vertex, edge);
+    while vertex != start && adj_list[&vertex].len() == 2 {
+        (edge, vertex) = adj_list[&vertex]
+            .iter()
+            .copied()
+            .find(|(e, _)| *e != edge)
+            .unwrap();
+        path.push(vertex, edge);
+    }
+    path
 }
 
 fn find_pivots<P: GraphPattern>(pattern: &P) -> Vec<TagId> {
@@ -618,6 +1028,12 @@ fn find_candidate_paths_from_vertex<P: GraphPattern>(
 
 impl<'a, C: Catalog> PatternDecomposer for HeuristicDecomposer<'a, C> {
     fn decompose<P: GraphPattern>(self, pattern: &P) -> Vec<CatalogPattern> {
+        self.decompose_inner(pattern)
+    }
+}
+
+impl<'a, C: Catalog> HeuristicDecomposer<'a, C> {
+    fn decompose_inner<P: GraphPattern>(&self, pattern: &P) -> Vec<CatalogPattern> {
         assert!(
             !pattern.vertices().is_empty(),
             "Empty pattern is not allowed"
@@ -653,7 +1069,6 @@ fn generate_spanning_trees<P: GraphPattern>(pattern: &P, limit: usize) -> Vec<Ge
     );
     let mut trees = vec![];
     let initial_spanning_tree = generate_initial_spanning_tree(pattern);
-    let mut tree = UnGraphMap::from(PatternWrapper(&initial_spanning_tree));
     let branch_edges = initial_spanning_tree.edges().to_owned();
     let chord_edges = pattern
         .edges()
@@ -668,23 +1083,21 @@ fn generate_spanning_trees<P: GraphPattern>(pattern: &P, limit: usize) -> Vec<Ge
     }
     for chord_code in 1usize..(1 << chord_edges.len().min(branch_edges.len())) {
         let chord_num = chord_code.count_ones();
-        for ce in ones(chord_code).map(|i| chord_edges[i as usize]) {
-            tree.add_edge(ce.src(), ce.dst(), ce.tag_id());
-        }
         for branch_code in
             (1usize..(1 << branch_edges.len())).filter(|code| code.count_ones() == chord_num)
         {
-            for be in ones(branch_code).map(|i| branch_edges[i as usize]) {
-                tree.remove_edge(be.src(), be.dst());
-            }
-            if !is_cyclic_undirected(&tree) {
+            // Parallel edges are kept apart, so an edge can be swapped with a chord between the
+            // same vertices, e.g., one of two edges in opposite directions.
+            let edges = ones(!branch_code & ((1 << branch_edges.len()) - 1))
+                .map(|i| branch_edges[i as usize])
+                .chain(ones(chord_code).map(|i| chord_edges[i as usize]))
+                .collect_vec();
+            if is_forest(&edges) {
                 let mut raw = RawPattern::new();
-                for vertex_tag_id in tree.nodes() {
-                    let vertex = pattern.get_vertex(vertex_tag_id).unwrap();
-                    raw.push_back_vertex(vertex);
+                for vertex in pattern.vertices() {
+                    raw.push_back_vertex(*vertex);
                 }
-                for (_, _, edge_tag_id) in tree.all_edges() {
-                    let edge = pattern.get_edge(*edge_tag_id).unwrap();
+                for edge in edges {
                     raw.push_back_edge(edge);
                 }
                 trees.push(raw.to_general().unwrap());
@@ -692,17 +1105,24 @@ fn generate_spanning_trees<P: GraphPattern>(pattern: &P, limit: usize) -> Vec<Ge
                     return trees;
                 }
             }
-            for be in ones(branch_code).map(|i| branch_edges[i as usize]) {
-                tree.add_edge(be.src(), be.dst(), be.tag_id());
-            }
-        }
-        for ce in ones(chord_code).map(|i| chord_edges[i as usize]) {
-            tree.remove_edge(ce.src(), ce.dst());
         }
     }
     trees
 }
 
+/// Return whether the edges form no cycle, treating parallel edges as a cycle.
+fn is_forest(edges: &[PatternEdge]) -> bool {
+    let bound = edges
+        .iter()
+        .map(|e| e.src().max(e.dst()) as usize + 1)
+        .max()
+        .unwrap_or(0);
+    let mut union_find = UnionFind::new(bound);
+    edges
+        .iter()
+        .all(|e| union_find.union(e.src() as usize, e.dst() as usize))
+}
+
 fn ones(bits: usize) -> Ones {
     Ones { bits }
 }
@@ -725,18 +1145,6 @@ impl Iterator for Ones {
     }
 }
 
-struct PatternWrapper<'a>(&'a GeneralPattern);
-
-impl<'a> From<PatternWrapper<'a>> for UnGraphMap<TagId, TagId> {
-    fn from(value: PatternWrapper<'a>) -> Self {
-        let mut graph = UnGraphMap::new();
-        for e in value.0.edges() {
-            graph.add_edge(e.src(), e.dst(), e.tag_id());
-        }
-        graph
-    }
-}
-
 /// Generate a spanning tree by BFS.
 fn generate_initial_spanning_tree<P: GraphPattern>(pattern: &P) -> GeneralPattern {
     // Start from the vertex with the minimum degree, as it is more likely to be the endpoint of a
@@ -831,7 +1239,9 @@ mod tests {
     #[test]
     fn test_decompose() {
         let catalog = build_test_catalog();
-        let decom = HeuristicDecomposer::new(&catalog, 2, 0, 0, 0, true, true, true);
+        // The parallel edges make the pattern cyclic. Without spanning trees (limit 0), it is
+        // decomposed as a whole without pruning, with a star for each dangling edge.
+        let decom = HeuristicDecomposer::new(&catalog, 2, 999, 999, 0, false, true, false);
         let p = RawPattern::with_vertices_edges(
             [(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0)],
             [
@@ -845,17 +1255,31 @@ mod tests {
         )
         .to_general()
         .unwrap();
-        let catalog_pattern = decom.decompose(&p);
+        let catalog_patterns = decom.decompose(&p);
         let mut expected = CatalogPattern::new();
         expected.add_vertex(CatalogVertex::new(2, 0));
         expected.add_vertex(CatalogVertex::new(3, 0));
-        expected.add_edge(CatalogEdge::star(1, 1, 2));
-        expected.add_edge(CatalogEdge::path(2, 0, 2, 3));
-        expected.add_edge(CatalogEdge::path(3, 0, 2, 3));
-        expected.add_edge(CatalogEdge::star(0, 2, 2));
-        expected.add_edge(CatalogEdge::star(4, 1, 3));
-        expected.add_edge(CatalogEdge::star(5, 2, 3));
-        assert_eq!(catalog_pattern, vec![expected]);
+        expected.add_edge(CatalogEdge::star(1, 1, 2).with_covered_edges([1]));
+        expected.add_edge(CatalogEdge::path(2, 0, 2, 3).with_covered_edges([2]));
+        expected.add_edge(CatalogEdge::path(3, 0, 2, 3).with_covered_edges([3]));
+        expected.add_edge(CatalogEdge::star(0, 2, 2).with_covered_edges([0]));
+        expected.add_edge(CatalogEdge::star(4, 1, 3).with_covered_edges([4]));
+        expected.add_edge(CatalogEdge::star(5, 2, 3).with_covered_edges([5]));
+        let [catalog_pattern] = &catalog_patterns[..] else {
+            panic!("expect a single decomposition: {catalog_patterns:?}");
+        };
+        // The order of the edges depends on the traversal of the candidate paths.
+        let sorted_edges = |p: &CatalogPattern| {
+            p.edges()
+                .cloned()
+                .sorted_by_key(|e| e.tag_id())
+                .collect_vec()
+        };
+        assert_eq!(
+            catalog_pattern.vertices().collect_vec(),
+            expected.vertices().collect_vec()
+        );
+        assert_eq!(sorted_edges(catalog_pattern), sorted_edges(&expected));
 
         // let decom = HeuristicDecomposerV2::new(&catalog, 2, 1, 2);
         // let p = RawPattern::with_vertices_edges(
@@ -893,7 +1317,10 @@ mod tests {
         let paths = find_candidate_paths(&p);
         let path = paths.get(&0).unwrap().first().unwrap();
         let edge = decom.translate_path(&p, path.to_segment());
-        assert_eq!(edge, CatalogEdge::path(0, 1, 0, 2));
+        assert_eq!(
+            edge,
+            CatalogEdge::path(0, 1, 0, 2).with_covered_edges([0, 1])
+        );
 
         let p =
             RawPattern::with_vertices_edges([(0, 0), (1, 0), (2, 0)], [(0, 1, 0, 0), (1, 2, 1, 0)])
@@ -902,7 +1329,10 @@ mod tests {
         let paths = find_candidate_paths(&p);
         let path = paths.get(&0).unwrap().first().unwrap();
         let edge = decom.translate_path(&p, path.to_segment());
-        assert_eq!(edge, CatalogEdge::path(0, 1, 2, 0))
+        assert_eq!(
+            edge,
+            CatalogEdge::path(0, 1, 2, 0).with_covered_edges([0, 1])
+        )
     }
 
     #[test]
@@ -917,14 +1347,14 @@ mod tests {
         path.push(1, 0);
         path.push(2, 1);
         let edge = decom.translate_star(&p, &[path.to_segment()], 2);
-        assert_eq!(edge, CatalogEdge::star(0, 4, 2));
+        assert_eq!(edge, CatalogEdge::star(0, 4, 2).with_covered_edges([0, 1]));
 
         let mut path1 = PathRef::new(1);
         path1.push(0, 0);
         let mut path2 = PathRef::new(1);
         path2.push(2, 1);
         let edge = decom.translate_star(&p, &[path2.to_segment(), path1.to_segment()], 1);
-        assert_eq!(edge, CatalogEdge::star(1, 5, 1));
+        assert_eq!(edge, CatalogEdge::star(1, 5, 1).with_covered_edges([1, 0]));
 
         let p = RawPattern::new()
             .push_back_vertex((0, 0))
@@ -938,7 +1368,8 @@ mod tests {
     #[test]
     fn test_decompose_path() {
         let catalog = build_test_catalog();
-        let decom = HeuristicDecomposer::new(&catalog, 2, 999, 999, 0, true, true, true);
+        // Paths with a star in the catalog are translated into stars.
+        let decom = HeuristicDecomposer::new(&catalog, 2, 999, 999, 0, false, true, true);
         let p1 =
             RawPattern::with_vertices_edges([(0, 0), (1, 0), (2, 0)], [(0, 0, 1, 0), (1, 1, 2, 0)])
                 .to_general()
@@ -948,14 +1379,14 @@ mod tests {
         path.push(2, 1);
         assert_eq!(
             decom.decompose_path(&p1, &path),
-            vec![CatalogEdge::star(0, 4, 2)]
+            vec![CatalogEdge::star(0, 4, 2).with_covered_edges([0, 1])]
         );
 
         let mut path = PathRef::new(1);
         path.push(2, 1);
         assert_eq!(
             decom.decompose_path(&p1, &path),
-            vec![CatalogEdge::star(1, 1, 1)]
+            vec![CatalogEdge::star(1, 1, 1).with_covered_edges([1])]
         );
 
         let p2 = RawPattern::with_vertices_edges(
@@ -970,10 +1401,140 @@ mod tests {
         path.push(3, 2);
         assert_eq!(
             decom.decompose_path(&p2, &path),
-            vec![CatalogEdge::path(0, 1, 0, 2), CatalogEdge::star(2, 1, 2)]
+            vec![
+                CatalogEdge::path(0, 1, 0, 2).with_covered_edges([0, 1]),
+                CatalogEdge::star(2, 1, 2).with_covered_edges([2])
+            ]
         );
     }
 
+    #[test]
+    fn test_required_statistics() {
+        let catalog = build_test_catalog();
+        let path =
+            RawPattern::with_vertices_edges([(0, 0), (1, 0), (2, 0)], [(0, 0, 1, 0), (1, 1, 2, 0)])
+                .to_general()
+                .unwrap();
+        let long_path = RawPattern::with_vertices_edges(
+            [(0, 0), (1, 0), (2, 0), (3, 0)],
+            [(0, 0, 1, 0), (1, 1, 2, 0), (2, 2, 3, 0)],
+        )
+        .to_general()
+        .unwrap();
+        let triangle = RawPattern::with_vertices_edges(
+            [(0, 0), (1, 0), (2, 0)],
+            [(0, 0, 1, 0), (1, 1, 2, 0), (2, 2, 0, 0)],
+        )
+        .to_general()
+        .unwrap();
+        for pattern in [&path, &long_path, &triangle] {
+            for disable_star in [false, true] {
+                let decom =
+                    || HeuristicDecomposer::new(&catalog, 2, 2, 2, 10, disable_star, false, false);
+                // The statistics looked up by decomposing with the catalog.
+                let mut paths = HashSet::new();
+                let mut stars = HashSet::new();
+                for catalog_pattern in decom().decompose(pattern) {
+                    for e in catalog_pattern.edges() {
+                        match e.kind() {
+                            CatalogEdgeKind::Path { .. } => {
+                                paths.insert(catalog.get_path(e.label_id()).unwrap().encode());
+                            }
+                            CatalogEdgeKind::Star { .. } => {
+                                let star = catalog.get_star(e.label_id()).unwrap();
+                                let rank = catalog.get_star_center_rank(e.label_id()).unwrap();
+                                stars.insert((rank, star.encode()));
+                            }
+                            CatalogEdgeKind::General(_) => unreachable!(),
+                        }
+                    }
+                }
+                let required = decom().required_statistics(pattern);
+                assert_eq!(required.paths.into_keys().collect::<HashSet<_>>(), paths);
+                assert_eq!(required.stars, stars);
+                assert_eq!(disable_star, stars.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_decompose_path_fallback() {
+        let catalog = build_test_catalog();
+        let p = RawPattern::with_vertices_edges(
+            [(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0)],
+            [
+                (0, 0, 1, 0),
+                (1, 1, 2, 0),
+                (2, 2, 3, 0),
+                (3, 0, 4, 0),
+                (4, 3, 5, 0),
+            ],
+        )
+        .to_general()
+        .unwrap();
+        let mut path = PathRef::new(0);
+        path.push(1, 0);
+        path.push(2, 1);
+        path.push(3, 2);
+
+        // The catalog has no path of length 3.
+        let decom = HeuristicDecomposer::new(&catalog, 3, 999, 999, 0, true, true, true);
+        let result = std::panic::catch_unwind(|| decom.decompose_path(&p, &path));
+        assert!(result.is_err());
+
+        let decom = HeuristicDecomposer::new(&catalog, 3, 999, 999, 0, true, true, true)
+            .enable_fallback(true);
+        assert_eq!(
+            decom.decompose_path(&p, &path),
+            vec![
+                CatalogEdge::path(0, 0, 0, 1).with_covered_edges([0]),
+                CatalogEdge::path(1, 1, 1, 3).with_covered_edges([1, 2])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decompose_path_fallback_missing_edge() {
+        let catalog = build_test_catalog();
+        // The catalog has no path with edge label 1.
+        let p =
+            RawPattern::with_vertices_edges([(0, 0), (1, 0), (2, 0)], [(0, 0, 1, 0), (1, 1, 2, 1)])
+                .to_general()
+                .unwrap();
+        let mut path = PathRef::new(0);
+        path.push(1, 0);
+        path.push(2, 1);
+
+        let decom = HeuristicDecomposer::new(&catalog, 2, 999, 999, 0, true, true, true);
+        let result = std::panic::catch_unwind(|| decom.decompose_path(&p, &path));
+        assert!(result.is_err());
+
+        let decom = HeuristicDecomposer::new(&catalog, 2, 999, 999, 0, true, true, true)
+            .enable_fallback(true);
+        assert_eq!(
+            decom.decompose_path(&p, &path),
+            vec![
+                CatalogEdge::path(0, 0, 0, 1).with_covered_edges([0]),
+                CatalogEdge::path(1, MISSING_LABEL_ID, 1, 2).with_covered_edges([1])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generate_spanning_trees_parallel_edges() {
+        let pattern =
+            RawPattern::with_vertices_edges([(0, 0), (1, 0)], [(0, 0, 1, 0), (1, 1, 0, 0)])
+                .to_general()
+                .unwrap();
+        let trees = generate_spanning_trees(&pattern, 10);
+        let edges = trees
+            .iter()
+            .map(|tree| tree.edges().iter().map(|e| e.tag_id()).collect_vec())
+            .sorted()
+            .collect_vec();
+        assert_eq!(edges, vec![vec![0], vec![1]]);
+    }
+
     #[test]
     fn test_path_split_at() {
         let mut p = PathRef::new(0);
@@ -6,20 +6,216 @@ use ahash::{HashSet, HashSetExt};
 use duckdb::types::FromSql;
 use duckdb::Connection;
 use itertools::Itertools;
-use log::{debug, trace};
+use log::{debug, trace, warn};
+use tracing::instrument;
 
-use super::catalog_pattern::{CatalogEdge, CatalogEdgeKind, CatalogPattern};
+use super::catalog_pattern::{CatalogEdge, CatalogEdgeKind, CatalogPattern, JointEdge};
 use crate::common::{LabelId, TagId};
-use crate::error::GCardResult;
+use crate::error::{CatalogError, GCardResult};
 
+/// The bound computed when joining the statistics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    /// Multiply the count of each table by the maximum degrees of the others and take the least
+    /// product, which is a pessimistic upper bound.
+    Upper,
+    /// Take the least count of the joined tables, which is a cheap heuristic lower estimate.
+    ///
+    /// It is not a sound lower bound. The counts of a bucket are joined as if all its vertices
+    /// matched in every table, so the true count is lower if the vertices with matches differ
+    /// between the tables, down to 0 if they are disjoint.
+    LeastCount,
+}
+
+/// The number of temporary views of an estimate beyond which the intermediate results are
+/// materialized into temporary tables, so that the views they depend on can be dropped.
+const MAX_TEMP_VIEWS: usize = 256;
+
+/// The kind of a catalog entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EntryKind {
+    Path,
+    Star,
+}
+
+/// A catalog entry used by an estimate, along with the total count of its statistics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Provenance {
+    pub label_id: LabelId,
+    pub kind: EntryKind,
+    pub count: u64,
+}
+
+/// Collect the catalog entries referenced by the edges of `pattern`.
+pub fn provenance(pattern: &CatalogPattern, conn: &Connection) -> GCardResult<Vec<Provenance>> {
+    pattern
+        .edges()
+        .map(|e| {
+            e.check_missing()?;
+            let kind = match e.kind() {
+                CatalogEdgeKind::Star { .. } => EntryKind::Star,
+                CatalogEdgeKind::Path { .. } => EntryKind::Path,
+                CatalogEdgeKind::General(_) => unreachable!(),
+            };
+            let count = entry_count(conn, kind, e.label_id())?;
+            Ok(Provenance {
+                label_id: e.label_id(),
+                kind,
+                count,
+            })
+        })
+        .collect()
+}
+
+pub(super) fn check_card(card: f64, clamp_invalid: bool) -> GCardResult<f64> {
+    if card.is_finite() && card >= 0.0 {
+        return Ok(card);
+    }
+    if clamp_invalid {
+        warn!("invalid estimate {card} is clamped to 0");
+        Ok(0.0)
+    } else {
+        Err(CatalogError::InvalidEstimate(card).into())
+    }
+}
+
+/// The counts of a step eliminating a vertex, including the final aggregation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EliminationStep {
+    /// The eliminated vertex.
+    pub vertex: TagId,
+    /// The total count computed with the bound.
+    pub bound_count: f64,
+    /// The total count computed by multiplying the counts of the joined tables.
+    pub product_count: f64,
+}
+
+/// Estimate like [`estimate`], and also record the counts of every elimination step.
+pub fn estimate_with_steps(
+    pattern: CatalogPattern,
+    conn: &Connection,
+    id_generator: &mut RangeFrom<usize>,
+    predefined_order: Option<Vec<TagId>>,
+    clamp_invalid: bool,
+) -> GCardResult<(f64, Vec<EliminationStep>)> {
+    debug!("estimate with steps: {pattern}");
+    let mut state = EstimateState::new(pattern, conn, id_generator, predefined_order, Bound::Upper);
+    state.clamp_invalid = clamp_invalid;
+    state.steps = Some(vec![]);
+    let (card, steps) = state.estimate()?;
+    debug!("card: {card}, steps: {steps:?}");
+    Ok((card, steps.unwrap()))
+}
+
+/// Estimate like [`estimate`], and also return the [`uncertainty`] of the estimate.
+pub fn estimate_with_uncertainty(
+    pattern: CatalogPattern,
+    conn: &Connection,
+    id_generator: &mut RangeFrom<usize>,
+    clamp_invalid: bool,
+) -> GCardResult<(f64, f64)> {
+    let num_edges = pattern.get_edges_num();
+    let num_empty = pattern
+        .edges()
+        .filter(|e| is_empty_entry(e.label_id()))
+        .count();
+    let (card, steps) = estimate_with_steps(pattern, conn, id_generator, None, clamp_invalid)?;
+    let uncertainty = uncertainty(&steps, num_empty as f64 / num_edges as f64);
+    debug!("uncertainty: {uncertainty}");
+    Ok((card, uncertainty))
+}
+
+/// Rate in `[0, 1]` how little an estimate can be trusted, from the fraction of the elimination
+/// `steps` where the bound is binding, i.e., its count is below the product of the counts of the
+/// joined tables, and the fraction of the catalog entries with empty statistics, as
+/// `1 - (1 - binding) * (1 - empty)`. It is 0 only if no step is bound and no statistics are
+/// missing.
+pub fn uncertainty(steps: &[EliminationStep], empty: f64) -> f64 {
+    // Tolerate the rounding of summing the counts in different orders.
+    const TOLERANCE: f64 = 1e-9;
+    let binding = if steps.is_empty() {
+        0.0
+    } else {
+        let num_binding = steps
+            .iter()
+            .filter(|step| step.bound_count < step.product_count * (1.0 - TOLERANCE))
+            .count();
+        num_binding as f64 / steps.len() as f64
+    };
+    (1.0 - (1.0 - binding) * (1.0 - empty.clamp(0.0, 1.0))).clamp(0.0, 1.0)
+}
+
+/// Estimate the cardinality of `pattern`. An estimate which is not finite or is negative (e.g.,
+/// overflowing to `inf` on huge graphs) is an error, or is clamped to 0 if `clamp_invalid` is set.
 pub fn estimate(
     pattern: CatalogPattern,
     conn: &Connection,
     id_generator: &mut RangeFrom<usize>,
-    predefined_order: Option<Vec<u8>>,
+    predefined_order: Option<Vec<TagId>>,
+    clamp_invalid: bool,
 ) -> GCardResult<f64> {
-    debug!("estimate: {:?}", pattern.edges().collect_vec());
-    let card = EstimateState::new(pattern, conn, id_generator, predefined_order).estimate()?;
+    estimate_bound(
+        pattern,
+        conn,
+        id_generator,
+        predefined_order,
+        Bound::Upper,
+        clamp_invalid,
+    )
+}
+
+/// Estimate like [`estimate`], with the count of each catalog edge scaled by the selectivity
+/// `overrides` of the original edges it covers.
+pub fn estimate_with_overrides(
+    pattern: CatalogPattern,
+    conn: &Connection,
+    id_generator: &mut RangeFrom<usize>,
+    overrides: &HashMap<TagId, f64>,
+    clamp_invalid: bool,
+) -> GCardResult<f64> {
+    debug!("estimate with overrides {overrides:?}: {pattern}");
+    let mut state = EstimateState::new(pattern, conn, id_generator, None, Bound::Upper);
+    state.clamp_invalid = clamp_invalid;
+    state.overrides = overrides.clone();
+    let (card, _) = state.estimate()?;
+    debug!("card: {card}");
+    Ok(card)
+}
+
+pub fn estimate_bound(
+    pattern: CatalogPattern,
+    conn: &Connection,
+    id_generator: &mut RangeFrom<usize>,
+    predefined_order: Option<Vec<TagId>>,
+    bound: Bound,
+    clamp_invalid: bool,
+) -> GCardResult<f64> {
+    debug!("estimate {bound:?}: {pattern}");
+    let mut state = EstimateState::new(pattern, conn, id_generator, predefined_order, bound);
+    state.clamp_invalid = clamp_invalid;
+    let (card, _) = state.estimate()?;
+    debug!("card: {card}");
+    Ok(card)
+}
+
+/// Estimate the number of distinct matches of the vertex `target` of `pattern`, instead of the
+/// number of matches of the whole pattern. The other vertices are eliminated, and the count of
+/// each bucket of `target` is divided by its mode, i.e., every vertex of the bucket is assumed to
+/// match as many times as the vertex matching the most. The statistics do not record the number
+/// of distinct vertices, so this is an approximation, which underestimates skewed buckets.
+pub fn estimate_distinct(
+    pattern: CatalogPattern,
+    conn: &Connection,
+    id_generator: &mut RangeFrom<usize>,
+    target: TagId,
+    clamp_invalid: bool,
+) -> GCardResult<f64> {
+    debug!("estimate distinct v{target}: {pattern}");
+    assert!(pattern.get_vertex(target).is_some());
+    let mut state = EstimateState::new(pattern, conn, id_generator, None, Bound::Upper);
+    state.clamp_invalid = clamp_invalid;
+    state.distinct_target = Some(target);
+    let (card, _) = state.estimate()?;
     debug!("card: {card}");
     Ok(card)
 }
@@ -31,6 +227,17 @@ struct EstimateState<'a> {
     id_generator: &'a mut RangeFrom<usize>,
     edge_table_map: HashMap<TagId, String>,
     predefined_order: Option<Vec<TagId>>,
+    bound: Bound,
+    steps: Option<Vec<EliminationStep>>,
+    clamp_invalid: bool,
+    overrides: HashMap<TagId, f64>,
+    /// The vertex which is never eliminated, whose distinct matches are counted.
+    distinct_target: Option<TagId>,
+    /// The live temporary views, in the order of creation.
+    views: Vec<String>,
+    /// The temporary tables materializing views.
+    spilled_tables: Vec<String>,
+    max_temp_views: usize,
 }
 
 impl<'a> EstimateState<'a> {
@@ -39,6 +246,7 @@ impl<'a> EstimateState<'a> {
         conn: &'a Connection,
         id_generator: &'a mut RangeFrom<usize>,
         predefined_order: Option<Vec<TagId>>,
+        bound: Bound,
     ) -> Self {
         Self {
             pattern,
@@ -46,30 +254,108 @@ impl<'a> EstimateState<'a> {
             id_generator,
             edge_table_map: HashMap::new(),
             predefined_order,
+            bound,
+            steps: None,
+            clamp_invalid: false,
+            overrides: HashMap::new(),
+            distinct_target: None,
+            views: vec![],
+            spilled_tables: vec![],
+            max_temp_views: MAX_TEMP_VIEWS,
         }
     }
 
-    fn estimate(mut self) -> GCardResult<f64> {
+    #[instrument(
+        skip_all,
+        fields(
+            bound = ?self.bound,
+            vertices = self.pattern.get_vertices_num(),
+            edges = self.pattern.get_edges_num(),
+        )
+    )]
+    fn estimate(mut self) -> GCardResult<(f64, Option<Vec<EliminationStep>>)> {
+        // The temporary views and tables are dropped even if the estimate fails.
+        let card = self.estimate_inner();
+        let dropped = self.drop_temp_objects();
+        let card = card?;
+        dropped?;
+        Ok((card, self.steps))
+    }
+
+    fn estimate_inner(&mut self) -> GCardResult<f64> {
         assert!(self.pattern.get_vertices_num() >= 1);
         assert!(self.pattern.get_edges_num() >= 1);
         for e in self.pattern.edges() {
-            let table_name = create_temp_table(self.conn, e, self.id_generator)?;
+            let selectivity = e
+                .covered_edges()
+                .iter()
+                .filter_map(|tag_id| self.overrides.get(tag_id))
+                .product();
+            let table_name = create_temp_table(self.conn, e, selectivity, self.id_generator)?;
+            self.views.push(table_name.clone());
             self.edge_table_map.insert(e.tag_id(), table_name);
         }
+        self.spill_if_needed()?;
         if let Some(order) = self.predefined_order.take() {
             for victim in order {
                 if self.pattern.get_vertices_num() <= 1 {
                     break;
                 }
                 self.eliminate_vertex(victim)?;
+                self.spill_if_needed()?;
             }
         } else {
             while self.pattern.get_vertices_num() > 1 {
-                let victim = self.choose_victim_vertex();
+                // Vertices with joint statistics are eliminated first, before their edges are
+                // joined with others.
+                let victim = self
+                    .pattern
+                    .joint_vertices()
+                    .find(|v| Some(*v) != self.distinct_target)
+                    .unwrap_or_else(|| choose_victim_vertex(&self.pattern, self.distinct_target));
                 self.eliminate_vertex(victim)?;
+                self.spill_if_needed()?;
             }
         }
-        self.finalize()
+        check_card(self.finalize()?, self.clamp_invalid)
+    }
+
+    /// If there are more than `max_temp_views` views, materialize the views of the remaining edges
+    /// into temporary tables and drop all the views.
+    fn spill_if_needed(&mut self) -> GCardResult<()> {
+        if self.views.len() <= self.max_temp_views {
+            return Ok(());
+        }
+        debug!("spill {} temporary views", self.views.len());
+        for e in self.pattern.edges() {
+            let view_name = self.edge_table_map.get_mut(&e.tag_id()).unwrap();
+            if !self.views.contains(view_name) {
+                continue;
+            }
+            let table_name = format!("temp_spill_{}", self.id_generator.next().unwrap());
+            let sql = format!("create temp table {table_name} as select * from {view_name}");
+            execute_sql(self.conn, &sql)?;
+            self.spilled_tables.push(table_name.clone());
+            *view_name = table_name;
+        }
+        self.drop_views()
+    }
+
+    /// Drop the views in the reverse order of creation, so that a view is dropped before those
+    /// it depends on.
+    fn drop_views(&mut self) -> GCardResult<()> {
+        while let Some(view_name) = self.views.pop() {
+            execute_sql(self.conn, &format!("drop view if exists {view_name}"))?;
+        }
+        Ok(())
+    }
+
+    fn drop_temp_objects(&mut self) -> GCardResult<()> {
+        self.drop_views()?;
+        while let Some(table_name) = self.spilled_tables.pop() {
+            execute_sql(self.conn, &format!("drop table if exists {table_name}"))?;
+        }
+        Ok(())
     }
 
     fn finalize(&mut self) -> GCardResult<f64> {
@@ -82,13 +368,29 @@ impl<'a> EstimateState<'a> {
             .map(|e| self.edge_table_map.get(&e.tag_id()).unwrap().as_str())
             .collect_vec();
         let vertex = self.pattern.vertices().next().unwrap();
-        let sql = build_finalize_statement(&tables, vertex.tag_id());
+        let sql = if self.distinct_target.is_some() {
+            build_distinct_finalize_statement(&tables, vertex.tag_id())
+        } else {
+            build_finalize_statement(&tables, vertex.tag_id(), self.bound)
+        };
         let sql = build_final_agg_statement(sql);
         let sql = build_view_statement(sql, &final_table_name);
         execute_sql(self.conn, &sql)?;
-        read_scalar_table(self.conn, &final_table_name)
+        self.views.push(final_table_name.clone());
+        let card = read_scalar_table(self.conn, &final_table_name)?;
+        if let Some(steps) = &mut self.steps {
+            let vertex_to_tables = BTreeMap::from([(vertex.tag_id(), tables.clone())]);
+            let product_count = read_product_count(self.conn, &tables, &vertex_to_tables)?;
+            steps.push(EliminationStep {
+                vertex: vertex.tag_id(),
+                bound_count: card,
+                product_count,
+            });
+        }
+        Ok(card)
     }
 
+    #[instrument(level = "debug", skip(self))]
     fn eliminate_vertex(&mut self, vertex_tag_id: TagId) -> GCardResult<()> {
         debug!("eliminate vertex: {vertex_tag_id}");
         let mut tables = Vec::new();
@@ -121,10 +423,36 @@ impl<'a> EstimateState<'a> {
             .collect_vec();
         let next_table_id = self.id_generator.next().unwrap();
         let next_table_name = format!("temp_table_{next_table_id}");
-        let sql = build_match_statement(&tables, &vertex_to_tables, vertex_tag_id, &neighbors);
-        let sql = build_agg_statement(sql, &neighbors);
+        let sql = build_match_statement(
+            &tables,
+            &vertex_to_tables,
+            vertex_tag_id,
+            &neighbors,
+            self.bound,
+        );
+        let mut sql = build_agg_statement(sql, &neighbors);
+        if let Some(joint) = self.pattern.get_joint(vertex_tag_id) {
+            let selectivity = joint
+                .edges
+                .iter()
+                .flat_map(|e| self.pattern.get_edge(*e).unwrap().covered_edges())
+                .filter_map(|tag_id| self.overrides.get(tag_id))
+                .product();
+            sql = build_joint_statement(sql, &neighbors, joint, selectivity);
+        }
         let sql = build_view_statement(sql, &next_table_name);
         execute_sql(self.conn, &sql)?;
+        self.views.push(next_table_name.clone());
+        if let Some(steps) = &mut self.steps {
+            let sql = format!("select sum(_count) from {next_table_name}");
+            let bound_count: Option<f64> = self.conn.query_row(&sql, [], |row| row.get(0))?;
+            let product_count = read_product_count(self.conn, &tables, &vertex_to_tables)?;
+            steps.push(EliminationStep {
+                vertex: vertex_tag_id,
+                bound_count: bound_count.unwrap_or_default(),
+                product_count,
+            });
+        }
 
         let next_edge_tag_id = self.pattern.next_edge_tag_id();
         let new_edge = match &neighbors[..] {
@@ -140,38 +468,40 @@ impl<'a> EstimateState<'a> {
         self.pattern.add_edge(new_edge);
         Ok(())
     }
+}
 
-    fn choose_victim_vertex(&self) -> TagId {
-        let mut victim = None;
-        let mut min_neighbors = usize::MAX;
-        for v in self.pattern.vertices() {
-            let mut neighbors = HashSet::new();
-            for e in self.pattern.incident_edges(v.tag_id()).unwrap() {
-                match e.kind() {
-                    CatalogEdgeKind::Star { center } => {
-                        neighbors.insert(*center);
-                    }
-                    CatalogEdgeKind::Path { src, dst } => {
-                        neighbors.insert(*src);
-                        neighbors.insert(*dst);
-                    }
-                    CatalogEdgeKind::General(vertices) => neighbors.extend(vertices),
-                }
-            }
-            neighbors.remove(&v.tag_id());
-            match neighbors.len().cmp(&min_neighbors) {
-                Ordering::Less => {
-                    min_neighbors = neighbors.len();
-                    victim = Some(v.tag_id());
+/// Choose the vertex other than `keep` with the fewest neighbors to eliminate next, breaking ties
+/// by the least tag id.
+pub(super) fn choose_victim_vertex(pattern: &CatalogPattern, keep: Option<TagId>) -> TagId {
+    let mut victim = None;
+    let mut min_neighbors = usize::MAX;
+    for v in pattern.vertices().filter(|v| Some(v.tag_id()) != keep) {
+        let mut neighbors = HashSet::new();
+        for e in pattern.incident_edges(v.tag_id()).unwrap() {
+            match e.kind() {
+                CatalogEdgeKind::Star { center } => {
+                    neighbors.insert(*center);
                 }
-                Ordering::Equal => {
-                    victim = victim.min(Some(v.tag_id()));
+                CatalogEdgeKind::Path { src, dst } => {
+                    neighbors.insert(*src);
+                    neighbors.insert(*dst);
                 }
-                Ordering::Greater => (),
+                CatalogEdgeKind::General(vertices) => neighbors.extend(vertices),
             }
         }
-        victim.unwrap()
+        neighbors.remove(&v.tag_id());
+        match neighbors.len().cmp(&min_neighbors) {
+            Ordering::Less => {
+                min_neighbors = neighbors.len();
+                victim = Some(v.tag_id());
+            }
+            Ordering::Equal => {
+                victim = victim.min(Some(v.tag_id()));
+            }
+            Ordering::Greater => (),
+        }
     }
+    victim.unwrap()
 }
 
 fn read_scalar_table<T: FromSql + Default>(conn: &Connection, table_name: &str) -> GCardResult<T> {
@@ -184,7 +514,7 @@ fn build_final_agg_statement(sql: String) -> String {
     format!("select sum(_count) as _count from ({sql})")
 }
 
-fn build_finalize_statement(tables: &[&str], vertex: TagId) -> String {
+fn build_finalize_statement(tables: &[&str], vertex: TagId, bound: Bound) -> String {
     if tables.len() == 1 {
         let table = tables.first().unwrap();
         return format!("select v{vertex}, _count from {table}");
@@ -210,31 +540,42 @@ fn build_finalize_statement(tables: &[&str], vertex: TagId) -> String {
         })
         .collect();
 
-    let new_count = multipliers
-        .iter()
-        .map(|(t, multiplier)| format!("{t}._count * {multiplier}"))
-        .join(", ");
-    let new_count = format!("least({new_count}) as _count");
+    let new_count = build_count_expr(&multipliers, bound);
     let first_table = tables.first().unwrap();
     format!("select {first_table}.v{vertex} as v{vertex}, {new_count} from {from_clause} where {where_clause}")
 }
 
+/// Like [`build_finalize_statement`], but count the distinct matches of `vertex` in each bucket as
+/// the least count divided by the mode among the tables.
+fn build_distinct_finalize_statement(tables: &[&str], vertex: TagId) -> String {
+    let distinct = |t: &str| format!("{t}._count / greatest({t}.v{vertex}_mode, 1)");
+    if tables.len() == 1 {
+        let table = tables.first().unwrap();
+        return format!(
+            "select v{vertex}, {} as _count from {table}",
+            distinct(table)
+        );
+    }
+    let from_clause = tables.join(", ");
+    let where_clause = tables
+        .iter()
+        .tuple_windows()
+        .map(|(t1, t2)| format!("{}.v{vertex} = {}.v{vertex}", t1, t2))
+        .join(" and ");
+    let new_count = tables.iter().map(|t| distinct(t)).join(", ");
+    let first_table = tables.first().unwrap();
+    format!("select {first_table}.v{vertex} as v{vertex}, least({new_count}) as _count from {from_clause} where {where_clause}")
+}
+
 fn build_match_statement(
     tables: &[&str],
     vertex_to_tables: &BTreeMap<TagId, Vec<&str>>,
     victim: TagId,
     neighbors: &[TagId],
+    bound: Bound,
 ) -> String {
     let from_clause = tables.join(", ");
-    let where_clause = vertex_to_tables
-        .iter()
-        .filter(|(_, t)| t.len() > 1)
-        .flat_map(|(v, t)| {
-            t.iter()
-                .tuple_windows()
-                .map(move |(t1, t2)| format!("{t1}.v{v} = {t2}.v{v}"))
-        })
-        .join(" and ");
+    let where_clause = build_where_clause(vertex_to_tables);
     let multipliers: BTreeMap<_, _> = tables
         .iter()
         .enumerate()
@@ -251,11 +592,7 @@ fn build_match_statement(
             (*t_i, multiplier)
         })
         .collect();
-    let new_count = multipliers
-        .iter()
-        .map(|(t, multiplier)| format!("{t}._count * {multiplier}"))
-        .join(", ");
-    let new_count = format!("least({new_count}) as _count");
+    let new_count = build_count_expr(&multipliers, bound);
 
     let new_modes = neighbors
         .iter()
@@ -266,8 +603,13 @@ fn build_match_statement(
                 .first()
                 .copied()
                 .unwrap();
-            let multiplier = multipliers.get(table).unwrap();
-            format!("{table}.v{neighbor}_mode * {multiplier} as v{neighbor}_mode")
+            match bound {
+                Bound::Upper => {
+                    let multiplier = multipliers.get(table).unwrap();
+                    format!("{table}.v{neighbor}_mode * {multiplier} as v{neighbor}_mode")
+                }
+                Bound::LeastCount => format!("{table}.v{neighbor}_mode as v{neighbor}_mode"),
+            }
         })
         .join(", ");
 
@@ -288,6 +630,47 @@ fn build_match_statement(
     }
 }
 
+fn build_where_clause(vertex_to_tables: &BTreeMap<TagId, Vec<&str>>) -> String {
+    vertex_to_tables
+        .iter()
+        .filter(|(_, t)| t.len() > 1)
+        .flat_map(|(v, t)| {
+            t.iter()
+                .tuple_windows()
+                .map(move |(t1, t2)| format!("{t1}.v{v} = {t2}.v{v}"))
+        })
+        .join(" and ")
+}
+
+/// Sum the products of the counts of the joined tables, i.e., the count without any bound.
+fn read_product_count(
+    conn: &Connection,
+    tables: &[&str],
+    vertex_to_tables: &BTreeMap<TagId, Vec<&str>>,
+) -> GCardResult<f64> {
+    let product = tables.iter().map(|t| format!("{t}._count")).join(" * ");
+    let from_clause = tables.join(", ");
+    let where_clause = build_where_clause(vertex_to_tables);
+    let sql = if where_clause.is_empty() {
+        format!("select sum({product}) from {from_clause}")
+    } else {
+        format!("select sum({product}) from {from_clause} where {where_clause}")
+    };
+    let count: Option<f64> = conn.query_row(&sql, [], |row| row.get(0))?;
+    Ok(count.unwrap_or_default())
+}
+
+fn build_count_expr(multipliers: &BTreeMap<&str, String>, bound: Bound) -> String {
+    let counts = match bound {
+        Bound::Upper => multipliers
+            .iter()
+            .map(|(t, multiplier)| format!("{t}._count * {multiplier}"))
+            .join(", "),
+        Bound::LeastCount => multipliers.keys().map(|t| format!("{t}._count")).join(", "),
+    };
+    format!("least({counts}) as _count")
+}
+
 fn build_agg_statement(sql: String, neighbors: &[TagId]) -> String {
     assert!(!neighbors.is_empty());
     let modes = neighbors
@@ -298,32 +681,94 @@ fn build_agg_statement(sql: String, neighbors: &[TagId]) -> String {
     format!("select {neighbors}, {modes}, sum(_count) as _count from ({sql}) group by {neighbors}")
 }
 
+/// Replace the counts of eliminating the middle vertex of `joint` by its joint statistics scaled by
+/// `selectivity`, summed over the ends which are not `neighbors`, i.e., the leaves of stars. The
+/// modes are kept.
+fn build_joint_statement(
+    sql: String,
+    neighbors: &[TagId],
+    joint: &JointEdge,
+    selectivity: f64,
+) -> String {
+    let keys = neighbors
+        .iter()
+        .map(|v| {
+            assert!(*v == joint.start || *v == joint.end);
+            if *v == joint.start {
+                "s"
+            } else {
+                "t"
+            }
+        })
+        .collect_vec();
+    let columns = neighbors
+        .iter()
+        .map(|v| format!("r.v{v} as v{v}, r.v{v}_mode as v{v}_mode"))
+        .join(", ");
+    let on_clause = neighbors
+        .iter()
+        .zip(&keys)
+        .map(|(v, key)| format!("r.v{v} = j.{key}"))
+        .join(" and ");
+    let keys = keys.join(", ");
+    let table = format!("joint_{}", joint.label_id);
+    format!(
+        "select {columns}, coalesce(j._count, 0) * {selectivity} as _count from ({sql}) r \
+        left join (select {keys}, sum(_count)::double as _count from {table} group by {keys}) j \
+        on {on_clause}"
+    )
+}
+
 fn build_view_statement(sql: String, table_name: &str) -> String {
     format!("create temp view {table_name} as ({sql})")
 }
 
-/// Create temporary table for each edge to avoid naming conflict
+/// Return the total count of the statistics of a catalog entry.
+pub fn entry_count(conn: &Connection, kind: EntryKind, label_id: LabelId) -> GCardResult<u64> {
+    let table_name = stats_table_name(kind, label_id);
+    let sql = format!("select coalesce(sum(_count), 0)::ubigint from {table_name}");
+    Ok(conn.query_row(&sql, [], |row| row.get(0))?)
+}
+
+/// Return whether a catalog entry has empty statistics, which are not materialized.
+pub(super) fn is_empty_entry(label_id: LabelId) -> bool {
+    label_id >= LabelId::MAX / 2
+}
+
+/// Return the table of the statistics of a catalog entry. Empty statistics share a single table.
+fn stats_table_name(kind: EntryKind, label_id: LabelId) -> String {
+    let prefix = match kind {
+        EntryKind::Path => "path",
+        EntryKind::Star => "star",
+    };
+    if !is_empty_entry(label_id) {
+        format!("{prefix}_{label_id}")
+    } else {
+        format!("{prefix}_{}", LabelId::MAX / 2)
+    }
+}
+
+/// Create temporary table for each edge to avoid naming conflict. The count is scaled by
+/// `selectivity`.
 fn create_temp_table(
     conn: &Connection,
     edge: &CatalogEdge,
+    selectivity: f64,
     id_generator: &mut RangeFrom<usize>,
 ) -> GCardResult<String> {
+    edge.check_missing()?;
     let table_id = id_generator.next().unwrap();
     let (sql, temp_table_name) = match edge.kind() {
         CatalogEdgeKind::Star { center } => {
             let temp_table_name = format!("temp_star_{}", table_id);
-            let original_table_name = if edge.label_id() < LabelId::MAX / 2 {
-                format!("star_{}", edge.label_id())
-            } else {
-                format!("star_{}", LabelId::MAX / 2)
-            };
+            let original_table_name = stats_table_name(EntryKind::Star, edge.label_id());
             let sql = format!(
                 r"
 CREATE TEMP VIEW {temp_table_name} AS (
 SELECT
     id AS v{center},
     _mode::double AS v{center}_mode,
-    _count::double AS _count
+    _count::double * {selectivity} AS _count
 FROM
     {original_table_name}
 )"
@@ -332,11 +777,7 @@ FROM
         }
         CatalogEdgeKind::Path { src, dst } if src != dst => {
             let temp_table_name = format!("temp_path_{}", table_id);
-            let original_table_name = if edge.label_id() < LabelId::MAX / 2 {
-                format!("path_{}", edge.label_id())
-            } else {
-                format!("path_{}", LabelId::MAX / 2)
-            };
+            let original_table_name = stats_table_name(EntryKind::Path, edge.label_id());
             let sql = format!(
                 r"
 CREATE TEMP VIEW {temp_table_name} AS (
@@ -345,7 +786,7 @@ SELECT
     t AS v{dst},
     _mode_s::double AS v{src}_mode,
     _mode_t::double AS v{dst}_mode,
-    _count::double AS _count
+    _count::double * {selectivity} AS _count
 FROM
     {original_table_name}
 )"
@@ -355,18 +796,14 @@ FROM
         // Handle self-cycle case
         CatalogEdgeKind::Path { src, .. } => {
             let temp_table_name = format!("temp_path_{}", table_id);
-            let original_table_name = if edge.label_id() < LabelId::MAX / 2 {
-                format!("path_{}", edge.label_id())
-            } else {
-                format!("path_{}", LabelId::MAX / 2)
-            };
+            let original_table_name = stats_table_name(EntryKind::Path, edge.label_id());
             let sql = format!(
                 r"
 CREATE TEMP VIEW {temp_table_name} AS (
 SELECT
     s AS v{src},
     least(_mode_s, _mode_t)::double AS v{src}_mode,
-    _count::double AS _count
+    _count::double * {selectivity} AS _count
 FROM
     {original_table_name}
 WHERE
@@ -385,3 +822,86 @@ fn execute_sql(conn: &Connection, sql: &str) -> GCardResult<()> {
     trace!("{}", sql);
     Ok(conn.execute_batch(sql)?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uncertainty() {
+        let step = |bound_count, product_count| EliminationStep {
+            vertex: 0,
+            bound_count,
+            product_count,
+        };
+        let exact = [step(10.0, 10.0), step(5.0, 5.0)];
+        assert_eq!(uncertainty(&exact, 0.0), 0.0);
+        assert_eq!(uncertainty(&exact, 1.0), 1.0);
+        let bound = [step(10.0, 10.0), step(2.0, 5.0)];
+        assert_eq!(uncertainty(&bound, 0.0), 0.5);
+        assert_eq!(uncertainty(&bound, 0.5), 0.75);
+        assert_eq!(uncertainty(&[step(0.0, 0.0)], 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_spill_temp_views() {
+        use std::sync::Arc;
+
+        use crate::pattern::RawPattern;
+        use crate::test_utils::{
+            build_estimator, build_ldbc_catalog, build_ldbc_graph, build_ldbc_schema,
+        };
+
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let catalog = build_ldbc_catalog(schema.clone(), graph);
+        let estimator = build_estimator(&catalog);
+        let count_temp_objects = || -> usize {
+            let sql = "select (select count(*) from duckdb_views() where temporary) \
+                + (select count(*) from duckdb_tables() where temporary)";
+            catalog.conn().query_row(sql, [], |row| row.get(0)).unwrap()
+        };
+        let num_temp_objects = count_temp_objects();
+
+        // A 4-cycle of knows.
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let knows = schema.get_edge_label_id("Person_knows_Person").unwrap();
+        let pattern = RawPattern::with_vertices_edges(
+            [(0, person), (1, person), (2, person), (3, person)],
+            [
+                (0, 0, 1, knows),
+                (1, 1, 2, knows),
+                (2, 2, 3, knows),
+                (3, 3, 0, knows),
+            ],
+        )
+        .to_general()
+        .unwrap();
+        let mut id_generator = catalog.next_table_id().get()..;
+        for p in estimator.decompose(&pattern) {
+            let estimate = |max_temp_views, id_generator: &mut RangeFrom<usize>| {
+                let mut state =
+                    EstimateState::new(p.clone(), catalog.conn(), id_generator, None, Bound::Upper);
+                state.max_temp_views = max_temp_views;
+                state.steps = Some(vec![]);
+                state.estimate().unwrap()
+            };
+            let expected = estimate(MAX_TEMP_VIEWS, &mut id_generator);
+            assert_eq!(count_temp_objects(), num_temp_objects);
+            for max_temp_views in [0, 1, 2] {
+                assert_eq!(estimate(max_temp_views, &mut id_generator), expected);
+                assert_eq!(count_temp_objects(), num_temp_objects);
+            }
+        }
+        catalog.next_table_id().set(id_generator.next().unwrap());
+    }
+
+    #[test]
+    fn test_check_card() {
+        assert_eq!(check_card(42.0, false).unwrap(), 42.0);
+        for card in [f64::NAN, f64::INFINITY, -1.0] {
+            assert!(check_card(card, false).is_err());
+            assert_eq!(check_card(card, true).unwrap(), 0.0);
+        }
+    }
+}
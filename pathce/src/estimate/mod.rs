@@ -1,16 +1,43 @@
+mod cache;
+mod calibration;
 mod catalog_pattern;
 pub mod decompose;
 pub mod join;
+pub mod memory;
+mod validation;
 
-pub use catalog_pattern::CatalogPattern;
+use std::collections::HashMap;
+use std::ops::RangeFrom;
+use std::time::{Duration, Instant};
+
+pub use cache::EstimateCache;
+pub use calibration::{calibrate, calibration_records, CalibrationRecord};
+pub use catalog_pattern::{
+    CatalogEdge, CatalogEdgeKind, CatalogPattern, CatalogVertex, IncidenceStats, MISSING_LABEL_ID,
+};
 use decompose::heuristic::HeuristicDecomposer;
 use decompose::PatternDecomposer;
 use itertools::Itertools;
+use join::{Bound, EliminationStep, EntryKind, Provenance};
+use murmur3::murmur3_x64_128;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use tracing::{info_span, instrument};
 
-use crate::catalog::DuckCatalog;
-use crate::common::TagId;
-use crate::error::GCardResult;
+use crate::catalog::{Catalog, DuckCatalog};
+use crate::common::{LabelId, TagId};
+use crate::error::{CatalogError, EstimateError, GCardResult};
 use crate::pattern::GraphPattern;
+use crate::schema::Schema;
+
+/// An estimate returned by [`CardinalityEstimator::estimate_within`], which is partial if the time
+/// budget ran out before every decomposition was joined.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BudgetedEstimate {
+    pub card: f64,
+    pub partial: bool,
+}
 
 pub struct CardinalityEstimator<'a> {
     catalog: &'a DuckCatalog,
@@ -21,6 +48,8 @@ pub struct CardinalityEstimator<'a> {
     disable_star: bool,
     disable_prune: bool,
     disable_cyclic: bool,
+    enable_fallback: bool,
+    clamp_invalid: bool,
 }
 
 impl<'a> CardinalityEstimator<'a> {
@@ -44,15 +73,28 @@ impl<'a> CardinalityEstimator<'a> {
             disable_star,
             disable_prune,
             disable_cyclic,
+            enable_fallback: false,
+            clamp_invalid: false,
         }
     }
 
-    pub fn estimate_with_order<P: GraphPattern>(
-        &self,
-        pattern: &P,
-        order: Vec<TagId>,
-    ) -> GCardResult<f64> {
-        let decomposer = HeuristicDecomposer::new(
+    /// Decompose paths and stars missing from the catalog into smaller cataloged pieces instead
+    /// of panicking. Estimating a pattern with a single edge missing from the catalog fails with
+    /// [`EstimateError::MissingEdge`].
+    pub fn enable_fallback(mut self, enable: bool) -> Self {
+        self.enable_fallback = enable;
+        self
+    }
+
+    /// Clamp estimates which are not finite or are negative to 0 with a warning instead of
+    /// returning an error.
+    pub fn clamp_invalid(mut self, enable: bool) -> Self {
+        self.clamp_invalid = enable;
+        self
+    }
+
+    fn decomposer(&self) -> HeuristicDecomposer<'a, DuckCatalog> {
+        HeuristicDecomposer::new(
             self.catalog,
             self.max_path_length,
             self.max_star_length,
@@ -61,20 +103,271 @@ impl<'a> CardinalityEstimator<'a> {
             self.disable_star,
             self.disable_prune,
             self.disable_cyclic,
-        );
-        let pattern = decomposer.decompose_with_pivots(pattern, &order);
-        let next_table_id = self.catalog.next_table_id().get();
-        let mut id_generator = next_table_id..;
-        let card = join::estimate(pattern, self.catalog.conn(), &mut id_generator, Some(order))?;
+        )
+        .enable_fallback(self.enable_fallback)
+    }
+
+    /// Run `f` with the generator of the ids of the temporary tables of the catalog, and advance
+    /// the catalog past the ids it takes.
+    fn with_table_ids<R>(&self, f: impl FnOnce(&mut RangeFrom<usize>) -> R) -> R {
+        let mut id_generator = self.catalog.next_table_id().get()..;
+        let result = f(&mut id_generator);
         self.catalog
             .next_table_id()
             .set(id_generator.next().unwrap());
-        Ok(card)
+        result
+    }
+
+    /// Join each of `patterns` with `join`, and return the result of the minimum estimate.
+    fn join_best<T>(
+        &self,
+        patterns: impl IntoIterator<Item = CatalogPattern>,
+        mut join: impl FnMut(CatalogPattern, &mut RangeFrom<usize>) -> GCardResult<(f64, T)>,
+    ) -> GCardResult<(f64, T)> {
+        self.with_table_ids(|id_generator| {
+            let mut best: Option<(f64, T)> = None;
+            for p in patterns {
+                let (card, value) = join(p, id_generator)?;
+                if best
+                    .as_ref()
+                    .map_or(true, |(best_card, _)| card.total_cmp(best_card).is_lt())
+                {
+                    best = Some((card, value));
+                }
+            }
+            Ok(best.expect("no decomposition to join"))
+        })
+    }
+
+    /// Join each decomposition of `pattern` with `join` like [`Self::estimate`], and return the
+    /// result of the minimum estimate.
+    fn estimate_best<P: GraphPattern, T>(
+        &self,
+        pattern: &P,
+        join: impl FnMut(CatalogPattern, &mut RangeFrom<usize>) -> GCardResult<(f64, T)>,
+    ) -> GCardResult<(f64, T)> {
+        let patterns = self.decompose(pattern);
+        assert!(!patterns.is_empty());
+        self.join_best(patterns, join)
+    }
+
+    /// Return the decompositions of `pattern` into catalog entries that [`Self::estimate`] joins,
+    /// e.g., to evaluate them with another backend. The label ids of the returned patterns refer
+    /// to the entries of the estimator's catalog.
+    pub fn decompose<P: GraphPattern>(&self, pattern: &P) -> Vec<CatalogPattern> {
+        let decomposer = self.decomposer();
+        info_span!("decompose").in_scope(|| decomposer.decompose(pattern))
     }
 
+    /// Return the decomposition of `pattern` that [`Self::estimate_with_order`] joins with
+    /// `order`. The label ids of the returned pattern refer to the entries of the estimator's
+    /// catalog.
+    pub fn decompose_with_order<P: GraphPattern>(
+        &self,
+        pattern: &P,
+        order: &[TagId],
+    ) -> CatalogPattern {
+        let decomposer = self.decomposer();
+        info_span!("decompose").in_scope(|| decomposer.decompose_with_pivots(pattern, order))
+    }
+
+    /// Estimate the number of `edge_label` edges from `src_label` to `dst_label` vertices directly
+    /// from the statistics of the single-edge path, without decomposition. The single-edge paths of
+    /// the catalog are the edges of the schema, so the estimate is 0 if the catalog has other
+    /// endpoints for `edge_label`. If it has none, the count of all `edge_label` edges is returned
+    /// instead.
+    pub fn estimate_edge(
+        &self,
+        src_label: LabelId,
+        edge_label: LabelId,
+        dst_label: LabelId,
+    ) -> GCardResult<f64> {
+        if let Some(label_id) = self
+            .catalog
+            .get_edge_label_id(src_label, dst_label, edge_label)
+        {
+            let count = join::entry_count(self.catalog.conn(), EntryKind::Path, label_id)?;
+            return Ok(count as f64);
+        }
+        if self
+            .catalog
+            .paths()
+            .iter()
+            .any(|path| path.len() == 1 && path.edges()[0].label_id() == edge_label)
+        {
+            return Ok(0.0);
+        }
+        let count = self
+            .catalog
+            .get_edge_count(edge_label)
+            .ok_or(CatalogError::EdgeLabelNotFound(edge_label))?;
+        Ok(count as f64)
+    }
+
+    #[instrument(skip_all)]
+    pub fn estimate_with_order<P: GraphPattern>(
+        &self,
+        pattern: &P,
+        order: Vec<TagId>,
+    ) -> GCardResult<f64> {
+        let pattern = self.decompose_with_order(pattern, &order);
+        self.with_table_ids(|id_generator| {
+            join::estimate(
+                pattern,
+                self.catalog.conn(),
+                id_generator,
+                Some(order),
+                self.clamp_invalid,
+            )
+        })
+    }
+
+    /// Estimate the cardinality of `pattern` with `num_orders` random elimination orders generated
+    /// from `seed`, and return each order along with its estimate like
+    /// [`Self::estimate_with_order`]. A wide spread suggests that the catalog statistics are
+    /// inadequate for `pattern`. The orders are permutations of the vertices of degree greater
+    /// than 1 (or of all the vertices if there is none), so they share the decomposition.
+    #[instrument(skip_all)]
+    pub fn estimate_order_distribution<P: GraphPattern>(
+        &self,
+        pattern: &P,
+        num_orders: usize,
+        seed: u64,
+    ) -> GCardResult<Vec<(Vec<TagId>, f64)>> {
+        let mut vertices = pattern
+            .vertices()
+            .iter()
+            .map(|v| v.tag_id())
+            .filter(|v| pattern.get_vertex_degree(*v).unwrap() > 1)
+            .collect_vec();
+        if vertices.is_empty() {
+            vertices = pattern.vertices().iter().map(|v| v.tag_id()).collect();
+        }
+        let decomposed = self.decompose_with_order(pattern, &vertices);
+        let mut rng = StdRng::seed_from_u64(seed);
+        self.with_table_ids(|id_generator| {
+            (0..num_orders)
+                .map(|_| {
+                    vertices.shuffle(&mut rng);
+                    let card = join::estimate(
+                        decomposed.clone(),
+                        self.catalog.conn(),
+                        id_generator,
+                        Some(vertices.clone()),
+                        self.clamp_invalid,
+                    )?;
+                    Ok((vertices.clone(), card))
+                })
+                .try_collect()
+        })
+    }
+
+    /// Estimate the cardinality of `pattern` with each of the two elimination orders, and return
+    /// both estimates along with the counts of every elimination step to show where the orders
+    /// diverge. The decomposition is shared if both orders eliminate the same set of vertices.
+    #[instrument(skip_all)]
+    pub fn compare_orders<P: GraphPattern>(
+        &self,
+        pattern: &P,
+        order_a: Vec<TagId>,
+        order_b: Vec<TagId>,
+    ) -> GCardResult<[(f64, Vec<EliminationStep>); 2]> {
+        let decomposer = self.decomposer();
+        let pattern_a = info_span!("decompose")
+            .in_scope(|| decomposer.decompose_with_pivots(pattern, &order_a));
+        let pattern_b = if order_a.iter().sorted().eq(order_b.iter().sorted()) {
+            pattern_a.clone()
+        } else {
+            info_span!("decompose").in_scope(|| decomposer.decompose_with_pivots(pattern, &order_b))
+        };
+        self.with_table_ids(|id_generator| {
+            let result_a = join::estimate_with_steps(
+                pattern_a,
+                self.catalog.conn(),
+                id_generator,
+                Some(order_a),
+                self.clamp_invalid,
+            )?;
+            let result_b = join::estimate_with_steps(
+                pattern_b,
+                self.catalog.conn(),
+                id_generator,
+                Some(order_b),
+                self.clamp_invalid,
+            )?;
+            Ok([result_a, result_b])
+        })
+    }
+
+    /// Estimate the cardinality of `pattern` with the decomposition given by `partition` instead
+    /// of the heuristic, where each group of edge tag ids is translated into a path or a star of
+    /// the catalog. See [`HeuristicDecomposer::decompose_with_partition`] for valid partitions.
+    #[instrument(skip_all)]
+    pub fn estimate_with_partition<P: GraphPattern>(
+        &self,
+        pattern: &P,
+        partition: Vec<Vec<TagId>>,
+    ) -> GCardResult<f64> {
+        let decomposer = self.decomposer();
+        let pattern = info_span!("decompose")
+            .in_scope(|| decomposer.decompose_with_partition(pattern, &partition))?;
+        self.with_table_ids(|id_generator| {
+            join::estimate(
+                pattern,
+                self.catalog.conn(),
+                id_generator,
+                None,
+                self.clamp_invalid,
+            )
+        })
+    }
+
+    /// Estimate the cardinality of `pattern` with the minimum estimate of its decompositions. The
+    /// empty pattern has exactly one match (the empty match), so its estimate is 1.
+    #[instrument(skip_all)]
     pub fn estimate<P: GraphPattern>(&self, pattern: &P) -> GCardResult<f64> {
-        let decomposer = HeuristicDecomposer::new(
-            self.catalog,
+        if pattern.vertices().is_empty() {
+            return Ok(1.0);
+        }
+        let (card, ()) = self.estimate_best(pattern, |p, id_generator| {
+            let card = join::estimate(
+                p,
+                self.catalog.conn(),
+                id_generator,
+                None,
+                self.clamp_invalid,
+            )?;
+            Ok((card, ()))
+        })?;
+        Ok(card)
+    }
+
+    /// Estimate the cardinality of `pattern` like [`Self::estimate`], looking it up in `cache`
+    /// first. The cache is bypassed if the catalog has no content hash to key it, i.e., if it is
+    /// not imported or its statistics changed since.
+    pub fn estimate_cached<P: GraphPattern>(
+        &self,
+        pattern: &P,
+        cache: &mut EstimateCache,
+    ) -> GCardResult<f64> {
+        let Some(key) = self.cache_key() else {
+            return self.estimate(pattern);
+        };
+        cache.validate(key);
+        let code = pattern.encode();
+        if let Some(card) = cache.get(&code) {
+            return Ok(card);
+        }
+        let card = self.estimate(pattern)?;
+        cache.insert(code, card);
+        Ok(card)
+    }
+
+    /// Return the hash of the catalog content and the settings which the estimates depend on.
+    fn cache_key(&self) -> Option<u128> {
+        let content_hash = self.catalog.content_hash()?;
+        let key = (
+            content_hash,
             self.max_path_length,
             self.max_star_length,
             self.max_star_degree,
@@ -82,19 +375,251 @@ impl<'a> CardinalityEstimator<'a> {
             self.disable_star,
             self.disable_prune,
             self.disable_cyclic,
+            self.enable_fallback,
+            self.clamp_invalid,
         );
-        let patterns = decomposer.decompose(pattern);
-        assert!(!patterns.is_empty());
-        let next_table_id = self.catalog.next_table_id().get();
-        let mut id_generator = next_table_id..;
-        let cards: Vec<_> = patterns
+        let bytes = bincode::serialize(&key).unwrap();
+        Some(murmur3_x64_128(&mut &bytes[..], 0).unwrap())
+    }
+
+    /// Estimate the cardinality of `pattern` like [`Self::estimate`] within `budget`, which is
+    /// checked between decompositions and between joins. When the budget runs out, the minimum
+    /// of the estimates joined so far is returned and flagged as partial. The first decomposition
+    /// is always joined, even if the budget runs out while decomposing, and the product of the
+    /// counts of the edges (see [`Self::estimate_edge`]) is returned only if there is none. A
+    /// single join is not interrupted, so the budget can still be exceeded.
+    #[instrument(skip_all)]
+    pub fn estimate_within<P: GraphPattern>(
+        &self,
+        pattern: &P,
+        budget: Duration,
+    ) -> GCardResult<BudgetedEstimate> {
+        let deadline = Instant::now() + budget;
+        let expired = || Instant::now() >= deadline;
+        let decomposer = self.decomposer().deadline(Some(deadline));
+        let patterns = info_span!("decompose").in_scope(|| decomposer.decompose(pattern));
+        if patterns.is_empty() {
+            let card = self.estimate_edge_product(pattern)?;
+            return Ok(BudgetedEstimate {
+                card,
+                partial: true,
+            });
+        }
+        // The decomposer may have stopped early if the budget ran out.
+        let mut partial = expired();
+        let patterns = patterns.into_iter().enumerate().map_while(|(i, p)| {
+            if i > 0 && expired() {
+                partial = true;
+                None
+            } else {
+                Some(p)
+            }
+        });
+        let (card, ()) = self.join_best(patterns, |p, id_generator| {
+            let card = join::estimate(
+                p,
+                self.catalog.conn(),
+                id_generator,
+                None,
+                self.clamp_invalid,
+            )?;
+            Ok((card, ()))
+        })?;
+        Ok(BudgetedEstimate { card, partial })
+    }
+
+    /// Return the product of the counts of the edges of `pattern`, which assumes every pair of
+    /// edges joins, and of the counts of its isolated vertices.
+    fn estimate_edge_product<P: GraphPattern>(&self, pattern: &P) -> GCardResult<f64> {
+        let mut card = 1.0;
+        for e in pattern.edges() {
+            let src_label = pattern.get_vertex(e.src()).unwrap().label_id();
+            let dst_label = pattern.get_vertex(e.dst()).unwrap().label_id();
+            card *= self.estimate_edge(src_label, e.label_id(), dst_label)?;
+        }
+        for v in pattern.vertices() {
+            if pattern.get_vertex_degree(v.tag_id()).unwrap() > 0 {
+                continue;
+            }
+            let label_id = self
+                .catalog
+                .get_vertex_label_id(v.label_id())
+                .ok_or(CatalogError::VertexLabelNotFound(v.label_id()))?;
+            card *= join::entry_count(self.catalog.conn(), EntryKind::Star, label_id)? as f64;
+        }
+        Ok(card)
+    }
+
+    /// Estimate the cardinality of `pattern` like [`Self::estimate`], with the count of every
+    /// catalog edge scaled by the `overrides` (keyed by the edge tag ids of `pattern`) of the
+    /// edges it covers. This incorporates selectivities the labels cannot represent, e.g., of
+    /// predicates. Edges dropped by the decomposition (e.g., the non-tree edges of a spanning
+    /// tree) are not scaled.
+    #[instrument(skip_all)]
+    pub fn estimate_with_overrides<P: GraphPattern>(
+        &self,
+        pattern: &P,
+        overrides: &HashMap<TagId, f64>,
+    ) -> GCardResult<f64> {
+        for (tag_id, selectivity) in overrides {
+            if pattern.get_edge(*tag_id).is_none() {
+                return Err(EstimateError::UnknownOverrideEdge(*tag_id).into());
+            }
+            if !selectivity.is_finite() || *selectivity < 0.0 {
+                return Err(EstimateError::InvalidOverride {
+                    edge: *tag_id,
+                    selectivity: *selectivity,
+                }
+                .into());
+            }
+        }
+        let (card, ()) = self.estimate_best(pattern, |p, id_generator| {
+            let card = join::estimate_with_overrides(
+                p,
+                self.catalog.conn(),
+                id_generator,
+                overrides,
+                self.clamp_invalid,
+            )?;
+            Ok((card, ()))
+        })?;
+        Ok(card)
+    }
+
+    /// Estimate the cardinality of `pattern` like [`Self::estimate`], and also return the catalog
+    /// entries used by the decomposition that gives the estimate.
+    #[instrument(skip_all)]
+    pub fn estimate_with_provenance<P: GraphPattern>(
+        &self,
+        pattern: &P,
+    ) -> GCardResult<(f64, Vec<Provenance>)> {
+        let (card, pattern) = self.estimate_best(pattern, |p, id_generator| {
+            let card = join::estimate(
+                p.clone(),
+                self.catalog.conn(),
+                id_generator,
+                None,
+                self.clamp_invalid,
+            )?;
+            Ok((card, p))
+        })?;
+        let provenance = join::provenance(&pattern, self.catalog.conn())?;
+        Ok((card, provenance))
+    }
+
+    /// Estimate the cardinality of `pattern` like [`Self::estimate`], and also return the counts
+    /// of every elimination step of the decomposition that gives the estimate.
+    #[instrument(skip_all)]
+    pub fn estimate_with_steps<P: GraphPattern>(
+        &self,
+        pattern: &P,
+    ) -> GCardResult<(f64, Vec<EliminationStep>)> {
+        self.estimate_best(pattern, |p, id_generator| {
+            join::estimate_with_steps(
+                p,
+                self.catalog.conn(),
+                id_generator,
+                None,
+                self.clamp_invalid,
+            )
+        })
+    }
+
+    /// Estimate the cardinality of `pattern` like [`Self::estimate`], and also return the
+    /// [`join::uncertainty`] of the decomposition that gives the estimate.
+    #[instrument(skip_all)]
+    pub fn estimate_with_uncertainty<P: GraphPattern>(
+        &self,
+        pattern: &P,
+    ) -> GCardResult<(f64, f64)> {
+        self.estimate_best(pattern, |p, id_generator| {
+            join::estimate_with_uncertainty(
+                p,
+                self.catalog.conn(),
+                id_generator,
+                self.clamp_invalid,
+            )
+        })
+    }
+
+    /// Estimate a `(lower, upper)` bracket of the cardinality of `pattern`.
+    ///
+    /// The upper bound is the same as [`Self::estimate`]. The lower end joins the decomposition of
+    /// the upper bound with [`Bound::LeastCount`], and is clamped to the upper bound. It is a
+    /// heuristic lower estimate rather than a sound lower bound, see [`Bound::LeastCount`].
+    #[instrument(skip_all)]
+    pub fn estimate_bounds<P: GraphPattern>(&self, pattern: &P) -> GCardResult<(f64, f64)> {
+        let conn = self.catalog.conn();
+        let (upper, decomposed) = self.estimate_best(pattern, |p, id_generator| {
+            let card = join::estimate_bound(
+                p.clone(),
+                conn,
+                id_generator,
+                None,
+                Bound::Upper,
+                self.clamp_invalid,
+            )?;
+            Ok((card, p))
+        })?;
+        let lower = self.with_table_ids(|id_generator| {
+            join::estimate_bound(
+                decomposed,
+                conn,
+                id_generator,
+                None,
+                Bound::LeastCount,
+                self.clamp_invalid,
+            )
+        })?;
+        Ok((lower.min(upper), upper))
+    }
+
+    /// Estimate the cardinality of `pattern`, whose labels may be abstract, as the sum of the
+    /// estimates of its expansions into concrete labels by [`Schema::expand_abstract_labels`].
+    pub fn estimate_expanded<P: GraphPattern>(
+        &self,
+        schema: &Schema,
+        pattern: &P,
+    ) -> GCardResult<f64> {
+        schema
+            .expand_abstract_labels(pattern)?
+            .iter()
+            .map(|p| self.estimate(p))
+            .sum()
+    }
+
+    /// Estimate the number of distinct vertices matching `target` in the matches of `pattern`,
+    /// e.g., to size a semijoin or a group by. Only the decompositions keeping `target` as a
+    /// vertex are joined, or the one pivoting on `target` if there is none. See
+    /// [`join::estimate_distinct`] for the approximation.
+    #[instrument(skip_all)]
+    pub fn estimate_distinct<P: GraphPattern>(
+        &self,
+        pattern: &P,
+        target: TagId,
+    ) -> GCardResult<f64> {
+        if pattern.get_vertex(target).is_none() {
+            return Err(EstimateError::UnknownDistinctTarget(target).into());
+        }
+        let mut patterns = self
+            .decompose(pattern)
             .into_iter()
-            .map(|p| join::estimate(p, self.catalog.conn(), &mut id_generator, None))
-            .try_collect()?;
-        self.catalog
-            .next_table_id()
-            .set(id_generator.next().unwrap());
-        Ok(cards.into_iter().min_by(|a, b| a.total_cmp(b)).unwrap())
+            .filter(|p| p.get_vertex(target).is_some())
+            .collect_vec();
+        if patterns.is_empty() {
+            patterns.push(self.decompose_with_order(pattern, &[target]));
+        }
+        let (card, ()) = self.join_best(patterns, |p, id_generator| {
+            let card = join::estimate_distinct(
+                p,
+                self.catalog.conn(),
+                id_generator,
+                target,
+                self.clamp_invalid,
+            )?;
+            Ok((card, ()))
+        })?;
+        Ok(card)
     }
 }
 
@@ -107,13 +632,787 @@ impl<'a> CardinalityEstimatorManual<'a> {
         Self { catalog }
     }
 
+    #[instrument(skip_all)]
     pub fn estimate(&self, pattern: CatalogPattern) -> GCardResult<f64> {
+        pattern.validate()?;
         let next_table_id = self.catalog.next_table_id().get();
         let mut id_generator = next_table_id..;
-        let card = join::estimate(pattern, self.catalog.conn(), &mut id_generator, None)?;
+        let card = join::estimate(pattern, self.catalog.conn(), &mut id_generator, None, false)?;
         self.catalog
             .next_table_id()
             .set(id_generator.next().unwrap());
         Ok(card)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::counter::ExactCounter;
+    use crate::error::GCardError;
+    use crate::graph::{LabeledGraph, LabeledVertex};
+    use crate::pattern::RawPattern;
+    use crate::test_utils::{
+        build_estimator, build_ldbc_catalog, build_ldbc_graph, build_ldbc_schema,
+        build_ldbc_schema_with_messages, build_test_pool, ldbc_catalog_builder, TempDir,
+    };
+
+    fn build_exact_counter(graph: Arc<LabeledGraph>) -> ExactCounter {
+        ExactCounter::new(graph, build_test_pool())
+    }
+
+    #[test]
+    fn test_estimate_empty_pattern() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let catalog = build_ldbc_catalog(schema, graph);
+        let estimator = build_estimator(&catalog);
+        let pattern = RawPattern::new().to_general().unwrap();
+        assert_eq!(estimator.estimate(&pattern).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_estimate_fallback_missing_edge() {
+        let catalog = DuckCatalog::init().unwrap();
+        let estimator = build_estimator(&catalog).enable_fallback(true);
+        // (v0)-[e0]->(v1)-[e1]->(v2), none of which is in the catalog
+        let pattern =
+            RawPattern::with_vertices_edges([(0, 0), (1, 0), (2, 0)], [(0, 0, 1, 0), (1, 1, 2, 0)])
+                .to_general()
+                .unwrap();
+        assert!(matches!(
+            estimator.estimate(&pattern),
+            Err(GCardError::Estimate(EstimateError::MissingEdge(_)))
+        ));
+    }
+
+    #[test]
+    fn test_estimate_cached() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let catalog = build_ldbc_catalog(schema.clone(), graph);
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let knows = schema.get_edge_label_id("Person_knows_Person").unwrap();
+        let edge = RawPattern::with_vertices_edges([(0, person), (1, person)], [(0, 0, 1, knows)])
+            .to_general()
+            .unwrap();
+        let vertex = RawPattern::new()
+            .push_back_vertex((0, person))
+            .to_general()
+            .unwrap();
+
+        // The cache is bypassed for catalogs which are not imported.
+        let mut cache = EstimateCache::new();
+        let estimator = build_estimator(&catalog);
+        let expected = estimator.estimate_cached(&edge, &mut cache).unwrap();
+        assert!(cache.is_empty());
+
+        let dir = TempDir::new("cache");
+        let cache_path = dir.join("cache.bincode");
+        catalog.export(&dir).unwrap();
+        let imported = DuckCatalog::import(&dir).unwrap();
+        let estimator = build_estimator(&imported);
+        assert_eq!(
+            estimator.estimate_cached(&edge, &mut cache).unwrap(),
+            expected
+        );
+        cache.save(&cache_path).unwrap();
+
+        let reimported = DuckCatalog::import(&dir).unwrap();
+        let mut cache = EstimateCache::load(&cache_path).unwrap();
+        assert_eq!(reimported.content_hash(), imported.content_hash());
+        assert!(imported.content_hash().is_some());
+        assert_eq!(cache.get(&edge.encode()), Some(expected));
+        let estimator = build_estimator(&reimported);
+        assert_eq!(
+            estimator.estimate_cached(&edge, &mut cache).unwrap(),
+            expected
+        );
+        assert_eq!(cache.len(), 1);
+
+        // Estimating with other settings clears the cache.
+        let estimator = CardinalityEstimator::new(&reimported, 2, 1, 2, 5, false, false, false);
+        estimator.estimate_cached(&vertex, &mut cache).unwrap();
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(&edge.encode()), None);
+    }
+
+    #[test]
+    fn test_estimate_with_steps() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let catalog = build_ldbc_catalog(schema.clone(), graph);
+        let estimator = build_estimator(&catalog);
+
+        // (c: Comment)-[:hasCreator]->(p1: Person)-[:knows]->(p2: Person)-[:knows]->(p3: Person)
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let comment = schema.get_vertex_label_id("Comment").unwrap();
+        let knows = schema.get_edge_label_id("Person_knows_Person").unwrap();
+        let has_creator = schema
+            .get_edge_label_id("Comment_hasCreator_Person")
+            .unwrap();
+        let pattern = RawPattern::new()
+            .push_back_vertex((0, comment))
+            .push_back_vertex((1, person))
+            .push_back_vertex((2, person))
+            .push_back_vertex((3, person))
+            .push_back_edge((0, 0, 1, has_creator))
+            .push_back_edge((1, 1, 2, knows))
+            .push_back_edge((2, 2, 3, knows))
+            .to_general()
+            .unwrap();
+        let (card, steps) = estimator.estimate_with_steps(&pattern).unwrap();
+        assert_eq!(card, estimator.estimate(&pattern).unwrap());
+        assert!(!steps.is_empty());
+        assert_eq!(steps.last().unwrap().bound_count, card);
+        for step in steps {
+            assert!(step.bound_count <= step.product_count, "{step:?}");
+        }
+    }
+
+    #[test]
+    fn test_decompose() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let catalog = build_ldbc_catalog(schema.clone(), graph);
+        let estimator = build_estimator(&catalog);
+
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let knows = schema.get_edge_label_id("Person_knows_Person").unwrap();
+        let pattern = RawPattern::with_vertices_edges(
+            [(0, person), (1, person), (2, person), (3, person)],
+            [(0, 0, 1, knows), (1, 1, 2, knows), (2, 2, 3, knows)],
+        )
+        .to_general()
+        .unwrap();
+        let patterns = estimator.decompose(&pattern);
+        assert!(!patterns.is_empty());
+        let mut id_generator = catalog.next_table_id().get()..;
+        let card = patterns
+            .into_iter()
+            .map(|p| {
+                p.validate().unwrap();
+                join::estimate(p, catalog.conn(), &mut id_generator, None, false).unwrap()
+            })
+            .min_by(|a, b| a.total_cmp(b))
+            .unwrap();
+        catalog.next_table_id().set(id_generator.next().unwrap());
+        assert_eq!(card, estimator.estimate(&pattern).unwrap());
+
+        let order = vec![1, 2];
+        let decomposed = estimator.decompose_with_order(&pattern, &order);
+        decomposed.validate().unwrap();
+        let mut id_generator = catalog.next_table_id().get()..;
+        let card = join::estimate(
+            decomposed,
+            catalog.conn(),
+            &mut id_generator,
+            Some(order.clone()),
+            false,
+        )
+        .unwrap();
+        catalog.next_table_id().set(id_generator.next().unwrap());
+        assert_eq!(
+            card,
+            estimator.estimate_with_order(&pattern, order).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_estimate_reciprocal_edges() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let catalog = build_ldbc_catalog(schema.clone(), graph.clone());
+        let estimator = build_estimator(&catalog);
+        let counter = build_exact_counter(graph.clone());
+
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let knows = schema.get_edge_label_id("Person_knows_Person").unwrap();
+        // Paths using knows in both directions are estimated exactly.
+        for edges in [
+            [(0, 0, 1, knows), (1, 2, 1, knows)],
+            [(0, 1, 0, knows), (1, 1, 2, knows)],
+        ] {
+            let pattern =
+                RawPattern::with_vertices_edges([(0, person), (1, person), (2, person)], edges)
+                    .to_general()
+                    .unwrap();
+            let card = estimator.estimate(&pattern).unwrap();
+            let truth = counter.count(&pattern, None).unwrap();
+            assert!(card > 0.);
+            assert_eq!(card, truth as f64);
+        }
+
+        // (p1: Person)-[:knows]->(p2: Person)-[:knows]->(p1)
+        let pattern = RawPattern::with_vertices_edges(
+            [(0, person), (1, person)],
+            [(0, 0, 1, knows), (1, 1, 0, knows)],
+        )
+        .to_general()
+        .unwrap();
+        let card = estimator.estimate(&pattern).unwrap();
+        assert!(card.is_finite() && card > 0.);
+        let num_edges = graph.get_num_edges(knows).unwrap() as f64;
+        assert!(card <= num_edges);
+    }
+
+    #[test]
+    fn test_estimate_distinct() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let catalog = build_ldbc_catalog(schema.clone(), graph.clone());
+        let estimator = build_estimator(&catalog);
+
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let knows = schema.get_edge_label_id("Person_knows_Person").unwrap();
+        // (p1: Person)-[:knows]->(p2: Person)
+        let pattern =
+            RawPattern::with_vertices_edges([(0, person), (1, person)], [(0, 0, 1, knows)])
+                .to_general()
+                .unwrap();
+        let card = estimator.estimate(&pattern).unwrap();
+        let distinct = estimator.estimate_distinct(&pattern, 0).unwrap();
+        // Dividing by the modes never exceeds the number of persons knowing someone.
+        let truth = graph
+            .vertices(person)
+            .unwrap()
+            .iter()
+            .filter(|id| {
+                let vertex = LabeledVertex::new(**id, person);
+                graph.outgoing_degree(vertex, knows).unwrap() > 0
+            })
+            .count();
+        assert!(distinct > 0.);
+        assert!(distinct <= truth as f64);
+        assert!(distinct <= card);
+
+        // (p1: Person)-[:knows]->(p2: Person)-[:knows]->(p3: Person), where the middle vertex is
+        // not kept by the decompositions.
+        let pattern = RawPattern::with_vertices_edges(
+            [(0, person), (1, person), (2, person)],
+            [(0, 0, 1, knows), (1, 1, 2, knows)],
+        )
+        .to_general()
+        .unwrap();
+        let distinct = estimator.estimate_distinct(&pattern, 1).unwrap();
+        assert!(distinct > 0.);
+        assert!(distinct <= estimator.estimate(&pattern).unwrap());
+
+        assert!(matches!(
+            estimator.estimate_distinct(&pattern, 3),
+            Err(GCardError::Estimate(EstimateError::UnknownDistinctTarget(
+                3
+            )))
+        ));
+    }
+
+    #[test]
+    fn test_estimate_within() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let catalog = build_ldbc_catalog(schema.clone(), graph);
+        let estimator = build_estimator(&catalog);
+
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let knows = schema.get_edge_label_id("Person_knows_Person").unwrap();
+        // A triangle of persons knowing each other
+        let pattern = RawPattern::with_vertices_edges(
+            [(0, person), (1, person), (2, person)],
+            [(0, 0, 1, knows), (1, 1, 2, knows), (2, 2, 0, knows)],
+        )
+        .to_general()
+        .unwrap();
+        let card = estimator.estimate(&pattern).unwrap();
+        let bounded = estimator
+            .estimate_within(&pattern, Duration::from_secs(3600))
+            .unwrap();
+        assert_eq!(
+            bounded,
+            BudgetedEstimate {
+                card,
+                partial: false
+            }
+        );
+
+        // Out of budget, only the first decomposition is joined.
+        let first = estimator.decompose(&pattern).swap_remove(0);
+        let first_card = CardinalityEstimatorManual::new(&catalog)
+            .estimate(first)
+            .unwrap();
+        assert!(first_card >= card);
+        let bounded = estimator.estimate_within(&pattern, Duration::ZERO).unwrap();
+        assert_eq!(
+            bounded,
+            BudgetedEstimate {
+                card: first_card,
+                partial: true
+            }
+        );
+
+        let knows_count = estimator.estimate_edge(person, knows, person).unwrap();
+        let product = estimator.estimate_edge_product(&pattern).unwrap();
+        assert_eq!(product, knows_count.powi(3));
+    }
+
+    #[test]
+    fn test_estimate_with_partition() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let catalog = build_ldbc_catalog(schema.clone(), graph);
+        let estimator = build_estimator(&catalog);
+
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let knows = schema.get_edge_label_id("Person_knows_Person").unwrap();
+        // (p1: Person)-[:knows]->(p2: Person)-[:knows]->(p3: Person)
+        let pattern = RawPattern::with_vertices_edges(
+            [(0, person), (1, person), (2, person)],
+            [(0, 0, 1, knows), (1, 1, 2, knows)],
+        )
+        .to_general()
+        .unwrap();
+        let card = estimator.estimate(&pattern).unwrap();
+        let whole = estimator
+            .estimate_with_partition(&pattern, vec![vec![0, 1]])
+            .unwrap();
+        assert_eq!(whole, card);
+        let split = estimator
+            .estimate_with_partition(&pattern, vec![vec![1], vec![0]])
+            .unwrap();
+        assert!(split > 0.);
+
+        // Uncovered, overlapping and unknown edges
+        assert!(matches!(
+            estimator.estimate_with_partition(&pattern, vec![vec![0]]),
+            Err(GCardError::Estimate(EstimateError::UncoveredEdge(1)))
+        ));
+        assert!(matches!(
+            estimator.estimate_with_partition(&pattern, vec![vec![0, 1], vec![1]]),
+            Err(GCardError::Estimate(EstimateError::OverlappingPartition(1)))
+        ));
+        assert!(matches!(
+            estimator.estimate_with_partition(&pattern, vec![vec![0], vec![1], vec![2]]),
+            Err(GCardError::Estimate(EstimateError::UnknownPartitionEdge(2)))
+        ));
+
+        // (p1: Person)<-[:knows]-(p2: Person)-[:knows]->(p3: Person), (p2)-[:knows]->(p4: Person)
+        let pattern = RawPattern::with_vertices_edges(
+            [(0, person), (1, person), (2, person), (3, person)],
+            [(0, 1, 0, knows), (1, 1, 2, knows), (2, 1, 3, knows)],
+        )
+        .to_general()
+        .unwrap();
+        let card = estimator
+            .estimate_with_partition(&pattern, vec![vec![0], vec![1], vec![2]])
+            .unwrap();
+        assert!(card > 0.);
+        // Stars of degree 3 are not in the catalog.
+        assert!(matches!(
+            estimator.estimate_with_partition(&pattern, vec![vec![0, 1, 2]]),
+            Err(GCardError::Estimate(EstimateError::GroupNotFound(_)))
+        ));
+        // p2 is inside the path of edges 0 and 1, so it cannot be joined with edge 2.
+        assert!(matches!(
+            estimator.estimate_with_partition(&pattern, vec![vec![0, 1], vec![2]]),
+            Err(GCardError::Estimate(EstimateError::SharedGroupVertex {
+                vertex: 1,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_estimate_expanded() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let catalog = build_ldbc_catalog(schema, graph.clone());
+        let estimator = build_estimator(&catalog);
+
+        // (m: Message)-[:hasCreator]->(p: Person)
+        let schema = build_ldbc_schema_with_messages();
+        let vertex = |name| schema.get_vertex_label_id(name).unwrap();
+        let edge = |name| schema.get_edge_label_id(name).unwrap();
+        let pattern = RawPattern::with_vertices_edges(
+            [(0, vertex("Message")), (1, vertex("Person"))],
+            [(0, 0, 1, edge("hasCreator"))],
+        )
+        .to_general()
+        .unwrap();
+        let card = estimator.estimate_expanded(&schema, &pattern).unwrap();
+        let num_edges = ["Comment_hasCreator_Person", "Post_hasCreator_Person"]
+            .map(|name| graph.get_num_edges(edge(name)).unwrap())
+            .iter()
+            .sum::<usize>();
+        assert_eq!(card, num_edges as f64);
+    }
+
+    #[test]
+    fn test_estimate_order_distribution() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let catalog = build_ldbc_catalog(schema.clone(), graph);
+        let estimator = build_estimator(&catalog);
+
+        // (p1: Person)-[:knows]->(p2: Person)-[:knows]->(p3: Person)-[:knows]->(p4: Person)
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let knows = schema.get_edge_label_id("Person_knows_Person").unwrap();
+        let pattern = RawPattern::with_vertices_edges(
+            [(0, person), (1, person), (2, person), (3, person)],
+            [(0, 0, 1, knows), (1, 1, 2, knows), (2, 2, 3, knows)],
+        )
+        .to_general()
+        .unwrap();
+        let distribution = estimator
+            .estimate_order_distribution(&pattern, 4, 42)
+            .unwrap();
+        assert_eq!(distribution.len(), 4);
+        for (order, card) in &distribution {
+            assert_eq!(order.iter().sorted().collect_vec(), [&1, &2]);
+            assert_eq!(
+                *card,
+                estimator
+                    .estimate_with_order(&pattern, order.clone())
+                    .unwrap()
+            );
+        }
+        assert_eq!(
+            estimator
+                .estimate_order_distribution(&pattern, 4, 42)
+                .unwrap(),
+            distribution
+        );
+
+        // (p1: Person)-[:knows]->(p2: Person)
+        let pattern =
+            RawPattern::with_vertices_edges([(0, person), (1, person)], [(0, 0, 1, knows)])
+                .to_general()
+                .unwrap();
+        let distribution = estimator
+            .estimate_order_distribution(&pattern, 2, 0)
+            .unwrap();
+        let card = estimator.estimate(&pattern).unwrap();
+        assert!(distribution.iter().all(|(_, c)| *c == card));
+    }
+
+    #[test]
+    fn test_compare_orders() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let catalog = build_ldbc_catalog(schema.clone(), graph);
+        let estimator = build_estimator(&catalog);
+
+        // (p1: Person)-[:knows]->(p2: Person)-[:knows]->(p3: Person)-[:knows]->(p4: Person)
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let knows = schema.get_edge_label_id("Person_knows_Person").unwrap();
+        let pattern = RawPattern::new()
+            .push_back_vertex((0, person))
+            .push_back_vertex((1, person))
+            .push_back_vertex((2, person))
+            .push_back_vertex((3, person))
+            .push_back_edge((0, 0, 1, knows))
+            .push_back_edge((1, 1, 2, knows))
+            .push_back_edge((2, 2, 3, knows))
+            .to_general()
+            .unwrap();
+        let (order_a, order_b) = (vec![1, 2], vec![2, 1]);
+        let [(card_a, steps_a), (card_b, steps_b)] = estimator
+            .compare_orders(&pattern, order_a.clone(), order_b.clone())
+            .unwrap();
+        assert_eq!(
+            card_a,
+            estimator.estimate_with_order(&pattern, order_a).unwrap()
+        );
+        assert_eq!(
+            card_b,
+            estimator.estimate_with_order(&pattern, order_b).unwrap()
+        );
+        assert_eq!(steps_a.first().unwrap().vertex, 1);
+        assert_eq!(steps_b.first().unwrap().vertex, 2);
+        assert_eq!(steps_a.last().unwrap().bound_count, card_a);
+        assert_eq!(steps_b.last().unwrap().bound_count, card_b);
+    }
+
+    #[test]
+    fn test_estimate_bounds() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let catalog = build_ldbc_catalog(schema.clone(), graph);
+        let estimator = build_estimator(&catalog);
+
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let knows = schema.get_edge_label_id("Person_knows_Person").unwrap();
+        let cycle = RawPattern::with_vertices_edges(
+            [(0, person), (1, person), (2, person), (3, person)],
+            [
+                (0, 0, 1, knows),
+                (1, 1, 2, knows),
+                (2, 2, 3, knows),
+                (3, 3, 0, knows),
+            ],
+        )
+        .to_general()
+        .unwrap();
+        let (lower, upper) = estimator.estimate_bounds(&cycle).unwrap();
+        assert_eq!(upper, estimator.estimate(&cycle).unwrap());
+        assert!(lower > 0.0 && lower <= upper);
+
+        // The lower end is joined from the decomposition of the upper bound only.
+        let patterns = estimator.decompose(&cycle);
+        assert!(patterns.len() > 1);
+        let mut id_generator = catalog.next_table_id().get()..;
+        let mut join = |p: &CatalogPattern, bound| {
+            let conn = catalog.conn();
+            join::estimate_bound(p.clone(), conn, &mut id_generator, None, bound, false).unwrap()
+        };
+        let (_, best) = patterns
+            .iter()
+            .map(|p| (join(p, Bound::Upper), p))
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .unwrap();
+        assert_eq!(lower, join(best, Bound::LeastCount).min(upper));
+        catalog.next_table_id().set(id_generator.next().unwrap());
+    }
+
+    #[test]
+    fn test_estimate_with_uncertainty() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let catalog = build_ldbc_catalog(schema.clone(), graph);
+        let estimator = build_estimator(&catalog);
+
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let knows = schema.get_edge_label_id("Person_knows_Person").unwrap();
+        let edge = RawPattern::with_vertices_edges([(0, person), (1, person)], [(0, 0, 1, knows)])
+            .to_general()
+            .unwrap();
+        let (card, uncertainty) = estimator.estimate_with_uncertainty(&edge).unwrap();
+        assert_eq!(card, estimator.estimate(&edge).unwrap());
+        assert_eq!(uncertainty, 0.0);
+
+        // The first elimination of a 4-cycle is bound, while the second is not.
+        let cycle = RawPattern::with_vertices_edges(
+            [(0, person), (1, person), (2, person), (3, person)],
+            [
+                (0, 0, 1, knows),
+                (1, 1, 2, knows),
+                (2, 2, 3, knows),
+                (3, 3, 0, knows),
+            ],
+        )
+        .to_general()
+        .unwrap();
+        let (card, uncertainty) = estimator.estimate_with_uncertainty(&cycle).unwrap();
+        let (_, steps) = estimator.estimate_with_steps(&cycle).unwrap();
+        assert_eq!(card, estimator.estimate(&cycle).unwrap());
+        assert_eq!(uncertainty, join::uncertainty(&steps, 0.0));
+        assert!(uncertainty > 0.0 && uncertainty < 1.0);
+    }
+
+    #[test]
+    fn test_estimate_with_provenance() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let catalog = build_ldbc_catalog(schema.clone(), graph.clone());
+        let estimator = build_estimator(&catalog);
+
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let knows = schema.get_edge_label_id("Person_knows_Person").unwrap();
+        let pattern = RawPattern::new()
+            .push_back_vertex((0, person))
+            .push_back_vertex((1, person))
+            .push_back_edge((0, 0, 1, knows))
+            .to_general()
+            .unwrap();
+        let (card, provenance) = estimator.estimate_with_provenance(&pattern).unwrap();
+        assert_eq!(card, estimator.estimate(&pattern).unwrap());
+        let num_edges = graph.get_num_edges(knows).unwrap() as u64;
+        assert_eq!(provenance.len(), 1);
+        let Provenance {
+            label_id,
+            kind,
+            count,
+        } = provenance[0];
+        assert_eq!(count, num_edges);
+        let edges = match kind {
+            EntryKind::Path => catalog.get_path(label_id).unwrap().edges(),
+            EntryKind::Star => catalog.get_star(label_id).unwrap().edges(),
+        };
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].label_id(), knows);
+    }
+
+    #[test]
+    fn test_estimate_edge() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let catalog = build_ldbc_catalog(schema.clone(), graph.clone());
+        let estimator = build_estimator(&catalog);
+
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let comment = schema.get_vertex_label_id("Comment").unwrap();
+        let knows = schema.get_edge_label_id("Person_knows_Person").unwrap();
+        let has_creator = schema
+            .get_edge_label_id("Comment_hasCreator_Person")
+            .unwrap();
+        for (src, edge, dst) in [(person, knows, person), (comment, has_creator, person)] {
+            let pattern = RawPattern::new()
+                .push_back_vertex((0, src))
+                .push_back_vertex((1, dst))
+                .push_back_edge((0, 0, 1, edge))
+                .to_general()
+                .unwrap();
+            let card = estimator.estimate_edge(src, edge, dst).unwrap();
+            assert_eq!(card, graph.get_num_edges(edge).unwrap() as f64);
+            assert_eq!(card, estimator.estimate(&pattern).unwrap());
+        }
+        // The schema has no knows edges between comments.
+        assert_eq!(
+            estimator.estimate_edge(comment, knows, comment).unwrap(),
+            0.0
+        );
+        assert_eq!(
+            estimator
+                .estimate_edge(person, has_creator, comment)
+                .unwrap(),
+            0.0
+        );
+        assert!(estimator
+            .estimate_edge(person, LabelId::MAX, person)
+            .is_err());
+    }
+
+    #[test]
+    fn test_estimate_with_joints() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let build = |joint_statistics| {
+            ldbc_catalog_builder(schema.clone(), graph.clone())
+                .max_path_length(1)
+                .joint_statistics(joint_statistics)
+                .build()
+                .unwrap()
+        };
+        let plain = build(false);
+        let joint = build(true);
+        assert!(plain.joint_paths().is_empty());
+        assert!(!joint.joint_paths().is_empty());
+        let counter = build_exact_counter(graph.clone());
+
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let knows = schema.get_edge_label_id("Person_knows_Person").unwrap();
+        let path = |len: usize| {
+            let vertices = (0..=len).map(|v| (v as TagId, person));
+            let edges = (0..len).map(|e| (e as TagId, e as TagId, e as TagId + 1, knows));
+            RawPattern::with_vertices_edges(vertices, edges)
+                .to_general()
+                .unwrap()
+        };
+        let estimate = |catalog, pattern| {
+            CardinalityEstimator::new(catalog, 1, 1, 2, 10, true, false, false)
+                .estimate(pattern)
+                .unwrap()
+        };
+
+        // The two hops of a 2-path are joined exactly.
+        let pattern = path(2);
+        let truth = counter.count(&pattern, None).unwrap() as f64;
+        assert!(estimate(&plain, &pattern) > truth);
+        assert_eq!(estimate(&joint, &pattern), truth);
+
+        // Longer paths are still bounded, and tighter.
+        let pattern = path(3);
+        let truth = counter.count(&pattern, None).unwrap() as f64;
+        let card = estimate(&joint, &pattern);
+        assert!(truth <= card && card <= estimate(&plain, &pattern));
+    }
+
+    #[test]
+    fn test_estimate_with_overrides() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let catalog = build_ldbc_catalog(schema.clone(), graph);
+        let estimator = build_estimator(&catalog);
+
+        // (c: Comment)-[:hasCreator]->(p1: Person)-[:knows]->(p2: Person)
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let comment = schema.get_vertex_label_id("Comment").unwrap();
+        let knows = schema.get_edge_label_id("Person_knows_Person").unwrap();
+        let has_creator = schema
+            .get_edge_label_id("Comment_hasCreator_Person")
+            .unwrap();
+        let pattern = RawPattern::new()
+            .push_back_vertex((0, comment))
+            .push_back_vertex((1, person))
+            .push_back_vertex((2, person))
+            .push_back_edge((0, 0, 1, has_creator))
+            .push_back_edge((1, 1, 2, knows))
+            .to_general()
+            .unwrap();
+        let card = estimator.estimate(&pattern).unwrap();
+        assert!(card > 0.0);
+        assert_eq!(
+            estimator
+                .estimate_with_overrides(&pattern, &HashMap::new())
+                .unwrap(),
+            card
+        );
+        for (overrides, factor) in [
+            (HashMap::from([(1, 0.5)]), 0.5),
+            (HashMap::from([(0, 0.5), (1, 0.2)]), 0.1),
+            (HashMap::from([(0, 0.0)]), 0.0),
+        ] {
+            let scaled = estimator
+                .estimate_with_overrides(&pattern, &overrides)
+                .unwrap();
+            assert!((scaled - card * factor).abs() < 1e-6, "{overrides:?}");
+        }
+        assert!(matches!(
+            estimator.estimate_with_overrides(&pattern, &HashMap::from([(2, 0.5)])),
+            Err(GCardError::Estimate(EstimateError::UnknownOverrideEdge(2)))
+        ));
+        assert!(matches!(
+            estimator.estimate_with_overrides(&pattern, &HashMap::from([(0, f64::NAN)])),
+            Err(GCardError::Estimate(EstimateError::InvalidOverride {
+                edge: 0,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_calibration_records() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let catalog = build_ldbc_catalog(schema.clone(), graph.clone());
+        let estimator = build_estimator(&catalog);
+        let counter = build_exact_counter(graph.clone());
+
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let knows = schema.get_edge_label_id("Person_knows_Person").unwrap();
+        let workload = [
+            RawPattern::with_vertices_edges([(0, person), (1, person)], [(0, 0, 1, knows)]),
+            RawPattern::with_vertices_edges(
+                [(0, person), (1, person), (2, person)],
+                [(0, 0, 1, knows), (1, 1, 2, knows)],
+            ),
+        ]
+        .map(|p| p.to_general().unwrap());
+        let records = calibration_records(&estimator, &counter, &workload, None).unwrap();
+        assert_eq!(records.len(), workload.len());
+        for (record, pattern) in records.iter().zip(&workload) {
+            assert_eq!(record.code, pattern.encode());
+            assert_eq!(record.estimate, estimator.estimate(pattern).unwrap());
+            assert_eq!(record.truth, counter.count(pattern, None));
+        }
+        assert_eq!(
+            records[0].truth,
+            Some(graph.get_num_edges(knows).unwrap() as u128)
+        );
+
+        let records =
+            calibration_records(&estimator, &counter, &workload, Some(Duration::ZERO)).unwrap();
+        assert!(records.iter().all(|record| record.truth.is_none()));
+    }
+}
@@ -0,0 +1,60 @@
+use std::path::Path;
+
+use super::{CardinalityEstimatorManual, CatalogEdge, CatalogPattern, CatalogVertex};
+use crate::catalog::{Catalog, DuckCatalog};
+use crate::error::{CatalogError, GCardResult};
+use crate::pattern::GraphPattern;
+
+/// Export validation lives with the estimator it relies on, so the catalog does not depend on it.
+impl DuckCatalog {
+    /// Export the catalog, then import it back and check that the estimates of all single-edge
+    /// paths and single-vertex stars are unchanged.
+    pub fn export_validated<P: AsRef<Path>>(&self, dir: P) -> GCardResult<()> {
+        self.export(&dir)?;
+        let imported = Self::import(&dir)?;
+        self.validate_estimates(&imported)
+    }
+
+    /// Check that `other` has the single-edge paths and single-vertex stars of the catalog with the
+    /// same label ids, and estimates them equally.
+    pub(crate) fn validate_estimates(&self, other: &DuckCatalog) -> GCardResult<()> {
+        let mut patterns = vec![];
+        for path in self.paths().iter().filter(|path| path.len() == 1) {
+            let code = path.encode();
+            let label_id = self.get_path_label_id(&code).unwrap();
+            if other.get_path_label_id(&code) != Some(label_id) {
+                return Err(CatalogError::PathMissingAfterImport(Box::new(path.clone())).into());
+            }
+            let (start, end) = (path.start(), path.end());
+            let mut pattern = CatalogPattern::new();
+            pattern.add_vertex(start.into());
+            pattern.add_vertex(end.into());
+            pattern.add_edge(CatalogEdge::path(0, label_id, start.tag_id(), end.tag_id()));
+            patterns.push((format!("path {path}"), pattern));
+        }
+        for star in self.stars().iter().filter(|star| star.edges().is_empty()) {
+            let code = star.encode();
+            let label_id = self.get_star_label_id(0, &code).unwrap();
+            if other.get_star_label_id(0, &code) != Some(label_id) {
+                return Err(CatalogError::StarMissingAfterImport(label_id).into());
+            }
+            let center = star.vertices()[0];
+            let mut pattern = CatalogPattern::from(CatalogVertex::from(center));
+            pattern.add_edge(CatalogEdge::star(0, label_id, center.tag_id()));
+            patterns.push((format!("star {label_id}"), pattern));
+        }
+        for (name, pattern) in patterns {
+            let expected = CardinalityEstimatorManual::new(self).estimate(pattern.clone())?;
+            let actual = CardinalityEstimatorManual::new(other).estimate(pattern)?;
+            if expected != actual {
+                let err = CatalogError::EstimateChanged {
+                    name,
+                    expected,
+                    actual,
+                };
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,12 +1,15 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
 use super::{ColumnGroup, ColumnRef};
 use crate::common::{DefaultVertexId, TagId};
 
 type ColumnPos = (usize, usize);
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Table {
     tag_id_to_column_pos: HashMap<TagId, ColumnPos>,
     groups: Vec<ColumnGroup>,
@@ -64,6 +67,14 @@ impl Table {
         self.groups.len()
     }
 
+    /// Return the number of values stored in all columns, excluding the offsets of groups.
+    pub fn num_values(&self) -> usize {
+        self.groups
+            .iter()
+            .map(|group| group.num_values() * group.num_columns())
+            .sum()
+    }
+
     pub fn num_items(&self) -> usize {
         if self.groups.is_empty() {
             0
@@ -83,6 +94,31 @@ impl Table {
         )
     }
 
+    /// Drop the items failing `pred`. For each item, `pred` is given the item slices of all
+    /// columns, ordered by group and then by column within the group.
+    pub fn retain_items<F>(&mut self, pred: F)
+    where
+        F: Fn(&[&[DefaultVertexId]]) -> bool,
+    {
+        let columns = self
+            .groups
+            .iter()
+            .flat_map(|group| (0..group.num_columns()).map(|i| group.get_column(i).unwrap()))
+            .collect_vec();
+        let mut item = Vec::with_capacity(columns.len());
+        let keep = (0..self.num_items())
+            .map(|i| {
+                item.clear();
+                item.extend(columns.iter().map(|column| column.get_item(i).unwrap()));
+                pred(&item)
+            })
+            .collect_vec();
+        drop(columns);
+        for group in &mut self.groups {
+            group.retain_items(&keep);
+        }
+    }
+
     pub fn count(&self) -> usize {
         if self.groups.is_empty() {
             return 0;
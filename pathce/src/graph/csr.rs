@@ -1,54 +1,167 @@
-use itertools::Itertools;
+use std::any::type_name;
+use std::fmt;
+
+use itertools::{Either, Itertools};
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
 
 use super::InternalId;
-use crate::common::DefaultVertexId;
-use crate::error::{GCardError, GCardResult};
+use crate::common::{DefaultVertexId, InternalVertexMap};
+use crate::error::{GCardResult, GraphError};
+
+/// An unsigned integer type of the offsets or the neighbors of a [`Csr`].
+pub trait CsrIndex: Copy + Ord + Default + Send + Sync + fmt::Debug {
+    fn from_usize(index: usize) -> Option<Self>;
+
+    fn to_usize(self) -> usize;
+}
+
+macro_rules! impl_csr_index {
+    ($($t:ty),*) => {
+        $(
+            impl CsrIndex for $t {
+                fn from_usize(index: usize) -> Option<Self> {
+                    Self::try_from(index).ok()
+                }
+
+                fn to_usize(self) -> usize {
+                    self as usize
+                }
+            }
+        )*
+    };
+}
+
+impl_csr_index!(u16, u32, usize);
+
+/// An integer type of the ids stored as the neighbors of a [`Csr`]. Narrow types store internal
+/// ids of the neighbor label, while `usize` stores the vertex ids themselves.
+pub trait NeighborIndex: CsrIndex {
+    fn as_neighbor_ids(ids: &[Self]) -> NeighborIds<'_>;
+
+    /// Return the id to store for the neighbor `vertex_id`, whose internal id is `internal_id`.
+    fn from_neighbor(vertex_id: DefaultVertexId, internal_id: InternalId) -> GCardResult<Self>;
+}
+
+impl NeighborIndex for u16 {
+    fn as_neighbor_ids(ids: &[Self]) -> NeighborIds<'_> {
+        NeighborIds::U16(ids)
+    }
+
+    fn from_neighbor(_: DefaultVertexId, internal_id: InternalId) -> GCardResult<Self> {
+        narrow(internal_id as usize)
+    }
+}
+
+impl NeighborIndex for u32 {
+    fn as_neighbor_ids(ids: &[Self]) -> NeighborIds<'_> {
+        NeighborIds::U32(ids)
+    }
+
+    fn from_neighbor(_: DefaultVertexId, internal_id: InternalId) -> GCardResult<Self> {
+        narrow(internal_id as usize)
+    }
+}
+
+impl NeighborIndex for usize {
+    fn as_neighbor_ids(ids: &[Self]) -> NeighborIds<'_> {
+        NeighborIds::Vertices(ids)
+    }
+
+    fn from_neighbor(vertex_id: DefaultVertexId, _: InternalId) -> GCardResult<Self> {
+        Ok(vertex_id)
+    }
+}
+
+/// The widths of the offsets and the neighbors of the CSRs of a graph.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CsrWidth {
+    /// The narrowest offsets and internal ids that fit the number of edges of every edge label and
+    /// the number of vertices of its endpoint labels, e.g., `u32` offsets and `u16` ids for labels
+    /// with fewer than 65536 vertices.
+    #[default]
+    Narrowest,
+    /// `usize` offsets and `u32` internal ids for every edge label.
+    Wide,
+    /// `usize` offsets and the vertex ids of the neighbors for every edge label, which take the
+    /// most memory but are returned by [`Neighbors::as_slice`] without looking them up.
+    VertexIds,
+}
+
+fn narrow<T: CsrIndex>(index: usize) -> GCardResult<T> {
+    T::from_usize(index).ok_or_else(|| {
+        let err = GraphError::IndexOverflow {
+            index,
+            width: type_name::<T>(),
+        };
+        err.into()
+    })
+}
 
+/// A CSR of the neighbors of each vertex, indexed by internal ids. The offsets are stored as `O`,
+/// and the neighbors as ids of type `I` (see [`NeighborIndex`]).
 #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
-pub struct Csr {
-    offsets: Vec<usize>,
-    neighbors: Vec<DefaultVertexId>,
+pub struct Csr<O, I> {
+    offsets: Vec<O>,
+    neighbors: Vec<I>,
 }
 
-impl Csr {
+impl<O: CsrIndex, I: NeighborIndex> Csr<O, I> {
     pub fn get_num_neighbors(&self) -> usize {
         self.neighbors.len()
     }
 
-    pub fn neighbors(&self, vertex_id: InternalId) -> &[DefaultVertexId] {
-        let max_vertex_id = self.offsets.len() as u32 - 2;
-        if vertex_id > max_vertex_id {
+    /// Return the stored ids of the neighbors of the given vertex.
+    pub fn neighbors(&self, vertex_id: InternalId) -> &[I] {
+        if vertex_id as usize + 1 >= self.offsets.len() {
             return &[];
         }
-        let start = self.offsets[vertex_id as usize];
-        let end = self.offsets[vertex_id as usize + 1];
+        let start = self.offsets[vertex_id as usize].to_usize();
+        let end = self.offsets[vertex_id as usize + 1].to_usize();
         &self.neighbors[start..end]
     }
 
+    /// Build a CSR from edges sorted by `(src, neighbor)`, where the neighbors are vertex ids
+    /// of `neighbor_map`, stored as [`NeighborIndex::from_neighbor`].
+    ///
+    /// The ordering guarantees that the neighbors of every vertex are in ascending order of their
+    /// vertex ids (not of their internal ids), which is relied on by binary searches and
+    /// merge-based intersections over [`Neighbors`].
     pub fn from_sorted_edges(
-        max_vertex_id: InternalId,
+        num_vertices: usize,
         edges: &[(InternalId, DefaultVertexId)],
+        neighbor_map: &InternalVertexMap,
     ) -> GCardResult<Self> {
-        let mut offsets = vec![0; max_vertex_id as usize + 2];
-        let neighbors = edges.iter().map(|(_, neighbor)| *neighbor).collect();
+        if let Some((e1, e2)) = edges.iter().tuple_windows().find(|(e1, e2)| e1 > e2) {
+            return Err(GraphError::UnsortedEdges(*e1, *e2).into());
+        }
+        let mut offsets = vec![O::default(); num_vertices + 1];
+        let neighbors = edges
+            .iter()
+            .map(|(_, neighbor)| {
+                let internal_id = neighbor_map
+                    .get_by_left(neighbor)
+                    .ok_or(GraphError::VertexNotFound(*neighbor))?;
+                I::from_neighbor(*neighbor, *internal_id)
+            })
+            .try_collect()?;
 
         let mut current_vertex_id = 0;
-        let mut current_offset = 0;
+        let mut current_offset = O::default();
 
         for (src, neighbors) in &edges.iter().chunk_by(|(src, _)| *src) {
-            if src < current_vertex_id {
-                return Err(GCardError::Graph("edges are not sorted".into()));
-            }
-            if src > max_vertex_id {
-                let err = format!("vertex id {src} exceeds max vertex id {max_vertex_id}");
-                return Err(GCardError::Graph(err));
+            if src as usize >= num_vertices {
+                let err = GraphError::VertexIdOutOfRange {
+                    vertex_id: src,
+                    num_vertices,
+                };
+                return Err(err.into());
             }
             for vertex_id in current_vertex_id..=src {
                 offsets[vertex_id as usize] = current_offset;
             }
             current_vertex_id = src + 1;
-            current_offset += neighbors.count();
+            current_offset = narrow(current_offset.to_usize() + neighbors.count())?;
         }
         offsets
             .iter_mut()
@@ -59,13 +172,13 @@ impl Csr {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct BidirectionalCsr {
-    forward: Csr,
-    backward: Csr,
+pub struct BidirectionalCsr<O, I> {
+    forward: Csr<O, I>,
+    backward: Csr<O, I>,
 }
 
-impl BidirectionalCsr {
-    pub fn new(forward: Csr, backward: Csr) -> Self {
+impl<O: CsrIndex, I: NeighborIndex> BidirectionalCsr<O, I> {
+    pub fn new(forward: Csr<O, I>, backward: Csr<O, I>) -> Self {
         Self { forward, backward }
     }
 
@@ -73,29 +186,412 @@ impl BidirectionalCsr {
         self.forward.get_num_neighbors()
     }
 
-    pub fn outgoing_neighbors(&self, vertex_id: InternalId) -> &[DefaultVertexId] {
+    /// Return the stored ids of the outgoing neighbors of the given vertex.
+    pub fn outgoing_neighbors(&self, vertex_id: InternalId) -> &[I] {
         self.forward.neighbors(vertex_id)
     }
 
-    pub fn incoming_neighbors(&self, vertex_id: InternalId) -> &[DefaultVertexId] {
+    /// Return the stored ids of the incoming neighbors of the given vertex.
+    pub fn incoming_neighbors(&self, vertex_id: InternalId) -> &[I] {
         self.backward.neighbors(vertex_id)
     }
 }
 
+/// The CSRs of an edge label, with the offset and neighbor types chosen by a [`CsrWidth`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum EdgeCsr {
+    U32U16(BidirectionalCsr<u32, u16>),
+    U32U32(BidirectionalCsr<u32, u32>),
+    UsizeU16(BidirectionalCsr<usize, u16>),
+    UsizeU32(BidirectionalCsr<usize, u32>),
+    UsizeVertexIds(BidirectionalCsr<usize, DefaultVertexId>),
+}
+
+macro_rules! dispatch {
+    ($csr:expr, $inner:ident => $body:expr) => {
+        match $csr {
+            EdgeCsr::U32U16($inner) => $body,
+            EdgeCsr::U32U32($inner) => $body,
+            EdgeCsr::UsizeU16($inner) => $body,
+            EdgeCsr::UsizeU32($inner) => $body,
+            EdgeCsr::UsizeVertexIds($inner) => $body,
+        }
+    };
+}
+
+impl EdgeCsr {
+    /// Build the CSRs of the given width from forward edges sorted by `(src, dst)` and backward
+    /// edges sorted by `(dst, src)`, where the sources and destinations are internal ids and
+    /// vertex ids respectively.
+    pub fn from_sorted_edges(
+        forward_edges: &[(InternalId, DefaultVertexId)],
+        backward_edges: &[(InternalId, DefaultVertexId)],
+        src_vertex_map: &InternalVertexMap,
+        dst_vertex_map: &InternalVertexMap,
+        width: CsrWidth,
+    ) -> GCardResult<Self> {
+        fn build<O: CsrIndex, I: NeighborIndex>(
+            forward_edges: &[(InternalId, DefaultVertexId)],
+            backward_edges: &[(InternalId, DefaultVertexId)],
+            src_vertex_map: &InternalVertexMap,
+            dst_vertex_map: &InternalVertexMap,
+        ) -> GCardResult<BidirectionalCsr<O, I>> {
+            // Internal ids are dense, and a vertex label may have no vertices at all.
+            let forward =
+                Csr::from_sorted_edges(src_vertex_map.len(), forward_edges, dst_vertex_map)?;
+            let backward =
+                Csr::from_sorted_edges(dst_vertex_map.len(), backward_edges, src_vertex_map)?;
+            Ok(BidirectionalCsr::new(forward, backward))
+        }
+
+        let (narrow_offsets, narrow_ids) = match width {
+            CsrWidth::Narrowest => {
+                let narrow_offsets = u32::from_usize(forward_edges.len()).is_some();
+                // Internal ids range from 0 to the number of vertices minus one.
+                let max_num_vertices = src_vertex_map.len().max(dst_vertex_map.len());
+                let narrow_ids = u16::from_usize(max_num_vertices.saturating_sub(1)).is_some();
+                (narrow_offsets, narrow_ids)
+            }
+            CsrWidth::Wide => (false, false),
+            CsrWidth::VertexIds => {
+                return Ok(EdgeCsr::UsizeVertexIds(build(
+                    forward_edges,
+                    backward_edges,
+                    src_vertex_map,
+                    dst_vertex_map,
+                )?));
+            }
+        };
+        let csr = match (narrow_offsets, narrow_ids) {
+            (true, true) => EdgeCsr::U32U16(build(
+                forward_edges,
+                backward_edges,
+                src_vertex_map,
+                dst_vertex_map,
+            )?),
+            (true, false) => EdgeCsr::U32U32(build(
+                forward_edges,
+                backward_edges,
+                src_vertex_map,
+                dst_vertex_map,
+            )?),
+            (false, true) => EdgeCsr::UsizeU16(build(
+                forward_edges,
+                backward_edges,
+                src_vertex_map,
+                dst_vertex_map,
+            )?),
+            (false, false) => EdgeCsr::UsizeU32(build(
+                forward_edges,
+                backward_edges,
+                src_vertex_map,
+                dst_vertex_map,
+            )?),
+        };
+        Ok(csr)
+    }
+
+    pub fn get_num_edges(&self) -> usize {
+        dispatch!(self, csr => csr.get_num_edges())
+    }
+
+    /// Return the outgoing neighbors of the given vertex, where `dst_vertices` are the vertices
+    /// of the destination label.
+    pub fn outgoing_neighbors<'a>(
+        &'a self,
+        vertex_id: InternalId,
+        dst_vertices: &'a [DefaultVertexId],
+    ) -> Neighbors<'a> {
+        let ids = dispatch!(self, csr => NeighborIndex::as_neighbor_ids(csr.outgoing_neighbors(vertex_id)));
+        Neighbors::new(ids, dst_vertices)
+    }
+
+    /// Return the incoming neighbors of the given vertex, where `src_vertices` are the vertices
+    /// of the source label.
+    pub fn incoming_neighbors<'a>(
+        &'a self,
+        vertex_id: InternalId,
+        src_vertices: &'a [DefaultVertexId],
+    ) -> Neighbors<'a> {
+        let ids = dispatch!(self, csr => NeighborIndex::as_neighbor_ids(csr.incoming_neighbors(vertex_id)));
+        Neighbors::new(ids, src_vertices)
+    }
+}
+
+/// The stored ids of the neighbors of a vertex.
+#[derive(Debug, Clone, Copy)]
+pub enum NeighborIds<'a> {
+    U16(&'a [u16]),
+    U32(&'a [u32]),
+    Vertices(&'a [DefaultVertexId]),
+}
+
+/// The neighbors of a vertex, stored as internal ids of the neighbor label and returned as vertex
+/// ids in ascending order.
+///
+/// The neighbors are generally not a slice of vertex ids, since storing internal ids narrower than
+/// vertex ids shrinks the CSR, so every vertex id is looked up in the vertices of the neighbor
+/// label. A graph built with [`CsrWidth::VertexIds`] stores the vertex ids instead, which
+/// [`Self::as_slice`] borrows without the lookups.
+#[derive(Clone, Copy)]
+pub struct Neighbors<'a> {
+    ids: NeighborIds<'a>,
+    vertices: &'a [DefaultVertexId],
+}
+
+impl<'a> Neighbors<'a> {
+    fn new(ids: NeighborIds<'a>, vertices: &'a [DefaultVertexId]) -> Self {
+        Self { ids, vertices }
+    }
+
+    pub fn len(&self) -> usize {
+        match self.ids {
+            NeighborIds::U16(ids) => ids.len(),
+            NeighborIds::U32(ids) => ids.len(),
+            NeighborIds::Vertices(ids) => ids.len(),
+        }
+    }
+
+    /// Return the neighbors as a slice of vertex ids, if they are stored as such (see
+    /// [`CsrWidth::VertexIds`]).
+    pub fn as_slice(&self) -> Option<&'a [DefaultVertexId]> {
+        match self.ids {
+            NeighborIds::Vertices(ids) => Some(ids),
+            _ => None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<DefaultVertexId> {
+        let internal_id = match self.ids {
+            NeighborIds::U16(ids) => ids.get(index)?.to_usize(),
+            NeighborIds::U32(ids) => ids.get(index)?.to_usize(),
+            NeighborIds::Vertices(ids) => return ids.get(index).copied(),
+        };
+        Some(self.vertices[internal_id])
+    }
+
+    pub fn first(&self) -> Option<DefaultVertexId> {
+        self.get(0)
+    }
+
+    pub fn iter(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = DefaultVertexId> + ExactSizeIterator + Clone + 'a {
+        let vertices = self.vertices;
+        match self.ids {
+            NeighborIds::U16(ids) => Either::Left(Either::Left(
+                ids.iter().map(move |id| vertices[*id as usize]),
+            )),
+            NeighborIds::U32(ids) => Either::Left(Either::Right(
+                ids.iter().map(move |id| vertices[*id as usize]),
+            )),
+            NeighborIds::Vertices(ids) => Either::Right(ids.iter().copied()),
+        }
+    }
+
+    pub fn par_iter(&self) -> impl IndexedParallelIterator<Item = DefaultVertexId> + 'a {
+        let vertices = self.vertices;
+        use rayon::iter::Either;
+        match self.ids {
+            NeighborIds::U16(ids) => Either::Left(Either::Left(
+                ids.par_iter().map(move |id| vertices[*id as usize]),
+            )),
+            NeighborIds::U32(ids) => Either::Left(Either::Right(
+                ids.par_iter().map(move |id| vertices[*id as usize]),
+            )),
+            NeighborIds::Vertices(ids) => Either::Right(ids.par_iter().copied()),
+        }
+    }
+
+    /// Binary-search the vertex id among the neighbors, as [`slice::binary_search`].
+    pub fn binary_search(&self, vertex_id: DefaultVertexId) -> Result<usize, usize> {
+        let vertices = self.vertices;
+        match self.ids {
+            NeighborIds::U16(ids) => {
+                ids.binary_search_by(|id| vertices[*id as usize].cmp(&vertex_id))
+            }
+            NeighborIds::U32(ids) => {
+                ids.binary_search_by(|id| vertices[*id as usize].cmp(&vertex_id))
+            }
+            NeighborIds::Vertices(ids) => ids.binary_search(&vertex_id),
+        }
+    }
+
+    pub fn contains(&self, vertex_id: DefaultVertexId) -> bool {
+        self.binary_search(vertex_id).is_ok()
+    }
+
+    /// Return the number of times the vertex id occurs among the neighbors, which is the number
+    /// of parallel edges to it.
+    pub fn multiplicity(&self, vertex_id: DefaultVertexId) -> usize {
+        fn multiplicity<I>(
+            ids: &[I],
+            vertex_of: impl Fn(&I) -> DefaultVertexId,
+            vertex_id: DefaultVertexId,
+        ) -> usize {
+            let start = ids.partition_point(|id| vertex_of(id) < vertex_id);
+            let end = ids.partition_point(|id| vertex_of(id) <= vertex_id);
+            end - start
+        }
+
+        let vertices = self.vertices;
+        match self.ids {
+            NeighborIds::U16(ids) => multiplicity(ids, |id| vertices[*id as usize], vertex_id),
+            NeighborIds::U32(ids) => multiplicity(ids, |id| vertices[*id as usize], vertex_id),
+            NeighborIds::Vertices(ids) => multiplicity(ids, |id| *id, vertex_id),
+        }
+    }
+
+    pub fn to_vec(self) -> Vec<DefaultVertexId> {
+        self.iter().collect()
+    }
+}
+
+impl Default for Neighbors<'_> {
+    fn default() -> Self {
+        Self::new(NeighborIds::U16(&[]), &[])
+    }
+}
+
+impl PartialEq for Neighbors<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for Neighbors<'_> {}
+
+impl fmt::Debug for Neighbors<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn vertex_map(vertices: &[DefaultVertexId]) -> InternalVertexMap {
+        vertices
+            .iter()
+            .enumerate()
+            .map(|(internal_id, id)| (*id, internal_id as InternalId))
+            .collect()
+    }
+
     #[test]
     fn test_csr() {
-        let csr = Csr::from_sorted_edges(6, &[(3, 1), (3, 2), (5, 1)]).unwrap();
+        let neighbor_map = vertex_map(&[2, 1]);
+        let csr = Csr::<u32, u16>::from_sorted_edges(7, &[(3, 1), (3, 2), (5, 1)], &neighbor_map)
+            .unwrap();
         let expected = Csr {
             offsets: vec![0, 0, 0, 0, 2, 2, 3, 3],
-            neighbors: vec![1, 2, 1],
+            neighbors: vec![1, 0, 1],
         };
         assert_eq!(csr, expected);
 
-        assert_eq!(csr.neighbors(3), &[1, 2]);
+        assert_eq!(csr.neighbors(3), &[1, 0]);
         assert!(csr.neighbors(4).is_empty());
+
+        let csr = Csr::<u32, u16>::from_sorted_edges(0, &[], &neighbor_map).unwrap();
+        assert_eq!(csr.offsets, vec![0]);
+        assert!(csr.neighbors(0).is_empty());
+        assert!(Csr::<u32, u16>::from_sorted_edges(0, &[(0, 1)], &neighbor_map).is_err());
+        // The neighbors must be in the neighbor map.
+        assert!(Csr::<u32, u16>::from_sorted_edges(7, &[(3, 4)], &neighbor_map).is_err());
+    }
+
+    #[test]
+    fn test_csr_width() {
+        let mut neighbor_map = InternalVertexMap::new();
+        neighbor_map.insert(1, u16::MAX as InternalId + 1);
+        let edges = [(0, 1)];
+        assert!(Csr::<u32, u32>::from_sorted_edges(1, &edges, &neighbor_map).is_ok());
+        let err = Csr::<u32, u16>::from_sorted_edges(1, &edges, &neighbor_map).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "GraphError: index 65536 does not fit in u16"
+        );
+
+        // The narrowest types are chosen from the number of vertices of both endpoint labels.
+        let small = vertex_map(&[1, 2]);
+        let large = vertex_map(&(0..=u16::MAX as DefaultVertexId + 1).collect_vec());
+        let build = |dst_vertex_map, backward_edges: &[_], width| {
+            EdgeCsr::from_sorted_edges(&[(0, 2)], backward_edges, &small, dst_vertex_map, width)
+                .unwrap()
+        };
+        let csr = build(&small, &[(1, 1)], CsrWidth::Narrowest);
+        assert!(matches!(csr, EdgeCsr::U32U16(_)));
+        let csr = build(&large, &[(2, 1)], CsrWidth::Narrowest);
+        assert!(matches!(csr, EdgeCsr::U32U32(_)));
+        let csr =
+            EdgeCsr::from_sorted_edges(&[], &[], &small, &vertex_map(&[]), CsrWidth::Narrowest)
+                .unwrap();
+        assert!(matches!(csr, EdgeCsr::U32U16(_)));
+        // The other widths do not depend on the number of vertices.
+        let csr = build(&small, &[(1, 1)], CsrWidth::Wide);
+        assert!(matches!(csr, EdgeCsr::UsizeU32(_)));
+        let csr = build(&small, &[(1, 1)], CsrWidth::VertexIds);
+        assert!(matches!(csr, EdgeCsr::UsizeVertexIds(_)));
+    }
+
+    #[test]
+    fn test_neighbors() {
+        let src_vertices = [7, 3];
+        let dst_vertices = [30, 10, 20];
+        for width in [CsrWidth::Narrowest, CsrWidth::Wide, CsrWidth::VertexIds] {
+            let csr = EdgeCsr::from_sorted_edges(
+                &[(0, 10), (0, 20), (0, 20), (0, 30), (1, 20)],
+                &[(0, 7), (1, 7), (2, 3), (2, 7), (2, 7)],
+                &vertex_map(&src_vertices),
+                &vertex_map(&dst_vertices),
+                width,
+            )
+            .unwrap();
+            assert_eq!(csr.get_num_edges(), 5);
+
+            let neighbors = csr.outgoing_neighbors(0, &dst_vertices);
+            assert_eq!(neighbors.to_vec(), vec![10, 20, 20, 30]);
+            assert_eq!(
+                neighbors.par_iter().collect::<Vec<_>>(),
+                vec![10, 20, 20, 30]
+            );
+            assert_eq!(neighbors.len(), 4);
+            assert_eq!(neighbors.first(), Some(10));
+            assert_eq!(neighbors.get(3), Some(30));
+            assert_eq!(neighbors.get(4), None);
+            assert_eq!(neighbors.binary_search(10), Ok(0));
+            assert_eq!(neighbors.binary_search(25), Err(3));
+            assert!(!neighbors.contains(7));
+            assert_eq!(neighbors.multiplicity(20), 2);
+            assert_eq!(neighbors.multiplicity(25), 0);
+            let as_slice = neighbors.as_slice();
+            if width == CsrWidth::VertexIds {
+                assert_eq!(as_slice, Some([10, 20, 20, 30].as_slice()));
+            } else {
+                assert_eq!(as_slice, None);
+            }
+
+            let neighbors = csr.incoming_neighbors(2, &src_vertices);
+            assert_eq!(neighbors.to_vec(), vec![3, 7, 7]);
+            assert!(csr.incoming_neighbors(3, &src_vertices).is_empty());
+            assert_eq!(
+                Neighbors::default(),
+                csr.outgoing_neighbors(2, &dst_vertices)
+            );
+        }
+    }
+
+    #[test]
+    fn test_csr_unsorted() {
+        let neighbor_map = vertex_map(&[1, 2]);
+        let unsorted = [(3, 2), (3, 1), (5, 1)];
+        assert!(Csr::<u32, u16>::from_sorted_edges(7, &unsorted, &neighbor_map).is_err());
+        let unsorted = [(5, 1), (3, 1), (3, 2)];
+        assert!(Csr::<u32, u16>::from_sorted_edges(7, &unsorted, &neighbor_map).is_err());
     }
 }
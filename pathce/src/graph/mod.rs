@@ -1,24 +1,35 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::{BufReader, BufWriter};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
 use std::path::Path;
+use std::time::Duration;
 
 use csv::ReaderBuilder;
-use itertools::Itertools;
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use duckdb::Connection;
+use itertools::{EitherOrBoth, Itertools};
+use log::{debug, warn};
+use memmap2::Mmap;
+use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use rayon::slice::ParallelSliceMut;
 use rayon::{ThreadPool, ThreadPoolBuilder};
 use serde::{Deserialize, Serialize};
 
-use self::csr::{BidirectionalCsr, Csr};
+use self::csr::EdgeCsr;
+pub use self::csr::{CsrWidth, Neighbors};
 use crate::common::{
     DefaultVertexId, EdgeDirection, InternalId, InternalVertexMap, LabelId, VertexId,
 };
-use crate::error::{GCardError, GCardResult};
+use crate::error::{GCardResult, GraphError};
+use crate::pattern::GraphPattern;
 use crate::schema::Schema;
 
 mod csr;
 
+const GRAPH_MAGIC: [u8; 8] = *b"PATHCEGR";
+/// The version of the serialized graph. Graphs serialized before the magic was introduced, which
+/// stored the neighbors as vertex ids, are reported as version 0.
+const GRAPH_VERSION: u32 = 1;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct LabeledVertex {
     pub id: DefaultVertexId,
@@ -35,7 +46,8 @@ impl LabeledVertex {
 pub struct LabeledGraph {
     vertex_map: HashMap<LabelId, InternalVertexMap>,
     vertices: HashMap<LabelId, Vec<DefaultVertexId>>,
-    csr: HashMap<LabelId, BidirectionalCsr>,
+    csr: HashMap<LabelId, EdgeCsr>,
+    endpoints: HashMap<LabelId, (LabelId, LabelId)>,
 }
 
 impl LabeledGraph {
@@ -47,12 +59,14 @@ impl LabeledGraph {
         self.vertex_map.get(&label_id)
     }
 
+    /// Return the vertex labels in ascending order.
     pub fn vertex_labels(&self) -> impl Iterator<Item = LabelId> + Clone + '_ {
-        self.vertex_map.keys().copied()
+        self.vertex_map.keys().copied().sorted_unstable()
     }
 
+    /// Return the edge labels in ascending order.
     pub fn edge_labels(&self) -> impl Iterator<Item = LabelId> + Clone + '_ {
-        self.csr.keys().copied()
+        self.csr.keys().copied().sorted_unstable()
     }
 
     pub fn vertices(&self, label_id: LabelId) -> Option<&[DefaultVertexId]> {
@@ -65,45 +79,120 @@ impl LabeledGraph {
         vertex: LabeledVertex,
         edge_label_id: LabelId,
         direction: EdgeDirection,
-    ) -> Option<&[DefaultVertexId]> {
+    ) -> Option<Neighbors<'_>> {
         match direction {
             EdgeDirection::Out => self.outgoing_neighbors(vertex, edge_label_id),
             EdgeDirection::In => self.incoming_neighbors(vertex, edge_label_id),
         }
     }
 
+    /// Return the outgoing neighbors of `vertex` along edges labeled `edge_label_id`.
+    ///
+    /// The returned neighbors are guaranteed to be sorted in ascending order.
     pub fn outgoing_neighbors(
         &self,
         vertex: LabeledVertex,
         edge_label_id: LabelId,
-    ) -> Option<&[DefaultVertexId]> {
+    ) -> Option<Neighbors<'_>> {
         let LabeledVertex { id, label_id } = vertex;
         let vertex_map = self.vertex_map.get(&label_id)?;
         let vertex_id = vertex_map.get_by_left(&id)?;
         let csr = self.csr.get(&edge_label_id)?;
-        Some(csr.outgoing_neighbors(*vertex_id))
+        let (_, dst_label) = self.endpoints.get(&edge_label_id)?;
+        let dst_vertices = self.vertices.get(dst_label)?;
+        Some(csr.outgoing_neighbors(*vertex_id, dst_vertices))
     }
 
     pub fn outgoing_degree(&self, vertex: LabeledVertex, edge_label_id: LabelId) -> Option<usize> {
         self.outgoing_neighbors(vertex, edge_label_id)
-            .map(<[DefaultVertexId]>::len)
+            .map(|neighbors| neighbors.len())
     }
 
+    /// Return the incoming neighbors of `vertex` along edges labeled `edge_label_id`.
+    ///
+    /// The returned neighbors are guaranteed to be sorted in ascending order.
     pub fn incoming_neighbors(
         &self,
         vertex: LabeledVertex,
         edge_label_id: LabelId,
-    ) -> Option<&[DefaultVertexId]> {
+    ) -> Option<Neighbors<'_>> {
         let LabeledVertex { id, label_id } = vertex;
         let vertex_map = self.vertex_map.get(&label_id)?;
         let vertex_id = vertex_map.get_by_left(&id)?;
         let csr = self.csr.get(&edge_label_id)?;
-        Some(csr.incoming_neighbors(*vertex_id))
+        let (src_label, _) = self.endpoints.get(&edge_label_id)?;
+        let src_vertices = self.vertices.get(src_label)?;
+        Some(csr.incoming_neighbors(*vertex_id, src_vertices))
     }
 
     pub fn incoming_degree(&self, vertex: LabeledVertex, edge_label_id: LabelId) -> Option<usize> {
         self.incoming_neighbors(vertex, edge_label_id)
-            .map(<[DefaultVertexId]>::len)
+            .map(|neighbors| neighbors.len())
+    }
+
+    /// Return the vertices that are neighbors of `vertex` along both `edge_label_a` and
+    /// `edge_label_b` in the given direction.
+    ///
+    /// The two sorted neighbor lists are merge-intersected, so no allocation is needed. The
+    /// intersection is empty if `vertex` or either edge label does not exist.
+    pub fn neighbor_intersection(
+        &self,
+        vertex: LabeledVertex,
+        edge_label_a: LabelId,
+        edge_label_b: LabelId,
+        direction: EdgeDirection,
+    ) -> impl Iterator<Item = DefaultVertexId> + '_ {
+        let neighbors_a = self
+            .neighbors(vertex, edge_label_a, direction)
+            .unwrap_or_default();
+        let neighbors_b = self
+            .neighbors(vertex, edge_label_b, direction)
+            .unwrap_or_default();
+        neighbors_a
+            .iter()
+            .merge_join_by(neighbors_b.iter(), |a, b| a.cmp(b))
+            .filter_map(|either| match either {
+                EitherOrBoth::Both(a, _) => Some(a),
+                _ => None,
+            })
+    }
+
+    /// Check whether there is an edge labeled `edge_label_id` from `src` to the vertex `dst_id`.
+    ///
+    /// The lookup binary-searches the outgoing neighbors of `src`, so it takes `O(log degree)`.
+    pub fn has_edge(
+        &self,
+        src: LabeledVertex,
+        dst_id: DefaultVertexId,
+        edge_label_id: LabelId,
+    ) -> bool {
+        self.outgoing_neighbors(src, edge_label_id)
+            .is_some_and(|neighbors| neighbors.contains(dst_id))
+    }
+
+    /// Return the number of edges labeled `edge_label_id` from `src` to the vertex `dst_id`, which
+    /// is more than one if the edges are parallel.
+    pub fn edge_multiplicity(
+        &self,
+        src: LabeledVertex,
+        dst_id: DefaultVertexId,
+        edge_label_id: LabelId,
+    ) -> usize {
+        self.outgoing_neighbors(src, edge_label_id)
+            .map_or(0, |neighbors| neighbors.multiplicity(dst_id))
+    }
+
+    /// Count the homomorphisms of the connected `pattern` in the graph, in parallel on the current
+    /// rayon pool. Parallel edges are counted with multiplicity, and the empty pattern has exactly
+    /// one match.
+    ///
+    /// Return `None` if the count is not finished within `timeout`.
+    pub fn count_pattern_exact<P: GraphPattern>(
+        &self,
+        pattern: &P,
+        timeout: Option<Duration>,
+    ) -> Option<u128> {
+        crate::counter::count_pattern_exact(self, pattern, timeout)
     }
 }
 
@@ -113,6 +202,9 @@ pub struct LabeledGraphBuilder {
     edges: HashMap<LabelId, Vec<(DefaultVertexId, DefaultVertexId)>>,
     edge_label_to_vertex_label: HashMap<LabelId, (LabelId, LabelId)>,
     num_threads: usize,
+    assume_sorted_edges: bool,
+    auto_add_missing_vertices: bool,
+    csr_width: CsrWidth,
 }
 
 impl LabeledGraphBuilder {
@@ -122,9 +214,40 @@ impl LabeledGraphBuilder {
             edges: HashMap::new(),
             edge_label_to_vertex_label: HashMap::new(),
             num_threads,
+            assume_sorted_edges: false,
+            auto_add_missing_vertices: false,
+            csr_width: CsrWidth::default(),
         }
     }
 
+    /// Hint that the edges of every label are added in ascending `(src, dst)` order, where the
+    /// sources are ordered as their vertices are added, so that sorting the outgoing edges can be
+    /// skipped.
+    ///
+    /// The contract is checked by a debug assertion, which panics with the offending pair of
+    /// edges. In release builds, a violation is reported as an error by [`Self::build`].
+    pub fn assume_sorted_edges(mut self, assume: bool) -> Self {
+        self.assume_sorted_edges = assume;
+        self
+    }
+
+    /// Add the vertices referenced by edges but never added to the vertex labels of the endpoints
+    /// of their edge labels, instead of failing in [`Self::build`].
+    ///
+    /// The missing vertices are appended after the added ones, so they may break the contract of
+    /// [`Self::assume_sorted_edges`].
+    pub fn auto_add_missing_vertices(mut self, enable: bool) -> Self {
+        self.auto_add_missing_vertices = enable;
+        self
+    }
+
+    /// Set the widths of the offsets and the neighbors of the CSRs, which trade the memory of the
+    /// graph for the cost of looking up the neighbors. Defaults to [`CsrWidth::Narrowest`].
+    pub fn csr_width(mut self, width: CsrWidth) -> Self {
+        self.csr_width = width;
+        self
+    }
+
     pub fn add_vertex_label(mut self, label_id: LabelId) -> Self {
         self.vertices.entry(label_id).or_default();
         self
@@ -161,7 +284,43 @@ impl LabeledGraphBuilder {
         self
     }
 
-    pub fn build(self) -> GCardResult<LabeledGraph> {
+    /// Add the endpoints of edges that are missing from the vertices of their labels, and return
+    /// the number of added vertices.
+    fn add_missing_vertices(&mut self) -> usize {
+        let mut known: HashMap<LabelId, HashSet<DefaultVertexId>> = self
+            .vertices
+            .iter()
+            .map(|(label_id, vertices)| (*label_id, vertices.iter().copied().collect()))
+            .collect();
+        let mut num_missing = 0;
+        // Visit the edge labels in order so that the vertices are added deterministically.
+        for label_id in self.edges.keys().copied().sorted() {
+            let Some((src_label, dst_label)) = self.edge_label_to_vertex_label.get(&label_id)
+            else {
+                continue;
+            };
+            for (src, dst) in &self.edges[&label_id] {
+                for (vertex_id, vertex_label) in [(*src, *src_label), (*dst, *dst_label)] {
+                    if known.entry(vertex_label).or_default().insert(vertex_id) {
+                        self.vertices
+                            .entry(vertex_label)
+                            .or_default()
+                            .push(vertex_id);
+                        num_missing += 1;
+                    }
+                }
+            }
+        }
+        num_missing
+    }
+
+    pub fn build(mut self) -> GCardResult<LabeledGraph> {
+        if self.auto_add_missing_vertices {
+            let num_missing = self.add_missing_vertices();
+            if num_missing > 0 {
+                warn!("added {num_missing} vertices referenced by edges but missing from vertices");
+            }
+        }
         let pool = ThreadPoolBuilder::new()
             .num_threads(self.num_threads)
             .build()?;
@@ -183,35 +342,43 @@ impl LabeledGraphBuilder {
                     Ok(vertex_map)
                 })
         })?;
-        let csr = self
+        let csr: HashMap<_, _> = self
             .edges
             .into_iter()
             .map(|(label_id, edges)| -> GCardResult<_> {
                 let (src_label, dst_label) = self
                     .edge_label_to_vertex_label
                     .get(&label_id)
-                    .ok_or_else(|| {
-                        let err = format!("cannot find src and dst label of edge label {label_id}");
-                        GCardError::Graph(err)
-                    })?;
-                let src_vertex_map = vertex_map.get(src_label).ok_or_else(|| {
-                    let err = format!("cannot find vertex map of vertex label {src_label}");
-                    GCardError::Graph(err)
-                })?;
-                let dst_vertex_map = vertex_map.get(dst_label).ok_or_else(|| {
-                    let err = format!("cannot find vertex map of vertex label {dst_label}");
-                    GCardError::Graph(err)
-                })?;
+                    .ok_or(GraphError::EdgeLabelEndpointsNotFound(label_id))?;
+                let src_vertex_map = vertex_map
+                    .get(src_label)
+                    .ok_or(GraphError::VertexMapNotFound(*src_label))?;
+                let dst_vertex_map = vertex_map
+                    .get(dst_label)
+                    .ok_or(GraphError::VertexMapNotFound(*dst_label))?;
                 Ok((
                     label_id,
-                    build_bidirectional_csr(edges, src_vertex_map, dst_vertex_map, &pool)?,
+                    build_bidirectional_csr(
+                        edges,
+                        src_vertex_map,
+                        dst_vertex_map,
+                        self.assume_sorted_edges,
+                        self.csr_width,
+                        &pool,
+                    )?,
                 ))
             })
             .try_collect()?;
+        let endpoints = self
+            .edge_label_to_vertex_label
+            .into_iter()
+            .filter(|(label_id, _)| csr.contains_key(label_id))
+            .collect();
         Ok(LabeledGraph {
             vertex_map,
             vertices,
             csr,
+            endpoints,
         })
     }
 }
@@ -220,15 +387,13 @@ fn build_internal_vertex_map(vertices: Vec<DefaultVertexId>) -> GCardResult<Inte
     let mut internal_vertex_map = InternalVertexMap::new();
     for (internal_id, vertex_id) in vertices.into_iter().enumerate() {
         if !vertex_id.is_valid() {
-            let err = format!("invalid vertex id: {vertex_id}");
-            return Err(GCardError::Graph(err));
+            return Err(GraphError::InvalidVertexId(vertex_id).into());
         }
         if internal_vertex_map
             .insert(vertex_id, internal_id as InternalId)
             .did_overwrite()
         {
-            let err = format!("duplicate vertex id: {vertex_id} found in the csv");
-            return Err(GCardError::Graph(err));
+            return Err(GraphError::DuplicateVertex(vertex_id).into());
         }
     }
     Ok(internal_vertex_map)
@@ -238,20 +403,22 @@ fn build_bidirectional_csr(
     edges: Vec<(DefaultVertexId, DefaultVertexId)>,
     src_vertex_map: &InternalVertexMap,
     dst_vertex_map: &InternalVertexMap,
+    assume_sorted: bool,
+    width: CsrWidth,
     pool: &ThreadPool,
-) -> GCardResult<BidirectionalCsr> {
+) -> GCardResult<EdgeCsr> {
     let (mut fes, mut bes) = pool.scope(|_| {
         edges
             .into_par_iter()
             .map(|(src, dst)| -> GCardResult<_> {
-                let src_internal = src_vertex_map.get_by_left(&src).copied().ok_or_else(|| {
-                    let err = format!("cannot find vertex {src} in the vertex map");
-                    GCardError::Graph(err)
-                })?;
-                let dst_internal = dst_vertex_map.get_by_left(&dst).copied().ok_or_else(|| {
-                    let err = format!("cannot find vertex {dst} in the vertex map");
-                    GCardError::Graph(err)
-                })?;
+                let src_internal = src_vertex_map
+                    .get_by_left(&src)
+                    .copied()
+                    .ok_or(GraphError::VertexNotFound(src))?;
+                let dst_internal = dst_vertex_map
+                    .get_by_left(&dst)
+                    .copied()
+                    .ok_or(GraphError::VertexNotFound(dst))?;
                 Ok(((src_internal, dst), (dst_internal, src)))
             })
             .try_fold(
@@ -272,96 +439,716 @@ fn build_bidirectional_csr(
                 },
             )
     })?;
+    // Sorting by `(src, neighbor)` keeps the neighbors of every vertex in ascending order.
     pool.scope(|_| {
-        fes.as_mut_slice().par_sort_unstable();
+        if assume_sorted {
+            debug_assert!(
+                fes.iter().tuple_windows().all(|(e1, e2)| e1 <= e2),
+                "edges are not sorted: {:?}",
+                fes.iter().tuple_windows().find(|(e1, e2)| e1 > e2).unwrap()
+            );
+        } else {
+            fes.as_mut_slice().par_sort_unstable();
+        }
+        // The backward edges are in the order of destinations and must always be sorted.
         bes.as_mut_slice().par_sort_unstable();
     });
-    let src_max_internal_id = src_vertex_map.right_values().max().copied().unwrap();
-    let dst_max_internal_id = dst_vertex_map.right_values().max().copied().unwrap();
-    let forward = Csr::from_sorted_edges(src_max_internal_id, &fes)?;
-    let backward = Csr::from_sorted_edges(dst_max_internal_id, &bes)?;
-    Ok(BidirectionalCsr::new(forward, backward))
+    EdgeCsr::from_sorted_edges(&fes, &bes, src_vertex_map, dst_vertex_map, width)
+}
+
+/// The delimiter of the fields of CSV records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimiter {
+    /// Fields are separated by a single byte, and every record must have the same number of
+    /// fields.
+    Byte(u8),
+    /// Fields are separated by runs of whitespace, and records may have different numbers of
+    /// fields (e.g., the datasets of gCARE).
+    Whitespace,
+}
+
+impl From<u8> for Delimiter {
+    fn from(delimiter: u8) -> Self {
+        Delimiter::Byte(delimiter)
+    }
+}
+
+/// The format of the CSV files of a label.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsvFormat {
+    delimiter: Delimiter,
+    quote: u8,
+    quoting: bool,
+}
+
+impl Default for CsvFormat {
+    fn default() -> Self {
+        Self {
+            delimiter: Delimiter::Byte(b','),
+            quote: b'"',
+            quoting: true,
+        }
+    }
+}
+
+impl CsvFormat {
+    pub fn delimiter(mut self, delimiter: impl Into<Delimiter>) -> Self {
+        self.delimiter = delimiter.into();
+        self
+    }
+
+    /// Set the quote character. This is ignored for [`Delimiter::Whitespace`].
+    pub fn quote(mut self, quote: u8) -> Self {
+        self.quote = quote;
+        self
+    }
+
+    /// Set whether quoted fields are unquoted. If disabled, quotes are kept as part of the fields.
+    /// This is ignored for [`Delimiter::Whitespace`].
+    pub fn quoting(mut self, enable: bool) -> Self {
+        self.quoting = enable;
+        self
+    }
+}
+
+/// Options of reading the CSV files of a graph, where the format of some labels may be overridden.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CsvOptions {
+    format: CsvFormat,
+    overrides: HashMap<String, CsvFormat>,
+    auto_add_missing_vertices: bool,
+}
+
+impl CsvOptions {
+    pub fn new(format: CsvFormat) -> Self {
+        Self {
+            format,
+            overrides: HashMap::new(),
+            auto_add_missing_vertices: false,
+        }
+    }
+
+    /// See [`LabeledGraphBuilder::auto_add_missing_vertices`].
+    pub fn auto_add_missing_vertices(mut self, enable: bool) -> Self {
+        self.auto_add_missing_vertices = enable;
+        self
+    }
+
+    /// Override the format of the CSV file of the vertex or edge label named `label_name`.
+    pub fn label_format(mut self, label_name: impl Into<String>, format: CsvFormat) -> Self {
+        self.overrides.insert(label_name.into(), format);
+        self
+    }
+
+    fn format_of(&self, label_name: &str) -> CsvFormat {
+        self.overrides
+            .get(label_name)
+            .copied()
+            .unwrap_or(self.format)
+    }
+}
+
+/// The progress of [`LabeledGraph::from_csv_with_progress`], reported when the CSV file of a
+/// label has been read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoadProgress<'a> {
+    /// The name of the vertex or edge label.
+    pub label_name: &'a str,
+    /// The CSV file of the label.
+    pub path: &'a Path,
+    /// The number of rows read from the file.
+    pub rows: usize,
+    /// The number of rows read from all the files so far.
+    pub total_rows: usize,
+}
+
+impl From<CsvFormat> for CsvOptions {
+    fn from(format: CsvFormat) -> Self {
+        Self::new(format)
+    }
 }
 
+impl From<Delimiter> for CsvOptions {
+    fn from(delimiter: Delimiter) -> Self {
+        Self::new(CsvFormat::default().delimiter(delimiter))
+    }
+}
+
+impl From<u8> for CsvOptions {
+    fn from(delimiter: u8) -> Self {
+        Delimiter::Byte(delimiter).into()
+    }
+}
+
+/// The table and the id columns of a label in DuckDB.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TableSource {
+    table: String,
+    columns: Vec<String>,
+}
+
+/// The tables of DuckDB the labels of a graph are read from by [`LabeledGraph::from_duckdb`].
+///
+/// By default, a label is read from the table named after it, taking the first column as the
+/// vertex id, or the first two columns as the src and dst vertex ids, like the CSV files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableMapping {
+    sources: HashMap<String, TableSource>,
+    auto_add_missing_vertices: bool,
+}
+
+impl TableMapping {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// See [`LabeledGraphBuilder::auto_add_missing_vertices`].
+    pub fn auto_add_missing_vertices(mut self, enable: bool) -> Self {
+        self.auto_add_missing_vertices = enable;
+        self
+    }
+
+    /// Read the vertex label named `label_name` from the column `id` of `table`. The table may be
+    /// any table expression, e.g., `read_parquet('person.parquet')`.
+    pub fn vertex_table(
+        mut self,
+        label_name: impl Into<String>,
+        table: impl Into<String>,
+        id: impl Into<String>,
+    ) -> Self {
+        let source = TableSource {
+            table: table.into(),
+            columns: vec![id.into()],
+        };
+        self.sources.insert(label_name.into(), source);
+        self
+    }
+
+    /// Read the edge label named `label_name` from the columns `src` and `dst` of `table`.
+    pub fn edge_table(
+        mut self,
+        label_name: impl Into<String>,
+        table: impl Into<String>,
+        src: impl Into<String>,
+        dst: impl Into<String>,
+    ) -> Self {
+        let source = TableSource {
+            table: table.into(),
+            columns: vec![src.into(), dst.into()],
+        };
+        self.sources.insert(label_name.into(), source);
+        self
+    }
+
+    /// Build the query selecting the `num_columns` id columns of the label named `label_name`.
+    fn query_of(&self, label_name: &str, num_columns: usize) -> String {
+        match self.sources.get(label_name) {
+            Some(source) => {
+                let columns = source
+                    .columns
+                    .iter()
+                    .map(|column| format!("\"{column}\"::ubigint"))
+                    .join(", ");
+                format!("select {columns} from {}", source.table)
+            }
+            None => {
+                let columns = (1..=num_columns)
+                    .map(|i| format!("#{i}::ubigint"))
+                    .join(", ");
+                format!("select {columns} from \"{label_name}\"")
+            }
+        }
+    }
+}
+
+/// The target size in bytes of the chunks parsed in parallel by [`par_parse_records`].
+const PARSE_CHUNK_SIZE: usize = 1 << 20;
+
+/// Parse the field `index` of `record` as a vertex id. `name` is used in errors.
+fn parse_vertex_id(
+    record: &[&str],
+    index: usize,
+    name: &str,
+    line: usize,
+) -> GCardResult<DefaultVertexId> {
+    let field = record.get(index).ok_or_else(|| GraphError::MissingField {
+        name: name.to_string(),
+        line,
+    })?;
+    let vertex_id =
+        field
+            .parse::<DefaultVertexId>()
+            .map_err(|source| GraphError::InvalidField {
+                name: name.to_string(),
+                field: field.to_string(),
+                line,
+                source,
+            })?;
+    Ok(vertex_id)
+}
+
+/// Read the vertices of `label_id` from the CSV file at `path`, parsed in parallel on `pool`, and
+/// return the number of rows.
 fn read_vertices_from_csv<P: AsRef<Path>>(
     builder: LabeledGraphBuilder,
     label_id: LabelId,
     path: P,
-    delimiter: u8,
-) -> GCardResult<LabeledGraphBuilder> {
-    let mut reader = ReaderBuilder::new().delimiter(delimiter).from_path(path)?;
-    reader
-        .records()
-        .enumerate()
-        .try_fold(builder, |builder, (line, record)| {
-            let record = record?;
-            let vertex_id = record
-                .get(0)
-                .ok_or_else(|| {
-                    let err = format!("expect vertex id in line {line}");
-                    GCardError::Graph(err)
-                })?
-                .parse::<DefaultVertexId>()
-                .map_err(|e| GCardError::Graph(e.to_string()))?;
-            Ok(builder.add_vertex(vertex_id, label_id))
+    format: CsvFormat,
+    pool: &ThreadPool,
+) -> GCardResult<(LabeledGraphBuilder, usize)> {
+    let path = path.as_ref();
+    let vertex_ids = pool.install(|| {
+        par_parse_records(path, format, PARSE_CHUNK_SIZE, |line, record| {
+            parse_vertex_id(record, 0, "vertex id", line)
+        })
+    })?;
+    let rows = vertex_ids.len();
+    let builder = vertex_ids.into_iter().fold(builder, |builder, vertex_id| {
+        builder.add_vertex(vertex_id, label_id)
+    });
+    Ok((builder, rows))
+}
+
+/// Return the end of the first line of `bytes` from `start` that ends outside quoted fields, i.e.,
+/// the index after its newline, or `None` if there is no such line. `quoted` is whether `start`
+/// is inside a quoted field.
+///
+/// A quoted field is opened and closed by `quote`, and a quote inside it is escaped by doubling
+/// it, so the quotes before a newline outside quoted fields are even in number. Quotes inside
+/// unquoted fields are not supported.
+fn find_record_end(bytes: &[u8], start: usize, quote: Option<u8>, quoted: bool) -> Option<usize> {
+    let mut quoted = quoted;
+    for (i, b) in bytes[start..].iter().enumerate() {
+        if Some(*b) == quote {
+            quoted = !quoted;
+        } else if *b == b'\n' && !quoted {
+            return Some(start + i + 1);
+        }
+    }
+    None
+}
+
+/// Split `bytes` into chunks of whole lines of about `chunk_size` bytes, where a line ends with a
+/// newline outside quoted fields (see [`find_record_end`]), and return them with the number of
+/// lines before each.
+///
+/// The quotes are counted in parallel on segments of `chunk_size` bytes, which tells whether the
+/// start of every segment is quoted, so each chunk ends at the first line end after a segment.
+fn split_lines(bytes: &[u8], quote: Option<u8>, chunk_size: usize) -> Vec<(&[u8], usize)> {
+    let segments = bytes.chunks(chunk_size.max(1)).collect_vec();
+    let num_quotes = segments
+        .par_iter()
+        .map(|segment| quote.map_or(0, |quote| segment.iter().filter(|b| **b == quote).count()))
+        .collect::<Vec<_>>();
+    let segment_starts = segments
+        .iter()
+        .zip(num_quotes)
+        .scan((0, false), |(start, quoted), (segment, num_quotes)| {
+            let segment_start = (*start, *quoted);
+            *start += segment.len();
+            *quoted ^= num_quotes % 2 == 1;
+            Some(segment_start)
+        })
+        .skip(1)
+        .collect_vec();
+    let mut ends = segment_starts
+        .into_par_iter()
+        .map(|(start, quoted)| find_record_end(bytes, start, quote, quoted).unwrap_or(bytes.len()))
+        .collect::<Vec<_>>();
+    ends.push(bytes.len());
+    ends.dedup();
+    let chunks = ends
+        .into_iter()
+        .scan(0, |start, end| {
+            let chunk = &bytes[*start..end];
+            *start = end;
+            Some(chunk)
+        })
+        .filter(|chunk| !chunk.is_empty())
+        .collect_vec();
+    // Every chunk starts outside quoted fields.
+    let num_lines = chunks
+        .par_iter()
+        .map(|chunk| {
+            let mut num_lines = 0;
+            let mut start = 0;
+            while let Some(end) = find_record_end(chunk, start, quote, false) {
+                num_lines += 1;
+                start = end;
+            }
+            num_lines
+        })
+        .collect::<Vec<_>>();
+    let first_lines = num_lines.into_iter().scan(0, |line, num_lines| {
+        let first_line = *line;
+        *line += num_lines;
+        Some(first_line)
+    });
+    chunks.into_iter().zip(first_lines).collect()
+}
+
+/// Parse the records of the CSV file at `path` with `parse` in parallel, on chunks of lines of
+/// about `chunk_size` bytes of the memory-mapped file. The records are in the order of the file,
+/// and the line given to `parse` is the index of the record unless the file has blank lines or
+/// lone `\r` line terminators.
+///
+/// A quoted field may span several lines, so the chunks are split at the newlines outside quoted
+/// fields, as told by the parity of the quotes before them (see [`find_record_end`]).
+fn par_parse_records<P, T, F>(
+    path: P,
+    format: CsvFormat,
+    chunk_size: usize,
+    parse: F,
+) -> GCardResult<Vec<T>>
+where
+    P: AsRef<Path>,
+    T: Send,
+    F: Fn(usize, &[&str]) -> GCardResult<T> + Sync,
+{
+    let file = File::open(path.as_ref())?;
+    // SAFETY: The file is only read, and must not be modified while the graph is loaded, like the
+    // other input files.
+    let bytes = unsafe { Mmap::map(&file)? };
+    let quote = match format.delimiter {
+        Delimiter::Byte(_) if format.quoting => Some(format.quote),
+        _ => None,
+    };
+    let Some(header_end) = find_record_end(&bytes, 0, quote, false) else {
+        // Only the header, if any
+        return Ok(vec![]);
+    };
+    let (header, body) = bytes.split_at(header_end);
+    let records = split_lines(body, quote, chunk_size)
+        .into_par_iter()
+        .map(|(chunk, first_line)| -> GCardResult<Vec<T>> {
+            let mut records = vec![];
+            match format.delimiter {
+                Delimiter::Byte(delimiter) => {
+                    // Parse the header again to check the number of fields of every record.
+                    let mut reader = ReaderBuilder::new()
+                        .delimiter(delimiter)
+                        .quote(format.quote)
+                        .quoting(format.quoting)
+                        .from_reader(header.chain(chunk));
+                    for (line, record) in reader.records().enumerate() {
+                        let record = record?;
+                        records.push(parse(first_line + line, &record.iter().collect_vec())?);
+                    }
+                }
+                Delimiter::Whitespace => {
+                    for (line, record) in chunk.lines().enumerate() {
+                        let record = record?;
+                        let fields = record.split_whitespace().collect_vec();
+                        if !fields.is_empty() {
+                            records.push(parse(first_line + line, &fields)?);
+                        }
+                    }
+                }
+            }
+            Ok(records)
         })
+        .collect::<GCardResult<Vec<_>>>()?;
+    Ok(records.into_iter().flatten().collect())
 }
 
+/// Read the edges of `label_id` from the CSV file at `path`, parsed in parallel on `pool`, and
+/// return the number of rows.
 fn read_edges_from_csv<P: AsRef<Path>>(
     builder: LabeledGraphBuilder,
     label_id: LabelId,
     path: P,
-    delimiter: u8,
-) -> GCardResult<LabeledGraphBuilder> {
-    let mut reader = ReaderBuilder::new().delimiter(delimiter).from_path(path)?;
-    reader
-        .records()
-        .enumerate()
-        .try_fold(builder, |builder, (line, record)| {
-            let record = record?;
-            let src = record
-                .get(0)
-                .ok_or_else(|| {
-                    let err = format!("expect src vertex id in line {line}");
-                    GCardError::Graph(err)
-                })?
-                .parse::<DefaultVertexId>()
-                .map_err(|e| GCardError::Graph(e.to_string()))?;
-            let dst = record
-                .get(1)
-                .ok_or_else(|| {
-                    let err = format!("expect dst vertex id in line {line}");
-                    GCardError::Graph(err)
-                })?
-                .parse::<DefaultVertexId>()
-                .map_err(|e| GCardError::Graph(e.to_string()))?;
-            Ok(builder.add_edge(src, dst, label_id))
+    format: CsvFormat,
+    pool: &ThreadPool,
+) -> GCardResult<(LabeledGraphBuilder, usize)> {
+    let path = path.as_ref();
+    let edges = pool.install(|| {
+        par_parse_records(path, format, PARSE_CHUNK_SIZE, |line, record| {
+            let src = parse_vertex_id(record, 0, "src vertex id", line)?;
+            let dst = parse_vertex_id(record, 1, "dst vertex id", line)?;
+            Ok((src, dst))
         })
+    })?;
+    let rows = edges.len();
+    let builder = edges.into_iter().fold(builder, |builder, (src, dst)| {
+        builder.add_edge(src, dst, label_id)
+    });
+    Ok((builder, rows))
+}
+
+/// Fold over the rows of `sql`, whose columns are all vertex ids.
+fn fold_rows<B, F>(conn: &Connection, sql: &str, init: B, mut f: F) -> GCardResult<B>
+where
+    F: FnMut(B, &[DefaultVertexId]) -> B,
+{
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query([])?;
+    let mut acc = init;
+    let mut ids = vec![];
+    while let Some(row) = rows.next()? {
+        ids.clear();
+        for i in 0..row.as_ref().column_count() {
+            ids.push(row.get::<_, u64>(i)? as DefaultVertexId);
+        }
+        acc = f(acc, &ids);
+    }
+    Ok(acc)
 }
 
 impl LabeledGraph {
     pub fn export_bincode<P: AsRef<Path>>(&self, path: P) -> GCardResult<()> {
         let file = File::create(path)?;
-        let writer = BufWriter::new(file);
+        let mut writer = BufWriter::new(file);
+        writer.write_all(&GRAPH_MAGIC)?;
+        bincode::serialize_into(&mut writer, &GRAPH_VERSION)?;
         bincode::serialize_into(writer, self)?;
         Ok(())
     }
 
     pub fn import_bincode<P: AsRef<Path>>(path: P) -> GCardResult<Self> {
         let file = File::open(path)?;
-        let reader = BufReader::new(file);
+        let mut reader = BufReader::new(file);
+        let mut magic = [0; GRAPH_MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if magic != GRAPH_MAGIC {
+            return Err(GraphError::UnsupportedFormatVersion(0).into());
+        }
+        let version: u32 = bincode::deserialize_from(&mut reader)?;
+        if version != GRAPH_VERSION {
+            return Err(GraphError::UnsupportedFormatVersion(version).into());
+        }
         let graph = bincode::deserialize_from(reader)?;
         Ok(graph)
     }
 
+    /// Project the graph onto the given vertex and edge labels. The endpoints of edge labels are
+    /// looked up in `schema`, and edge labels whose endpoints are not both retained are dropped.
+    pub fn project(
+        &self,
+        schema: &Schema,
+        vertex_labels: &[LabelId],
+        edge_labels: &[LabelId],
+    ) -> GCardResult<Self> {
+        let mut graph = Self::default();
+        for label_id in vertex_labels {
+            let (Some(vertex_map), Some(vertices)) =
+                (self.vertex_map.get(label_id), self.vertices.get(label_id))
+            else {
+                return Err(GraphError::VertexLabelNotFound(*label_id).into());
+            };
+            graph.vertex_map.insert(*label_id, vertex_map.clone());
+            graph.vertices.insert(*label_id, vertices.clone());
+        }
+        for label_id in edge_labels {
+            let (Some(csr), Some((from, to)), Some(edge)) = (
+                self.csr.get(label_id),
+                self.endpoints.get(label_id),
+                schema.get_edge(*label_id),
+            ) else {
+                return Err(GraphError::EdgeLabelNotFound(*label_id).into());
+            };
+            // The neighbors are internal ids of the endpoints, so both must be retained.
+            let retained = [edge.from, edge.to, *from, *to]
+                .iter()
+                .all(|label_id| graph.vertex_map.contains_key(label_id));
+            if !retained {
+                warn!("edge label {label_id} is dropped since its endpoints are not retained");
+                continue;
+            }
+            graph.csr.insert(*label_id, csr.clone());
+            graph.endpoints.insert(*label_id, (*from, *to));
+        }
+        Ok(graph)
+    }
+
+    /// Check that the graph is consistent with `schema`: every schema label is present, no vertex
+    /// id appears under two vertex labels, and both endpoints of every edge carry the vertex
+    /// labels declared for its edge label. Edge labels are checked in parallel.
+    pub fn validate_against_schema(&self, schema: &Schema) -> GCardResult<()> {
+        for vertex in schema.vertices() {
+            if !self.vertex_map.contains_key(&vertex.label) {
+                return Err(GraphError::VertexLabelNotFound(vertex.label).into());
+            }
+        }
+        for edge in schema.edges() {
+            if !self.csr.contains_key(&edge.label) {
+                return Err(GraphError::EdgeLabelNotFound(edge.label).into());
+            }
+        }
+
+        let mut labeled_ids = self
+            .vertex_map
+            .iter()
+            .flat_map(|(label_id, vertex_map)| vertex_map.left_values().map(|id| (*id, *label_id)))
+            .collect_vec();
+        labeled_ids.par_sort_unstable();
+        if let Some(((id, l1), (_, l2))) = labeled_ids
+            .iter()
+            .tuple_windows()
+            .find(|((id1, _), (id2, _))| id1 == id2)
+        {
+            let err = GraphError::AmbiguousVertexLabel {
+                vertex_id: *id,
+                labels: (*l1, *l2),
+            };
+            return Err(err.into());
+        }
+
+        self.edge_labels()
+            .collect_vec()
+            .into_par_iter()
+            .try_for_each(|label_id| {
+                let edge = schema
+                    .get_edge(label_id)
+                    .ok_or(GraphError::EdgeLabelNotInSchema(label_id))?;
+                let (from, to) = self.endpoints[&label_id];
+                // The neighbors are internal ids of the endpoints the graph was built with, so
+                // they only need checking if the schema declares other endpoints.
+                let check = |vertex_label: LabelId,
+                             neighbor_label: LabelId,
+                             direction: EdgeDirection|
+                 -> GCardResult<()> {
+                    let neighbor_map = &self.vertex_map[&neighbor_label];
+                    for id in self.vertices(vertex_label).unwrap_or_default() {
+                        let vertex = LabeledVertex::new(*id, vertex_label);
+                        let neighbors = self
+                            .neighbors(vertex, label_id, direction)
+                            .unwrap_or_default();
+                        if let Some(neighbor) = neighbors
+                            .iter()
+                            .find(|neighbor| !neighbor_map.contains_left(neighbor))
+                        {
+                            let err = GraphError::EndpointLabelMismatch {
+                                edge_label: label_id,
+                                vertex_id: neighbor,
+                                vertex_label: neighbor_label,
+                            };
+                            return Err(err.into());
+                        }
+                    }
+                    Ok(())
+                };
+                if to != edge.to {
+                    check(from, edge.to, EdgeDirection::Out)?;
+                }
+                if from != edge.from {
+                    check(to, edge.from, EdgeDirection::In)?;
+                }
+                Ok(())
+            })
+    }
+
+    /// Check whether `other` has the same vertex ids and edges for every label, regardless of the
+    /// internal ids and the insertion order of vertices. The endpoints of edge labels are looked
+    /// up in `schema`, and the graphs are not equal if an edge label is not in `schema`.
+    pub fn structurally_eq(&self, other: &Self, schema: &Schema) -> bool {
+        if !self.vertex_labels().eq(other.vertex_labels())
+            || !self.edge_labels().eq(other.edge_labels())
+        {
+            return false;
+        }
+        let same_vertices = self.vertex_map.iter().all(|(label_id, vertex_map)| {
+            let other_vertex_map = &other.vertex_map[label_id];
+            vertex_map.len() == other_vertex_map.len()
+                && vertex_map
+                    .left_values()
+                    .all(|id| other_vertex_map.contains_left(id))
+        });
+        if !same_vertices {
+            return false;
+        }
+        self.edge_labels().all(|label_id| {
+            let Some(edge) = schema.get_edge(label_id) else {
+                return false;
+            };
+            self.get_num_edges(label_id) == other.get_num_edges(label_id)
+                && self
+                    .vertices(edge.from)
+                    .unwrap_or_default()
+                    .iter()
+                    .all(|id| {
+                        let vertex = LabeledVertex::new(*id, edge.from);
+                        self.outgoing_neighbors(vertex, label_id)
+                            == other.outgoing_neighbors(vertex, label_id)
+                    })
+        })
+    }
+
     pub fn from_csv<P: AsRef<Path>>(
         dir: P,
         schema: &Schema,
-        delimiter: u8,
+        options: impl Into<CsvOptions>,
         num_threads: usize,
     ) -> GCardResult<Self> {
+        Self::from_csv_with_progress(dir, schema, options, num_threads, |_| ())
+    }
+
+    /// Read the graph like [`Self::from_csv`], calling `progress` whenever the CSV file of a label
+    /// has been read, e.g., to report the progress of loading a large graph.
+    pub fn from_csv_with_progress<P, F>(
+        dir: P,
+        schema: &Schema,
+        options: impl Into<CsvOptions>,
+        num_threads: usize,
+        mut progress: F,
+    ) -> GCardResult<Self>
+    where
+        P: AsRef<Path>,
+        F: FnMut(LoadProgress),
+    {
         let dir = dir.as_ref();
-        let builder = LabeledGraphBuilder::new(num_threads);
+        let mut total_rows = 0;
+        let mut report = |label_name: &str, path: &Path, rows: usize| {
+            total_rows += rows;
+            progress(LoadProgress {
+                label_name,
+                path,
+                rows,
+                total_rows,
+            });
+        };
+        let options = options.into();
+        let builder = LabeledGraphBuilder::new(num_threads)
+            .auto_add_missing_vertices(options.auto_add_missing_vertices);
+        let builder = schema.vertices().iter().fold(builder, |builder, vertex| {
+            builder.add_vertex_label(vertex.label)
+        });
+        let mut builder = schema.edges().iter().fold(builder, |builder, edge| {
+            builder.add_edge_label(edge.label, edge.from, edge.to)
+        });
+        let pool = ThreadPoolBuilder::new().num_threads(num_threads).build()?;
+        for vertex in schema.vertices() {
+            let label_id = vertex.label;
+            let label_name = schema.get_vertex_label_name(label_id).unwrap();
+            let path = dir.join(format!("{label_name}.csv"));
+            debug!("read vertices from {}", path.display());
+            let format = options.format_of(label_name);
+            let rows;
+            (builder, rows) = read_vertices_from_csv(builder, label_id, &path, format, &pool)?;
+            report(label_name, &path, rows);
+        }
+        for edge in schema.edges() {
+            let label_id = edge.label;
+            let label_name = schema.get_edge_label_name(label_id).unwrap();
+            let path = dir.join(format!("{label_name}.csv"));
+            debug!("read edges from {}", path.display());
+            let format = options.format_of(label_name);
+            let rows;
+            (builder, rows) = read_edges_from_csv(builder, label_id, &path, format, &pool)?;
+            report(label_name, &path, rows);
+        }
+        builder.build()
+    }
+
+    /// Read the graph from the tables of DuckDB given by `mapping`, without a round-trip through
+    /// CSV files.
+    pub fn from_duckdb(
+        conn: &Connection,
+        schema: &Schema,
+        mapping: &TableMapping,
+        num_threads: usize,
+    ) -> GCardResult<Self> {
+        let builder = LabeledGraphBuilder::new(num_threads)
+            .auto_add_missing_vertices(mapping.auto_add_missing_vertices);
         let builder = schema.vertices().iter().fold(builder, |builder, vertex| {
             builder.add_vertex_label(vertex.label)
         });
@@ -374,14 +1161,18 @@ impl LabeledGraph {
             .try_fold(builder, |builder, vertex| {
                 let label_id = vertex.label;
                 let label_name = schema.get_vertex_label_name(label_id).unwrap();
-                let path = dir.join(format!("{label_name}.csv"));
-                read_vertices_from_csv(builder, label_id, path, delimiter)
+                let sql = mapping.query_of(label_name, 1);
+                fold_rows(conn, &sql, builder, |builder, ids| {
+                    builder.add_vertex(ids[0], label_id)
+                })
             })?;
         let builder = schema.edges().iter().try_fold(builder, |builder, edge| {
             let label_id = edge.label;
             let label_name = schema.get_edge_label_name(label_id).unwrap();
-            let path = dir.join(format!("{label_name}.csv"));
-            read_edges_from_csv(builder, label_id, path, delimiter)
+            let sql = mapping.query_of(label_name, 2);
+            fold_rows(conn, &sql, builder, |builder, ids| {
+                builder.add_edge(ids[0], ids[1], label_id)
+            })
         })?;
         builder.build()
     }
@@ -390,7 +1181,9 @@ impl LabeledGraph {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_utils::{build_ldbc_graph, build_ldbc_schema};
+    use crate::common::INVALID_VERTEX_ID;
+    use crate::error::GCardError;
+    use crate::test_utils::{build_ldbc_graph, build_ldbc_schema, TempDir};
 
     #[test]
     fn test_build_graph() {
@@ -426,4 +1219,670 @@ mod tests {
         assert_eq!(in_deg_sum, 44742);
         assert_eq!(out_deg_sum, 44742);
     }
+
+    #[test]
+    fn test_project() {
+        let schema = build_ldbc_schema();
+        let graph = build_ldbc_graph();
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let comment = schema.get_vertex_label_id("Comment").unwrap();
+        let edge_labels = [
+            schema.get_edge_label_id("Person_knows_Person").unwrap(),
+            schema
+                .get_edge_label_id("Comment_hasCreator_Person")
+                .unwrap(),
+            schema.get_edge_label_id("Person_likes_Comment").unwrap(),
+            schema.get_edge_label_id("Post_hasCreator_Person").unwrap(),
+        ];
+        let projected = graph
+            .project(&schema, &[person, comment], &edge_labels)
+            .unwrap();
+        assert_eq!(
+            projected.vertex_labels().sorted().collect_vec(),
+            vec![comment, person]
+        );
+        assert_eq!(
+            projected.edge_labels().sorted().collect_vec(),
+            edge_labels[..3].iter().copied().sorted().collect_vec()
+        );
+        assert_eq!(projected.vertices(person), graph.vertices(person));
+
+        let mut in_deg_sum = 0;
+        let mut out_deg_sum = 0;
+        let retained = |label_id: &LabelId| projected.get_num_edges(*label_id).is_some();
+        for vertex_label in projected.vertex_labels() {
+            for v in projected.vertices(vertex_label).unwrap() {
+                let vertex = LabeledVertex::new(*v, vertex_label);
+                for e in schema.outgoing_edges(vertex_label).unwrap() {
+                    if retained(&e.label) {
+                        out_deg_sum += projected.outgoing_degree(vertex, e.label).unwrap();
+                    }
+                }
+                for e in schema.incoming_edges(vertex_label).unwrap() {
+                    if retained(&e.label) {
+                        in_deg_sum += projected.incoming_degree(vertex, e.label).unwrap();
+                    }
+                }
+            }
+        }
+        let num_edges: usize = projected
+            .edge_labels()
+            .map(|label_id| projected.get_num_edges(label_id).unwrap())
+            .sum();
+        assert_eq!(in_deg_sum, num_edges);
+        assert_eq!(out_deg_sum, num_edges);
+
+        assert!(graph.project(&schema, &[LabelId::MAX], &[]).is_err());
+        assert!(graph.project(&schema, &[person], &[LabelId::MAX]).is_err());
+    }
+
+    #[test]
+    fn test_has_edge() {
+        let schema = build_ldbc_schema();
+        let graph = build_ldbc_graph();
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let knows = schema.get_edge_label_id("Person_knows_Person").unwrap();
+        let has_creator = schema
+            .get_edge_label_id("Comment_hasCreator_Person")
+            .unwrap();
+
+        let src = LabeledVertex::new(4225, person);
+        assert!(graph.has_edge(src, 4238, knows));
+        assert!(graph.has_edge(src, 4251, knows));
+        assert!(graph.has_edge(src, 4257, knows));
+        assert!(!graph.has_edge(src, 4226, knows));
+        assert!(!graph.has_edge(LabeledVertex::new(4238, person), 4225, knows));
+        assert!(!graph.has_edge(src, 4238, has_creator));
+        assert!(!graph.has_edge(LabeledVertex::new(usize::MAX, person), 4238, knows));
+    }
+
+    #[test]
+    fn test_sorted_neighbors() {
+        let schema = build_ldbc_schema();
+        let graph = build_ldbc_graph();
+        for edge in schema.edges() {
+            for v in graph.vertices(edge.from).unwrap() {
+                let neighbors = graph
+                    .outgoing_neighbors(LabeledVertex::new(*v, edge.from), edge.label)
+                    .unwrap();
+                assert!(neighbors.iter().is_sorted());
+            }
+            for v in graph.vertices(edge.to).unwrap() {
+                let neighbors = graph
+                    .incoming_neighbors(LabeledVertex::new(*v, edge.to), edge.label)
+                    .unwrap();
+                assert!(neighbors.iter().is_sorted());
+            }
+        }
+    }
+
+    #[test]
+    fn test_csr_width() {
+        let schema = build_ldbc_schema();
+        let graph = build_ldbc_graph();
+        let mut builder = LabeledGraphBuilder::new(2);
+        for label_id in graph.vertex_labels() {
+            for v in graph.vertices(label_id).unwrap() {
+                builder = builder.add_vertex(*v, label_id);
+            }
+        }
+        for edge in schema.edges() {
+            builder = builder.add_edge_label(edge.label, edge.from, edge.to);
+            for v in graph.vertices(edge.from).unwrap() {
+                let src = LabeledVertex::new(*v, edge.from);
+                for dst in graph.outgoing_neighbors(src, edge.label).unwrap().iter() {
+                    builder = builder.add_edge(*v, dst, edge.label);
+                }
+            }
+        }
+        for width in [CsrWidth::Narrowest, CsrWidth::Wide, CsrWidth::VertexIds] {
+            let rebuilt = builder.clone().csr_width(width).build().unwrap();
+            for edge in schema.edges() {
+                for (label_id, direction) in [
+                    (edge.from, EdgeDirection::Out),
+                    (edge.to, EdgeDirection::In),
+                ] {
+                    for v in graph.vertices(label_id).unwrap() {
+                        let v = LabeledVertex::new(*v, label_id);
+                        let neighbors = rebuilt.neighbors(v, edge.label, direction).unwrap();
+                        assert_eq!(
+                            neighbors,
+                            graph.neighbors(v, edge.label, direction).unwrap()
+                        );
+                        assert_eq!(neighbors.as_slice().is_some(), width == CsrWidth::VertexIds);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_neighbor_intersection() {
+        let graph = LabeledGraphBuilder::new(1)
+            .add_vertex_label(0)
+            .add_vertex_label(1)
+            .add_edge_label(0, 0, 1)
+            .add_edge_label(1, 0, 1)
+            .add_vertex(0, 0)
+            .add_vertex(1, 0)
+            .add_vertex(10, 1)
+            .add_vertex(11, 1)
+            .add_vertex(12, 1)
+            .add_vertex(13, 1)
+            .add_edge(0, 13, 0)
+            .add_edge(0, 10, 0)
+            .add_edge(0, 12, 0)
+            .add_edge(0, 12, 1)
+            .add_edge(0, 11, 1)
+            .add_edge(0, 13, 1)
+            .add_edge(1, 10, 0)
+            .add_edge(1, 11, 1)
+            .build()
+            .unwrap();
+
+        let intersection = graph
+            .neighbor_intersection(LabeledVertex::new(0, 0), 0, 1, EdgeDirection::Out)
+            .collect_vec();
+        assert_eq!(intersection, vec![12, 13]);
+        let intersection = graph
+            .neighbor_intersection(LabeledVertex::new(1, 0), 0, 1, EdgeDirection::Out)
+            .collect_vec();
+        assert!(intersection.is_empty());
+        let intersection = graph
+            .neighbor_intersection(LabeledVertex::new(12, 1), 0, 1, EdgeDirection::In)
+            .collect_vec();
+        assert_eq!(intersection, vec![0]);
+        let intersection = graph
+            .neighbor_intersection(LabeledVertex::new(0, 0), 0, 2, EdgeDirection::Out)
+            .collect_vec();
+        assert!(intersection.is_empty());
+    }
+
+    #[test]
+    fn test_assume_sorted_edges() {
+        let build = |edges: &[(DefaultVertexId, DefaultVertexId)]| {
+            edges
+                .iter()
+                .fold(
+                    LabeledGraphBuilder::new(1)
+                        .assume_sorted_edges(true)
+                        .add_vertex_label(0)
+                        .add_edge_label(0, 0, 0)
+                        .add_vertex(2, 0)
+                        .add_vertex(0, 0)
+                        .add_vertex(1, 0),
+                    |builder, (src, dst)| builder.add_edge(*src, *dst, 0),
+                )
+                .build()
+        };
+        // Sources are ordered by the insertion order of vertices.
+        let graph = build(&[(2, 0), (2, 1), (0, 1), (1, 0), (1, 2)]).unwrap();
+        assert_eq!(
+            graph
+                .outgoing_neighbors(LabeledVertex::new(2, 0), 0)
+                .map(|neighbors| neighbors.to_vec()),
+            Some(vec![0, 1])
+        );
+        assert_eq!(
+            graph
+                .incoming_neighbors(LabeledVertex::new(1, 0), 0)
+                .map(|neighbors| neighbors.to_vec()),
+            Some(vec![0, 2])
+        );
+
+        let result = std::panic::catch_unwind(|| build(&[(2, 1), (2, 0), (0, 1)]));
+        assert!(result.is_err() || result.unwrap().is_err());
+    }
+
+    #[test]
+    fn test_from_csv_whitespace() {
+        use crate::common::EdgeCardinality;
+        use crate::schema::SchemaUnchecked;
+
+        let schema: Schema = SchemaUnchecked::default()
+            .add_vertex_label("Person".into(), 0)
+            .add_edge_label("knows".into(), 0)
+            .add_vertex((0, false))
+            .add_edge((0, 0, 0, EdgeCardinality::ManyToMany))
+            .try_into()
+            .unwrap();
+        let dir = TempDir::new("whitespace");
+        std::fs::write(dir.join("Person.csv"), "id\n0\n1 extra\n\n  2\n").unwrap();
+        std::fs::write(dir.join("knows.csv"), "src dst\n0   1\n1\t2 3\n").unwrap();
+        let graph = LabeledGraph::from_csv(&dir, &schema, Delimiter::Whitespace, 1).unwrap();
+        assert_eq!(graph.vertices(0).unwrap().len(), 3);
+        assert_eq!(graph.outgoing_degree(LabeledVertex::new(1, 0), 0), Some(1));
+        assert_eq!(graph.incoming_degree(LabeledVertex::new(2, 0), 0), Some(1));
+
+        std::fs::write(dir.join("knows.csv"), "src dst\n0 1\n2\n").unwrap();
+        let err = LabeledGraph::from_csv(&dir, &schema, Delimiter::Whitespace, 1).unwrap_err();
+        assert!(err.to_string().contains("expect dst vertex id in line 1"));
+        std::fs::write(dir.join("Person.csv"), "id\n0\n1\nx\n").unwrap();
+        let err = LabeledGraph::from_csv(&dir, &schema, Delimiter::Whitespace, 2).unwrap_err();
+        assert!(err.to_string().contains("\"x\" in line 2"), "{err}");
+    }
+
+    /// Fold over the records of a CSV file with a header serially, as [`par_parse_records`] does in
+    /// parallel. `f` is given the index of each record and
+    /// its fields.
+    fn fold_records<P, B, F>(path: P, format: CsvFormat, init: B, mut f: F) -> GCardResult<B>
+    where
+        P: AsRef<Path>,
+        F: FnMut(B, usize, &[&str]) -> GCardResult<B>,
+    {
+        match format.delimiter {
+            Delimiter::Byte(delimiter) => {
+                let mut reader = ReaderBuilder::new()
+                    .delimiter(delimiter)
+                    .quote(format.quote)
+                    .quoting(format.quoting)
+                    .from_path(path)?;
+                reader
+                    .records()
+                    .enumerate()
+                    .try_fold(init, |acc, (line, record)| {
+                        let record = record?;
+                        f(acc, line, &record.iter().collect_vec())
+                    })
+            }
+            Delimiter::Whitespace => {
+                let reader = BufReader::new(File::open(path)?);
+                reader
+                    .lines()
+                    // Skip the header
+                    .skip(1)
+                    .enumerate()
+                    .try_fold(init, |acc, (line, record)| {
+                        let record = record?;
+                        let fields = record.split_whitespace().collect_vec();
+                        if fields.is_empty() {
+                            return Ok(acc);
+                        }
+                        f(acc, line, &fields)
+                    })
+            }
+        }
+    }
+
+    #[test]
+    fn test_par_parse_records() {
+        let dir = TempDir::new("par_parse");
+        let path = dir.join("knows.csv");
+        let parse = |line: usize, record: &[&str]| -> GCardResult<_> {
+            let src = parse_vertex_id(record, 0, "src vertex id", line)?;
+            let dst = parse_vertex_id(record, 1, "dst vertex id", line)?;
+            Ok((line, src, dst))
+        };
+        let sequential = |format| {
+            fold_records(&path, format, vec![], |mut records, line, record| {
+                records.push(parse(line, record)?);
+                Ok(records)
+            })
+            .unwrap()
+        };
+
+        let content = (0..1000).fold("src|dst\n".to_string(), |content, i| {
+            content + &format!("{}|{}\n", i * 7 % 13, i)
+        });
+        std::fs::write(&path, &content).unwrap();
+        let format = CsvFormat::default().delimiter(b'|');
+        let expected = sequential(format);
+        assert_eq!(expected.len(), 1000);
+        for chunk_size in [1, 64, 1 << 20] {
+            let records = par_parse_records(&path, format, chunk_size, parse).unwrap();
+            assert_eq!(records, expected);
+        }
+
+        // Without the trailing newline, and with the lines of gCARE
+        let content = content.trim_end().replace('|', " \t");
+        std::fs::write(&path, content).unwrap();
+        let format = CsvFormat::default().delimiter(Delimiter::Whitespace);
+        let records = par_parse_records(&path, format, 64, parse).unwrap();
+        assert_eq!(records, expected);
+
+        // Quoted fields with quotes, delimiters and newlines, in the header too
+        let content = (0..300).fold("src,dst,\"no\nte\"\n".to_string(), |content, i| {
+            let note = match i % 3 {
+                0 => "plain".to_string(),
+                1 => format!("\"a \"\"{i}\"\",\nb\n\""),
+                _ => "\"\"".to_string(),
+            };
+            content + &format!("\"{}\",{},{}\n", i * 7 % 13, i, note)
+        });
+        std::fs::write(&path, &content).unwrap();
+        let expected = sequential(CsvFormat::default());
+        assert_eq!(expected.len(), 300);
+        assert_eq!(expected[299], (299, 299 * 7 % 13, 299));
+        for chunk_size in [1, 7, 64, 1 << 20] {
+            let records = par_parse_records(&path, CsvFormat::default(), chunk_size, parse);
+            assert_eq!(records.unwrap(), expected);
+        }
+
+        for content in ["", "src,dst", "src,dst\n"] {
+            std::fs::write(&path, content).unwrap();
+            let records = par_parse_records(&path, CsvFormat::default(), 4, parse).unwrap();
+            assert!(records.is_empty());
+        }
+
+        // Errors in later chunks report the line.
+        std::fs::write(&path, "src,dst\n0,1\n1,2\n2,x\n").unwrap();
+        let err = par_parse_records(&path, CsvFormat::default(), 4, parse).unwrap_err();
+        assert!(err.to_string().contains("\"x\" in line 2"), "{err}");
+        std::fs::write(&path, "src,dst\n0,1\n1,2,3\n").unwrap();
+        assert!(par_parse_records(&path, CsvFormat::default(), 4, parse).is_err());
+    }
+
+    #[test]
+    fn test_from_duckdb() {
+        use crate::common::EdgeCardinality;
+        use crate::schema::SchemaUnchecked;
+
+        let schema: Schema = SchemaUnchecked::default()
+            .add_vertex_label("Person".into(), 0)
+            .add_edge_label("knows".into(), 0)
+            .add_edge_label("follows".into(), 1)
+            .add_vertex((0, false))
+            .add_edge((0, 0, 0, EdgeCardinality::ManyToMany))
+            .add_edge((0, 0, 1, EdgeCardinality::ManyToMany))
+            .try_into()
+            .unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            r#"
+CREATE TABLE "Person" (id BIGINT, name VARCHAR);
+INSERT INTO "Person" VALUES (0, 'a'), (1, 'b'), (2, 'c');
+CREATE TABLE "knows" (src INTEGER, dst INTEGER);
+INSERT INTO "knows" VALUES (0, 1), (1, 2);
+CREATE TABLE edges (kind VARCHAR, a BIGINT, b BIGINT);
+INSERT INTO edges VALUES ('follows', 2, 0), ('likes', 0, 2);
+"#,
+        )
+        .unwrap();
+        let mapping = TableMapping::new().edge_table(
+            "follows",
+            "(select * from edges where kind = 'follows')",
+            "a",
+            "b",
+        );
+        let graph = LabeledGraph::from_duckdb(&conn, &schema, &mapping, 1).unwrap();
+        graph.validate_against_schema(&schema).unwrap();
+        assert_eq!(graph.vertices(0).unwrap().len(), 3);
+        assert_eq!(graph.get_num_edges(0), Some(2));
+        assert_eq!(graph.get_num_edges(1), Some(1));
+        assert!(graph.has_edge(LabeledVertex::new(1, 0), 2, 0));
+        assert!(graph.has_edge(LabeledVertex::new(2, 0), 0, 1));
+
+        // The edge of vertex 3 is rejected unless missing vertices are added.
+        conn.execute_batch(r#"INSERT INTO "knows" VALUES (2, 3)"#)
+            .unwrap();
+        assert!(LabeledGraph::from_duckdb(&conn, &schema, &mapping, 1).is_err());
+        let mapping = mapping.auto_add_missing_vertices(true);
+        let graph = LabeledGraph::from_duckdb(&conn, &schema, &mapping, 1).unwrap();
+        assert_eq!(graph.vertices(0).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_from_csv_options() {
+        use crate::common::EdgeCardinality;
+        use crate::schema::SchemaUnchecked;
+
+        let schema: Schema = SchemaUnchecked::default()
+            .add_vertex_label("Person".into(), 0)
+            .add_edge_label("knows".into(), 0)
+            .add_edge_label("follows".into(), 1)
+            .add_vertex((0, false))
+            .add_edge((0, 0, 0, EdgeCardinality::ManyToMany))
+            .add_edge((0, 0, 1, EdgeCardinality::ManyToMany))
+            .try_into()
+            .unwrap();
+        let dir = TempDir::new("csv_options");
+        std::fs::write(dir.join("Person.csv"), "id|name\n0|'a|b'\n1|c\n2|d\n").unwrap();
+        std::fs::write(dir.join("knows.csv"), "src|dst\n'0'|'1'\n").unwrap();
+        std::fs::write(dir.join("follows.csv"), "src;dst\n1;2\n2;0\n").unwrap();
+        let format = CsvFormat::default().delimiter(b'|').quote(b'\'');
+        let options = CsvOptions::new(format).label_format("follows", format.delimiter(b';'));
+        let graph = LabeledGraph::from_csv(&dir, &schema, options.clone(), 1).unwrap();
+        assert_eq!(graph.vertices(0).unwrap().len(), 3);
+        assert_eq!(graph.outgoing_degree(LabeledVertex::new(0, 0), 0), Some(1));
+        assert_eq!(graph.outgoing_degree(LabeledVertex::new(2, 0), 1), Some(1));
+
+        // Quotes are kept as part of the ids if quoting is disabled.
+        let options = options.label_format("knows", format.quoting(false));
+        let err = LabeledGraph::from_csv(&dir, &schema, options, 1).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("invalid src vertex id \"'0'\" in line 0"));
+    }
+
+    #[test]
+    fn test_from_csv_with_progress() {
+        use crate::common::EdgeCardinality;
+        use crate::schema::SchemaUnchecked;
+
+        let schema: Schema = SchemaUnchecked::default()
+            .add_vertex_label("Person".into(), 0)
+            .add_edge_label("knows".into(), 0)
+            .add_vertex((0, false))
+            .add_edge((0, 0, 0, EdgeCardinality::ManyToMany))
+            .try_into()
+            .unwrap();
+        let dir = TempDir::new("csv_progress");
+        std::fs::write(dir.join("Person.csv"), "id\n0\n1\n2\n").unwrap();
+        std::fs::write(dir.join("knows.csv"), "src,dst\n0,1\n1,2\n").unwrap();
+        let mut reports = vec![];
+        let graph = LabeledGraph::from_csv_with_progress(&dir, &schema, b',', 1, |progress| {
+            reports.push((
+                progress.label_name.to_string(),
+                progress.path.to_path_buf(),
+                progress.rows,
+                progress.total_rows,
+            ))
+        })
+        .unwrap();
+        assert_eq!(graph.get_num_edges(0), Some(2));
+        assert_eq!(
+            reports,
+            [
+                ("Person".to_string(), dir.join("Person.csv"), 3, 3),
+                ("knows".to_string(), dir.join("knows.csv"), 2, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_auto_add_missing_vertices() {
+        let builder = LabeledGraphBuilder::new(1)
+            .add_vertex_label(0)
+            .add_vertex_label(1)
+            .add_edge_label(0, 0, 1)
+            .add_vertex(0, 0)
+            .add_vertex(10, 1)
+            .add_edge(0, 10, 0)
+            .add_edge(1, 10, 0)
+            .add_edge(1, 11, 0);
+        let err = builder.clone().build().unwrap_err();
+        assert!(err.to_string().contains("cannot find vertex 1"), "{err}");
+
+        let graph = builder.auto_add_missing_vertices(true).build().unwrap();
+        assert_eq!(graph.vertices(0), Some(&[0, 1][..]));
+        assert_eq!(graph.vertices(1), Some(&[10, 11][..]));
+        assert_eq!(
+            graph
+                .outgoing_neighbors(LabeledVertex::new(1, 0), 0)
+                .map(|neighbors| neighbors.to_vec()),
+            Some(vec![10, 11])
+        );
+        assert_eq!(
+            graph
+                .incoming_neighbors(LabeledVertex::new(11, 1), 0)
+                .map(|neighbors| neighbors.to_vec()),
+            Some(vec![1])
+        );
+    }
+
+    #[test]
+    fn test_reject_invalid_vertex_id() {
+        let build = |vertex_id| {
+            LabeledGraphBuilder::new(1)
+                .add_vertex_label(0)
+                .add_vertex(vertex_id, 0)
+                .build()
+        };
+        assert!(build(INVALID_VERTEX_ID - 1).is_ok());
+        let err = build(INVALID_VERTEX_ID).unwrap_err();
+        assert!(err.to_string().contains("invalid vertex id"), "{err}");
+    }
+
+    #[test]
+    fn test_labels_sorted() {
+        let schema = build_ldbc_schema();
+        let graph = build_ldbc_graph();
+        let vertex_labels = graph.vertex_labels().collect_vec();
+        let expected = schema
+            .vertices()
+            .iter()
+            .map(|v| v.label)
+            .sorted()
+            .collect_vec();
+        assert_eq!(vertex_labels, expected);
+        let edge_labels = graph.edge_labels().collect_vec();
+        let expected = schema
+            .edges()
+            .iter()
+            .map(|e| e.label)
+            .sorted()
+            .collect_vec();
+        assert_eq!(edge_labels, expected);
+    }
+
+    #[test]
+    fn test_structurally_eq() {
+        use crate::common::EdgeCardinality;
+        use crate::schema::SchemaUnchecked;
+
+        let schema: Schema = SchemaUnchecked::default()
+            .add_vertex_label("Person".into(), 0)
+            .add_vertex_label("Comment".into(), 1)
+            .add_edge_label("knows".into(), 0)
+            .add_edge_label("hasCreator".into(), 1)
+            .add_vertex((0, false))
+            .add_vertex((1, false))
+            .add_edge((0, 0, 0, EdgeCardinality::ManyToMany))
+            .add_edge((1, 0, 1, EdgeCardinality::ManyToOne))
+            .try_into()
+            .unwrap();
+        let build = |persons: &[DefaultVertexId], knows: &[(DefaultVertexId, DefaultVertexId)]| {
+            let builder = LabeledGraphBuilder::new(1)
+                .add_vertex_label(0)
+                .add_vertex_label(1)
+                .add_edge_label(0, 0, 0)
+                .add_edge_label(1, 1, 0)
+                .add_vertex(10, 1)
+                .add_edge(10, 1, 1);
+            let builder = persons
+                .iter()
+                .fold(builder, |builder, id| builder.add_vertex(*id, 0));
+            knows
+                .iter()
+                .fold(builder, |builder, (src, dst)| {
+                    builder.add_edge(*src, *dst, 0)
+                })
+                .build()
+                .unwrap()
+        };
+        let graph = build(&[1, 2, 3], &[(1, 2), (2, 3), (3, 1)]);
+        // Internal ids follow the insertion order of vertices.
+        let reordered = build(&[3, 1, 2], &[(3, 1), (1, 2), (2, 3)]);
+        assert!(graph.structurally_eq(&reordered, &schema));
+        assert!(reordered.structurally_eq(&graph, &schema));
+
+        let reversed = build(&[1, 2, 3], &[(2, 1), (3, 2), (1, 3)]);
+        assert!(!graph.structurally_eq(&reversed, &schema));
+        let missing_edge = build(&[1, 2, 3], &[(1, 2), (2, 3)]);
+        assert!(!graph.structurally_eq(&missing_edge, &schema));
+        let extra_vertex = build(&[1, 2, 3, 4], &[(1, 2), (2, 3), (3, 1)]);
+        assert!(!graph.structurally_eq(&extra_vertex, &schema));
+        let projected = graph.project(&schema, &[0, 1], &[0]).unwrap();
+        assert!(!graph.structurally_eq(&projected, &schema));
+    }
+
+    #[test]
+    fn test_bincode() {
+        let schema = build_ldbc_schema();
+        let graph = build_ldbc_graph();
+        let dir = TempDir::new("graph_bincode");
+        let path = dir.join("graph.bincode");
+        graph.export_bincode(&path).unwrap();
+        let imported = LabeledGraph::import_bincode(&path).unwrap();
+        assert!(imported.structurally_eq(&graph, &schema));
+
+        // Graphs serialized without the magic are rejected instead of misread.
+        std::fs::write(
+            &path,
+            bincode::serialize(&HashMap::<u8, u8>::new()).unwrap(),
+        )
+        .unwrap();
+        let err = LabeledGraph::import_bincode(&path).unwrap_err();
+        assert!(matches!(
+            err,
+            GCardError::Graph(GraphError::UnsupportedFormatVersion(0))
+        ));
+    }
+
+    #[test]
+    fn test_validate_against_schema() {
+        use crate::common::EdgeCardinality;
+        use crate::schema::SchemaUnchecked;
+
+        let schema: Schema = SchemaUnchecked::default()
+            .add_vertex_label("Person".into(), 0)
+            .add_vertex_label("Comment".into(), 1)
+            .add_edge_label("knows".into(), 0)
+            .add_edge_label("hasCreator".into(), 1)
+            .add_vertex((0, false))
+            .add_vertex((1, false))
+            .add_edge((0, 0, 0, EdgeCardinality::ManyToMany))
+            .add_edge((1, 0, 1, EdgeCardinality::ManyToOne))
+            .try_into()
+            .unwrap();
+        let builder = LabeledGraphBuilder::new(1)
+            .add_vertex_label(0)
+            .add_vertex_label(1)
+            .add_edge_label(1, 1, 0)
+            .add_vertex(0, 0)
+            .add_vertex(1, 0)
+            .add_vertex(10, 1)
+            .add_edge(10, 0, 1);
+        let graph = builder
+            .clone()
+            .add_edge_label(0, 0, 0)
+            .add_edge(0, 1, 0)
+            .build()
+            .unwrap();
+        graph.validate_against_schema(&schema).unwrap();
+
+        // The edge is built as Person -> Comment while the schema declares Person -> Person.
+        let graph = builder
+            .add_edge_label(0, 0, 1)
+            .add_edge(1, 10, 0)
+            .build()
+            .unwrap();
+        let err = graph.validate_against_schema(&schema).unwrap_err();
+        assert!(err.to_string().contains("not labeled 0"), "{err}");
+
+        let graph = LabeledGraphBuilder::new(1)
+            .add_vertex_label(0)
+            .add_vertex_label(1)
+            .add_edge_label(0, 0, 0)
+            .add_edge_label(1, 1, 0)
+            .add_vertex(0, 0)
+            .add_vertex(0, 1)
+            .build()
+            .unwrap();
+        let err = graph.validate_against_schema(&schema).unwrap_err();
+        assert!(err.to_string().contains("appears under both"), "{err}");
+
+        let schema = build_ldbc_schema();
+        build_ldbc_graph().validate_against_schema(&schema).unwrap();
+    }
 }
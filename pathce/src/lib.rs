@@ -6,18 +6,22 @@
     iterator_try_reduce
 )]
 
-mod binning;
+pub mod binning;
 pub mod catalog;
 pub mod catalog_builder;
 pub mod common;
 pub mod counter;
-mod error;
+pub mod error;
 pub mod estimate;
 mod factorization;
 pub mod graph;
 pub mod pattern;
+mod quick;
 pub mod sample;
 pub mod schema;
 mod statistics;
 #[cfg(test)]
 mod test_utils;
+
+pub use factorization::{count_valid_values, DEFAULT_PAR_COUNT_THRESHOLD};
+pub use quick::{quick_estimate, QuickEstimateParams};
@@ -0,0 +1,54 @@
+use std::fmt::{Debug, Display, Write};
+
+use serde::{Deserialize, Serialize};
+
+/// The encoding of a pattern returned by [`super::GraphPattern::encode`], which is equal for
+/// isomorphic patterns. It keys the paths and stars of catalogs, and renders as hex.
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct EncodedPattern(Vec<u8>);
+
+impl EncodedPattern {
+    pub(crate) fn new(code: Vec<u8>) -> Self {
+        Self(code)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl Display for EncodedPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for b in &self.0 {
+            write!(f, "{b:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for EncodedPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EncodedPattern(")?;
+        Display::fmt(self, f)?;
+        f.write_char(')')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encoded_pattern_hex() {
+        let code = EncodedPattern::new(vec![0, 1, 0xab, 0xff]);
+        assert_eq!(code.to_string(), "0001abff");
+        assert_eq!(format!("{code:?}"), "EncodedPattern(0001abff)");
+        assert_eq!(code.as_bytes(), [0, 1, 0xab, 0xff]);
+        assert_eq!(code.into_bytes(), vec![0, 1, 0xab, 0xff]);
+    }
+}
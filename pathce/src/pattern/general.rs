@@ -1,8 +1,13 @@
 use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::sync::OnceLock;
 
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
-use super::{GraphPattern, PatternAdjacency, PatternEdge, PatternVertex};
+use super::{
+    encode_pattern, EncodedPattern, GraphPattern, PatternAdjacency, PatternEdge, PatternVertex,
+};
 use crate::common::TagId;
 use crate::pattern::RawPattern;
 
@@ -20,6 +25,57 @@ pub struct GeneralPattern {
     pub(super) edge_rank_map: HashMap<TagId, TagId>,
     pub(super) rank_vertex_map: HashMap<TagId, TagId>,
     pub(super) rank_edge_map: HashMap<TagId, TagId>,
+    pub(super) encoding: EncodingCache,
+}
+
+/// The encoding of a pattern, computed on first use. Patterns are immutable once built, so the
+/// encoding never goes stale. It is ignored when comparing patterns.
+#[derive(Clone, Default)]
+pub(super) struct EncodingCache(OnceLock<EncodedPattern>);
+
+impl PartialEq for EncodingCache {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for EncodingCache {}
+
+impl Debug for EncodingCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EncodingCache")
+    }
+}
+
+impl Display for GeneralPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.edges.is_empty() {
+            let vertices = self
+                .vertices
+                .iter()
+                .map(|v| format!("({}:{})", v.tag_id(), v.label_id()))
+                .join(", ");
+            return write!(f, "{vertices}");
+        }
+        let edges = self
+            .edges
+            .iter()
+            .map(|e| {
+                let src = self.get_vertex(e.src()).unwrap();
+                let dst = self.get_vertex(e.dst()).unwrap();
+                format!(
+                    "({}:{})-[{}:{}]->({}:{})",
+                    src.tag_id(),
+                    src.label_id(),
+                    e.tag_id(),
+                    e.label_id(),
+                    dst.tag_id(),
+                    dst.label_id()
+                )
+            })
+            .join(", ");
+        write!(f, "{edges}")
+    }
 }
 
 impl GraphPattern for GeneralPattern {
@@ -27,6 +83,10 @@ impl GraphPattern for GeneralPattern {
         &self.vertices
     }
 
+    fn encode(&self) -> EncodedPattern {
+        self.encoding.0.get_or_init(|| encode_pattern(self)).clone()
+    }
+
     fn edges(&self) -> &[PatternEdge] {
         &self.edges
     }
@@ -1,13 +1,16 @@
 mod canonical;
+mod encoded;
 mod general;
 mod path;
 mod raw;
 
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 
-use bytes::BufMut;
+use bytes::{Buf, BufMut};
 pub use canonical::*;
+pub use encoded::*;
 pub use general::*;
 use itertools::Itertools;
 pub use path::*;
@@ -15,18 +18,19 @@ pub use raw::*;
 use serde::{Deserialize, Serialize};
 
 use crate::common::{EdgeDirection, LabelId, TagId};
+use crate::error::{GCardResult, PatternError};
 
 const EDGE_ENCODING_LENGTH: usize = 14;
 
-pub fn encode_vertex(vertex_label_id: LabelId) -> Vec<u8> {
-    Vec::from(vertex_label_id.to_le_bytes())
+pub fn encode_vertex(vertex_label_id: LabelId) -> EncodedPattern {
+    EncodedPattern::new(Vec::from(vertex_label_id.to_le_bytes()))
 }
 
 pub fn encode_edge(
     src_label_id: LabelId,
     dst_label_id: LabelId,
     edge_label_id: LabelId,
-) -> Vec<u8> {
+) -> EncodedPattern {
     let mut code = Vec::with_capacity(EDGE_ENCODING_LENGTH);
     code.put_u32(edge_label_id);
     code.put_u32(src_label_id);
@@ -37,35 +41,84 @@ pub fn encode_edge(
     };
     code.put_u8(src_rank);
     code.put_u8(dst_rank);
-    code
+    EncodedPattern::new(code)
+}
+
+fn encode_pattern<P: GraphPattern>(pattern: &P) -> EncodedPattern {
+    match pattern.edges().len() {
+        0 if pattern.vertices().is_empty() => EncodedPattern::default(),
+        0 if pattern.vertices().len() == 1 => {
+            encode_vertex(pattern.vertices().first().unwrap().label_id)
+        }
+        0 => unreachable!(),
+        1 => {
+            let e = pattern.edges().first().unwrap();
+            let src_label_id = pattern.get_vertex(e.src).unwrap().label_id;
+            let dst_label_id = pattern.get_vertex(e.dst).unwrap().label_id;
+            encode_edge(src_label_id, dst_label_id, e.label_id)
+        }
+        _ => EncodedPattern::new(encode_normal(pattern)),
+    }
 }
 
 fn encode_normal<P: GraphPattern>(pattern: &P) -> Vec<u8> {
-    let mut code = Vec::with_capacity(pattern.edges().len() * EDGE_ENCODING_LENGTH);
-    pattern
-        .edges()
-        .iter()
-        .map(|e| (*e, pattern.get_edge_rank(e.tag_id).unwrap()))
-        .sorted_unstable_by_key(|(_, rank)| *rank)
-        .for_each(|(e, _)| {
-            let edge_label_id = e.label_id;
-            let src_tag_id = e.src;
-            let src_rank = pattern.get_vertex_rank(src_tag_id).unwrap();
-            let src_label_id = pattern.get_vertex(src_tag_id).unwrap().label_id;
-
-            let dst_tag_id = e.dst;
-            let dst_rank = pattern.get_vertex_rank(dst_tag_id).unwrap();
-            let dst_label_id = pattern.get_vertex(dst_tag_id).unwrap().label_id;
-
-            code.put_u32(edge_label_id);
-            code.put_u32(src_label_id);
-            code.put_u32(dst_label_id);
-            code.put_u8(src_rank);
-            code.put_u8(dst_rank);
-        });
+    // Look up the rank and the label of every vertex once
+    let max_tag_id = pattern.max_vertex_tag_id().unwrap();
+    let mut vertex_ranks_and_labels = vec![(0, 0); max_tag_id as usize + 1];
+    for v in pattern.vertices() {
+        let rank = pattern.get_vertex_rank(v.tag_id).unwrap();
+        vertex_ranks_and_labels[v.tag_id as usize] = (rank, v.label_id);
+    }
+    let num_edges = pattern.edges().len();
+    let mut code = Vec::with_capacity(num_edges * EDGE_ENCODING_LENGTH);
+    // The edge ranks are `0..num_edges`
+    for rank in 0..num_edges {
+        let e = pattern.get_edge_from_rank(rank as TagId).unwrap();
+        let (src_rank, src_label_id) = vertex_ranks_and_labels[e.src as usize];
+        let (dst_rank, dst_label_id) = vertex_ranks_and_labels[e.dst as usize];
+        code.put_u32(e.label_id);
+        code.put_u32(src_label_id);
+        code.put_u32(dst_label_id);
+        code.put_u8(src_rank);
+        code.put_u8(dst_rank);
+    }
     code
 }
 
+/// Reconstruct a pattern from the encoding returned by [`GraphPattern::encode`], e.g., to inspect
+/// the entries of a catalog. The tag ids of the vertices and the edges are their ranks in the
+/// encoded pattern, which the decoded pattern is isomorphic to.
+pub fn decode(code: &[u8]) -> GCardResult<GeneralPattern> {
+    let mut raw = RawPattern::new();
+    if code.is_empty() {
+        return raw.to_general();
+    }
+    if code.len() == size_of::<LabelId>() {
+        let label_id = LabelId::from_le_bytes(code.try_into().unwrap());
+        return raw.push_back_vertex((0, label_id)).to_general();
+    }
+    if code.len() % EDGE_ENCODING_LENGTH != 0 {
+        return Err(PatternError::InvalidEncoding(code.len()).into());
+    }
+    let mut vertex_labels = BTreeMap::new();
+    let mut buf = code;
+    for tag_id in 0..code.len() / EDGE_ENCODING_LENGTH {
+        let label_id = buf.get_u32();
+        let src_label_id = buf.get_u32();
+        let dst_label_id = buf.get_u32();
+        let src = buf.get_u8();
+        let dst = buf.get_u8();
+        for (rank, label_id) in [(src, src_label_id), (dst, dst_label_id)] {
+            if *vertex_labels.entry(rank).or_insert(label_id) != label_id {
+                return Err(PatternError::ConflictingLabels(rank).into());
+            }
+        }
+        raw.push_back_edge((tag_id as TagId, src, dst, label_id));
+    }
+    raw.extend_vertices(vertex_labels);
+    raw.to_general()
+}
+
 pub trait GraphPattern: Debug + Clone {
     fn vertices(&self) -> &[PatternVertex];
     fn edges(&self) -> &[PatternEdge];
@@ -89,6 +142,12 @@ pub trait GraphPattern: Debug + Clone {
         )
     }
 
+    /// Return the tag ids of the neighbors of the given vertex, in the order of
+    /// [`Self::adjacencies`].
+    fn neighbor_tag_ids(&self, tag_id: TagId) -> Option<impl Iterator<Item = TagId> + '_> {
+        Some(self.adjacencies(tag_id)?.map(|adj| adj.neighbor_tag_id()))
+    }
+
     fn get_vertex_out_degree(&self, tag_id: TagId) -> Option<usize> {
         Some(self.outgoing_adjacencies(tag_id)?.len())
     }
@@ -133,21 +192,33 @@ pub trait GraphPattern: Debug + Clone {
         self.edges().iter().map(|e| e.tag_id).max()
     }
 
-    fn encode(&self) -> Vec<u8> {
-        match self.edges().len() {
-            0 if self.vertices().is_empty() => vec![],
-            0 if self.vertices().len() == 1 => {
-                encode_vertex(self.vertices().first().unwrap().label_id)
-            }
-            0 => unreachable!(),
-            1 => {
-                let e = self.edges().first().unwrap();
-                let src_label_id = self.get_vertex(e.src).unwrap().label_id;
-                let dst_label_id = self.get_vertex(e.dst).unwrap().label_id;
-                encode_edge(src_label_id, dst_label_id, e.label_id)
-            }
-            _ => encode_normal(self),
+    fn encode(&self) -> EncodedPattern {
+        encode_pattern(self)
+    }
+
+    /// Convert the pattern into a [`RawPattern`], e.g., to edit it.
+    fn to_raw(&self) -> RawPattern {
+        RawPattern::from(self)
+    }
+
+    /// Check whether the edges with the given tag ids and their endpoints form a connected
+    /// subpattern. Return `false` if `edges` is empty or has an edge not in the pattern.
+    fn induced_edges_connected(&self, edges: &[TagId]) -> bool {
+        let Some(edges) = edges
+            .iter()
+            .map(|tag_id| self.get_edge(*tag_id))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return false;
+        };
+        if edges.is_empty() {
+            return false;
         }
+        let mut raw = RawPattern::new();
+        for tag_id in edges.iter().flat_map(|e| [e.src, e.dst]).unique() {
+            raw.push_back_vertex(self.get_vertex(tag_id).unwrap());
+        }
+        raw.extend_edges(edges).is_connected()
     }
 
     fn is_cyclic(&self) -> bool {
@@ -287,6 +358,8 @@ impl PatternAdjacency {
 
 #[cfg(test)]
 mod tests {
+    use itertools::Itertools;
+
     use super::*;
 
     #[test]
@@ -297,7 +370,7 @@ mod tests {
             .push_back_edge((0, 3, 4, 7))
             .to_general()
             .unwrap();
-        let code1 = encode_normal(&p);
+        let code1 = EncodedPattern::new(encode_normal(&p));
         let code2 = encode_edge(123, 124, 7);
         assert_eq!(code1, code2);
 
@@ -307,7 +380,7 @@ mod tests {
             .push_back_edge((0, 3, 4, 7))
             .to_general()
             .unwrap();
-        let code1 = encode_normal(&p);
+        let code1 = EncodedPattern::new(encode_normal(&p));
         let code2 = encode_edge(125, 124, 7);
         assert_eq!(code1, code2);
 
@@ -317,11 +390,46 @@ mod tests {
             .push_back_edge((0, 3, 4, 7))
             .to_general()
             .unwrap();
-        let code1 = encode_normal(&p);
+        let code1 = EncodedPattern::new(encode_normal(&p));
         let code2 = encode_edge(123, 123, 7);
         assert_eq!(code1, code2)
     }
 
+    #[test]
+    fn test_neighbor_tag_ids() {
+        let star = RawPattern::with_vertices_edges(
+            [(0, 1), (1, 1), (2, 2), (3, 2)],
+            [(0, 0, 1, 0), (1, 2, 0, 1), (2, 0, 3, 1)],
+        )
+        .to_general()
+        .unwrap();
+        let neighbors = star.neighbor_tag_ids(0).unwrap().sorted().collect_vec();
+        assert_eq!(neighbors, vec![1, 2, 3]);
+        for leaf in 1..=3 {
+            let neighbors = star.neighbor_tag_ids(leaf).unwrap().collect_vec();
+            assert_eq!(neighbors, vec![0]);
+        }
+        assert!(star.neighbor_tag_ids(4).is_none());
+    }
+
+    #[test]
+    fn test_induced_edges_connected() {
+        // A star centered at 0 with legs 0-1-4, 0-2 and 0-3.
+        let star = RawPattern::with_vertices_edges(
+            [(0, 1), (1, 1), (2, 2), (3, 2), (4, 1)],
+            [(0, 0, 1, 0), (1, 2, 0, 1), (2, 0, 3, 1), (3, 1, 4, 0)],
+        )
+        .to_general()
+        .unwrap();
+        assert!(star.induced_edges_connected(&[0]));
+        assert!(star.induced_edges_connected(&[1, 2]));
+        assert!(star.induced_edges_connected(&[3, 0, 2]));
+        assert!(star.induced_edges_connected(&[0, 1, 2, 3]));
+        assert!(!star.induced_edges_connected(&[3, 1]));
+        assert!(!star.induced_edges_connected(&[]));
+        assert!(!star.induced_edges_connected(&[0, 4]));
+    }
+
     #[test]
     fn test_is_path() {
         let edges: [PatternEdge; 0] = [];
@@ -343,4 +451,83 @@ mod tests {
         .unwrap();
         assert!(!p3.is_path());
     }
+
+    #[test]
+    fn test_to_raw() {
+        let path = RawPattern::with_vertices_edges([(0, 1), (1, 2)], [(0, 0, 1, 0)])
+            .to_path()
+            .unwrap();
+        assert_eq!(path.to_raw().to_path().unwrap(), path);
+
+        let extended = path
+            .to_raw()
+            .push_back_vertex((2, 1))
+            .push_back_edge((1, 2, 1, 0))
+            .to_general()
+            .unwrap();
+        let expected =
+            RawPattern::with_vertices_edges([(0, 1), (1, 2), (2, 1)], [(0, 0, 1, 0), (1, 2, 1, 0)])
+                .to_general()
+                .unwrap();
+        assert_eq!(extended, expected);
+        assert_eq!(extended.to_raw().to_general().unwrap(), extended);
+    }
+
+    #[test]
+    fn test_decode() {
+        assert!(decode(&[]).unwrap().vertices().is_empty());
+        let vertex = decode(encode_vertex(6).as_bytes()).unwrap();
+        assert_eq!(vertex.vertices(), [PatternVertex::new(0, 6)]);
+        for (src_label_id, dst_label_id) in [(1, 6), (6, 1), (6, 6)] {
+            let code = encode_edge(src_label_id, dst_label_id, 14);
+            let edge = decode(code.as_bytes()).unwrap();
+            assert_eq!(edge.encode(), code);
+        }
+
+        let schema = crate::test_utils::build_ldbc_schema();
+        for pattern in schema.generate_patterns(3, 5000) {
+            let code = pattern.encode();
+            assert_eq!(decode(code.as_bytes()).unwrap().encode(), code, "{pattern}");
+        }
+
+        let code = encode_edge(1, 6, 14).into_bytes();
+        let err = decode(&code[1..]).unwrap_err();
+        assert!(err.to_string().contains("13 bytes"), "{err}");
+        // The vertex of rank 0 is labeled 1 by the first edge and 6 by the second one.
+        let mut code = code.clone();
+        code.extend_from_slice(encode_edge(7, 6, 14).as_bytes());
+        let err = decode(&code).unwrap_err();
+        assert!(err.to_string().contains("conflicting labels"), "{err}");
+    }
+
+    #[test]
+    fn test_encode_normal() {
+        // The encoding before ranks were looked up once per call
+        fn encode_reference<P: GraphPattern>(pattern: &P) -> Vec<u8> {
+            let mut code = vec![];
+            for e in pattern
+                .edges()
+                .iter()
+                .sorted_unstable_by_key(|e| pattern.get_edge_rank(e.tag_id).unwrap())
+            {
+                code.put_u32(e.label_id);
+                code.put_u32(pattern.get_vertex(e.src).unwrap().label_id);
+                code.put_u32(pattern.get_vertex(e.dst).unwrap().label_id);
+                code.put_u8(pattern.get_vertex_rank(e.src).unwrap());
+                code.put_u8(pattern.get_vertex_rank(e.dst).unwrap());
+            }
+            code
+        }
+
+        let schema = crate::test_utils::build_ldbc_schema();
+        let patterns = schema.generate_patterns(3, 5000);
+        assert!(patterns.iter().any(|p| p.edges().len() == 3));
+        for pattern in patterns.iter().filter(|p| p.edges().len() > 1) {
+            let expected = encode_reference(pattern);
+            assert_eq!(encode_normal(pattern), expected, "{pattern}");
+            // Once from scratch and once from the cache
+            assert_eq!(pattern.encode().as_bytes(), expected, "{pattern}");
+            assert_eq!(pattern.encode().as_bytes(), expected, "{pattern}");
+        }
+    }
 }
@@ -1,11 +1,13 @@
 use std::fmt::Display;
 
+use bytes::BufMut;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
 use super::general::GeneralPattern;
-use super::{GraphPattern, PatternAdjacency, PatternEdge, PatternVertex};
+use super::{EncodedPattern, GraphPattern, PatternAdjacency, PatternEdge, PatternVertex};
 use crate::common::{EdgeCardinality, EdgeDirection, TagId};
+use crate::error::{GCardResult, PatternError};
 use crate::pattern::RawPattern;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -62,6 +64,10 @@ impl GraphPattern for PathPattern {
         self.pattern.vertices()
     }
 
+    fn encode(&self) -> EncodedPattern {
+        self.pattern.encode()
+    }
+
     fn edges(&self) -> &[PatternEdge] {
         self.pattern.edges()
     }
@@ -164,6 +170,40 @@ impl PathPattern {
         self.edges().len()
     }
 
+    /// Return the path of the first `k` edges.
+    pub fn prefix(&self, k: usize) -> GCardResult<PathPattern> {
+        self.check_bounds(k)?;
+        self.slice(0, k)
+    }
+
+    /// Return the path of the last `k` edges.
+    pub fn suffix(&self, k: usize) -> GCardResult<PathPattern> {
+        self.check_bounds(k)?;
+        self.slice(self.len() - k, self.len())
+    }
+
+    fn check_bounds(&self, k: usize) -> GCardResult<()> {
+        if k > self.len() {
+            let len = self.len();
+            return Err(PatternError::PathOutOfBounds { k, len }.into());
+        }
+        Ok(())
+    }
+
+    /// Return the path of the edges in `start..end`, keeping their cardinalities.
+    fn slice(&self, start: usize, end: usize) -> GCardResult<PathPattern> {
+        let mut raw = RawPattern::new();
+        for v in &self.vertices()[start..=end] {
+            raw.push_back_vertex(*v);
+        }
+        for e in &self.edges()[start..end] {
+            raw.push_back_edge(*e);
+        }
+        let mut path = raw.to_path()?;
+        path.cards.copy_from_slice(&self.cards[start..end]);
+        Ok(path)
+    }
+
     pub fn directions(&self) -> &[EdgeDirection] {
         &self.directions
     }
@@ -181,33 +221,53 @@ impl PathPattern {
     }
 
     pub fn is_symmetric(&self) -> bool {
-        let rev = self.reverse();
-        let vertices = self
-            .vertices()
-            .iter()
-            .zip_eq(rev.vertices())
-            .all(|(v1, v2)| v1.label_id() == v2.label_id());
-        if !vertices {
-            return false;
-        }
-        let edges = self
-            .edges()
-            .iter()
-            .zip_eq(rev.edges())
-            .all(|(e1, e2)| e1.label_id() == e2.label_id());
-        if !edges {
-            return false;
+        self.encode_sequence() == self.encode_reverse()
+    }
+
+    /// Encode the labels of the vertices and the edges and the directions of the edges in order
+    /// from the start. Unlike [`GraphPattern::encode`], the encoding depends on the orientation of
+    /// the path, so a path is symmetric if and only if it encodes the same as its reverse.
+    pub fn encode_sequence(&self) -> Vec<u8> {
+        self.encode_sequence_inner(
+            self.vertices().iter(),
+            self.edges()
+                .iter()
+                .zip_eq(self.directions().iter().copied()),
+        )
+    }
+
+    /// Encode like [`Self::encode_sequence`] from the end, i.e., as the reversed path, without
+    /// building the reversed path.
+    pub fn encode_reverse(&self) -> Vec<u8> {
+        self.encode_sequence_inner(
+            self.vertices().iter().rev(),
+            self.edges()
+                .iter()
+                .rev()
+                .zip_eq(self.directions().iter().rev().map(|d| d.reverse())),
+        )
+    }
+
+    fn encode_sequence_inner<'a>(
+        &self,
+        mut vertices: impl Iterator<Item = &'a PatternVertex>,
+        edges: impl Iterator<Item = (&'a PatternEdge, EdgeDirection)>,
+    ) -> Vec<u8> {
+        let mut code = Vec::with_capacity(4 + self.len() * 9);
+        code.put_u32(vertices.next().unwrap().label_id());
+        for (v, (e, d)) in vertices.zip_eq(edges) {
+            code.put_u32(e.label_id());
+            code.put_u8(d as u8);
+            code.put_u32(v.label_id());
         }
-        self.directions()
-            .iter()
-            .zip_eq(rev.directions())
-            .all(|(d1, d2)| d1 == d2)
+        code
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::GCardError;
     use crate::pattern::raw::RawPattern;
 
     #[test]
@@ -246,6 +306,49 @@ mod tests {
         assert_eq!(center_rank, expected_rank);
     }
 
+    #[test]
+    fn test_prefix_suffix() {
+        let mut p = RawPattern::with_vertices_edges(
+            [(0, 0), (1, 1), (2, 2), (3, 3)],
+            [(0, 0, 1, 0), (1, 2, 1, 1), (2, 2, 3, 2)],
+        )
+        .to_path()
+        .unwrap();
+        p.cards_mut()[1] = EdgeCardinality::OneToMany;
+
+        let prefix = p.prefix(2).unwrap();
+        let expected =
+            RawPattern::with_vertices_edges([(0, 0), (1, 1), (2, 2)], [(0, 0, 1, 0), (1, 2, 1, 1)])
+                .to_path()
+                .unwrap();
+        assert_eq!(prefix.encode(), expected.encode());
+        assert_eq!(
+            prefix.directions(),
+            &[EdgeDirection::Out, EdgeDirection::In]
+        );
+        assert_eq!(
+            prefix.cards(),
+            &[EdgeCardinality::default(), EdgeCardinality::OneToMany]
+        );
+
+        let suffix = p.suffix(1).unwrap();
+        assert_eq!(suffix.start().tag_id(), 2);
+        assert_eq!(suffix.end().tag_id(), 3);
+        assert_eq!(suffix.directions(), &[EdgeDirection::Out]);
+
+        let empty = p.prefix(0).unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(empty.start().tag_id(), 0);
+        assert_eq!(p.suffix(3).unwrap(), p);
+        assert!(matches!(
+            p.prefix(4),
+            Err(GCardError::Pattern(PatternError::PathOutOfBounds {
+                k: 4,
+                len: 3
+            }))
+        ));
+    }
+
     #[test]
     fn test_serde_json() {
         let p = RawPattern::new()
@@ -277,4 +380,35 @@ mod tests {
             .unwrap();
         assert_eq!(p1.encode(), p2.encode());
     }
+
+    #[test]
+    fn test_encode_reverse() {
+        // The symmetry test before the sequence encodings
+        fn is_symmetric_reference(path: &PathPattern) -> bool {
+            let rev = path.reverse();
+            path.vertices()
+                .iter()
+                .zip_eq(rev.vertices())
+                .all(|(v1, v2)| v1.label_id() == v2.label_id())
+                && path
+                    .edges()
+                    .iter()
+                    .zip_eq(rev.edges())
+                    .all(|(e1, e2)| e1.label_id() == e2.label_id())
+                && path.directions() == rev.directions()
+        }
+
+        let schema = crate::test_utils::build_ldbc_schema();
+        let mut num_symmetric = 0;
+        for length in 0..=3 {
+            for path in schema.generate_paths(length) {
+                let rev = path.reverse();
+                assert_eq!(path.encode_reverse(), rev.encode_sequence(), "{path}");
+                assert_eq!(rev.encode_reverse(), path.encode_sequence(), "{path}");
+                assert_eq!(path.is_symmetric(), is_symmetric_reference(&path), "{path}");
+                num_symmetric += path.is_symmetric() as usize;
+            }
+        }
+        assert!(num_symmetric > 0);
+    }
 }
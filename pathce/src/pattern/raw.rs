@@ -7,7 +7,7 @@ use super::general::GeneralPattern;
 use super::path::PathPattern;
 use super::{canonicalize, GraphPattern, PatternAdjacency, PatternEdge, PatternVertex};
 use crate::common::{EdgeCardinality, EdgeDirection, TagId};
-use crate::error::{GCardError, GCardResult};
+use crate::error::{GCardError, GCardResult, PatternError};
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RawPattern {
@@ -100,6 +100,51 @@ impl RawPattern {
         self
     }
 
+    /// Push `vertices` to the back in order, like repeated [`Self::push_back_vertex`].
+    pub fn extend_vertices<V, I>(&mut self, vertices: I) -> &mut Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<PatternVertex>,
+    {
+        self.vertices.extend(vertices.into_iter().map(Into::into));
+        self
+    }
+
+    /// Push `edges` to the back in order, like repeated [`Self::push_back_edge`].
+    pub fn extend_edges<E, I>(&mut self, edges: I) -> &mut Self
+    where
+        I: IntoIterator<Item = E>,
+        E: Into<PatternEdge>,
+    {
+        self.edges.extend(edges.into_iter().map(Into::into));
+        self
+    }
+
+    /// Check whether the pattern is connected without building it. Edges referencing missing
+    /// vertices make the pattern disconnected.
+    pub fn is_connected(&self) -> bool {
+        let mut adjacencies: HashMap<_, Vec<_>> =
+            self.vertices.iter().map(|v| (v.tag_id, vec![])).collect();
+        for e in &self.edges {
+            if !adjacencies.contains_key(&e.src) || !adjacencies.contains_key(&e.dst) {
+                return false;
+            }
+            adjacencies.get_mut(&e.src).unwrap().push(e.dst);
+            adjacencies.get_mut(&e.dst).unwrap().push(e.src);
+        }
+        let Some(start) = self.vertices.front() else {
+            return true;
+        };
+        let mut visited = HashSet::new();
+        let mut stack = vec![start.tag_id];
+        while let Some(tag_id) = stack.pop() {
+            if visited.insert(tag_id) {
+                stack.extend(&adjacencies[&tag_id]);
+            }
+        }
+        visited.len() == adjacencies.len()
+    }
+
     pub fn to_general(&self) -> GCardResult<GeneralPattern> {
         let tag_vertex_map: HashMap<_, _> = self
             .vertices
@@ -108,7 +153,7 @@ impl RawPattern {
             .map(|(i, v)| (v.tag_id, i))
             .collect();
         if tag_vertex_map.len() != self.vertices.len() {
-            return Err(GCardError::Pattern("duplicate vertex tag id".into()));
+            return Err(PatternError::DuplicateVertexTag.into());
         }
         let tag_edge_map: HashMap<_, _> = self
             .edges
@@ -117,7 +162,7 @@ impl RawPattern {
             .map(|(i, e)| (e.tag_id, i))
             .collect();
         if tag_edge_map.len() != self.edges.len() {
-            return Err(GCardError::Pattern("duplicate edge tag id".into()));
+            return Err(PatternError::DuplicateEdgeTag.into());
         }
         let mut outgoing_adjacencies: HashMap<_, _> =
             self.vertices.iter().map(|v| (v.tag_id, vec![])).collect();
@@ -132,10 +177,7 @@ impl RawPattern {
             };
             outgoing_adjacencies
                 .get_mut(&e.src)
-                .ok_or_else(|| {
-                    let err = format!("vertex with tag id {} not exist", e.src);
-                    GCardError::Pattern(err)
-                })?
+                .ok_or(PatternError::MissingVertex(e.src))?
                 .push(outgoing_adjacency);
 
             let incoming_adjacency = PatternAdjacency {
@@ -146,10 +188,7 @@ impl RawPattern {
             };
             incoming_adjacencies
                 .get_mut(&e.dst)
-                .ok_or_else(|| {
-                    let err = format!("vertex with tag id {} not exist", e.dst);
-                    GCardError::Pattern(err)
-                })?
+                .ok_or(PatternError::MissingVertex(e.dst))?
                 .push(incoming_adjacency);
         }
         let vertices = self.vertices.clone();
@@ -165,9 +204,10 @@ impl RawPattern {
             edge_rank_map: HashMap::new(),
             rank_vertex_map: HashMap::new(),
             rank_edge_map: HashMap::new(),
+            encoding: Default::default(),
         };
         if !is_connected(&pattern) {
-            return Err(GCardError::Pattern("pattern not connected".into()));
+            return Err(PatternError::NotConnected.into());
         }
         let (vertex_rank_map, edge_rank_map) = canonicalize(&pattern);
         let rank_vertex_map = vertex_rank_map
@@ -188,7 +228,7 @@ impl RawPattern {
     pub fn to_path(&self) -> GCardResult<PathPattern> {
         let pattern = self.to_general()?;
         if pattern.vertices().is_empty() {
-            return Err(GCardError::Pattern("empty path is not allowed".into()));
+            return Err(PatternError::EmptyPath.into());
         }
         let mut directions = Vec::new();
         let mut start = pattern.vertices().first().unwrap().tag_id;
@@ -201,7 +241,7 @@ impl RawPattern {
                 directions.push(EdgeDirection::In);
                 start = e.src;
             } else {
-                return Err(GCardError::Pattern("invalid path".into()));
+                return Err(PatternError::InvalidPath.into());
             }
         }
         let cards = iter::repeat(EdgeCardinality::default())
@@ -214,7 +254,7 @@ impl RawPattern {
                 cards,
             })
         } else {
-            Err(GCardError::Pattern("invalid path".into()))
+            Err(PatternError::InvalidPath.into())
         }
     }
 }
@@ -273,9 +313,8 @@ fn is_connected<P: GraphPattern>(pattern: &P) -> bool {
         visited.insert(tag_id);
         stack.extend(
             pattern
-                .adjacencies(tag_id)
+                .neighbor_tag_ids(tag_id)
                 .unwrap()
-                .map(|v| v.neighbor_tag_id)
                 .filter(|tag_id| !visited.contains(tag_id)),
         );
     }
@@ -286,6 +325,23 @@ fn is_connected<P: GraphPattern>(pattern: &P) -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_extend() {
+        let vertices = vec![(1, 6), (2, 6)];
+        let edges = vec![(0, 0, 1, 14), (1, 1, 2, 14)];
+        let mut raw = RawPattern::new();
+        raw.push_back_vertex((0, 6))
+            .extend_vertices(vertices.clone())
+            .extend_edges(edges.clone());
+        let expected = RawPattern::with_vertices_edges(iter::once((0, 6)).chain(vertices), edges);
+        assert_eq!(raw.get_vertices_num(), 3);
+        assert_eq!(raw.get_edges_num(), 2);
+        assert_eq!(
+            raw.to_path().unwrap().encode(),
+            expected.to_path().unwrap().encode()
+        );
+    }
+
     #[test]
     fn test_build_general_pattern() {
         assert!(RawPattern::new()
@@ -314,6 +370,26 @@ mod tests {
         assert!(RawPattern::new().to_general().is_ok());
     }
 
+    #[test]
+    fn test_is_connected() {
+        let mut raw = RawPattern::new();
+        assert!(raw.is_connected());
+        raw.push_back_vertex((0, 1))
+            .push_back_vertex((1, 1))
+            .push_back_vertex((2, 2));
+        assert!(!raw.is_connected());
+        raw.push_back_edge((0, 0, 1, 123));
+        assert!(!raw.is_connected());
+        assert!(raw.to_general().is_err());
+        raw.push_back_edge((1, 2, 1, 345));
+        assert!(raw.is_connected());
+        assert!(raw.to_general().is_ok());
+        // An edge referencing a missing vertex
+        raw.push_back_edge((2, 2, 3, 345));
+        assert!(!raw.is_connected());
+        assert!(raw.to_general().is_err());
+    }
+
     #[test]
     fn test_build_path() {
         assert!(RawPattern::new()
@@ -351,4 +427,72 @@ mod tests {
             .unwrap();
         assert_eq!(p.directions(), [EdgeDirection::Out, EdgeDirection::In])
     }
+
+    #[test]
+    fn test_encode_independent_of_insertion_order() {
+        use rand::rngs::StdRng;
+        use rand::seq::SliceRandom;
+        use rand::SeedableRng;
+
+        // (tag_id, label_id) of vertices and (tag_id, src, dst, label_id) of edges
+        type Spec = (Vec<(TagId, u32)>, Vec<(TagId, TagId, TagId, u32)>);
+        let specs: Vec<Spec> = vec![
+            // A triangle with a parallel edge of the same label in the opposite direction.
+            (
+                vec![(0, 1), (1, 1), (2, 1)],
+                vec![(0, 0, 1, 7), (1, 1, 2, 7), (2, 2, 0, 7), (3, 1, 0, 7)],
+            ),
+            // A square with a diagonal and mixed labels.
+            (
+                vec![(0, 1), (1, 2), (2, 1), (3, 2)],
+                vec![
+                    (0, 0, 1, 3),
+                    (1, 1, 2, 3),
+                    (2, 2, 3, 4),
+                    (3, 3, 0, 4),
+                    (4, 0, 2, 5),
+                ],
+            ),
+            // A star whose leaves share labels.
+            (
+                vec![(0, 0), (1, 1), (2, 1), (3, 1), (4, 2)],
+                vec![(0, 0, 1, 1), (1, 0, 2, 1), (2, 3, 0, 1), (3, 0, 4, 2)],
+            ),
+            // A 5-cycle of identical vertices and edges with a pendant edge.
+            (
+                vec![(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0)],
+                vec![
+                    (0, 0, 1, 0),
+                    (1, 1, 2, 0),
+                    (2, 2, 3, 0),
+                    (3, 3, 4, 0),
+                    (4, 4, 0, 0),
+                    (5, 2, 5, 0),
+                ],
+            ),
+        ];
+        let mut rng = StdRng::seed_from_u64(0);
+        for (mut vertices, mut edges) in specs {
+            let build = |vertices: &[(TagId, u32)], edges: &[(TagId, TagId, TagId, u32)]| {
+                let mut pattern = RawPattern::new();
+                for v in vertices {
+                    pattern.push_back_vertex(*v);
+                }
+                for e in edges {
+                    pattern.push_back_edge(*e);
+                }
+                pattern.to_general().unwrap()
+            };
+            let expected = build(&vertices, &edges).encode();
+            for _ in 0..2000 {
+                vertices.shuffle(&mut rng);
+                edges.shuffle(&mut rng);
+                assert_eq!(
+                    build(&vertices, &edges).encode(),
+                    expected,
+                    "vertices: {vertices:?}, edges: {edges:?}"
+                );
+            }
+        }
+    }
 }
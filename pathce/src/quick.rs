@@ -0,0 +1,98 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use rayon::ThreadPoolBuilder;
+
+use crate::catalog_builder::CatalogBuilder;
+use crate::error::GCardResult;
+use crate::estimate::CardinalityEstimator;
+use crate::graph::{CsvOptions, LabeledGraph};
+use crate::pattern::GraphPattern;
+use crate::schema::Schema;
+
+/// The parameters of [`quick_estimate`]. The defaults favor a fast build over accuracy.
+#[derive(Debug, Clone)]
+pub struct QuickEstimateParams {
+    pub csv_options: CsvOptions,
+    pub num_threads: usize,
+    pub max_path_length: usize,
+    pub max_star_length: usize,
+    pub max_star_degree: usize,
+    pub buckets: usize,
+    /// The number of spanning trees when decomposing cyclic patterns.
+    pub limit: usize,
+}
+
+impl Default for QuickEstimateParams {
+    fn default() -> Self {
+        Self {
+            csv_options: b','.into(),
+            num_threads: 4,
+            max_path_length: 2,
+            max_star_length: 1,
+            max_star_degree: 2,
+            buckets: 50,
+            limit: 10,
+        }
+    }
+}
+
+/// Load the graph from the CSV files in `graph_dir`, build an in-memory catalog, and estimate the
+/// cardinality of `pattern`, e.g., for smoke tests. The catalog is dropped afterwards, so build
+/// it with [`CatalogBuilder`] to estimate more than one pattern.
+pub fn quick_estimate<P, G>(
+    schema: &Schema,
+    graph_dir: G,
+    pattern: &P,
+    params: QuickEstimateParams,
+) -> GCardResult<f64>
+where
+    P: GraphPattern,
+    G: AsRef<Path>,
+{
+    let graph = LabeledGraph::from_csv(graph_dir, schema, params.csv_options, params.num_threads)?;
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(params.num_threads)
+        .build()?;
+    let catalog = CatalogBuilder::new(Arc::new(schema.clone()), Arc::new(graph), Arc::new(pool))
+        .max_path_length(params.max_path_length)
+        .max_star_length(params.max_star_length)
+        .max_star_degree(params.max_star_degree)
+        .buckets(params.buckets)
+        .build()?;
+    let estimator = CardinalityEstimator::new(
+        &catalog,
+        params.max_path_length,
+        params.max_star_length,
+        params.max_star_degree,
+        params.limit,
+        false,
+        false,
+        false,
+    );
+    estimator.estimate(pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::pattern::RawPattern;
+    use crate::test_utils::{build_ldbc_graph, build_ldbc_schema};
+
+    #[test]
+    fn test_quick_estimate() {
+        let schema = build_ldbc_schema();
+        let graph_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/sf0.003");
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let knows = schema.get_edge_label_id("Person_knows_Person").unwrap();
+        let pattern =
+            RawPattern::with_vertices_edges([(0, person), (1, person)], [(0, 0, 1, knows)])
+                .to_general()
+                .unwrap();
+        let card = quick_estimate(&schema, graph_dir, &pattern, Default::default()).unwrap();
+        let num_edges = build_ldbc_graph().get_num_edges(knows).unwrap();
+        assert_eq!(card, num_edges as f64);
+    }
+}
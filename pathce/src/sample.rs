@@ -1,37 +1,131 @@
 use std::sync::Arc;
 
+use itertools::{repeat_n, Itertools};
 use rayon::iter::{IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator};
 
-use crate::common::{DefaultVertexId, EdgeDirection, LabelId, VertexId};
+use crate::common::{DefaultVertexId, EdgeDirection, LabelId, TagId, VertexId, INVALID_VERTEX_ID};
+use crate::error::{GCardResult, SampleError};
 use crate::factorization::{ColumnGroup, SingleColumnGroup, Table};
 use crate::graph::{LabeledGraph, LabeledVertex};
 use crate::pattern::{GraphPattern, PathPattern};
+use crate::schema::Schema;
 
 #[derive(Debug)]
 pub struct PathSampler {
     graph: Arc<LabeledGraph>,
+    schema: Option<Arc<Schema>>,
 }
 
 impl PathSampler {
     pub fn new(graph: Arc<LabeledGraph>) -> Self {
-        PathSampler { graph }
+        PathSampler {
+            graph,
+            schema: None,
+        }
+    }
+
+    /// Use the edge cardinalities of `schema` to sample functional edges from their functional
+    /// side.
+    pub fn with_schema(mut self, schema: Arc<Schema>) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Whether following an edge of `edge_label_id` in `direction` reaches at most one vertex, by
+    /// the [`crate::common::EdgeCardinality`] of the edge in the schema. Always false without a
+    /// schema.
+    fn is_functional(&self, edge_label_id: LabelId, direction: EdgeDirection) -> bool {
+        self.schema
+            .as_ref()
+            .and_then(|schema| schema.get_edge(edge_label_id))
+            .is_some_and(|e| e.card.is_functional(direction))
     }
 
-    pub fn sample(&self, path: &PathPattern) -> Table {
-        match path.len() {
+    /// Check that the vertex and edge labels of `path` are in the graph.
+    fn check_labels(&self, path: &PathPattern) -> GCardResult<()> {
+        if let Some(v) = path
+            .vertices()
+            .iter()
+            .find(|v| self.graph.vertices(v.label_id()).is_none())
+        {
+            return Err(SampleError::VertexLabelNotFound(v.label_id()).into());
+        }
+        if let Some(e) = path
+            .edges()
+            .iter()
+            .find(|e| self.graph.get_num_edges(e.label_id()).is_none())
+        {
+            return Err(SampleError::EdgeLabelNotFound(e.label_id()).into());
+        }
+        Ok(())
+    }
+
+    pub fn sample(&self, path: &PathPattern) -> GCardResult<Table> {
+        self.check_labels(path)?;
+        let table = match path.len() {
             0 => self.sample_0(path),
             1 => self.sample_1(path),
             2 => self.sample_2(path),
-            _ => todo!(),
-        }
+            _ => self.sample_n(path),
+        };
+        Ok(table)
     }
 
+    /// Extend `base_table`, a sample of `base_path`, to `new_path`, which extends `base_path` by
+    /// one edge at its end if `FROM_END` or at its start otherwise.
     pub fn extend<const FROM_END: bool>(
         &self,
         base_path: &PathPattern,
         base_table: &Table,
         new_path: &PathPattern,
-    ) -> Table {
+    ) -> GCardResult<Table> {
+        self.check_labels(new_path)?;
+        if new_path.len() != base_path.len() + 1 {
+            let err = SampleError::NotExtension {
+                base: Box::new(base_path.clone()),
+                new: Box::new(new_path.clone()),
+            };
+            return Err(err.into());
+        }
+        if base_table.num_tags() != base_path.len() + 1 {
+            let err = SampleError::TableMismatch {
+                path: Box::new(base_path.clone()),
+                num_tags: base_table.num_tags(),
+            };
+            return Err(err.into());
+        }
+        let (start_tag_id, end_tag_id, new_column) =
+            self.extend_column::<FROM_END>(base_path, base_table, new_path);
+        // Columns and offsets are `Arc`-backed, so cloning the table only shares them.
+        let mut table = base_table.clone();
+        let (group_id, _) = table.get_column_pos(start_tag_id).unwrap();
+        let column_id = table.add_column(group_id, new_column);
+        table.add_tag(end_tag_id, group_id, column_id);
+        Ok(table)
+    }
+
+    /// Extend `base_path` to each of `new_paths`. The columns of `base_table` are shared by all the
+    /// returned tables, and each table only owns the column of its new vertex.
+    pub fn extend_many<const FROM_END: bool>(
+        &self,
+        base_path: &PathPattern,
+        base_table: &Table,
+        new_paths: &[PathPattern],
+    ) -> GCardResult<Vec<Table>> {
+        new_paths
+            .iter()
+            .map(|new_path| self.extend::<FROM_END>(base_path, base_table, new_path))
+            .collect()
+    }
+
+    /// Compute the column of the new end of `new_path`. Return the tag ids of the extended vertex
+    /// of `base_path` and the new vertex, and the new column.
+    fn extend_column<const FROM_END: bool>(
+        &self,
+        base_path: &PathPattern,
+        base_table: &Table,
+        new_path: &PathPattern,
+    ) -> (TagId, TagId, Arc<Vec<DefaultVertexId>>) {
         let graph = self.graph.clone();
         let extend_start = if FROM_END {
             base_path.end()
@@ -53,8 +147,6 @@ impl PathSampler {
         } else {
             new_path.directions().first().unwrap().reverse()
         };
-        assert_eq!(base_table.num_tags(), base_path.len() + 1);
-
         let column = base_table.get_column(extend_start.tag_id()).unwrap();
         let mut new_column: Vec<_> = column.values().par_iter().copied().collect();
 
@@ -68,19 +160,17 @@ impl PathSampler {
                     .unwrap()
                     .first();
                 *id = if let Some(new_id) = new_id {
-                    *new_id
+                    new_id
                 } else {
                     DefaultVertexId::invalid()
                 };
             });
 
-        let new_column = Arc::new(new_column);
-
-        let mut table = base_table.clone();
-        let (group_id, _) = table.get_column_pos(extend_start.tag_id()).unwrap();
-        let column_id = table.add_column(group_id, new_column);
-        table.add_tag(extend_end.tag_id(), group_id, column_id);
-        table
+        (
+            extend_start.tag_id(),
+            extend_end.tag_id(),
+            Arc::new(new_column),
+        )
     }
 
     fn sample_0(&self, path: &PathPattern) -> Table {
@@ -126,11 +216,41 @@ impl PathSampler {
                     self.graph
                         .neighbors(start_vertex, edge_label_id, direction)
                         .unwrap()
+                        .par_iter()
                 }),
         );
         (start_column.into(), end_column.into())
     }
 
+    /// Sample the edges as a single group of `(start, end)` pairs, which stores no offsets. Each
+    /// start vertex without neighbors is dropped.
+    fn sample_1_flat(
+        &self,
+        start_label: LabelId,
+        edge_label_id: LabelId,
+        direction: EdgeDirection,
+    ) -> ColumnGroup {
+        let (starts, ends): (Vec<_>, Vec<_>) = self
+            .graph
+            .vertices(start_label)
+            .unwrap()
+            .par_iter()
+            .flat_map_iter(|start_id| {
+                let start_vertex = LabeledVertex::new(*start_id, start_label);
+                self.graph
+                    .neighbors(start_vertex, edge_label_id, direction)
+                    .unwrap()
+                    .iter()
+                    .map(|end_id| (*start_id, end_id))
+            })
+            .unzip();
+        let mut start_column = SingleColumnGroup::single();
+        start_column.extend(starts);
+        let mut group = ColumnGroup::from(start_column);
+        group.add_column(Arc::new(ends));
+        group
+    }
+
     fn sample_1(&self, path: &PathPattern) -> Table {
         assert_eq!(path.len(), 1);
         let start = path.start();
@@ -140,9 +260,25 @@ impl PathSampler {
         let edge_label_id = edge.label_id();
         let mut table = Table::default();
 
-        if self.graph.vertices(start.label_id()).unwrap().len()
-            < self.graph.vertices(end.label_id()).unwrap().len()
-        {
+        // Traversing the edge from the side where it is functional has a fan-out of at most 1, so
+        // the edges are sampled from that side as flat pairs. Otherwise, the endpoint with fewer
+        // vertices is the seed.
+        if self.is_functional(edge_label_id, direction) {
+            table.add_group(self.sample_1_flat(start.label_id(), edge_label_id, direction));
+            table.add_tag(start.tag_id(), 0, 0);
+            table.add_tag(end.tag_id(), 0, 1);
+            return table;
+        }
+        if self.is_functional(edge_label_id, direction.reverse()) {
+            let group = self.sample_1_flat(end.label_id(), edge_label_id, direction.reverse());
+            table.add_group(group);
+            table.add_tag(start.tag_id(), 0, 1);
+            table.add_tag(end.tag_id(), 0, 0);
+            return table;
+        }
+        let num_start_vertices = self.graph.vertices(start.label_id()).unwrap().len();
+        let num_end_vertices = self.graph.vertices(end.label_id()).unwrap().len();
+        if num_start_vertices < num_end_vertices {
             let (start_group, end_group) =
                 self.sample_1_inner(start.label_id(), edge_label_id, direction);
             table.add_group(start_group);
@@ -158,73 +294,190 @@ impl PathSampler {
         table
     }
 
+    /// Sample a path of length 2 from its middle vertex. The end reached from the middle vertex
+    /// along a functional edge is stored as a column of the middle group instead of a group of its
+    /// own, dropping the middle vertices without it, which have no paths.
     fn sample_2(&self, path: &PathPattern) -> Table {
         assert_eq!(path.len(), 2);
-        let start = path.start();
-        let end = path.end();
         let [first_edge, second_edge] = path.edges().first_chunk().unwrap();
         let [first_direction, second_direction] = path.directions().first_chunk().unwrap();
         let mid = match first_direction {
             EdgeDirection::Out => path.get_vertex(first_edge.dst()).unwrap(),
             EdgeDirection::In => path.get_vertex(first_edge.src()).unwrap(),
         };
-        let mut mid_column = SingleColumnGroup::single();
-        mid_column.par_extend(
+        let sides = [
+            (
+                path.start(),
+                first_edge.label_id(),
+                first_direction.reverse(),
+            ),
+            (path.end(), second_edge.label_id(), *second_direction),
+        ];
+        let neighbors = |mid_id: DefaultVertexId, edge_label_id, direction| {
+            let mid_vertex = LabeledVertex::new(mid_id, mid.label_id());
             self.graph
-                .vertices(mid.label_id())
+                .neighbors(mid_vertex, edge_label_id, direction)
                 .unwrap()
-                .par_iter()
-                .copied(),
-        );
+        };
+        let mid_ids = self.graph.vertices(mid.label_id()).unwrap();
+        // The declared cardinality is only trusted if the graph agrees.
+        let functional = sides.map(|(_, edge_label_id, direction)| {
+            self.is_functional(edge_label_id, direction)
+                && mid_ids
+                    .par_iter()
+                    .all(|id| neighbors(*id, edge_label_id, direction).len() <= 1)
+        });
+        let mid_ids: Vec<_> = mid_ids
+            .par_iter()
+            .copied()
+            .filter(|id| {
+                sides
+                    .iter()
+                    .zip(functional)
+                    .all(|((_, edge_label_id, direction), f)| {
+                        !f || !neighbors(*id, *edge_label_id, *direction).is_empty()
+                    })
+            })
+            .collect();
 
-        let mut start_column = SingleColumnGroup::multiple();
-        let mut end_column = SingleColumnGroup::multiple();
+        let mut mid_column = SingleColumnGroup::single();
+        mid_column.par_extend(mid_ids.par_iter().copied());
+        let mut table = Table::default();
+        table.add_group(mid_column.into());
+        table.add_tag(mid.tag_id(), 0, 0);
+        for ((vertex, edge_label_id, direction), f) in sides.into_iter().zip(functional) {
+            if f {
+                let column = mid_ids
+                    .par_iter()
+                    .map(|id| neighbors(*id, edge_label_id, direction).first().unwrap())
+                    .collect();
+                let column_id = table.add_column(0, Arc::new(column));
+                table.add_tag(vertex.tag_id(), 0, column_id);
+            } else {
+                let mut column = SingleColumnGroup::multiple();
+                column.par_extend_from_segments(
+                    mid_ids
+                        .par_iter()
+                        .map(|id| neighbors(*id, edge_label_id, direction).par_iter()),
+                );
+                let group_id = table.add_group(column.into());
+                table.add_tag(vertex.tag_id(), group_id, 0);
+            }
+        }
+        table
+    }
 
-        start_column.par_extend_from_segments(
-            self.graph
-                .vertices(mid.label_id())
-                .unwrap()
-                .par_iter()
-                .map(|mid_id| {
-                    let mid_vertex = LabeledVertex::new(*mid_id, mid.label_id());
-                    let start_column = self
-                        .graph
-                        .neighbors(mid_vertex, first_edge.label_id(), first_direction.reverse())
-                        .unwrap();
-                    start_column
-                }),
-        );
-        end_column.par_extend_from_segments(
-            self.graph
-                .vertices(mid.label_id())
-                .unwrap()
-                .par_iter()
-                .map(|mid_id| {
-                    let mid_vertex = LabeledVertex::new(*mid_id, mid.label_id());
-                    let end_column = self
-                        .graph
-                        .neighbors(mid_vertex, second_edge.label_id(), *second_direction)
-                        .unwrap();
-                    end_column
-                }),
-        );
+    /// Sample a path of any length like [`Self::sample_2`], from the vertex with the fewest
+    /// vertices of its label instead of the middle one. The walks from the pivot towards either
+    /// end are stored as a group with a column for each of their vertices.
+    fn sample_n(&self, path: &PathPattern) -> Table {
+        let vertices = path.vertices();
+        let num_vertices = |i: usize| self.graph.vertices(vertices[i].label_id()).unwrap().len();
+        let pivot = (0..vertices.len())
+            .min_by_key(|&i| (num_vertices(i), i.abs_diff(path.len() / 2)))
+            .unwrap();
+        let pivot_ids = self.graph.vertices(vertices[pivot].label_id()).unwrap();
+        let mut pivot_column = SingleColumnGroup::single();
+        pivot_column.par_extend(pivot_ids.par_iter().copied());
 
         let mut table = Table::default();
-        table.add_group(start_column.into());
-        table.add_group(mid_column.into());
-        table.add_group(end_column.into());
-        table.add_tag(start.tag_id(), 0, 0);
-        table.add_tag(mid.tag_id(), 1, 0);
-        table.add_tag(end.tag_id(), 2, 0);
+        table.add_group(pivot_column.into());
+        table.add_tag(vertices[pivot].tag_id(), 0, 0);
+        // The vertices towards the start and towards the end, with the hops reaching them given by
+        // the label of the vertex left, the edge label and the direction.
+        let hop = |from: usize, to: usize| {
+            let edge = from.min(to);
+            let direction = path.directions()[edge];
+            let direction = if from < to {
+                direction
+            } else {
+                direction.reverse()
+            };
+            (
+                vertices[from].label_id(),
+                path.edges()[edge].label_id(),
+                direction,
+            )
+        };
+        let backward = (0..pivot).rev().map(|i| (i, hop(i + 1, i))).collect_vec();
+        let forward = (pivot + 1..vertices.len())
+            .map(|i| (i, hop(i - 1, i)))
+            .collect_vec();
+        for side in [backward, forward] {
+            if side.is_empty() {
+                continue;
+            }
+            let (reached, hops): (Vec<_>, Vec<_>) = side.into_iter().unzip();
+            let walks: Vec<_> = pivot_ids
+                .par_iter()
+                .map(|pivot_id| self.walks(*pivot_id, &hops))
+                .collect();
+            let mut first_column = SingleColumnGroup::multiple();
+            first_column
+                .par_extend_from_segments(walks.par_iter().map(|w| w[0].par_iter().copied()));
+            let mut group = ColumnGroup::from(first_column);
+            for k in 1..hops.len() {
+                let column = walks.par_iter().flat_map_iter(|w| w[k].iter().copied());
+                group.add_column(Arc::new(column.collect()));
+            }
+            let group_id = table.add_group(group);
+            for (column_id, i) in reached.into_iter().enumerate() {
+                table.add_tag(vertices[i].tag_id(), group_id, column_id);
+            }
+        }
         table
     }
+
+    /// Enumerate the walks from `pivot_id` along `hops`, each given by the label of the vertex it
+    /// leaves, the edge label and the direction. Return a column for each hop, whose rows are the
+    /// walks. An invalid vertex has a single invalid neighbor, as in [`Self::extend`].
+    fn walks(
+        &self,
+        pivot_id: DefaultVertexId,
+        hops: &[(LabelId, LabelId, EdgeDirection)],
+    ) -> Vec<Vec<DefaultVertexId>> {
+        let mut columns: Vec<Vec<DefaultVertexId>> = Vec::with_capacity(hops.len());
+        let mut frontier = vec![pivot_id];
+        for &(label_id, edge_label_id, direction) in hops {
+            let mut repeats = Vec::with_capacity(frontier.len());
+            let mut next = vec![];
+            for id in &frontier {
+                if id.is_valid() {
+                    let vertex = LabeledVertex::new(*id, label_id);
+                    let neighbors = self
+                        .graph
+                        .neighbors(vertex, edge_label_id, direction)
+                        .unwrap();
+                    repeats.push(neighbors.len());
+                    next.extend(neighbors.iter());
+                } else {
+                    repeats.push(1);
+                    next.push(INVALID_VERTEX_ID);
+                }
+            }
+            // Repeat each walk so far once for each of its extensions.
+            for column in &mut columns {
+                *column = column
+                    .iter()
+                    .zip(&repeats)
+                    .flat_map(|(id, n)| repeat_n(*id, *n))
+                    .collect();
+            }
+            columns.push(next.clone());
+            frontier = next;
+        }
+        columns
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use super::*;
+    use crate::error::GCardError;
     use crate::pattern::RawPattern;
-    use crate::test_utils::build_ldbc_graph;
+    use crate::test_utils::{build_ldbc_graph, build_ldbc_schema};
 
     fn build_path(len: u8) -> PathPattern {
         let mut raw = RawPattern::new();
@@ -242,7 +495,7 @@ mod tests {
         let path = build_path(0);
         let graph = build_ldbc_graph();
         let sampler = PathSampler::new(Arc::new(graph));
-        let table = sampler.sample(&path);
+        let table = sampler.sample(&path).unwrap();
         assert_eq!(table.count(), 50);
     }
 
@@ -251,31 +504,286 @@ mod tests {
         let path = build_path(1);
         let graph = build_ldbc_graph();
         let sampler = PathSampler::new(Arc::new(graph));
-        let table = sampler.sample(&path);
+        let table = sampler.sample(&path).unwrap();
         assert_eq!(table.count(), 88);
     }
 
+    #[test]
+    fn test_sample_1_functional() {
+        let graph = Arc::new(build_ldbc_graph());
+        let sampler = PathSampler::new(graph.clone()).with_schema(Arc::new(build_ldbc_schema()));
+        let seeded_sampler = PathSampler::new(graph.clone());
+        // Comment_hasCreator_Person is ManyToOne, so it is sampled from the comments as flat pairs
+        // in both directions, while Person_knows_Person is seeded. Without the schema, both edges
+        // are seeded from the endpoint with fewer vertices.
+        for (src_label, edge_label, dst_label, functional) in [(1, 1, 6, true), (6, 14, 6, false)] {
+            let num_edges = graph.get_num_edges(edge_label).unwrap();
+            for reverse in [false, true] {
+                let path = if reverse {
+                    RawPattern::with_vertices_edges(
+                        [(0, dst_label), (1, src_label)],
+                        [(0, 1, 0, edge_label)],
+                    )
+                } else {
+                    RawPattern::with_vertices_edges(
+                        [(0, src_label), (1, dst_label)],
+                        [(0, 0, 1, edge_label)],
+                    )
+                }
+                .to_path()
+                .unwrap();
+                let seeded_table = seeded_sampler.sample(&path).unwrap();
+                assert_eq!(seeded_table.count(), num_edges);
+                assert_eq!(seeded_table.num_groups(), 2);
+                let table = sampler.sample(&path).unwrap();
+                assert_eq!(table.count(), num_edges);
+                if !functional {
+                    assert_eq!(table.num_groups(), 2);
+                    continue;
+                }
+                assert_eq!(table.num_groups(), 1);
+                let start_column = table.get_column(path.start().tag_id()).unwrap();
+                let end_column = table.get_column(path.end().tag_id()).unwrap();
+                let src_column = if reverse { &end_column } else { &start_column };
+                assert!(src_column.values().iter().all_unique());
+                for (start_id, end_id) in start_column.values().iter().zip(end_column.values()) {
+                    let (src_id, dst_id) = if reverse {
+                        (*end_id, *start_id)
+                    } else {
+                        (*start_id, *end_id)
+                    };
+                    let src = LabeledVertex::new(src_id, src_label);
+                    assert!(graph.has_edge(src, dst_id, edge_label));
+                }
+                // Each edge is a single row of the flat group.
+                assert_eq!(table.num_values(), 2 * num_edges);
+            }
+        }
+    }
+
     #[test]
     fn test_sample_2() {
         let path = build_path(2);
         let graph = build_ldbc_graph();
         let sampler = PathSampler::new(Arc::new(graph));
-        let table = sampler.sample(&path);
+        let table = sampler.sample(&path).unwrap();
         assert_eq!(table.count(), 246);
     }
 
+    #[test]
+    fn test_sample_2_functional() {
+        // Comment_hasCreator_Person, Person_isLocatedIn_City
+        let path = RawPattern::with_vertices_edges(
+            [(0, 1), (1, 6), (2, 0)],
+            [(0, 0, 1, 1), (1, 1, 2, 13)],
+        )
+        .to_path()
+        .unwrap();
+        let graph = Arc::new(build_ldbc_graph());
+        let sampler = PathSampler::new(graph.clone()).with_schema(Arc::new(build_ldbc_schema()));
+        let table = sampler.sample(&path).unwrap();
+        // The city of each person is a column of the group of persons.
+        assert_eq!(table.num_groups(), 2);
+        let expected: usize = graph
+            .vertices(6)
+            .unwrap()
+            .iter()
+            .map(|id| {
+                let person = LabeledVertex::new(*id, 6);
+                let num_comments = graph.neighbors(person, 1, EdgeDirection::In).unwrap().len();
+                let num_cities = graph
+                    .neighbors(person, 13, EdgeDirection::Out)
+                    .unwrap()
+                    .len();
+                num_comments * num_cities
+            })
+            .sum();
+        assert!(expected > 0);
+        assert_eq!(table.count(), expected);
+    }
+
+    /// Count the walks of `len` knows edges among the persons.
+    fn count_knows_walks(graph: &LabeledGraph, len: usize) -> usize {
+        let persons = graph.vertices(6).unwrap();
+        let mut counts: HashMap<_, _> = persons.iter().map(|id| (*id, 1)).collect();
+        for _ in 0..len {
+            counts = persons
+                .iter()
+                .map(|id| {
+                    let vertex = LabeledVertex::new(*id, 6);
+                    let neighbors = graph.neighbors(vertex, 14, EdgeDirection::Out).unwrap();
+                    (*id, neighbors.iter().map(|n| counts[&n]).sum())
+                })
+                .collect();
+        }
+        counts.values().sum()
+    }
+
+    #[test]
+    fn test_sample_3() {
+        let path = build_path(3);
+        let graph = Arc::new(build_ldbc_graph());
+        let sampler = PathSampler::new(graph.clone()).with_schema(Arc::new(build_ldbc_schema()));
+        let table = sampler.sample(&path).unwrap();
+        assert_eq!(table.num_tags(), 4);
+        assert_eq!(table.count(), count_knows_walks(&graph, 3));
+        // The pivot is the second person, and every walk from it follows the knows edges.
+        assert_eq!(table.get_column_pos(1), Some((0, 0)));
+        let pivot = table.get_column(1).unwrap();
+        let start = table.get_column(0).unwrap();
+        for (pivot_id, start_ids) in pivot.values().iter().zip(start.items()) {
+            for start_id in start_ids {
+                assert!(graph.has_edge(LabeledVertex::new(*start_id, 6), *pivot_id, 14));
+            }
+        }
+        let (mid, end) = (table.get_column(2).unwrap(), table.get_column(3).unwrap());
+        for (mid_id, end_id) in mid.values().iter().zip(end.values()) {
+            assert!(graph.has_edge(LabeledVertex::new(*mid_id, 6), *end_id, 14));
+        }
+    }
+
+    #[test]
+    fn test_sample_4() {
+        let path = build_path(4);
+        let graph = Arc::new(build_ldbc_graph());
+        let sampler = PathSampler::new(graph.clone()).with_schema(Arc::new(build_ldbc_schema()));
+        let table = sampler.sample(&path).unwrap();
+        assert_eq!(table.num_tags(), 5);
+        assert_eq!(table.count(), count_knows_walks(&graph, 4));
+    }
+
+    #[test]
+    fn test_serialize_table() {
+        let path = build_path(2);
+        let graph = build_ldbc_graph();
+        let sampler = PathSampler::new(Arc::new(graph));
+        let table = sampler.sample(&path).unwrap();
+        let bytes = bincode::serialize(&table).unwrap();
+        let table_new: Table = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(table_new.count(), 246);
+        assert_eq!(table_new.num_groups(), table.num_groups());
+        for tag_id in table.tags() {
+            assert_eq!(table_new.get_column(tag_id), table.get_column(tag_id));
+        }
+        let json = serde_json::to_string(&table).unwrap();
+        let table_new: Table = serde_json::from_str(&json).unwrap();
+        assert_eq!(table_new.count(), 246);
+    }
+
+    #[test]
+    fn test_retain_items() {
+        // (p1: Person)<-[:hasMember]-(f: Forum)-[:hasModerator]->(p2: Person)
+        let path = RawPattern::new()
+            .push_back_vertex((0, 6))
+            .push_back_vertex((1, 5))
+            .push_back_vertex((2, 6))
+            .push_back_edge((0, 1, 0, 9))
+            .push_back_edge((1, 1, 2, 10))
+            .to_path()
+            .unwrap();
+        let graph = build_ldbc_graph();
+        let sampler = PathSampler::new(Arc::new(graph));
+        let mut table = sampler.sample(&path).unwrap();
+        let total = table.count();
+        // Items are grouped by the forum, whose moderator is a column of the group of forums; drop
+        // those where the start and the end may be equal.
+        let distinct_endpoints =
+            |item: &[&[DefaultVertexId]]| item[2].iter().all(|start| !item[1].contains(start));
+        let start = table.get_column(0).unwrap();
+        let end = table.get_column(2).unwrap();
+        let expected: usize = start
+            .items()
+            .zip(end.items())
+            .filter(|(start, end)| distinct_endpoints(&[&[], end, start]))
+            .map(|(start, end)| start.len() * end.len())
+            .sum();
+        table.retain_items(distinct_endpoints);
+        assert!(table.count() < total);
+        assert!(expected > 0);
+        assert_eq!(table.count(), expected);
+        let start = table.get_column(0).unwrap();
+        let end = table.get_column(2).unwrap();
+        for (start, end) in start.items().zip(end.items()) {
+            assert!(start.iter().all(|v| !end.contains(v)));
+        }
+    }
+
     #[test]
     fn test_extend() {
         let path = build_path(2);
         let graph = build_ldbc_graph();
         let sampler = PathSampler::new(Arc::new(graph));
-        let table = sampler.sample(&path);
+        let table = sampler.sample(&path).unwrap();
         let new_path = RawPattern::from(&path)
             .push_back_vertex((3, 0))
             .push_back_edge((2, 2, 3, 13))
             .to_path()
             .unwrap();
-        let table = sampler.extend::<true>(&path, &table, &new_path);
+        let err = sampler.extend::<true>(&path, &table, &path).unwrap_err();
+        assert!(matches!(
+            err,
+            GCardError::Sample(SampleError::NotExtension { .. })
+        ));
+        let err = sampler
+            .extend::<true>(&build_path(1), &table, &build_path(2))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            GCardError::Sample(SampleError::TableMismatch { num_tags: 3, .. })
+        ));
+        let table = sampler.extend::<true>(&path, &table, &new_path).unwrap();
         assert_eq!(table.count(), 246);
+
+        let unknown = RawPattern::from(&path)
+            .push_back_vertex((3, 100))
+            .push_back_edge((2, 2, 3, 13))
+            .to_path()
+            .unwrap();
+        let err = sampler.sample(&unknown).unwrap_err();
+        assert!(matches!(
+            err,
+            GCardError::Sample(SampleError::VertexLabelNotFound(100))
+        ));
+    }
+
+    #[test]
+    fn test_extend_many() {
+        let path = build_path(2);
+        let graph = build_ldbc_graph();
+        let sampler = PathSampler::new(Arc::new(graph));
+        let table = sampler.sample(&path).unwrap();
+        // (p2: Person)-[:isLocatedIn]->(City) and (p2: Person)<-[:hasCreator]-(Comment)
+        let new_paths = [
+            RawPattern::from(&path)
+                .push_back_vertex((3, 0))
+                .push_back_edge((2, 2, 3, 13))
+                .to_path()
+                .unwrap(),
+            RawPattern::from(&path)
+                .push_back_vertex((3, 1))
+                .push_back_edge((2, 3, 2, 1))
+                .to_path()
+                .unwrap(),
+        ];
+        let tables = sampler
+            .extend_many::<true>(&path, &table, &new_paths)
+            .unwrap();
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].count(), 246);
+        for (new_path, extended) in new_paths.iter().zip(&tables) {
+            let expected = sampler.extend::<true>(&path, &table, new_path).unwrap();
+            assert_eq!(extended.count(), expected.count());
+            assert_eq!(
+                extended.get_column(3).unwrap().values(),
+                expected.get_column(3).unwrap().values()
+            );
+            // The columns of the base table are shared rather than copied.
+            for tag_id in 0..3 {
+                assert_eq!(
+                    extended.get_column(tag_id).unwrap().values().as_ptr(),
+                    table.get_column(tag_id).unwrap().values().as_ptr()
+                );
+            }
+        }
     }
 }
@@ -0,0 +1,211 @@
+use bimap::BiHashMap;
+use serde::Serialize;
+
+use super::Schema;
+use crate::common::LabelId;
+
+/// A label present in both schemas under different ids.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LabelIdChange {
+    pub name: String,
+    pub old_id: LabelId,
+    pub new_id: LabelId,
+}
+
+/// The difference between the labels (of vertices or edges) of two schemas, matched by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LabelDiff {
+    pub added: Vec<(String, LabelId)>,
+    pub removed: Vec<(String, LabelId)>,
+    pub changed: Vec<LabelIdChange>,
+}
+
+impl LabelDiff {
+    fn new(old: &BiHashMap<String, LabelId>, new: &BiHashMap<String, LabelId>) -> Self {
+        let mut diff = Self::default();
+        for (name, old_id) in old {
+            match new.get_by_left(name) {
+                None => diff.removed.push((name.clone(), *old_id)),
+                Some(new_id) if new_id != old_id => diff.changed.push(LabelIdChange {
+                    name: name.clone(),
+                    old_id: *old_id,
+                    new_id: *new_id,
+                }),
+                Some(_) => (),
+            }
+        }
+        for (name, new_id) in new {
+            if !old.contains_left(name) {
+                diff.added.push((name.clone(), *new_id));
+            }
+        }
+        diff.added.sort_unstable();
+        diff.removed.sort_unstable();
+        diff.changed.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// An edge label present in both schemas whose endpoints (by vertex label name) differ.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EndpointChange {
+    pub name: String,
+    pub old: (String, String),
+    pub new: (String, String),
+}
+
+/// The difference between two schemas, see [`Schema::diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SchemaDiff {
+    pub vertex_labels: LabelDiff,
+    pub edge_labels: LabelDiff,
+    pub endpoints: Vec<EndpointChange>,
+}
+
+impl SchemaDiff {
+    pub fn is_empty(&self) -> bool {
+        self.vertex_labels.is_empty() && self.edge_labels.is_empty() && self.endpoints.is_empty()
+    }
+
+    /// Return whether a catalog or graph built on the old schema may be invalid under the new
+    /// one, i.e., a label is removed or gets a new id, or an edge label gets new endpoints. Adding
+    /// labels is not breaking.
+    pub fn is_breaking(&self) -> bool {
+        !self.vertex_labels.removed.is_empty()
+            || !self.vertex_labels.changed.is_empty()
+            || !self.edge_labels.removed.is_empty()
+            || !self.edge_labels.changed.is_empty()
+            || !self.endpoints.is_empty()
+    }
+}
+
+impl Schema {
+    /// Compare the labels and edge endpoints of `self` (the old schema) against `other` (the new
+    /// one). Labels are matched by name.
+    pub fn diff(&self, other: &Schema) -> SchemaDiff {
+        let vertex_labels = LabelDiff::new(&self.vertex_label_map, &other.vertex_label_map);
+        let edge_labels = LabelDiff::new(&self.edge_label_map, &other.edge_label_map);
+        let mut endpoints = vec![];
+        for (name, old_id) in &self.edge_label_map {
+            let Some(new_id) = other.get_edge_label_id(name) else {
+                continue;
+            };
+            let (Some(old_edge), Some(new_edge)) = (self.get_edge(*old_id), other.get_edge(new_id))
+            else {
+                continue;
+            };
+            let old = (
+                self.vertex_name(old_edge.from),
+                self.vertex_name(old_edge.to),
+            );
+            let new = (
+                other.vertex_name(new_edge.from),
+                other.vertex_name(new_edge.to),
+            );
+            if old != new {
+                endpoints.push(EndpointChange {
+                    name: name.clone(),
+                    old,
+                    new,
+                });
+            }
+        }
+        endpoints.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+        SchemaDiff {
+            vertex_labels,
+            edge_labels,
+            endpoints,
+        }
+    }
+
+    fn vertex_name(&self, label_id: LabelId) -> String {
+        self.get_vertex_label_name(label_id)
+            .cloned()
+            .unwrap_or_else(|| label_id.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::EdgeCardinality;
+    use crate::schema::SchemaUnchecked;
+
+    fn base_schema() -> SchemaUnchecked {
+        SchemaUnchecked::default()
+            .add_vertex_label("person".into(), 0)
+            .add_vertex_label("city".into(), 1)
+            .add_edge_label("knows".into(), 0)
+            .add_edge_label("isLocatedIn".into(), 1)
+            .add_vertex((0, false))
+            .add_vertex((1, true))
+            .add_edge((0, 0, 0, EdgeCardinality::ManyToMany))
+            .add_edge((0, 1, 1, EdgeCardinality::ManyToOne))
+    }
+
+    #[test]
+    fn test_diff() {
+        let old: Schema = base_schema().try_into().unwrap();
+        assert!(old.diff(&old).is_empty());
+
+        // Adding labels is compatible.
+        let new: Schema = base_schema()
+            .add_vertex_label("country".into(), 2)
+            .add_edge_label("isPartOf".into(), 2)
+            .add_vertex((2, true))
+            .add_edge((1, 2, 2, EdgeCardinality::ManyToOne))
+            .try_into()
+            .unwrap();
+        let diff = old.diff(&new);
+        assert!(!diff.is_breaking());
+        assert_eq!(diff.vertex_labels.added, vec![("country".into(), 2)]);
+        assert_eq!(diff.edge_labels.added, vec![("isPartOf".into(), 2)]);
+        let diff = new.diff(&old);
+        assert!(diff.is_breaking());
+        assert_eq!(diff.vertex_labels.removed, vec![("country".into(), 2)]);
+        assert_eq!(diff.edge_labels.removed, vec![("isPartOf".into(), 2)]);
+
+        // Swap the ids of person and city, and let knows connect cities.
+        let new: Schema = SchemaUnchecked::default()
+            .add_vertex_label("person".into(), 1)
+            .add_vertex_label("city".into(), 0)
+            .add_edge_label("knows".into(), 0)
+            .add_edge_label("isLocatedIn".into(), 1)
+            .add_vertex((1, false))
+            .add_vertex((0, true))
+            .add_edge((0, 0, 0, EdgeCardinality::ManyToMany))
+            .add_edge((1, 0, 1, EdgeCardinality::ManyToOne))
+            .try_into()
+            .unwrap();
+        let diff = old.diff(&new);
+        assert!(diff.is_breaking());
+        assert!(diff.edge_labels.is_empty());
+        assert_eq!(
+            diff.vertex_labels.changed,
+            vec![
+                LabelIdChange {
+                    name: "city".into(),
+                    old_id: 1,
+                    new_id: 0
+                },
+                LabelIdChange {
+                    name: "person".into(),
+                    old_id: 0,
+                    new_id: 1
+                },
+            ]
+        );
+        assert_eq!(
+            diff.endpoints,
+            vec![EndpointChange {
+                name: "knows".into(),
+                old: ("person".into(), "person".into()),
+                new: ("city".into(), "city".into()),
+            }]
+        );
+    }
+}
@@ -1,18 +1,23 @@
+mod diff;
 mod path;
 mod path_v2;
 
 use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
 use std::fs::File;
 use std::io::{BufReader, BufWriter};
 use std::path::Path;
 
 use bimap::BiHashMap;
+pub use diff::*;
+use itertools::Itertools;
 pub use path::*;
 pub use path_v2::*;
 use serde::{Deserialize, Serialize};
 
-use crate::common::{EdgeCardinality, LabelId};
-use crate::error::{GCardError, GCardResult};
+use crate::common::{EdgeCardinality, EdgeDirection, LabelId};
+use crate::error::{GCardError, GCardResult, SchemaError};
+use crate::pattern::{GeneralPattern, GraphPattern, PatternEdge, PatternVertex, RawPattern};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct SchemaVertex {
@@ -51,6 +56,12 @@ pub struct SchemaUnchecked {
     edge_labels: HashMap<String, LabelId>,
     vertices: Vec<SchemaVertex>,
     edges: Vec<SchemaEdge>,
+    /// The supertype of each vertex label which has one.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    subtype_of: HashMap<LabelId, LabelId>,
+    /// The supertype of each edge label which has one.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    edge_subtype_of: HashMap<LabelId, LabelId>,
 }
 
 impl SchemaUnchecked {
@@ -79,6 +90,21 @@ impl SchemaUnchecked {
         self.edges.push(edge.into());
         self
     }
+
+    /// Make the vertex label `subtype` a subtype of the abstract vertex label `supertype`, which
+    /// has no vertices of its own, e.g., `Post` and `Comment` of `Message`.
+    pub fn add_subtype(mut self, subtype: LabelId, supertype: LabelId) -> Self {
+        self.subtype_of.insert(subtype, supertype);
+        self
+    }
+
+    /// Make the edge label `subtype` a subtype of the abstract edge label `supertype`, which has no
+    /// edge of its own, e.g., `Post_hasCreator_Person` and `Comment_hasCreator_Person` of
+    /// `hasCreator`.
+    pub fn add_edge_subtype(mut self, subtype: LabelId, supertype: LabelId) -> Self {
+        self.edge_subtype_of.insert(subtype, supertype);
+        self
+    }
 }
 
 impl TryFrom<SchemaUnchecked> for Schema {
@@ -91,24 +117,42 @@ impl TryFrom<SchemaUnchecked> for Schema {
         let mut label_to_edge_id = HashMap::with_capacity(edge_label_map.len());
         for (vertex_id, vertex) in unchecked.vertices.iter().enumerate() {
             if !vertex_label_map.contains_right(&vertex.label) {
-                let err = format!("vertex label id: {} does not exist", vertex.label);
-                return Err(GCardError::Schema(err));
+                return Err(SchemaError::UnknownVertexLabel(vertex.label).into());
             }
             if label_to_vertex_id.insert(vertex.label, vertex_id).is_some() {
-                let err = format!("duplicate vertex: {:?}", vertex);
-                return Err(GCardError::Schema(err));
+                return Err(SchemaError::DuplicateVertex(vertex.clone()).into());
             }
         }
         for (edge_id, edge) in unchecked.edges.iter().enumerate() {
             if !edge_label_map.contains_right(&edge.label) {
-                let err = format!("edge label id: {} does not exist", edge.label);
-                return Err(GCardError::Schema(err));
+                return Err(SchemaError::UnknownEdgeLabel(edge.label).into());
             }
             if label_to_edge_id.insert(edge.label, edge_id).is_some() {
-                let err = format!("duplicate edge: {:?}", edge);
-                return Err(GCardError::Schema(err));
+                return Err(SchemaError::DuplicateEdge(edge.clone()).into());
             }
         }
+        for (subtype, supertype) in &unchecked.subtype_of {
+            for label in [subtype, supertype] {
+                if !vertex_label_map.contains_right(label) {
+                    return Err(SchemaError::UnknownVertexLabel(*label).into());
+                }
+            }
+            if label_to_vertex_id.contains_key(supertype) {
+                return Err(SchemaError::ConcreteSupertype(*supertype).into());
+            }
+        }
+        for (subtype, supertype) in &unchecked.edge_subtype_of {
+            for label in [subtype, supertype] {
+                if !edge_label_map.contains_right(label) {
+                    return Err(SchemaError::UnknownEdgeLabel(*label).into());
+                }
+            }
+            if label_to_edge_id.contains_key(supertype) {
+                return Err(SchemaError::ConcreteSupertype(*supertype).into());
+            }
+        }
+        check_acyclic(&unchecked.subtype_of)?;
+        check_acyclic(&unchecked.edge_subtype_of)?;
         let mut outgoing_adj_lists: HashMap<_, _> = vertex_label_map
             .right_values()
             .map(|label_id| (*label_id, Vec::new()))
@@ -119,14 +163,12 @@ impl TryFrom<SchemaUnchecked> for Schema {
             if let Some(adj_list) = outgoing_adj_lists.get_mut(&edge.from) {
                 adj_list.push(edge_id)
             } else {
-                let err = format!("vertex with label id: {} does not exist", edge.from);
-                return Err(GCardError::Schema(err));
+                return Err(SchemaError::MissingVertex(edge.from).into());
             }
             if let Some(adj_list) = incoming_adj_lists.get_mut(&edge.to) {
                 adj_list.push(edge_id)
             } else {
-                let err = format!("vertex with label id: {} does not exist", edge.to);
-                return Err(GCardError::Schema(err));
+                return Err(SchemaError::MissingVertex(edge.to).into());
             }
         }
         let schema = Schema {
@@ -138,11 +180,12 @@ impl TryFrom<SchemaUnchecked> for Schema {
             label_to_edge_id,
             outgoing_adj_lists,
             incoming_adj_lists,
+            subtype_of: unchecked.subtype_of,
+            edge_subtype_of: unchecked.edge_subtype_of,
         };
         let wcc = schema.weak_connected_components();
         if wcc.len() != 1 {
-            let err = format!("schema not connected, cc: {wcc:?}");
-            return Err(GCardError::Schema(err));
+            return Err(SchemaError::Disconnected(wcc).into());
         }
         Ok(schema)
     }
@@ -159,6 +202,8 @@ impl From<Schema> for SchemaUnchecked {
             edge_labels,
             vertices,
             edges,
+            subtype_of: value.subtype_of,
+            edge_subtype_of: value.edge_subtype_of,
         }
     }
 }
@@ -175,6 +220,42 @@ pub struct Schema {
     label_to_edge_id: HashMap<LabelId, usize>,
     outgoing_adj_lists: HashMap<LabelId, Vec<usize>>,
     incoming_adj_lists: HashMap<LabelId, Vec<usize>>,
+    subtype_of: HashMap<LabelId, LabelId>,
+    edge_subtype_of: HashMap<LabelId, LabelId>,
+}
+
+/// Check that no label is its own (indirect) supertype.
+fn check_acyclic(subtype_of: &HashMap<LabelId, LabelId>) -> GCardResult<()> {
+    for (subtype, supertype) in subtype_of {
+        // A chain of supertypes longer than the number of subtypes has a cycle.
+        let mut current = *supertype;
+        for _ in 0..subtype_of.len() {
+            if current == *subtype {
+                return Err(SchemaError::CyclicSubtype(*subtype).into());
+            }
+            match subtype_of.get(&current) {
+                Some(next) => current = *next,
+                None => break,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Return whether `label_id` is `ancestor` or one of its (indirect) subtypes.
+fn is_subtype(
+    subtype_of: &HashMap<LabelId, LabelId>,
+    label_id: LabelId,
+    ancestor: LabelId,
+) -> bool {
+    let mut current = Some(label_id);
+    while let Some(label_id) = current {
+        if label_id == ancestor {
+            return true;
+        }
+        current = subtype_of.get(&label_id).copied();
+    }
+    false
 }
 
 impl Schema {
@@ -228,6 +309,26 @@ impl Schema {
         Some(adj_list.iter().map(|&edge_id| &self.edges[edge_id]))
     }
 
+    /// Return the distinct edge labels incident to `vertex_label_id` with their directions from
+    /// it, outgoing ones first. A self-loop label appears in both directions. The iterator is
+    /// empty if the vertex label does not exist.
+    pub fn incident_edge_labels(
+        &self,
+        vertex_label_id: LabelId,
+    ) -> impl Iterator<Item = (LabelId, EdgeDirection)> + '_ {
+        let outgoing = self
+            .outgoing_edges(vertex_label_id)
+            .into_iter()
+            .flatten()
+            .map(|e| (e.label, EdgeDirection::Out));
+        let incoming = self
+            .incoming_edges(vertex_label_id)
+            .into_iter()
+            .flatten()
+            .map(|e| (e.label, EdgeDirection::In));
+        outgoing.chain(incoming).unique()
+    }
+
     pub fn get_vertex_label_id(&self, name: &str) -> Option<LabelId> {
         self.vertex_label_map.get_by_left(name).copied()
     }
@@ -244,6 +345,169 @@ impl Schema {
         self.edge_label_map.get_by_right(&id)
     }
 
+    /// Return the direct supertype of the vertex label `label_id`, if any.
+    pub fn get_supertype(&self, label_id: LabelId) -> Option<LabelId> {
+        self.subtype_of.get(&label_id).copied()
+    }
+
+    /// Return the direct supertype of the edge label `label_id`, if any.
+    pub fn get_edge_supertype(&self, label_id: LabelId) -> Option<LabelId> {
+        self.edge_subtype_of.get(&label_id).copied()
+    }
+
+    /// Return whether the vertex label `label_id` is abstract, i.e., has no vertices of its own.
+    pub fn is_abstract(&self, label_id: LabelId) -> bool {
+        self.get_vertex(label_id).is_none() && self.vertex_label_map.contains_right(&label_id)
+    }
+
+    /// Return the vertex labels with vertices which are `label_id` or its (indirect) subtypes, in
+    /// ascending order.
+    pub fn concrete_subtypes(&self, label_id: LabelId) -> Vec<LabelId> {
+        self.vertices
+            .iter()
+            .map(|v| v.label)
+            .filter(|label| is_subtype(&self.subtype_of, *label, label_id))
+            .sorted_unstable()
+            .collect()
+    }
+
+    /// Return the edge labels with edges which are `label_id` or its (indirect) subtypes, in
+    /// ascending order.
+    pub fn concrete_edge_subtypes(&self, label_id: LabelId) -> Vec<LabelId> {
+        self.edges
+            .iter()
+            .map(|e| e.label)
+            .filter(|label| is_subtype(&self.edge_subtype_of, *label, label_id))
+            .sorted_unstable()
+            .collect()
+    }
+
+    /// Expand `pattern` into a pattern for every combination of the concrete subtypes of its
+    /// vertex and edge labels where the edges connect the vertex labels given by the schema.
+    /// Every vertex and edge of a graph has a single concrete label, so the matches of `pattern`
+    /// are the disjoint union of the matches of the expanded patterns, and its cardinality is
+    /// their sum.
+    pub fn expand_abstract_labels<P: GraphPattern>(
+        &self,
+        pattern: &P,
+    ) -> GCardResult<Vec<GeneralPattern>> {
+        let choices: Vec<_> = pattern
+            .vertices()
+            .iter()
+            .map(|v| {
+                let subtypes = self.concrete_subtypes(v.label_id());
+                if subtypes.is_empty() {
+                    return Err(SchemaError::UnknownVertexLabel(v.label_id()));
+                }
+                Ok(subtypes
+                    .into_iter()
+                    .map(|label_id| PatternVertex::new(v.tag_id(), label_id))
+                    .collect_vec())
+            })
+            .try_collect()?;
+        let edge_choices: Vec<_> = pattern
+            .edges()
+            .iter()
+            .map(|e| {
+                let subtypes = self.concrete_edge_subtypes(e.label_id());
+                if subtypes.is_empty() {
+                    return Err(SchemaError::UnknownEdgeLabel(e.label_id()));
+                }
+                Ok(subtypes
+                    .into_iter()
+                    .map(|label_id| PatternEdge::new(e.tag_id(), e.src(), e.dst(), label_id))
+                    .collect_vec())
+            })
+            .try_collect()?;
+        let mut patterns = vec![];
+        for vertices in choices.into_iter().multi_cartesian_product() {
+            let labels: HashMap<_, _> = vertices
+                .iter()
+                .map(|v| (v.tag_id(), v.label_id()))
+                .collect();
+            // Keep the subtypes of each edge connecting the labels of its endpoints.
+            let edge_choices = edge_choices
+                .iter()
+                .map(|edges| {
+                    edges
+                        .iter()
+                        .filter(|e| {
+                            let edge = self.get_edge(e.label_id()).unwrap();
+                            edge.from == labels[&e.src()] && edge.to == labels[&e.dst()]
+                        })
+                        .copied()
+                        .collect_vec()
+                })
+                .collect_vec();
+            if edge_choices.is_empty() {
+                let raw = RawPattern::with_vertices_edges(vertices, Vec::<PatternEdge>::new());
+                patterns.push(raw.to_general()?);
+                continue;
+            }
+            for edges in edge_choices.into_iter().multi_cartesian_product() {
+                let raw = RawPattern::with_vertices_edges(vertices.clone(), edges);
+                patterns.push(raw.to_general()?);
+            }
+        }
+        Ok(patterns)
+    }
+
+    /// Render the schema in the Graphviz DOT language. Vertex labels are nodes, with abstract ones
+    /// dashed and subtype relations dotted, and every edge label is an arrow, so self-loops and
+    /// parallel edges are drawn separately.
+    pub fn to_dot(&self) -> String {
+        let escape = |name: &str| name.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut dot = String::from("digraph schema {\n");
+        for (name, label) in self
+            .vertex_label_map
+            .iter()
+            .sorted_unstable_by_key(|(_, l)| **l)
+        {
+            let name = escape(name);
+            match self.get_vertex(*label) {
+                Some(v) if v.discrete => {
+                    writeln!(dot, "  v{label} [label=\"{name}\\n(discrete)\"];").unwrap()
+                }
+                Some(_) => writeln!(dot, "  v{label} [label=\"{name}\"];").unwrap(),
+                None => writeln!(dot, "  v{label} [label=\"{name}\", style=dashed];").unwrap(),
+            }
+        }
+        for (sub, sup) in self.subtype_of.iter().sorted_unstable() {
+            writeln!(dot, "  v{sub} -> v{sup} [style=dotted, arrowhead=empty];").unwrap();
+        }
+        for e in self.edges.iter().sorted_unstable_by_key(|e| e.label) {
+            let name = escape(self.get_edge_label_name(e.label).unwrap());
+            writeln!(
+                dot,
+                "  v{} -> v{} [label=\"{name}\\n{:?}\"];",
+                e.from, e.to, e.card
+            )
+            .unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Return the vertex labels reachable from `start` by following edges in their direction,
+    /// including `start` itself, i.e., the end labels of the directed paths from `start`. The set
+    /// is empty if the vertex label does not exist.
+    pub fn reachable_labels(&self, start: LabelId) -> HashSet<LabelId> {
+        let mut visited = HashSet::new();
+        if self.get_vertex(start).is_none() {
+            return visited;
+        }
+        let mut stack = vec![start];
+        visited.insert(start);
+        while let Some(u) = stack.pop() {
+            for e in self.outgoing_edges(u).unwrap() {
+                if visited.insert(e.to) {
+                    stack.push(e.to);
+                }
+            }
+        }
+        visited
+    }
+
     fn weak_connected_components(&self) -> Vec<Vec<LabelId>> {
         if self.vertices.is_empty() {
             return vec![];
@@ -275,6 +539,7 @@ mod tests {
     use maplit::hashset;
 
     use super::*;
+    use crate::test_utils::{build_ldbc_schema, build_ldbc_schema_with_messages};
 
     fn build_test_schema() -> Schema {
         SchemaUnchecked::default()
@@ -321,6 +586,162 @@ mod tests {
         assert_eq!(edges, expected);
     }
 
+    #[test]
+    fn test_incident_edge_labels() {
+        let schema = build_ldbc_schema();
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let label = |name| schema.get_edge_label_id(name).unwrap();
+        let labels = schema.incident_edge_labels(person).collect_vec();
+        assert!(labels.iter().all_unique());
+        let num_edges = schema.outgoing_edges(person).unwrap().count()
+            + schema.incoming_edges(person).unwrap().count();
+        assert_eq!(labels.len(), num_edges);
+        assert!(labels.contains(&(label("Person_knows_Person"), EdgeDirection::Out)));
+        assert!(labels.contains(&(label("Person_knows_Person"), EdgeDirection::In)));
+        assert!(labels.contains(&(label("Comment_hasCreator_Person"), EdgeDirection::In)));
+        assert!(!labels.contains(&(label("Comment_hasCreator_Person"), EdgeDirection::Out)));
+        assert_eq!(schema.incident_edge_labels(LabelId::MAX).count(), 0);
+    }
+
+    #[test]
+    fn test_reachable_labels() {
+        // (person)-[:knows]->(person)-[:isLocatedIn]->(city)-[:isPartOf]->(country)
+        let schema = build_test_schema();
+        assert_eq!(schema.reachable_labels(0), hashset! {0, 1, 2});
+        assert_eq!(schema.reachable_labels(1), hashset! {1, 2});
+        assert_eq!(schema.reachable_labels(2), hashset! {2});
+        assert!(schema.reachable_labels(3).is_empty());
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let schema: Schema = SchemaUnchecked::default()
+            .add_vertex_label("person".into(), 0)
+            .add_vertex_label("city".into(), 1)
+            .add_vertex_label("place".into(), 2)
+            .add_edge_label("knows".into(), 0)
+            .add_edge_label("livesIn".into(), 1)
+            .add_edge_label("worksIn".into(), 2)
+            .add_vertex((0, false))
+            .add_vertex((1, true))
+            .add_edge((0, 0, 0, EdgeCardinality::ManyToMany))
+            .add_edge((0, 1, 1, EdgeCardinality::ManyToOne))
+            .add_edge((0, 1, 2, EdgeCardinality::ManyToOne))
+            .add_subtype(1, 2)
+            .try_into()
+            .unwrap();
+        let expected = "digraph schema {
+  v0 [label=\"person\"];
+  v1 [label=\"city\\n(discrete)\"];
+  v2 [label=\"place\", style=dashed];
+  v1 -> v2 [style=dotted, arrowhead=empty];
+  v0 -> v0 [label=\"knows\\nManyToMany\"];
+  v0 -> v1 [label=\"livesIn\\nManyToOne\"];
+  v0 -> v1 [label=\"worksIn\\nManyToOne\"];
+}
+";
+        assert_eq!(schema.to_dot(), expected);
+    }
+
+    #[test]
+    fn test_label_hierarchy() {
+        let schema = build_ldbc_schema_with_messages();
+        let vertex = |name| schema.get_vertex_label_id(name).unwrap();
+        let edge = |name| schema.get_edge_label_id(name).unwrap();
+        let (message, comment, post) = (vertex("Message"), vertex("Comment"), vertex("Post"));
+        assert!(schema.is_abstract(message));
+        assert!(!schema.is_abstract(comment));
+        assert_eq!(schema.get_supertype(post), Some(message));
+        assert_eq!(schema.get_supertype(message), None);
+        assert_eq!(
+            schema.concrete_subtypes(message),
+            [comment, post].into_iter().sorted().collect_vec()
+        );
+        assert_eq!(schema.concrete_subtypes(post), [post]);
+        assert_eq!(
+            schema.get_edge_supertype(edge("Post_hasCreator_Person")),
+            Some(edge("hasCreator"))
+        );
+        assert_eq!(schema.concrete_edge_subtypes(edge("hasCreator")).len(), 2);
+
+        // (p1: Person)-[:likes]->(m: Message)-[:hasCreator]->(p2: Person)
+        let person = vertex("Person");
+        let pattern = RawPattern::with_vertices_edges(
+            [(0, person), (1, message), (2, person)],
+            [(0, 0, 1, edge("likes")), (1, 1, 2, edge("hasCreator"))],
+        )
+        .to_general()
+        .unwrap();
+        let expanded = schema.expand_abstract_labels(&pattern).unwrap();
+        let expected = [
+            (
+                "Comment",
+                "Person_likes_Comment",
+                "Comment_hasCreator_Person",
+            ),
+            ("Post", "Person_likes_Post", "Post_hasCreator_Person"),
+        ]
+        .map(|(m, likes, has_creator)| {
+            RawPattern::with_vertices_edges(
+                [(0, person), (1, vertex(m)), (2, person)],
+                [(0, 0, 1, edge(likes)), (1, 1, 2, edge(has_creator))],
+            )
+            .to_general()
+            .unwrap()
+        });
+        assert_eq!(expanded.len(), 2);
+        assert!(expected.iter().all(|p| expanded.contains(p)));
+
+        // A concrete pattern expands to itself.
+        let concrete = &expected[0];
+        assert_eq!(
+            schema.expand_abstract_labels(concrete).unwrap(),
+            [concrete.clone()]
+        );
+        // (m: Message)
+        let pattern = RawPattern::with_vertices_edges([(0, message)], Vec::<PatternEdge>::new())
+            .to_general()
+            .unwrap();
+        assert_eq!(schema.expand_abstract_labels(&pattern).unwrap().len(), 2);
+
+        let json = serde_json::to_string(&schema).unwrap();
+        assert_eq!(serde_json::from_str::<Schema>(&json).unwrap(), schema);
+        assert!(!serde_json::to_string(&build_ldbc_schema())
+            .unwrap()
+            .contains("subtype_of"));
+    }
+
+    #[test]
+    fn test_invalid_label_hierarchy() {
+        let schema = build_test_schema();
+        let unchecked = SchemaUnchecked::from(schema)
+            .add_vertex_label("place".into(), 3)
+            .add_vertex_label("thing".into(), 4);
+        let result = Schema::try_from(unchecked.clone().add_subtype(1, 0));
+        assert!(matches!(
+            result,
+            Err(GCardError::Schema(SchemaError::ConcreteSupertype(0)))
+        ));
+        let result = Schema::try_from(unchecked.clone().add_subtype(1, 5));
+        assert!(matches!(
+            result,
+            Err(GCardError::Schema(SchemaError::UnknownVertexLabel(5)))
+        ));
+        let result = Schema::try_from(unchecked.clone().add_subtype(3, 4).add_subtype(4, 3));
+        assert!(matches!(
+            result,
+            Err(GCardError::Schema(SchemaError::CyclicSubtype(_)))
+        ));
+        let schema = Schema::try_from(
+            unchecked
+                .add_subtype(1, 3)
+                .add_subtype(2, 3)
+                .add_subtype(3, 4),
+        )
+        .unwrap();
+        assert_eq!(schema.concrete_subtypes(4), [1, 2]);
+    }
+
     #[test]
     fn test_serde() {
         let schema = build_test_schema();
@@ -6,12 +6,14 @@ use std::marker::PhantomData;
 
 use ahash::{HashSet, HashSetExt};
 use itertools::Itertools;
+use log::warn;
 use ptree::{write_tree, TreeItem};
 
 use super::Schema;
-use crate::common::{EdgeCardinality, LabelId, TagId};
+use crate::common::{EdgeCardinality, EdgeDirection, LabelId, TagId};
 use crate::pattern::{
-    GeneralPattern, GraphPattern, PathPattern, PatternEdge, PatternVertex, RawPattern,
+    EncodedPattern, GeneralPattern, GraphPattern, PathPattern, PatternEdge, PatternVertex,
+    RawPattern,
 };
 
 #[derive(Debug, Clone)]
@@ -141,6 +143,24 @@ impl PathFamily {
         self.get_node(0).expect("path family must have a root node")
     }
 
+    /// Return the paths in the family whose start or end vertex has the given label.
+    pub fn paths_with_endpoint_label(&self, label_id: LabelId) -> Vec<&PathPattern> {
+        self.nodes
+            .iter()
+            .map(|node| &node.path)
+            .filter(|path| path.start().label_id() == label_id || path.end().label_id() == label_id)
+            .collect()
+    }
+
+    /// Return the paths in the family with `len` edges.
+    pub fn paths_of_length(&self, len: usize) -> Vec<&PathPattern> {
+        self.nodes
+            .iter()
+            .map(|node| &node.path)
+            .filter(|path| path.len() == len)
+            .collect()
+    }
+
     pub fn left_iter(&self) -> Iter<LeftIter> {
         Iter {
             family: self,
@@ -276,11 +296,17 @@ impl<'a> Iterator for Iter<'a, RightIter> {
     }
 }
 
+/// The maximum length of the paths in a path family, regardless of the given limit.
+const MAX_PATH_FAMILY_LENGTH: usize = 16;
+
 struct PathFamilyGenerateState {
     family: PathFamily,
     current_node_id: usize,
     raw: RawPattern,
     label_count: HashMap<LabelId, usize>,
+    /// The edges of the schema traversed by the current path as `(src label, dst label, edge
+    /// label)`, except self loops, which are not traversed again.
+    schema_edges: Vec<(LabelId, LabelId, LabelId)>,
     repeated_label_limit: usize,
     limit: usize,
 }
@@ -301,13 +327,20 @@ impl PathFamilyGenerateState {
     }
 }
 
+/// Whether an edge of cardinality `card` is functional in either direction. Paths generated
+/// without many-to-one edges skip them, since path families cover the functional extensions.
+fn is_functional_either_way(card: EdgeCardinality) -> bool {
+    card.is_functional(EdgeDirection::Out) || card.is_functional(EdgeDirection::In)
+}
+
 impl Schema {
     fn generate_paths_from_vertex_inner(
         &self,
         vertex_label: LabelId,
         length: usize,
         with_many_to_one: bool,
-    ) -> BTreeMap<Vec<u8>, PathPattern> {
+        discrete_as_endpoints: bool,
+    ) -> BTreeMap<EncodedPattern, PathPattern> {
         let mut paths = BTreeMap::new();
         let mut queue = VecDeque::new();
         queue.push_back(
@@ -322,18 +355,15 @@ impl Schema {
                 continue;
             }
             let end = path.end();
+            // Extending the path would make its end an intermediate vertex
+            if discrete_as_endpoints && !path.is_empty() && self.is_discrete(end.label_id()) {
+                continue;
+            }
             let mut raw = RawPattern::from(path);
             let next_vertex_tag_id = raw.next_vertex_tag_id();
             let next_edge_tag_id = raw.next_edge_tag_id();
             for e in self.outgoing_edges(end.label_id()).unwrap() {
-                if !with_many_to_one
-                    && matches!(
-                        e.card,
-                        EdgeCardinality::OneToOne
-                            | EdgeCardinality::ManyToOne
-                            | EdgeCardinality::OneToMany
-                    )
-                {
+                if !with_many_to_one && is_functional_either_way(e.card) {
                     continue;
                 }
                 let path = raw
@@ -345,14 +375,7 @@ impl Schema {
                 raw.pop_back_edge().pop_back_vertex();
             }
             for e in self.incoming_edges(end.label_id()).unwrap() {
-                if !with_many_to_one
-                    && matches!(
-                        e.card,
-                        EdgeCardinality::OneToOne
-                            | EdgeCardinality::ManyToOne
-                            | EdgeCardinality::OneToMany
-                    )
-                {
+                if !with_many_to_one && is_functional_either_way(e.card) {
                     continue;
                 }
                 let path = raw
@@ -372,15 +395,27 @@ impl Schema {
         vertex_label: LabelId,
         length: usize,
     ) -> Vec<PathPattern> {
-        self.generate_paths_from_vertex_inner(vertex_label, length, true)
+        self.generate_paths_from_vertex_inner(vertex_label, length, true, false)
             .into_values()
             .collect()
     }
 
-    fn generate_paths_inner(&self, length: usize, with_many_to_one: bool) -> Vec<PathPattern> {
+    fn generate_paths_inner(
+        &self,
+        length: usize,
+        with_many_to_one: bool,
+        discrete_as_endpoints: bool,
+    ) -> Vec<PathPattern> {
         self.vertices()
             .iter()
-            .map(|v| self.generate_paths_from_vertex_inner(v.label, length, with_many_to_one))
+            .map(|v| {
+                self.generate_paths_from_vertex_inner(
+                    v.label,
+                    length,
+                    with_many_to_one,
+                    discrete_as_endpoints,
+                )
+            })
             .reduce(|mut a, b| {
                 a.extend(b);
                 a
@@ -391,46 +426,78 @@ impl Schema {
     }
 
     pub fn generate_paths(&self, length: usize) -> Vec<PathPattern> {
-        self.generate_paths_inner(length, true)
+        self.generate_paths_inner(length, true, false)
+    }
+
+    /// Generate paths in which discrete vertices only appear as endpoints.
+    pub fn generate_paths_discrete_as_endpoints(&self, length: usize) -> Vec<PathPattern> {
+        self.generate_paths_inner(length, true, true)
     }
 
     pub fn generate_stars(&self, degree: usize) -> Vec<GeneralPattern> {
+        self.generate_stars_inner(degree, None, false)
+    }
+
+    /// Generate stars in which discrete vertices only appear as leaves, i.e., stars of degree 2 or
+    /// more are not centered at discrete vertices.
+    pub fn generate_stars_discrete_as_leaves(&self, degree: usize) -> Vec<GeneralPattern> {
+        self.generate_stars_inner(degree, None, true)
+    }
+
+    fn is_discrete(&self, vertex_label: LabelId) -> bool {
+        self.get_vertex(vertex_label).unwrap().discrete
+    }
+
+    /// Generate stars whose edges all have the given direction relative to the center.
+    pub fn generate_stars_directed(
+        &self,
+        degree: usize,
+        direction: EdgeDirection,
+    ) -> Vec<GeneralPattern> {
+        self.generate_stars_inner(degree, Some(direction), false)
+    }
+
+    fn generate_stars_inner(
+        &self,
+        degree: usize,
+        direction: Option<EdgeDirection>,
+        discrete_as_leaves: bool,
+    ) -> Vec<GeneralPattern> {
         if degree == 0 {
             return vec![];
         }
         let mut stars = BTreeMap::new();
         for v in self.vertices() {
-            for comb in self
+            if discrete_as_leaves && degree > 1 && v.discrete {
+                continue;
+            }
+            let outgoing_edges = self
                 .outgoing_edges(v.label)
                 .unwrap()
-                .chain(self.incoming_edges(v.label).unwrap())
-                .combinations(degree)
-            {
+                .map(|e| (e, EdgeDirection::Out));
+            let incoming_edges = self
+                .incoming_edges(v.label)
+                .unwrap()
+                .map(|e| (e, EdgeDirection::In));
+            let edges = outgoing_edges
+                .chain(incoming_edges)
+                .filter(|(_, d)| direction.map_or(true, |direction| *d == direction));
+            for comb in edges.combinations(degree) {
                 let mut raw = RawPattern::new();
                 let center_tag_id = raw.next_vertex_tag_id();
                 raw.push_back_vertex((center_tag_id, v.label));
-                for e in comb {
-                    // Outgoing edge
-                    if e.from == v.label {
-                        let nbr_tag_id = raw.next_vertex_tag_id();
-                        raw.push_back_vertex((nbr_tag_id, e.to));
-                        raw.push_back_edge((
-                            raw.next_edge_tag_id(),
-                            center_tag_id,
-                            nbr_tag_id,
-                            e.label,
-                        ));
-                    }
-                    // Incoming edge
-                    else {
-                        let nbr_tag_id = raw.next_vertex_tag_id();
-                        raw.push_back_vertex((nbr_tag_id, e.from));
-                        raw.push_back_edge((
-                            raw.next_edge_tag_id(),
-                            nbr_tag_id,
-                            center_tag_id,
-                            e.label,
-                        ));
+                for (e, d) in comb {
+                    let nbr_tag_id = raw.next_vertex_tag_id();
+                    let edge_tag_id = raw.next_edge_tag_id();
+                    match d {
+                        EdgeDirection::Out => {
+                            raw.push_back_vertex((nbr_tag_id, e.to));
+                            raw.push_back_edge((edge_tag_id, center_tag_id, nbr_tag_id, e.label));
+                        }
+                        EdgeDirection::In => {
+                            raw.push_back_vertex((nbr_tag_id, e.from));
+                            raw.push_back_edge((edge_tag_id, nbr_tag_id, center_tag_id, e.label));
+                        }
                     }
                 }
                 let star = raw.to_general().unwrap();
@@ -440,7 +507,20 @@ impl Schema {
         stars.into_values().collect()
     }
 
+    /// Generate cycles by closing paths of length `length - 1`. The edges of a cycle may have
+    /// arbitrary directions, e.g., `(a)->(b)<-(c)->(a)` is a 3-cycle.
     pub fn generate_cycles(&self, length: usize) -> Vec<GeneralPattern> {
+        self.generate_cycles_inner(length, false)
+    }
+
+    /// Generate cycles whose edges are consistently oriented, e.g., `(a)->(b)->(c)->(a)`. Unlike
+    /// [`Schema::generate_cycles`], only paths with a single direction are closed, and only by an
+    /// edge continuing that direction.
+    pub fn generate_directed_cycles(&self, length: usize) -> Vec<GeneralPattern> {
+        self.generate_cycles_inner(length, true)
+    }
+
+    fn generate_cycles_inner(&self, length: usize, directed: bool) -> Vec<GeneralPattern> {
         if length == 0 {
             return vec![];
         }
@@ -450,32 +530,28 @@ impl Schema {
         for p in paths {
             let start = p.start();
             let end = p.end();
-            for e in self
+            // Whether the path can be closed by `start -> end` or `end -> start`, respectively.
+            let (close_out, close_in) = if directed {
+                let all_in = p.directions().iter().all(|&d| d == EdgeDirection::In);
+                (all_in, p.is_single_direction())
+            } else {
+                (true, true)
+            };
+            let outgoing = self
                 .outgoing_edges(start.label_id())
                 .unwrap()
-                .filter(|e| e.to == end.label_id())
-            {
-                let mut raw = RawPattern::from(&p);
-                let next_edge_tag_id = raw.next_edge_tag_id();
-                let cycle = raw
-                    .push_back_edge((next_edge_tag_id, start.tag_id(), end.tag_id(), e.label))
-                    .to_general()
-                    .unwrap();
-                let code = cycle.encode();
-                if !cycle_set.contains(&code) {
-                    cycles.push(cycle);
-                    cycle_set.insert(code);
-                }
-            }
-            for e in self
+                .filter(|e| close_out && e.to == end.label_id())
+                .map(|e| (start.tag_id(), end.tag_id(), e.label));
+            let incoming = self
                 .incoming_edges(start.label_id())
                 .unwrap()
-                .filter(|e| e.from == end.label_id())
-            {
+                .filter(|e| close_in && e.from == end.label_id())
+                .map(|e| (end.tag_id(), start.tag_id(), e.label));
+            for (src, dst, label) in outgoing.chain(incoming) {
                 let mut raw = RawPattern::from(&p);
                 let next_edge_tag_id = raw.next_edge_tag_id();
                 let cycle = raw
-                    .push_back_edge((next_edge_tag_id, end.tag_id(), start.tag_id(), e.label))
+                    .push_back_edge((next_edge_tag_id, src, dst, label))
                     .to_general()
                     .unwrap();
                 let code = cycle.encode();
@@ -488,16 +564,113 @@ impl Schema {
         cycles
     }
 
+    /// Generate all connected patterns with at most `max_edges` edges admitted by the schema, by
+    /// growing patterns edge by edge. At most `limit` patterns are generated, and a warning is
+    /// reported if the result is truncated.
+    pub fn generate_patterns(&self, max_edges: usize, limit: usize) -> Vec<GeneralPattern> {
+        let mut patterns = Vec::new();
+        let mut pattern_set = HashSet::new();
+        let mut frontier = self
+            .vertices()
+            .iter()
+            .map(|v| {
+                RawPattern::new()
+                    .push_back_vertex((0, v.label))
+                    .to_general()
+                    .unwrap()
+            })
+            .collect_vec();
+        for _ in 0..max_edges {
+            let mut next_frontier = Vec::new();
+            for p in &frontier {
+                for next in self.extend_pattern(p) {
+                    let code = next.encode();
+                    if pattern_set.contains(&code) {
+                        continue;
+                    }
+                    if patterns.len() >= limit {
+                        warn!("pattern generation is truncated at {limit} patterns");
+                        return patterns;
+                    }
+                    pattern_set.insert(code);
+                    patterns.push(next.clone());
+                    next_frontier.push(next);
+                }
+            }
+            frontier = next_frontier;
+        }
+        patterns
+    }
+
+    /// Extend `pattern` by one edge, either to a new vertex or between two existing vertices.
+    fn extend_pattern(&self, pattern: &GeneralPattern) -> Vec<GeneralPattern> {
+        let mut extended = Vec::new();
+        let push_edge =
+            |src: TagId, dst: TagId, label: LabelId, new_vertex: Option<PatternVertex>| {
+                let mut raw = RawPattern::from(pattern);
+                if let Some(v) = new_vertex {
+                    raw.push_back_vertex(v);
+                }
+                let edge_tag_id = raw.next_edge_tag_id();
+                raw.push_back_edge((edge_tag_id, src, dst, label))
+                    .to_general()
+                    .unwrap()
+            };
+        let next_vertex_tag_id = RawPattern::from(pattern).next_vertex_tag_id();
+        for v in pattern.vertices() {
+            for e in self.outgoing_edges(v.label_id()).unwrap() {
+                let new_vertex = PatternVertex::new(next_vertex_tag_id, e.to);
+                extended.push(push_edge(
+                    v.tag_id(),
+                    next_vertex_tag_id,
+                    e.label,
+                    Some(new_vertex),
+                ));
+                for u in pattern.vertices().iter().filter(|u| u.label_id() == e.to) {
+                    // Skip parallel edges with the same label
+                    let exists = pattern.edges().iter().any(|edge| {
+                        edge.src() == v.tag_id()
+                            && edge.dst() == u.tag_id()
+                            && edge.label_id() == e.label
+                    });
+                    if !exists {
+                        extended.push(push_edge(v.tag_id(), u.tag_id(), e.label, None));
+                    }
+                }
+            }
+            for e in self.incoming_edges(v.label_id()).unwrap() {
+                let new_vertex = PatternVertex::new(next_vertex_tag_id, e.from);
+                extended.push(push_edge(
+                    next_vertex_tag_id,
+                    v.tag_id(),
+                    e.label,
+                    Some(new_vertex),
+                ));
+            }
+        }
+        extended
+    }
+
     pub fn generate_paths_without_many_to_one(&self, length: usize) -> Vec<PathPattern> {
-        self.generate_paths_inner(length, false)
+        self.generate_paths_inner(length, false, false)
     }
 
+    /// Generate the family of `path` by extending its endpoints along functional (ManyToOne or
+    /// OneToOne) edges, up to `limit` edges (capped at [`MAX_PATH_FAMILY_LENGTH`]) and at most
+    /// `repeated_label_limit` edges per label.
+    ///
+    /// An extension never traverses an edge of the schema already in the path, except a self loop
+    /// like `Comment_replyOf_Comment`, whose chains are bounded by `repeated_label_limit` alone. So
+    /// a cycle of functional edges over distinct labels is followed at most once instead of over
+    /// and over, while a vertex label may still be revisited through other edges. Nor does an
+    /// extension go right back along the edge it came from, which would reach the same vertex.
     pub fn generate_path_family_from_path(
         &self,
         path: &PathPattern,
         repeated_label_limit: usize,
         limit: usize,
     ) -> PathFamily {
+        let limit = limit.min(MAX_PATH_FAMILY_LENGTH);
         let family = PathFamily::new(path.clone());
         if path.len() >= limit {
             return family;
@@ -512,6 +685,16 @@ impl Schema {
             current_node_id: 0,
             raw,
             label_count,
+            schema_edges: path
+                .edges()
+                .iter()
+                .map(|e| {
+                    let src = path.get_vertex(e.src()).unwrap().label_id();
+                    let dst = path.get_vertex(e.dst()).unwrap().label_id();
+                    (src, dst, e.label_id())
+                })
+                .filter(|(src, dst, _)| src != dst)
+                .collect(),
             repeated_label_limit,
             limit,
         };
@@ -536,23 +719,32 @@ impl Schema {
         let current_path = current_node.path();
         let current_path_start = current_path.start();
         let current_path_end = current_path.end();
-        let current_vertex_label = if FROM_END {
-            current_path_end.label_id()
+        let (current_vertex, last_edge) = if FROM_END {
+            (current_path_end, current_path.edges().last().copied())
         } else {
-            current_path_start.label_id()
+            (current_path_start, current_path.edges().first().copied())
         };
+        let current_vertex_label = current_vertex.label_id();
+        // Going back along the last edge in the other direction, which is functional, would only
+        // reach the previous vertex again.
+        let backtrack = last_edge.map(|e| {
+            if e.src() == current_vertex.tag_id() {
+                (e.label_id(), EdgeDirection::Out)
+            } else {
+                (e.label_id(), EdgeDirection::In)
+            }
+        });
         for e in self
             .outgoing_edges(current_vertex_label)
             .unwrap()
-            .filter(|e| {
-                matches!(
-                    e.card,
-                    EdgeCardinality::ManyToOne | EdgeCardinality::OneToOne
-                )
-            })
+            .filter(|e| e.card.is_functional(EdgeDirection::Out))
         {
             let current_label_count = state.label_count.get(&e.label).copied().unwrap_or_default();
-            if current_label_count == state.repeated_label_limit {
+            let schema_edge = (e.from, e.to, e.label);
+            if current_label_count == state.repeated_label_limit
+                || state.schema_edges.contains(&schema_edge)
+                || backtrack == Some((e.label, EdgeDirection::Out))
+            {
                 continue;
             }
             let next_vertex_label = e.to;
@@ -586,8 +778,14 @@ impl Schema {
                 state.family.add_left_node(path, current_node_id)
             };
             *state.label_count.entry(e.label).or_default() += 1;
+            if e.from != e.to {
+                state.schema_edges.push(schema_edge);
+            }
             state.current_node_id = current_node_id;
             self.generate_path_family_from_path_recursive::<FROM_END>(state);
+            if e.from != e.to {
+                state.schema_edges.pop();
+            }
             *state.label_count.get_mut(&e.label).unwrap() -= 1;
             if FROM_END {
                 state.raw.pop_back_edge().pop_back_vertex();
@@ -598,15 +796,14 @@ impl Schema {
         for e in self
             .incoming_edges(current_vertex_label)
             .unwrap()
-            .filter(|e| {
-                matches!(
-                    e.card,
-                    EdgeCardinality::OneToMany | EdgeCardinality::OneToOne
-                )
-            })
+            .filter(|e| e.card.is_functional(EdgeDirection::In))
         {
             let current_label_count = state.label_count.get(&e.label).copied().unwrap_or_default();
-            if current_label_count == state.repeated_label_limit {
+            let schema_edge = (e.from, e.to, e.label);
+            if current_label_count == state.repeated_label_limit
+                || state.schema_edges.contains(&schema_edge)
+                || backtrack == Some((e.label, EdgeDirection::In))
+            {
                 continue;
             }
             let next_vertex_label = e.from;
@@ -640,8 +837,14 @@ impl Schema {
                 state.family.add_left_node(path, current_node_id)
             };
             *state.label_count.entry(e.label).or_default() += 1;
+            if e.from != e.to {
+                state.schema_edges.push(schema_edge);
+            }
             state.current_node_id = current_node_id;
             self.generate_path_family_from_path_recursive::<FROM_END>(state);
+            if e.from != e.to {
+                state.schema_edges.pop();
+            }
             *state.label_count.get_mut(&e.label).unwrap() -= 1;
             if FROM_END {
                 state.raw.pop_back_edge().pop_back_vertex();
@@ -666,6 +869,89 @@ mod tests {
         assert_eq!(schema.generate_paths(3).len(), 1021);
     }
 
+    #[test]
+    fn test_generate_stars_self_loop() {
+        let schema = build_ldbc_schema();
+        let person = schema.get_vertex_label_id("Person").unwrap();
+        let knows = schema.get_edge_label_id("Person_knows_Person").unwrap();
+        let knows_stars = schema
+            .generate_stars(2)
+            .into_iter()
+            .filter(|star| star.edges().iter().all(|e| e.label_id() == knows))
+            .map(|star| star.encode())
+            .collect_vec();
+        let star = |edges| {
+            RawPattern::with_vertices_edges([(0, person), (1, person), (2, person)], edges)
+                .to_general()
+                .unwrap()
+                .encode()
+        };
+        // The incoming leg of knows used to be built as a second outgoing leg, which gave the star
+        // with two outgoing knows legs instead.
+        assert_eq!(knows_stars, [star([(0, 0, 1, knows), (1, 2, 0, knows)])]);
+    }
+
+    #[test]
+    fn test_generate_stars_directed() {
+        let schema = build_ldbc_schema();
+        let counts = [(1, 25, 25, 25), (2, 136, 40, 28), (3, 301, 50, 19)];
+        for (degree, mixed, out, inc) in counts {
+            let out_stars = schema.generate_stars_directed(degree, EdgeDirection::Out);
+            let in_stars = schema.generate_stars_directed(degree, EdgeDirection::In);
+            assert_eq!(schema.generate_stars(degree).len(), mixed);
+            assert_eq!(out_stars.len(), out);
+            assert_eq!(in_stars.len(), inc);
+            assert!(out_stars.iter().all(|star| star
+                .vertices()
+                .iter()
+                .any(|v| star.get_vertex_out_degree(v.tag_id()) == Some(degree))));
+            assert!(in_stars.iter().all(|star| star
+                .vertices()
+                .iter()
+                .any(|v| star.get_vertex_in_degree(v.tag_id()) == Some(degree))));
+        }
+    }
+
+    #[test]
+    fn test_generate_directed_cycles() {
+        let schema = build_ldbc_schema();
+        let cycles = schema.generate_cycles(3);
+        let directed_cycles = schema.generate_directed_cycles(3);
+        assert_eq!(cycles.len(), 48);
+        assert_eq!(directed_cycles.len(), 7);
+        assert!(directed_cycles
+            .iter()
+            .all(|cycle| cycle.vertices().iter().all(|v| {
+                cycle.get_vertex_out_degree(v.tag_id()) == Some(1)
+                    && cycle.get_vertex_in_degree(v.tag_id()) == Some(1)
+            })));
+        let codes: HashSet<_> = cycles.iter().map(|cycle| cycle.encode()).collect();
+        assert!(directed_cycles
+            .iter()
+            .all(|cycle| codes.contains(&cycle.encode())));
+    }
+
+    #[test]
+    fn test_generate_patterns() {
+        let schema = build_ldbc_schema();
+        let patterns = schema.generate_patterns(2, usize::MAX);
+        let count = |num_edges: usize, acyclic: bool| {
+            patterns
+                .iter()
+                .filter(|p| p.edges().len() == num_edges)
+                .filter(|p| !acyclic || p.vertices().len() == num_edges + 1)
+                .count()
+        };
+        assert_eq!(count(1, true), schema.generate_paths(1).len());
+        assert_eq!(count(2, true), schema.generate_paths(2).len());
+        assert!(patterns.iter().all(|p| RawPattern::from(p).is_connected()));
+        let codes: HashSet<_> = patterns.iter().map(|p| p.encode()).collect();
+        assert_eq!(codes.len(), patterns.len());
+
+        let truncated = schema.generate_patterns(2, 10);
+        assert_eq!(truncated.len(), 10);
+    }
+
     #[test]
     fn test_generate_path_family() {
         let schema = build_ldbc_schema();
@@ -679,4 +965,163 @@ mod tests {
         assert_eq!(family.len(), 7);
         println!("{}", family)
     }
+
+    #[test]
+    fn test_generate_path_family_one_to_one_cycle() {
+        use crate::schema::SchemaUnchecked;
+
+        // A cycle of 6 vertex labels connected by OneToOne edges, and a OneToOne self loop.
+        let n = 6;
+        let mut schema = SchemaUnchecked::default();
+        for i in 0..n {
+            schema = schema
+                .add_vertex_label(format!("v{i}"), i)
+                .add_edge_label(format!("e{i}"), i)
+                .add_vertex((i, false))
+                .add_edge((i, (i + 1) % n, i, EdgeCardinality::OneToOne));
+        }
+        let schema: Schema = schema
+            .add_edge_label("self".into(), n)
+            .add_edge((0, 0, n, EdgeCardinality::OneToOne))
+            .try_into()
+            .unwrap();
+        let path = RawPattern::new()
+            .push_back_vertex((0, 0))
+            .push_back_vertex((1, 1))
+            .push_back_edge((0, 0, 1, 0))
+            .to_path()
+            .unwrap();
+        let family = schema.generate_path_family_from_path(&path, usize::MAX, usize::MAX);
+        let paths = (0..=MAX_PATH_FAMILY_LENGTH)
+            .flat_map(|len| family.paths_of_length(len))
+            .collect_vec();
+        assert_eq!(paths.len(), family.len());
+        assert_eq!(family.len(), 181);
+        // The cycle is followed at most once, and the self loop is only bounded by the length.
+        assert!(paths.iter().all(|p| p
+            .edges()
+            .iter()
+            .map(|e| e.label_id())
+            .filter(|l| *l < n)
+            .all_unique()));
+        assert!(!family.paths_of_length(MAX_PATH_FAMILY_LENGTH).is_empty());
+    }
+
+    #[test]
+    fn test_generate_path_family_self_loop() {
+        let schema = build_ldbc_schema();
+        let reply_of = schema.get_edge_label_id("Comment_replyOf_Comment").unwrap();
+        // (:Person)-[:likes]->(:Comment)
+        let path = RawPattern::with_vertices_edges([(0, 6), (1, 1)], [(0, 0, 1, 15)])
+            .to_path()
+            .unwrap();
+        for repeated_label_limit in 1..=3 {
+            let family = schema.generate_path_family_from_path(&path, repeated_label_limit, 5);
+            let max_reply_of = (0..=5)
+                .flat_map(|len| family.paths_of_length(len))
+                .map(|p| {
+                    p.edges()
+                        .iter()
+                        .filter(|e| e.label_id() == reply_of)
+                        .count()
+                })
+                .max();
+            assert_eq!(max_reply_of, Some(repeated_label_limit));
+        }
+        // The creator of the post replied to is a Person again, reached by a different edge.
+        let family = schema.generate_path_family_from_path(&path, 1, 3);
+        let labels = |p: &PathPattern| p.edges().iter().map(|e| e.label_id()).collect_vec();
+        assert!(family
+            .paths_of_length(3)
+            .into_iter()
+            .any(|p| labels(p) == [15, 5, 19] || labels(p) == [19, 5, 15]));
+    }
+
+    #[test]
+    fn test_path_family_queries() {
+        let schema = build_ldbc_schema();
+        let path = RawPattern::new()
+            .push_back_vertex((0, 6))
+            .push_back_vertex((1, 6))
+            .push_back_edge((0, 0, 1, 14))
+            .to_path()
+            .unwrap();
+        let family = schema.generate_path_family_from_path(&path, 2, 5);
+        let node_path = |id: usize| &family.nodes[id].path;
+        let total: usize = (0..=5).map(|len| family.paths_of_length(len).len()).sum();
+        assert_eq!(total, family.len());
+        assert_eq!(family.paths_of_length(1), vec![node_path(0)]);
+        assert_eq!(family.paths_of_length(2), vec![node_path(1), node_path(4)]);
+        assert!(family.paths_of_length(5).is_empty());
+
+        assert_eq!(family.paths_with_endpoint_label(6).len(), family.len());
+        assert_eq!(
+            family.paths_with_endpoint_label(4),
+            vec![node_path(2), node_path(5)]
+        );
+        assert!(family.paths_with_endpoint_label(LabelId::MAX).is_empty());
+    }
+
+    #[test]
+    fn test_generate_discrete_as_endpoints() {
+        use crate::schema::SchemaUnchecked;
+
+        let (person, gender, comment) = (0, 1, 2);
+        let schema: Schema = SchemaUnchecked::default()
+            .add_vertex_label("Person".into(), person)
+            .add_vertex_label("Gender".into(), gender)
+            .add_vertex_label("Comment".into(), comment)
+            .add_edge_label("Person_knows_Person".into(), 0)
+            .add_edge_label("Person_hasGender_Gender".into(), 1)
+            .add_edge_label("Comment_hasCreator_Person".into(), 2)
+            .add_edge_label("Person_prefersGender_Gender".into(), 3)
+            .add_vertex((person, false))
+            .add_vertex((gender, true))
+            .add_vertex((comment, false))
+            .add_edge((person, person, 0, EdgeCardinality::ManyToMany))
+            .add_edge((person, gender, 1, EdgeCardinality::ManyToOne))
+            .add_edge((comment, person, 2, EdgeCardinality::ManyToOne))
+            .add_edge((person, gender, 3, EdgeCardinality::ManyToMany))
+            .try_into()
+            .unwrap();
+        let has_intermediate_gender = |path: &PathPattern| {
+            let vertices = path.vertices();
+            vertices[1..vertices.len() - 1]
+                .iter()
+                .any(|v| v.label_id() == gender)
+        };
+        for length in 1..=3 {
+            let all_paths = schema.generate_paths(length);
+            let paths = schema.generate_paths_discrete_as_endpoints(length);
+            assert!(length == 1 || all_paths.iter().any(has_intermediate_gender));
+            assert!(!paths.iter().any(has_intermediate_gender));
+            // Only the paths through discrete vertices are pruned
+            let expected = all_paths
+                .iter()
+                .filter(|path| !has_intermediate_gender(path))
+                .map(|path| path.encode())
+                .collect_vec();
+            assert_eq!(
+                paths.iter().map(|path| path.encode()).collect_vec(),
+                expected
+            );
+            assert!(paths.iter().any(|path| [path.start(), path.end()]
+                .iter()
+                .any(|v| v.label_id() == gender)));
+        }
+
+        let stars = schema.generate_stars_discrete_as_leaves(2);
+        assert!(stars.len() < schema.generate_stars(2).len());
+        for star in stars {
+            for v in star.vertices() {
+                if star.get_vertex_degree(v.tag_id()).unwrap() > 1 {
+                    assert_ne!(v.label_id(), gender, "{star}");
+                }
+            }
+        }
+        assert_eq!(
+            schema.generate_stars_discrete_as_leaves(1).len(),
+            schema.generate_stars(1).len()
+        );
+    }
 }
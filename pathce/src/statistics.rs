@@ -3,20 +3,25 @@ use std::ptr;
 use std::sync::{Arc, Mutex, OnceLock};
 use std::time::Instant;
 
-use ahash::{HashMap, HashMapExt};
+use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
 use bimap::BiHashMap;
 use itertools::Itertools;
-use log::{debug, trace};
+use log::{debug, trace, warn};
 use num::PrimInt;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use rayon::iter::{
     IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator,
     IntoParallelRefMutIterator, ParallelIterator,
 };
 use serde::Serialize;
+use tracing::{debug_span, instrument};
 
 use crate::common::{DefaultVertexId, EdgeDirection, GlobalBucketMap, LabelId, TagId};
 use crate::graph::{LabeledGraph, LabeledVertex};
-use crate::pattern::{merge_paths_to_star, GeneralPattern, GraphPattern, PathPattern, RawPattern};
+use crate::pattern::{
+    merge_paths_to_star, EncodedPattern, GeneralPattern, GraphPattern, PathPattern, RawPattern,
+};
 use crate::schema::{PathTreeNodeRef, Schema};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -131,6 +136,16 @@ pub struct PathStatistics {
     pub end_max_degree: Vec<Box<[u64]>>,
 }
 
+/// The counts of a length-2 path by the buckets of its start and end vertices, like the `count` of
+/// [`PathStatistics`] but without the maximum degrees. Joining the statistics of its two edges
+/// assumes that they are independent given the bucket of the middle vertex, which these counts
+/// avoid.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JointStatistics {
+    pub path: PathPattern,
+    pub count: Vec<Box<[u64]>>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct PathStatisticsInner {
     path: PathPattern,
@@ -157,9 +172,113 @@ pub struct StatisticsAnalyzer {
     max_star_length: usize,
     max_star_degree: usize,
     bucket_values: OnceLock<HashMap<LabelId, Vec<Vec<usize>>>>,
+    required: Option<RequiredStatistics>,
+    max_star_combinations: Option<usize>,
+    path_sample: Option<PathSample>,
+    joint_statistics: bool,
+    matrix_rows: OnceLock<HashMap<LabelId, MatrixRows>>,
+}
+
+/// The fractions of the vertices of each label which path statistics are computed from.
+#[derive(Debug, Clone)]
+struct PathSample {
+    fractions: HashMap<LabelId, f64>,
+    seed: u64,
+}
+
+/// The vertices of a label with rows in the per-vertex count matrices of path statistics, which
+/// are all of them unless the label is sampled.
+#[derive(Debug)]
+struct MatrixRows {
+    num_vertices: usize,
+    /// The internal ids of the sampled vertices in ascending order.
+    sampled: Option<Vec<u32>>,
+}
+
+impl MatrixRows {
+    fn len(&self) -> usize {
+        self.sampled
+            .as_ref()
+            .map_or(self.num_vertices, |sampled| sampled.len())
+    }
+
+    fn internal_id(&self, row: usize) -> u32 {
+        self.sampled
+            .as_ref()
+            .map_or(row as u32, |sampled| sampled[row])
+    }
+
+    fn row(&self, internal_id: u32) -> Option<usize> {
+        match &self.sampled {
+            Some(sampled) => sampled.binary_search(&internal_id).ok(),
+            None => Some(internal_id as usize),
+        }
+    }
+
+    /// Return the inverse of the sampled fraction of the vertices.
+    fn scale(&self) -> f64 {
+        if self.len() == 0 {
+            1.0
+        } else {
+            self.num_vertices as f64 / self.len() as f64
+        }
+    }
 }
 
-type StarState = HashMap<LabelId, HashMap<(TagId, Vec<u8>), (PathPattern, CountVec<u64>)>>;
+/// Scale up the counts of sampled vertices, or return them as is if `scale` is 1.
+fn scale_counts(counts: Vec<Box<[u64]>>, scale: f64) -> Vec<Box<[u64]>> {
+    if scale == 1.0 {
+        return counts;
+    }
+    counts
+        .into_iter()
+        .map(|row| {
+            row.iter()
+                .map(|count| (*count as f64 * scale).round() as u64)
+                .collect()
+        })
+        .collect()
+}
+
+/// The paths and stars whose statistics are required, keyed like the results of
+/// [`StatisticsAnalyzer::compute_path_statistics`] and
+/// [`StatisticsAnalyzer::compute_star_statistics`].
+#[derive(Debug, Clone, Default)]
+pub struct RequiredStatistics {
+    pub paths: HashMap<EncodedPattern, PathPattern>,
+    pub stars: HashSet<(TagId, EncodedPattern)>,
+}
+
+impl RequiredStatistics {
+    /// Return the encodings of all the prefixes of the required paths from both ends. A path is
+    /// computed by extending its prefixes, so these are the paths that must be visited.
+    pub(crate) fn path_prefix_codes(&self) -> HashSet<EncodedPattern> {
+        let mut codes = HashSet::new();
+        for path in self.paths.values() {
+            for path in [path.clone(), path.reverse()] {
+                let mut raw = RawPattern::new();
+                let mut current = path.start();
+                raw.push_back_vertex(current);
+                for (e, d) in path.edges().iter().zip_eq(path.directions()) {
+                    let next_tag_id = match d {
+                        EdgeDirection::Out => e.dst(),
+                        EdgeDirection::In => e.src(),
+                    };
+                    current = path.get_vertex(next_tag_id).unwrap();
+                    raw.push_back_vertex(current).push_back_edge(*e);
+                    codes.insert(raw.to_path().unwrap().encode());
+                }
+            }
+        }
+        codes
+    }
+
+    fn contains_star(&self, center_rank: TagId, code: &EncodedPattern) -> bool {
+        self.stars.contains(&(center_rank, code.clone()))
+    }
+}
+
+type StarState = HashMap<LabelId, HashMap<(TagId, EncodedPattern), (PathPattern, CountVec<u64>)>>;
 
 impl StatisticsAnalyzer {
     #[allow(clippy::too_many_arguments)]
@@ -181,14 +300,74 @@ impl StatisticsAnalyzer {
             max_star_length,
             max_star_degree,
             bucket_values: OnceLock::new(),
+            required: None,
+            max_star_combinations: None,
+            path_sample: None,
+            joint_statistics: false,
+            matrix_rows: OnceLock::new(),
+        }
+    }
+
+    /// Compute the path statistics from a uniform sample of the given fraction of the vertices of
+    /// each label in `fractions`, chosen with `seed`, and scale the counts up by the inverse
+    /// fractions. This bounds the per-vertex count matrices of huge labels. A path is counted only
+    /// if all its vertices are sampled, so the counts are unbiased for paths of distinct vertices,
+    /// overestimate paths visiting a vertex twice, and are noisier for rare paths. The maximum
+    /// degrees are taken over the sampled vertices, so they may be underestimated.
+    pub fn sample_path_vertices(mut self, fractions: HashMap<LabelId, f64>, seed: u64) -> Self {
+        assert!(
+            fractions.values().all(|f| *f > 0.0 && *f <= 1.0),
+            "the sampled fractions should be in (0, 1]"
+        );
+        self.path_sample = Some(PathSample { fractions, seed });
+        self
+    }
+
+    /// Compute the statistics of at most `limit` stars per center label and degree. The remaining
+    /// stars are skipped with a warning, which keeps the memory bounded on dense schemas.
+    pub fn max_star_combinations(mut self, limit: usize) -> Self {
+        self.max_star_combinations = Some(limit);
+        self
+    }
+
+    /// Also compute the [`JointStatistics`] of the length-2 paths longer than the maximum path
+    /// length, i.e., when it is 1, which are returned by [`Self::compute_path_statistics`].
+    /// Restricted to required statistics, only the required length-2 paths are computed.
+    pub fn joint_statistics(mut self, enable: bool) -> Self {
+        self.joint_statistics = enable;
+        self
+    }
+
+    /// Return the length of the paths computed by [`Self::compute_path_statistics`].
+    fn path_tree_depth(&self) -> usize {
+        if self.joint_statistics {
+            self.max_path_length.max(2)
+        } else {
+            self.max_path_length
         }
     }
 
-    pub fn compute_star_statistics(&self) -> HashMap<(TagId, Vec<u8>), StarStatistics> {
+    /// Only compute the statistics of the given paths and stars, instead of all the paths and stars
+    /// within the maximum lengths and degree.
+    pub fn restrict_to(mut self, required: RequiredStatistics) -> Self {
+        self.required = Some(required);
+        self
+    }
+
+    /// Whether the statistics of the star are required.
+    fn is_star_required(&self, center_rank: TagId, code: &EncodedPattern) -> bool {
+        self.required
+            .as_ref()
+            .map_or(true, |required| required.contains_star(center_rank, code))
+    }
+
+    #[instrument(skip_all)]
+    pub fn compute_star_statistics(&self) -> HashMap<(TagId, EncodedPattern), StarStatistics> {
         self.compute_bucket_values();
         let mut state = StarState::new();
         // NOTE: We compute star statistics for endpoints of all the k-paths
         for i in 0..=self.max_path_length {
+            let _span = debug_span!("update_star_state", length = i).entered();
             let start = Instant::now();
             self.update_star_state(&mut state, i);
             debug!(
@@ -209,26 +388,7 @@ impl StatisticsAnalyzer {
                 *rank == start_rank
             }));
 
-            // Handle single-vertex path
-            let (vertex_path, count_vec) = current_star_state
-                .values()
-                .find(|(path, _)| path.is_empty())
-                .unwrap();
-            let bucket_values = self.bucket_values.get().unwrap().get(&v.label).unwrap();
-            let vertex_map = self.graph.get_internal_vertex_map(v.label).unwrap();
-            let count = self.summarize_count_for_vec(count_vec, vertex_map, bucket_values);
-            let max_degree =
-                self.summarize_max_degree_for_vec(count_vec, vertex_map, bucket_values);
-            let center_rank = vertex_path.get_vertex_rank(0).unwrap();
-            star_statistics.insert(
-                (center_rank, vertex_path.encode()),
-                StarStatistics {
-                    star: vertex_path.clone().into(),
-                    center_rank,
-                    count,
-                    max_degree,
-                },
-            );
+            self.insert_vertex_star_statistics(v.label, current_star_state, &mut star_statistics);
 
             self.combine_star_states_for_paths(current_star_state, &mut star_statistics);
 
@@ -244,14 +404,63 @@ impl StatisticsAnalyzer {
         star_statistics
     }
 
+    /// Compute only the statistics of the single-vertex stars, which the estimator needs to look up
+    /// vertex labels even without any other star.
+    #[instrument(skip_all)]
+    pub fn compute_vertex_star_statistics(
+        &self,
+    ) -> HashMap<(TagId, EncodedPattern), StarStatistics> {
+        self.compute_bucket_values();
+        let mut state = StarState::new();
+        self.update_star_state(&mut state, 0);
+        let mut star_statistics = HashMap::new();
+        for v in self.schema.vertices() {
+            let current_star_state = state.get(&v.label).unwrap();
+            self.insert_vertex_star_statistics(v.label, current_star_state, &mut star_statistics);
+        }
+        star_statistics
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn insert_vertex_star_statistics(
+        &self,
+        label_id: LabelId,
+        state: &HashMap<(TagId, EncodedPattern), (PathPattern, CountVec<u64>)>,
+        stats: &mut HashMap<(TagId, EncodedPattern), StarStatistics>,
+    ) {
+        // Handle single-vertex path
+        let (vertex_path, count_vec) = state.values().find(|(path, _)| path.is_empty()).unwrap();
+        let center_rank = vertex_path.get_vertex_rank(0).unwrap();
+        if self.is_star_required(center_rank, &vertex_path.encode()) {
+            let bucket_values = self.bucket_values.get().unwrap().get(&label_id).unwrap();
+            let vertex_map = self.graph.get_internal_vertex_map(label_id).unwrap();
+            let count = self.summarize_count_for_vec(count_vec, vertex_map, bucket_values);
+            let max_degree =
+                self.summarize_max_degree_for_vec(count_vec, vertex_map, bucket_values);
+            stats.insert(
+                (center_rank, vertex_path.encode()),
+                StarStatistics {
+                    star: vertex_path.clone().into(),
+                    center_rank,
+                    count,
+                    max_degree,
+                },
+            );
+        }
+    }
+
     #[allow(clippy::type_complexity)]
     fn combine_star_states_for_paths(
         &self,
-        state: &HashMap<(u8, Vec<u8>), (PathPattern, CountVec<u64>)>,
-        stats: &mut HashMap<(TagId, Vec<u8>), StarStatistics>,
+        state: &HashMap<(TagId, EncodedPattern), (PathPattern, CountVec<u64>)>,
+        stats: &mut HashMap<(TagId, EncodedPattern), StarStatistics>,
     ) {
         // Compute star stats for endpoints of all paths
-        for (path, vec) in state.values().filter(|(path, _)| !path.is_empty()) {
+        for (_, (path, vec)) in state
+            .iter()
+            .filter(|(_, (path, _))| !path.is_empty())
+            .sorted_unstable_by(|((r1, c1), _), ((r2, c2), _)| (c1, r1).cmp(&(c2, r2)))
+        {
             let label_id = path.start().label_id();
             let center_rank = path.get_vertex_rank(path.start().tag_id()).unwrap();
             trace!(
@@ -260,26 +469,34 @@ impl StatisticsAnalyzer {
                 path.start().tag_id(),
                 center_rank
             );
-            stats
-                .entry((center_rank, path.encode()))
-                .or_insert_with(|| {
-                    let bucket_values = self.bucket_values.get().unwrap().get(&label_id).unwrap();
-                    let vertex_map = self.graph.get_internal_vertex_map(label_id).unwrap();
-                    let count = self.summarize_count_for_vec(vec, vertex_map, bucket_values);
-                    let max_degree =
-                        self.summarize_max_degree_for_vec(vec, vertex_map, bucket_values);
-                    StarStatistics {
-                        star: path.clone().into(),
-                        center_rank,
-                        count,
-                        max_degree,
-                    }
-                });
+            let code = path.encode();
+            let compute = || {
+                let bucket_values = self.bucket_values.get().unwrap().get(&label_id).unwrap();
+                let vertex_map = self.graph.get_internal_vertex_map(label_id).unwrap();
+                let count = self.summarize_count_for_vec(vec, vertex_map, bucket_values);
+                let max_degree = self.summarize_max_degree_for_vec(vec, vertex_map, bucket_values);
+                StarStatistics {
+                    star: path.clone().into(),
+                    center_rank,
+                    count,
+                    max_degree,
+                }
+            };
+            if self.is_star_required(center_rank, &code) {
+                stats
+                    .entry((center_rank, code.clone()))
+                    .or_insert_with(compute);
+            }
             if path.is_symmetric() {
-                let mut stat = stats.get(&(center_rank, path.encode())).unwrap().clone();
-                let center_rank = path.get_vertex_rank(path.end().tag_id()).unwrap();
-                stat.center_rank = center_rank;
-                stats.entry((center_rank, path.encode())).or_insert(stat);
+                let end_rank = path.get_vertex_rank(path.end().tag_id()).unwrap();
+                if self.is_star_required(end_rank, &code) {
+                    let mut stat = stats
+                        .get(&(center_rank, code.clone()))
+                        .cloned()
+                        .unwrap_or_else(compute);
+                    stat.center_rank = end_rank;
+                    stats.entry((end_rank, code)).or_insert(stat);
+                }
             }
         }
     }
@@ -289,43 +506,76 @@ impl StatisticsAnalyzer {
         &self,
         label_id: LabelId,
         degree: usize,
-        state: &HashMap<(u8, Vec<u8>), (PathPattern, CountVec<u64>)>,
-        stats: &mut HashMap<(TagId, Vec<u8>), StarStatistics>,
+        state: &HashMap<(TagId, EncodedPattern), (PathPattern, CountVec<u64>)>,
+        stats: &mut HashMap<(TagId, EncodedPattern), StarStatistics>,
     ) {
-        // Handle real stars
+        // Handle real stars. The inputs are sorted by path encoding so that the combinations (and
+        // thus the produced statistics) do not depend on the iteration order of `state`.
+        let mut num_stars = 0;
         for comb in state
-            .values()
-            .map(|(path, vec)| (path, vec))
-            .filter(|(path, _)| !path.is_empty() && path.len() <= self.max_star_length)
+            .iter()
+            .filter(|(_, (path, _))| !path.is_empty() && path.len() <= self.max_star_length)
+            .sorted_unstable_by(|((r1, c1), _), ((r2, c2), _)| (c1, r1).cmp(&(c2, r2)))
+            .map(|(_, (path, vec))| (path, vec, vec.as_ref().iter().all(|c| *c == 0)))
             .combinations(degree)
         {
-            let (paths, vecs): (Vec<_>, Vec<_>) = comb.into_iter().unzip();
+            let (paths, vecs): (Vec<_>, Vec<_>) = comb.iter().map(|(p, v, _)| (*p, *v)).unzip();
             assert!(vecs.iter().map(|v| v.len()).all_equal());
             let (star, center_rank) = merge_paths_to_star(&paths);
-            stats
-                .entry((center_rank, star.encode()))
-                .or_insert_with(|| {
-                    let (first, other) = vecs.split_first().unwrap();
-                    let mut vec = (*first).clone();
-                    vec.as_mut()
-                        .par_iter_mut()
-                        .enumerate()
-                        .for_each(|(idx, count)| {
-                            *count = other.iter().map(|v| v[idx]).fold(*count, |a, b| a * b);
-                        });
-
-                    let bucket_values = self.bucket_values.get().unwrap().get(&label_id).unwrap();
-                    let vertex_map = self.graph.get_internal_vertex_map(label_id).unwrap();
-                    let count = self.summarize_count_for_vec(&vec, vertex_map, bucket_values);
-                    let max_degree =
-                        self.summarize_max_degree_for_vec(&vec, vertex_map, bucket_values);
+            if !self.is_star_required(center_rank, &star.encode()) {
+                continue;
+            }
+            let key = (center_rank, star.encode());
+            if stats.contains_key(&key) {
+                continue;
+            }
+            if self
+                .max_star_combinations
+                .is_some_and(|limit| num_stars >= limit)
+            {
+                warn!(
+                    "stars of label {label_id} with degree {degree} are truncated to {num_stars}"
+                );
+                break;
+            }
+            num_stars += 1;
+            // An empty leg makes the product empty, so the product is skipped.
+            if comb.iter().any(|(_, _, is_zero)| *is_zero) {
+                let count = vec![0; self.buckets];
+                let max_degree = vec![0; self.buckets];
+                stats.insert(
+                    key,
                     StarStatistics {
                         star,
                         center_rank,
                         count,
                         max_degree,
-                    }
+                    },
+                );
+                continue;
+            }
+            let (first, other) = vecs.split_first().unwrap();
+            let mut vec = (*first).clone();
+            vec.as_mut()
+                .par_iter_mut()
+                .enumerate()
+                .for_each(|(idx, count)| {
+                    *count = other.iter().map(|v| v[idx]).fold(*count, |a, b| a * b);
                 });
+
+            let bucket_values = self.bucket_values.get().unwrap().get(&label_id).unwrap();
+            let vertex_map = self.graph.get_internal_vertex_map(label_id).unwrap();
+            let count = self.summarize_count_for_vec(&vec, vertex_map, bucket_values);
+            let max_degree = self.summarize_max_degree_for_vec(&vec, vertex_map, bucket_values);
+            stats.insert(
+                key,
+                StarStatistics {
+                    star,
+                    center_rank,
+                    count,
+                    max_degree,
+                },
+            );
         }
     }
 
@@ -400,7 +650,7 @@ impl StatisticsAnalyzer {
                             .par_iter()
                             .map(|neighbor_id| {
                                 let neighbor_internal_id =
-                                    start_vertex_map.get_by_left(neighbor_id).unwrap();
+                                    start_vertex_map.get_by_left(&neighbor_id).unwrap();
                                 start_count_vec[*neighbor_internal_id as usize]
                             })
                             .sum::<u64>();
@@ -457,11 +707,23 @@ impl StatisticsAnalyzer {
         }
     }
 
-    pub fn compute_path_statistics(&self) -> HashMap<Vec<u8>, PathStatistics> {
+    /// Compute the path statistics, and the joint statistics of the length-2 paths if enabled by
+    /// [`Self::joint_statistics`], which are the counts of the same traversal.
+    #[instrument(skip_all)]
+    pub fn compute_path_statistics(
+        &self,
+    ) -> (
+        HashMap<EncodedPattern, PathStatistics>,
+        HashMap<EncodedPattern, JointStatistics>,
+    ) {
         self.compute_bucket_values();
 
+        let prefix_codes = self
+            .required
+            .as_ref()
+            .map(|required| required.path_prefix_codes());
         let start = Instant::now();
-        let mut results = self.init_path_statistics();
+        let mut results = self.init_path_statistics(prefix_codes.as_ref());
         debug!("init path results: {} s", start.elapsed().as_secs_f64());
 
         let start = Instant::now();
@@ -472,21 +734,48 @@ impl StatisticsAnalyzer {
                 .unwrap();
             let tree = self
                 .schema
-                .generate_path_tree_from_path_end(&path, self.max_path_length);
+                .generate_path_tree_from_path_end(&path, self.path_tree_depth());
             let count_matrix = self.init_path_count_matrix_for_vertex(v.label);
             let vertex_map = self.graph.get_internal_vertex_map(v.label).unwrap();
+            debug!(
+                "path count matrix of vertex label {}: {} of {} vertices",
+                v.label,
+                count_matrix.len(),
+                vertex_map.len()
+            );
             for child in tree.root().children() {
                 self.compute_path_statistics_recursive(
                     child,
                     vertex_map,
                     &count_matrix,
                     0,
+                    prefix_codes.as_ref(),
                     &mut results,
                 );
             }
         }
         debug!("summarize path: {} s", start.elapsed().as_secs_f64());
 
+        // Prefixes of the required paths may be visited from only one end
+        if let Some(required) = &self.required {
+            results.retain(|code, _| required.paths.contains_key(code));
+        }
+
+        // Paths beyond the maximum length are only visited for the joint statistics
+        let mut joints = HashMap::new();
+        results.retain(|code, stat| {
+            let len = stat.path.len();
+            if len > self.max_path_length {
+                if len == 2 {
+                    let count = stat.count.take().unwrap();
+                    let path = stat.path.clone();
+                    joints.insert(code.clone(), JointStatistics { path, count });
+                }
+                return false;
+            }
+            true
+        });
+
         // Validation
         let start = Instant::now();
         for stat in results.values_mut() {
@@ -499,7 +788,7 @@ impl StatisticsAnalyzer {
         }
         debug!("validate path: {} s", start.elapsed().as_secs_f64());
 
-        results
+        let paths = results
             .into_iter()
             .map(
                 |(
@@ -522,11 +811,13 @@ impl StatisticsAnalyzer {
                     )
                 },
             )
-            .collect()
+            .collect();
+        (paths, joints)
     }
 
     fn compute_bucket_values(&self) {
         self.bucket_values.get_or_init(|| {
+            let _span = debug_span!("compute_bucket_values").entered();
             let start = Instant::now();
             let bucket_values = self
                 .schema
@@ -555,7 +846,10 @@ impl StatisticsAnalyzer {
         bucket_values
     }
 
-    fn init_path_statistics(&self) -> HashMap<Vec<u8>, PathStatisticsInner> {
+    fn init_path_statistics(
+        &self,
+        prefix_codes: Option<&HashSet<EncodedPattern>>,
+    ) -> HashMap<EncodedPattern, PathStatisticsInner> {
         let mut results = HashMap::new();
         for v in self.schema.vertices() {
             let path = RawPattern::new()
@@ -564,18 +858,20 @@ impl StatisticsAnalyzer {
                 .unwrap();
             let tree = self
                 .schema
-                .generate_path_tree_from_path_end(&path, self.max_path_length);
+                .generate_path_tree_from_path_end(&path, self.path_tree_depth());
             let mut queue = tree.root().children();
             while let Some(node) = queue.pop() {
                 let path = node.path().clone();
-                results
-                    .entry(path.encode())
-                    .or_insert_with(|| PathStatisticsInner {
-                        path,
-                        count: None,
-                        start_max_degree: None,
-                        end_max_degree: None,
-                    });
+                let code = path.encode();
+                if prefix_codes.is_some_and(|prefix_codes| !prefix_codes.contains(&code)) {
+                    continue;
+                }
+                results.entry(code).or_insert_with(|| PathStatisticsInner {
+                    path,
+                    count: None,
+                    start_max_degree: None,
+                    end_max_degree: None,
+                });
                 queue.extend(node.children());
             }
         }
@@ -587,15 +883,62 @@ impl StatisticsAnalyzer {
         (0..len).into_par_iter().map(|_| vec.clone()).collect()
     }
 
+    fn matrix_rows(&self, vertex_label: LabelId) -> &MatrixRows {
+        let matrix_rows = self.matrix_rows.get_or_init(|| {
+            self.schema
+                .vertices()
+                .iter()
+                .map(|v| (v.label, self.sample_matrix_rows(v.label)))
+                .collect()
+        });
+        matrix_rows.get(&vertex_label).unwrap()
+    }
+
+    fn sample_matrix_rows(&self, vertex_label: LabelId) -> MatrixRows {
+        let num_vertices = self.graph.vertices(vertex_label).unwrap().len();
+        let fraction = self
+            .path_sample
+            .as_ref()
+            .and_then(|sample| Some((sample.fractions.get(&vertex_label)?, sample.seed)));
+        let Some((fraction, seed)) = fraction.filter(|(f, _)| **f < 1.0) else {
+            return MatrixRows {
+                num_vertices,
+                sampled: None,
+            };
+        };
+        // Keep at least one vertex of a non-empty label
+        let amount = ((num_vertices as f64 * fraction).round() as usize).clamp(1, num_vertices);
+        let mut rng = StdRng::seed_from_u64(seed.wrapping_add(vertex_label as u64));
+        let sampled = rand::seq::index::sample(&mut rng, num_vertices, amount)
+            .into_iter()
+            .map(|i| i as u32)
+            .sorted_unstable()
+            .collect();
+        MatrixRows {
+            num_vertices,
+            sampled: Some(sampled),
+        }
+    }
+
+    /// Return the inverse of the probability that all the vertices of `path` are sampled.
+    fn path_scale(&self, path: &PathPattern) -> f64 {
+        path.vertices()
+            .iter()
+            .map(|v| self.matrix_rows(v.label_id()).scale())
+            .product()
+    }
+
     fn init_path_count_matrix_for_vertex(&self, vertex_label: LabelId) -> Vec<CountVec<u64>> {
         let vertex_map = self.graph.get_internal_vertex_map(vertex_label).unwrap();
         let bucket_map = self.bucket_map.get(&vertex_label).unwrap();
-        let mut count_matrix = self.init_path_count_matrix(vertex_map.len());
+        let rows = self.matrix_rows(vertex_label);
+        let mut count_matrix = self.init_path_count_matrix(rows.len());
         count_matrix
             .par_iter_mut()
             .enumerate()
-            .for_each(|(internal_id, count_vec)| {
-                let vertex_id = vertex_map.get_by_right(&(internal_id as u32)).unwrap();
+            .for_each(|(row, count_vec)| {
+                let internal_id = rows.internal_id(row);
+                let vertex_id = vertex_map.get_by_right(&internal_id).unwrap();
                 let bucket_id = bucket_map.get(vertex_id).unwrap();
                 count_vec[*bucket_id] = 1;
             });
@@ -625,6 +968,7 @@ impl StatisticsAnalyzer {
     fn summarize_count(
         &self,
         count_matrix: &[CountVec<u64>],
+        rows: &MatrixRows,
         vertex_map: &BiHashMap<usize, u32>,
         bucket_values: &[Vec<DefaultVertexId>],
     ) -> Vec<Box<[u64]>> {
@@ -633,9 +977,9 @@ impl StatisticsAnalyzer {
             .map(|values| {
                 values
                     .par_iter()
-                    .map(|vertex_id| {
+                    .filter_map(|vertex_id| {
                         let internal_id = vertex_map.get_by_left(vertex_id).unwrap();
-                        count_matrix.get(*internal_id as usize).unwrap()
+                        Some(&count_matrix[rows.row(*internal_id)?])
                     })
                     .fold(
                         || CountVec::zeroed(self.buckets),
@@ -659,6 +1003,7 @@ impl StatisticsAnalyzer {
     fn summarize_max_degree(
         &self,
         count_matrix: &[CountVec<u64>],
+        rows: &MatrixRows,
         vertex_map: &BiHashMap<usize, u32>,
         bucket_values: &[Vec<DefaultVertexId>],
     ) -> Vec<Box<[u64]>> {
@@ -667,9 +1012,9 @@ impl StatisticsAnalyzer {
             .map(|values| {
                 values
                     .par_iter()
-                    .map(|vertex_id| {
+                    .filter_map(|vertex_id| {
                         let internal_id = vertex_map.get_by_left(vertex_id).unwrap();
-                        count_matrix.get(*internal_id as usize).unwrap()
+                        Some(&count_matrix[rows.row(*internal_id)?])
                     })
                     .fold(
                         || CountVec::zeroed(self.buckets),
@@ -717,16 +1062,24 @@ impl StatisticsAnalyzer {
         parent_vertex_map: &BiHashMap<DefaultVertexId, u32>,
         parent_count_matrix: &[CountVec<u64>],
         parent_vertex_tag_id: TagId,
-        results: &mut HashMap<Vec<u8>, PathStatisticsInner>,
+        prefix_codes: Option<&HashSet<EncodedPattern>>,
+        results: &mut HashMap<EncodedPattern, PathStatisticsInner>,
     ) {
         let path = node.path();
+        let code = path.encode();
+        if prefix_codes.is_some_and(|prefix_codes| !prefix_codes.contains(&code)) {
+            return;
+        }
         let edge = path.get_edge(path.max_edge_tag_id().unwrap()).unwrap();
         let vertex = path.get_vertex(path.max_vertex_tag_id().unwrap()).unwrap();
         let vertex_map = self
             .graph
             .get_internal_vertex_map(vertex.label_id())
             .unwrap();
-        let mut count_matrix = self.init_path_count_matrix(vertex_map.len());
+        let rows = self.matrix_rows(vertex.label_id());
+        let parent_label_id = path.get_vertex(parent_vertex_tag_id).unwrap().label_id();
+        let parent_rows = self.matrix_rows(parent_label_id);
+        let mut count_matrix = self.init_path_count_matrix(rows.len());
         let direction = if edge.src() == parent_vertex_tag_id {
             EdgeDirection::In
         } else if edge.dst() == parent_vertex_tag_id {
@@ -737,8 +1090,8 @@ impl StatisticsAnalyzer {
         count_matrix
             .par_iter_mut()
             .enumerate()
-            .for_each(|(internal_id, count_vec)| {
-                let vertex_id = vertex_map.get_by_right(&(internal_id as u32)).unwrap();
+            .for_each(|(row, count_vec)| {
+                let vertex_id = vertex_map.get_by_right(&rows.internal_id(row)).unwrap();
                 self.graph
                     .neighbors(
                         LabeledVertex::new(*vertex_id, vertex.label_id()),
@@ -747,9 +1100,9 @@ impl StatisticsAnalyzer {
                     )
                     .unwrap()
                     .iter()
-                    .map(|nbr_id| {
-                        let nbr_internal_id = parent_vertex_map.get_by_left(nbr_id).unwrap();
-                        parent_count_matrix.get(*nbr_internal_id as usize).unwrap()
+                    .filter_map(|nbr_id| {
+                        let nbr_internal_id = parent_vertex_map.get_by_left(&nbr_id).unwrap();
+                        Some(&parent_count_matrix[parent_rows.row(*nbr_internal_id)?])
                     })
                     .for_each(|nbr_count_vec| {
                         *count_vec += nbr_count_vec;
@@ -761,25 +1114,35 @@ impl StatisticsAnalyzer {
             .unwrap()
             .get(&vertex.label_id())
             .unwrap();
-        let statistics = results.get_mut(&path.encode()).unwrap();
+        // The counts of a vertex are the paths from it whose other vertices are sampled.
+        let count_scale = self.path_scale(path);
+        let degree_scale = count_scale / rows.scale();
+        let count = || {
+            let count = self.summarize_count(&count_matrix, rows, vertex_map, local_bucket_values);
+            scale_counts(count, count_scale)
+        };
+        let max_degree = || {
+            let max_degree =
+                self.summarize_max_degree(&count_matrix, rows, vertex_map, local_bucket_values);
+            scale_counts(max_degree, degree_scale)
+        };
+        // The maximum degrees of joint statistics are not kept
+        let with_max_degree = path.len() <= self.max_path_length;
+        let statistics = results.get_mut(&code).unwrap();
         let vertex_rank = path.get_vertex_rank(vertex.tag_id()).unwrap();
         let path_vertex = statistics.path.get_vertex_from_rank(vertex_rank).unwrap();
         if path_vertex == statistics.path.start() {
-            statistics.count.get_or_insert_with(|| {
-                self.summarize_count(&count_matrix, vertex_map, local_bucket_values)
-            });
-            statistics.start_max_degree.get_or_insert_with(|| {
-                self.summarize_max_degree(&count_matrix, vertex_map, local_bucket_values)
-            });
+            statistics.count.get_or_insert_with(count);
+            if with_max_degree {
+                statistics.start_max_degree.get_or_insert_with(max_degree);
+            }
         } else if path_vertex == statistics.path.end() {
-            statistics.count.get_or_insert_with(|| {
-                self.summarize_count(&count_matrix, vertex_map, local_bucket_values)
-                    .transpose()
-            });
-            statistics.end_max_degree.get_or_insert_with(|| {
-                self.summarize_max_degree(&count_matrix, vertex_map, local_bucket_values)
-                    .transpose()
-            });
+            statistics.count.get_or_insert_with(|| count().transpose());
+            if with_max_degree {
+                statistics
+                    .end_max_degree
+                    .get_or_insert_with(|| max_degree().transpose());
+            }
         } else {
             unreachable!()
         }
@@ -790,6 +1153,7 @@ impl StatisticsAnalyzer {
                 vertex_map,
                 &count_matrix,
                 vertex.tag_id(),
+                prefix_codes,
                 results,
             );
         }
@@ -869,7 +1233,7 @@ mod tests {
             2,
             4,
         );
-        let results = analyzer.compute_path_statistics();
+        let results = analyzer.compute_path_statistics().0;
         for (i, stat) in results.into_values().enumerate() {
             println!(
                 "[{i}] {}, count: {:?}, start_max_degree: {:?}, end_max_degree: {:?}",
@@ -877,4 +1241,123 @@ mod tests {
             )
         }
     }
+
+    #[test]
+    fn test_sample_path_vertices() {
+        const PERSON: LabelId = 6;
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let bucket_map = Arc::new(build_bucket_map(&graph, 2));
+        let analyzer = || {
+            StatisticsAnalyzer::new(
+                graph.clone(),
+                schema.clone(),
+                bucket_map.clone(),
+                2,
+                2,
+                1,
+                1,
+            )
+        };
+        let full = analyzer().compute_path_statistics().0;
+        let fractions = |f| HashMap::from_iter([(PERSON, f)]);
+        let unsampled = analyzer()
+            .sample_path_vertices(fractions(1.0), 0)
+            .compute_path_statistics()
+            .0;
+        assert_eq!(unsampled, full);
+
+        let sampled = analyzer()
+            .sample_path_vertices(fractions(0.5), 42)
+            .compute_path_statistics()
+            .0;
+        let resampled = analyzer()
+            .sample_path_vertices(fractions(0.5), 42)
+            .compute_path_statistics()
+            .0;
+        assert_eq!(sampled, resampled);
+        assert_eq!(sampled.len(), full.len());
+        let total = |stat: &PathStatistics| -> u64 { stat.count.iter().flatten().sum() };
+        for (code, stat) in &sampled {
+            let expected = full.get(code).unwrap();
+            if stat.path.vertices().iter().all(|v| v.label_id() != PERSON) {
+                assert_eq!(stat, expected);
+            }
+            if stat.path.len() == 1 && total(expected) > 100 {
+                let ratio = total(stat) as f64 / total(expected) as f64;
+                assert!((0.5..2.0).contains(&ratio), "{}: {ratio}", stat.path);
+            }
+        }
+    }
+
+    #[test]
+    fn test_joint_statistics() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let bucket_map = Arc::new(build_bucket_map(&graph, 4));
+        let analyzer = |max_path_length| {
+            StatisticsAnalyzer::new(
+                graph.clone(),
+                schema.clone(),
+                bucket_map.clone(),
+                4,
+                max_path_length,
+                1,
+                1,
+            )
+        };
+        let (paths, joints) = analyzer(1).joint_statistics(true).compute_path_statistics();
+        assert_eq!(paths, analyzer(1).compute_path_statistics().0);
+
+        // The joint statistics are the counts of the length-2 paths.
+        let (full, no_joints) = analyzer(2).joint_statistics(true).compute_path_statistics();
+        assert!(no_joints.is_empty());
+        let expected: HashMap<_, _> = full
+            .into_iter()
+            .filter(|(_, stat)| stat.path.len() == 2)
+            .map(|(code, stat)| (code, (stat.path, stat.count)))
+            .collect();
+        let joints: HashMap<_, _> = joints
+            .into_iter()
+            .map(|(code, stat)| (code, (stat.path, stat.count)))
+            .collect();
+        assert!(!joints.is_empty());
+        assert_eq!(joints, expected);
+    }
+
+    #[test]
+    fn test_max_star_combinations() {
+        let schema = Arc::new(build_ldbc_schema());
+        let graph = Arc::new(build_ldbc_graph());
+        let bucket_map = Arc::new(build_bucket_map(&graph, 4));
+        let analyzer = || {
+            StatisticsAnalyzer::new(
+                graph.clone(),
+                schema.clone(),
+                bucket_map.clone(),
+                4,
+                1,
+                1,
+                3,
+            )
+        };
+        let full = analyzer().compute_star_statistics();
+        let truncated = analyzer()
+            .max_star_combinations(1)
+            .compute_star_statistics();
+        assert!(truncated.len() < full.len());
+        for (key, stat) in &truncated {
+            assert_eq!(full.get(key).unwrap(), stat);
+        }
+        // Each center label has at most one star per degree above 1.
+        let stars = truncated
+            .values()
+            .filter(|stat| stat.star.edges().len() > 1)
+            .map(|stat| {
+                let center = stat.star.get_vertex_from_rank(stat.center_rank).unwrap();
+                (center.label_id(), stat.star.edges().len())
+            })
+            .collect_vec();
+        assert!(stars.iter().all_unique());
+    }
 }
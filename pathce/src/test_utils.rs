@@ -1,11 +1,52 @@
-use std::fs::File;
-use std::path::PathBuf;
+use std::fs::{create_dir_all, remove_dir_all, File};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 use ahash::HashMapExt;
+use rayon::{ThreadPool, ThreadPoolBuilder};
 
+use crate::catalog::DuckCatalog;
+use crate::catalog_builder::CatalogBuilder;
 use crate::common::GlobalBucketMap;
+use crate::estimate::CardinalityEstimator;
 use crate::graph::LabeledGraph;
-use crate::schema::Schema;
+use crate::schema::{Schema, SchemaUnchecked};
+
+/// A new directory under the temporary directory of the system, which is removed with its content
+/// when dropped, even if the test panics.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("pathce_{name}_{}_{id}", std::process::id()));
+        create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = remove_dir_all(&self.0);
+    }
+}
 
 pub fn build_ldbc_schema() -> Schema {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/ldbc_pathce_schema.json");
@@ -13,6 +54,27 @@ pub fn build_ldbc_schema() -> Schema {
     serde_json::from_reader(file).unwrap()
 }
 
+/// Build the LDBC schema with the abstract vertex label `Message` of `Comment` and `Post`, and the
+/// abstract edge labels `hasCreator` and `likes` of the edge labels of messages.
+pub fn build_ldbc_schema_with_messages() -> Schema {
+    let schema = build_ldbc_schema();
+    let vertex = |name| schema.get_vertex_label_id(name).unwrap();
+    let edge = |name| schema.get_edge_label_id(name).unwrap();
+    let (message, has_creator, likes) = (11, 25, 26);
+    SchemaUnchecked::from(schema.clone())
+        .add_vertex_label("Message".into(), message)
+        .add_edge_label("hasCreator".into(), has_creator)
+        .add_edge_label("likes".into(), likes)
+        .add_subtype(vertex("Comment"), message)
+        .add_subtype(vertex("Post"), message)
+        .add_edge_subtype(edge("Comment_hasCreator_Person"), has_creator)
+        .add_edge_subtype(edge("Post_hasCreator_Person"), has_creator)
+        .add_edge_subtype(edge("Person_likes_Comment"), likes)
+        .add_edge_subtype(edge("Person_likes_Post"), likes)
+        .try_into()
+        .unwrap()
+}
+
 pub fn build_ldbc_graph() -> LabeledGraph {
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/sf0.003");
     let schema = build_ldbc_schema();
@@ -32,3 +94,27 @@ pub fn build_bucket_map(graph: &LabeledGraph, buckets: usize) -> GlobalBucketMap
     }
     global_bucket_map
 }
+
+pub fn build_test_pool() -> Arc<ThreadPool> {
+    Arc::new(ThreadPoolBuilder::new().num_threads(2).build().unwrap())
+}
+
+/// Return a builder of the catalog of `graph` with paths of length up to 2, stars of length 1 and
+/// degree up to 2, and 4 buckets, on a pool of [`build_test_pool`].
+pub fn ldbc_catalog_builder(schema: Arc<Schema>, graph: Arc<LabeledGraph>) -> CatalogBuilder {
+    CatalogBuilder::new(schema, graph, build_test_pool())
+        .max_path_length(2)
+        .max_star_length(1)
+        .max_star_degree(2)
+        .buckets(4)
+}
+
+pub fn build_ldbc_catalog(schema: Arc<Schema>, graph: Arc<LabeledGraph>) -> DuckCatalog {
+    ldbc_catalog_builder(schema, graph).build().unwrap()
+}
+
+/// Return the estimator of `catalog` with the lengths and degree of [`ldbc_catalog_builder`],
+/// which tries up to 10 decompositions.
+pub fn build_estimator(catalog: &DuckCatalog) -> CardinalityEstimator<'_> {
+    CardinalityEstimator::new(catalog, 2, 1, 2, 10, false, false, false)
+}